@@ -0,0 +1,211 @@
+/**
+ * A small event emitter with wildcard listeners, once-handlers and
+ * asynchronous dispatch. Used as a benchmark corpus for the lexer.
+ * @module emitter
+ */
+'use strict';
+
+const WILDCARD = '*';
+const MAX_LISTENERS = 0x10;
+const NAME_PATTERN = /^[a-z][a-z0-9_.:-]*$/i;
+const DEFAULT_OPTIONS = Object.freeze({
+  maxListeners: MAX_LISTENERS,
+  captureRejections: false,
+  delimiter: ".",
+  verbose: 0b0,
+});
+
+class EmitterError extends Error {
+  constructor(message, code = "EEMITTER") {
+    super(message);
+    this.name = 'EmitterError';
+    this.code = code;
+  }
+}
+
+function assertName(name) {
+  if (typeof name !== "string" && typeof name !== "symbol") {
+    throw new TypeError(`Event name must be a string or symbol, got ${typeof name}`);
+  }
+  if (typeof name === "string" && name !== WILDCARD && !NAME_PATTERN.test(name)) {
+    throw new EmitterError(`Invalid event name: "${name}"`, 'EBADNAME');
+  }
+}
+
+/**
+ * Splits a namespaced event name into its segments.
+ * @param {string} name
+ * @param {string} delimiter
+ * @returns {string[]}
+ */
+function segments(name, delimiter) {
+  return typeof name === 'string' ? name.split(delimiter).filter(Boolean) : [name];
+}
+
+export class Emitter {
+  #listeners = new Map();
+  #options;
+  #pending = 0;
+
+  constructor(options = {}) {
+    this.#options = { ...DEFAULT_OPTIONS, ...options };
+  }
+
+  get listenerCount() {
+    let count = 0;
+    for (const [, list] of this.#listeners) {
+      count += list.length;
+    }
+    return count;
+  }
+
+  on(name, listener, { once = false, priority = 0 } = {}) {
+    assertName(name);
+    if (typeof listener !== 'function') {
+      throw new TypeError("listener must be a function");
+    }
+
+    const list = this.#listeners.get(name) ?? [];
+    if (this.#options.maxListeners > 0 && list.length >= this.#options.maxListeners) {
+      console.warn(`Possible memory leak: ${list.length + 1} listeners for "${String(name)}"`);
+    }
+
+    list.push({ listener, once, priority });
+    list.sort((a, b) => b.priority - a.priority);
+    this.#listeners.set(name, list);
+
+    return () => this.off(name, listener);
+  }
+
+  once(name, listener, options) {
+    return this.on(name, listener, { ...options, once: true });
+  }
+
+  off(name, listener) {
+    const list = this.#listeners.get(name);
+    if (!list) return false;
+
+    const index = list.findIndex((entry) => entry.listener === listener);
+    if (index === -1) return false;
+
+    list.splice(index, 1);
+    if (list.length === 0) {
+      this.#listeners.delete(name);
+    }
+    return true;
+  }
+
+  emit(name, ...args) {
+    assertName(name);
+    let handled = false;
+    const names = [name, WILDCARD];
+
+    for (const key of names) {
+      const list = this.#listeners.get(key);
+      if (!list) continue;
+
+      for (const entry of [...list]) {
+        handled = true;
+        if (entry.once) {
+          this.off(key, entry.listener);
+        }
+        try {
+          const result = entry.listener.apply(this, key === WILDCARD ? [name, ...args] : args);
+          if (result && typeof result.then === "function" && this.#options.captureRejections) {
+            this.#pending++;
+            result
+              .catch((error) => this.emit('error', error))
+              .finally(() => { this.#pending--; });
+          }
+        } catch (error) {
+          if (name === 'error') throw error;
+          this.emit("error", error);
+        }
+      }
+    }
+
+    return handled;
+  }
+
+  async emitAsync(name, ...args) {
+    const list = this.#listeners.get(name) ?? [];
+    const results = await Promise.allSettled(list.map(({ listener }) => listener(...args)));
+    return results.filter((r) => r.status === "rejected").map((r) => r.reason);
+  }
+
+  async *iterate(name, { signal } = {}) {
+    const queue = [];
+    let wake = null;
+    const unsubscribe = this.on(name, (...args) => {
+      queue.push(args);
+      wake?.();
+    });
+
+    try {
+      while (!signal?.aborted) {
+        if (queue.length === 0) {
+          await new Promise((resolve) => (wake = resolve));
+          wake = null;
+          continue;
+        }
+        yield queue.shift();
+      }
+    } finally {
+      unsubscribe();
+    }
+  }
+
+  static mixin(target) {
+    const emitter = new Emitter();
+    for (const method of ["on", "once", "off", "emit"]) {
+      target[method] = emitter[method].bind(emitter);
+    }
+    return target;
+  }
+}
+
+// Throttle and debounce helpers, kept here so the corpus has a mix of
+// arithmetic, bit operations and numeric literals.
+export function throttle(fn, wait = 100) {
+  let last = 0, timer = null;
+  return function throttled(...args) {
+    const now = Date.now();
+    const remaining = wait - (now - last);
+    if (remaining <= 0 || remaining > wait) {
+      clearTimeout(timer);
+      timer = null;
+      last = now;
+      return fn.apply(this, args);
+    }
+    timer ??= setTimeout(() => {
+      last = Date.now();
+      timer = null;
+      fn.apply(this, args);
+    }, remaining);
+  };
+}
+
+export function hash(text) {
+  let h = 0x811c9dc5;
+  for (let i = 0; i < text.length; i++) {
+    h ^= text.charCodeAt(i);
+    h = (h * 0x01000193) >>> 0;
+  }
+  return (h & 0xffff_ffff).toString(16).padStart(8, '0');
+}
+
+export const units = {
+  kb: 1024,
+  mb: 1024 ** 2,
+  ratio: 1.618_033_988,
+  epsilon: 2.220446049250313e-16,
+  big: 9007199254740993n,
+};
+
+const escapes = "tab:\t newline:\n quote:\" unicode:\u00e9 \u{1F600} hex:\x41";
+const raw = String.raw`C:\path\to\${"file"}.txt`;
+const nested = `outer ${`inner ${1 + 2} text`} done`;
+const isEmail = (s) => /^[^\s@]+@[^\s@]+\.[a-z]{2,}$/i.test(s) && s.length / 2 > 1;
+const label = (n) => n > 1 ? `${n} items` : n === 1 ? "1 item" : 'empty';
+
+export default Emitter;
@@ -0,0 +1,151 @@
+// A typed key/value store with subscriptions and selectors.
+
+export type Listener<T> = (value: T, previous: T | undefined) => void;
+export type Selector<S, R> = (state: Readonly<S>) => R;
+
+export interface StoreOptions<S> {
+  readonly name?: string;
+  equals?: (a: unknown, b: unknown) => boolean;
+  middleware?: Array<Middleware<S>>;
+}
+
+export interface Middleware<S> {
+  (next: (state: S) => void): (state: S) => void;
+}
+
+declare global {
+  interface Window {
+    __STORES__?: Map<string, Store<any>>;
+  }
+}
+
+enum Status {
+  Idle = 0,
+  Updating = 1 << 0,
+  Notifying = 1 << 1,
+}
+
+const enum Limits {
+  MaxDepth = 32,
+  MaxSubscribers = 1_000,
+}
+
+abstract class Base<S> {
+  protected abstract get snapshot(): S;
+  public toJSON(): string {
+    return JSON.stringify(this.snapshot);
+  }
+}
+
+export class Store<S extends object> extends Base<S> implements Iterable<[keyof S, S[keyof S]]> {
+  private state: S;
+  private status: Status = Status.Idle;
+  private readonly listeners = new Set<Listener<S>>();
+  readonly #name: string;
+  declare readonly brand: unique symbol;
+
+  constructor(initial: S, private readonly options: StoreOptions<S> = {}) {
+    super();
+    this.state = { ...initial };
+    this.#name = options.name ?? `store-${Math.random().toString(36).slice(2, 8)}`;
+  }
+
+  protected get snapshot(): S {
+    return this.state;
+  }
+
+  get<K extends keyof S>(key: K): S[K] {
+    return this.state[key];
+  }
+
+  set<K extends keyof S>(key: K, value: S[K]): void {
+    const equals = this.options.equals ?? Object.is;
+    if (equals(this.state[key], value)) return;
+    this.update((draft) => {
+      draft[key] = value;
+    });
+  }
+
+  update(recipe: (draft: S) => void): void {
+    if (this.status & Status.Updating) {
+      throw new Error(`[${this.#name}] nested update is not allowed`);
+    }
+    const previous = this.state;
+    const draft = structuredClone(previous) as S;
+    this.status |= Status.Updating;
+    try {
+      recipe(draft);
+    } finally {
+      this.status &= ~Status.Updating;
+    }
+
+    const apply = (this.options.middleware ?? []).reduceRight<(state: S) => void>(
+      (next, middleware) => middleware(next),
+      (state) => {
+        this.state = state;
+      },
+    );
+    apply(draft);
+    this.notify(previous);
+  }
+
+  select<R>(selector: Selector<S, R>): R {
+    return selector(this.state as Readonly<S>);
+  }
+
+  subscribe(listener: Listener<S>): () => void {
+    if (this.listeners.size >= Limits.MaxSubscribers) {
+      throw new RangeError("too many subscribers");
+    }
+    this.listeners.add(listener);
+    return () => void this.listeners.delete(listener);
+  }
+
+  private notify(previous: S | undefined): void {
+    this.status |= Status.Notifying;
+    for (const listener of this.listeners) {
+      listener(this.state, previous);
+    }
+    this.status ^= Status.Notifying;
+  }
+
+  *[Symbol.iterator](): Iterator<[keyof S, S[keyof S]]> {
+    for (const key of Object.keys(this.state) as Array<keyof S>) {
+      yield [key, this.state[key]];
+    }
+  }
+}
+
+export function createStore<S extends object>(initial: S, options?: StoreOptions<S>): Store<S> {
+  const store = new Store(initial, options);
+  if (typeof window !== "undefined") {
+    (window.__STORES__ ??= new Map()).set(options?.name ?? "anonymous", store);
+  }
+  return store;
+}
+
+export const logger: Middleware<unknown> = (next) => (state) => {
+  console.debug("%c[store]", "color: #09f", state);
+  next(state);
+};
+
+type DeepPartial<T> = T extends object ? { [P in keyof T]?: DeepPartial<T[P]> } : T;
+type Paths<T, D extends number = 3> = [D] extends [never]
+  ? never
+  : T extends object
+    ? { [K in keyof T & string]: K | `${K}.${Paths<T[K]>}` }[keyof T & string]
+    : never;
+
+function isPlainObject(value: unknown): value is Record<string, unknown> {
+  return value !== null && typeof value === "object" && !Array.isArray(value);
+}
+
+export function merge<T extends object>(target: T, source: DeepPartial<T>, depth = 0): T {
+  if (depth > Limits.MaxDepth) return target;
+  for (const [key, value] of Object.entries(source)) {
+    const current = (target as any)[key];
+    (target as any)[key] =
+      isPlainObject(current) && isPlainObject(value) ? merge(current, value, depth + 1) : value;
+  }
+  return target;
+}
@@ -0,0 +1,118 @@
+//! Lexer throughput benchmark.
+//!
+//! Lexes the files in `benches/corpus`, repeated until the input is at least
+//! 8 MiB, and reports throughput with SIMD and with scalar scanning. Then does
+//! the same for the part of the corpus that the original lexer can lex, which
+//! it also writes to `target/bench-original.js`. Run with
+//! `cargo bench --bench lexer`. See README.md for how it compares with the
+//! original lexer.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rhi::{Lexer, ScanMode};
+
+const MIN_INPUT_LEN: usize = 8 * 1024 * 1024;
+const ITERATIONS: usize = 30;
+
+/// The characters the original lexer accepts outside comments and numbers.
+const ORIGINAL_CHARACTERS: &str = " \t\r\n;(){}[],~:@.+-*/%=!?<>&|^";
+
+fn main() {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/corpus");
+    let mut files: Vec<_> = fs::read_dir(&corpus_dir)
+        .expect("benches/corpus should exist")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    files.sort();
+
+    let mut corpus = String::new();
+    for file in &files {
+        corpus.push_str(&fs::read_to_string(file).expect("corpus files should be UTF-8"));
+        corpus.push('\n');
+    }
+
+    let input = repeat(&corpus);
+    println!(
+        "lexing {} files, {:.1} MiB",
+        files.len(),
+        input.len() as f64 / (1024.0 * 1024.0)
+    );
+    for scan_mode in [ScanMode::Simd, ScanMode::Scalar] {
+        run(&input, scan_mode);
+    }
+
+    let input = repeat(&original_subset(&corpus));
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/bench-original.js");
+    fs::write(&path, &input).expect("target/ should be writable");
+    println!(
+        "lexing the part the original lexer can lex, {:.1} MiB, written to {}",
+        input.len() as f64 / (1024.0 * 1024.0),
+        path.display()
+    );
+    for scan_mode in [ScanMode::Simd, ScanMode::Scalar] {
+        run(&input, scan_mode);
+    }
+}
+
+/// `text` repeated until it is at least `MIN_INPUT_LEN` bytes long.
+fn repeat(text: &str) -> String {
+    let mut input = String::with_capacity(MIN_INPUT_LEN + text.len());
+    while input.len() < MIN_INPUT_LEN {
+        input.push_str(text);
+    }
+    input
+}
+
+/// `corpus` with every name, keyword, literal and template replaced by ` 0 `,
+/// the one number the original lexer reads correctly. Comments, whitespace
+/// and punctuators are kept, so that both lexers lex it without errors.
+fn original_subset(corpus: &str) -> String {
+    let (tokens, errors) = Lexer::new(corpus).lex_with_trivia();
+    assert!(
+        errors.is_empty(),
+        "benches/corpus should lex without errors"
+    );
+    let mut subset = String::with_capacity(corpus.len());
+    for token in tokens {
+        let text = &corpus[token.start as usize..token.end as usize];
+        let kept = token.token_type.is_comment()
+            || ((token.token_type.is_trivia() || token.token_type.is_punctuation())
+                && text.chars().all(|c| ORIGINAL_CHARACTERS.contains(c)));
+        subset.push_str(if kept { text } else { " 0 " });
+    }
+    subset
+}
+
+fn run(input: &str, scan_mode: ScanMode) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    let mut errors = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        tokens = 0;
+        errors = 0;
+        let mut lexer = Lexer::with_scan_mode(input, scan_mode);
+        for token in lexer.lex() {
+            match black_box(token) {
+                Ok(_) => tokens += 1,
+                Err(_) => errors += 1,
+            }
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let seconds = best.as_secs_f64();
+    println!(
+        "{:?}: {} tokens, {} errors, best of {}: {:.2} ms, {:.1} MiB/s, {:.1} Mtokens/s",
+        scan_mode,
+        tokens,
+        errors,
+        ITERATIONS,
+        seconds * 1000.0,
+        input.len() as f64 / (1024.0 * 1024.0) / seconds,
+        (tokens + errors) as f64 / 1e6 / seconds
+    );
+}
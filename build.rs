@@ -0,0 +1,140 @@
+//! Generates the keyword lookup from `src/keywords.txt`.
+//!
+//! Each keyword is packed into a zero-padded little-endian `u128`, and a
+//! multiply-shift hash of that word is made perfect by searching for a seed
+//! under which no two keywords share a slot. A lookup is then one multiply,
+//! two table loads and one compare, with no data-dependent branches before
+//! the final (almost always false) comparison. `KEYWORDS` lists the
+//! spellings in `TokenType` order so a keyword kind can be turned back into
+//! its text by index.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const KEYWORDS: &str = "src/keywords.txt";
+
+fn main() {
+    println!("cargo::rerun-if-changed={}", KEYWORDS);
+
+    let list = fs::read_to_string(KEYWORDS).expect("failed to read the keyword list");
+    let keywords: Vec<(&str, &str)> = list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(text), Some(variant), None) => (text, variant),
+                _ => panic!("{}: malformed line {:?}", KEYWORDS, line),
+            }
+        })
+        .collect();
+
+    for &(text, _) in &keywords {
+        assert!(
+            !text.is_empty() && text.len() < 16 && text.bytes().all(|b| b.is_ascii_lowercase()),
+            "{}: keyword {:?} must be 1 to 15 lowercase ASCII letters",
+            KEYWORDS,
+            text
+        );
+    }
+    assert!(keywords.len() < u8::MAX as usize, "too many keywords");
+
+    let words: Vec<u128> = keywords.iter().map(|(text, _)| pack(text)).collect();
+    let (seed, bits) = find_seed(&words);
+    let mut slots = vec![keywords.len() as u8; 1 << bits];
+    for (i, &word) in words.iter().enumerate() {
+        slots[hash(word, seed, bits)] = i as u8;
+    }
+
+    let min = keywords.iter().map(|(text, _)| text.len()).min().unwrap();
+    let max = keywords.iter().map(|(text, _)| text.len()).max().unwrap();
+
+    let mut out = String::new();
+    writeln!(out, "// @generated by build.rs from {}.\n", KEYWORDS).unwrap();
+
+    writeln!(
+        out,
+        "/// Keyword spellings, indexed by `kind as u8 - TokenType::FIRST_KEYWORD as u8`."
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "pub static KEYWORDS: [(&str, TokenType); {}] = [",
+        keywords.len()
+    )
+    .unwrap();
+    for (text, variant) in &keywords {
+        writeln!(out, "    ({:?}, TokenType::{}),", text, variant).unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(out, "pub const MIN_LEN: usize = {};", min).unwrap();
+    writeln!(out, "pub const MAX_LEN: usize = {};", max).unwrap();
+    writeln!(out, "const SEED: u64 = {:#x};", seed).unwrap();
+    writeln!(out, "const BITS: u32 = {};\n", bits).unwrap();
+
+    writeln!(
+        out,
+        "/// Index into `WORDS` for each hash slot. Empty slots point at the"
+    )
+    .unwrap();
+    writeln!(out, "/// sentinel past the last keyword.").unwrap();
+    writeln!(out, "static SLOTS: [u8; {}] = {:?};\n", slots.len(), slots).unwrap();
+
+    writeln!(
+        out,
+        "/// Packed keyword spellings in `KEYWORDS` order, then a sentinel that"
+    )
+    .unwrap();
+    writeln!(out, "/// no packed identifier can equal.").unwrap();
+    writeln!(out, "static WORDS: [u128; {}] = [", words.len() + 1).unwrap();
+    for (word, (text, _)) in words.iter().zip(&keywords) {
+        writeln!(out, "    {:#x}, // {}", word, text).unwrap();
+    }
+    writeln!(out, "    u128::MAX,").unwrap();
+    writeln!(out, "];").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("keywords.rs");
+    fs::write(path, out).expect("failed to write the keyword table");
+}
+
+/// `text` as a little-endian integer, zero-padded to 16 bytes.
+fn pack(text: &str) -> u128 {
+    let mut bytes = [0u8; 16];
+    bytes[..text.len()].copy_from_slice(text.as_bytes());
+    u128::from_le_bytes(bytes)
+}
+
+/// Must match `hash` in `src/keyword.rs`.
+fn hash(word: u128, seed: u64, bits: u32) -> usize {
+    let folded = (word as u64) ^ ((word >> 64) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (folded.wrapping_mul(seed) >> (64 - bits)) as usize
+}
+
+/// Finds the smallest table, and a seed for it, under which `hash` has no
+/// collisions. The search is deterministic so builds are reproducible.
+fn find_seed(words: &[u128]) -> (u64, u32) {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut bits = (words.len() as f64).log2().ceil() as u32;
+
+    loop {
+        for _ in 0..100_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let seed = state | 1;
+
+            let mut used = vec![false; 1 << bits];
+            if words
+                .iter()
+                .all(|&word| !std::mem::replace(&mut used[hash(word, seed, bits)], true))
+            {
+                return (seed, bits);
+            }
+        }
+        bits += 1;
+    }
+}
@@ -0,0 +1,112 @@
+//! Lexes programs generated from the grammars in `docs/`, seeded by the
+//! fuzzer's input, and checks that they lex without errors, as
+//! `tests/grammar.rs` does for a fixed set of seeds.
+//!
+//! cargo +nightly fuzz run grammar
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use libfuzzer_sys::fuzz_target;
+use rhi::ebnf::generate::Generator;
+use rhi::ebnf::{Dialect, Grammar};
+use rhi::{LanguageVariant, Lexer, LexerError, Token, TokenType};
+
+static ISO: LazyLock<Grammar> = LazyLock::new(|| {
+    Grammar::parse(include_str!("../../docs/grammar_iso.ebnf"), Dialect::Iso).unwrap()
+});
+static TS: LazyLock<Grammar> = LazyLock::new(|| {
+    Grammar::parse(include_str!("../../docs/grammar-ts.ebnf"), Dialect::Iso).unwrap()
+});
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&start, seed)) = data.split_first() else {
+        return;
+    };
+    let mut bytes = [0; 8];
+    for (byte, &b) in bytes.iter_mut().zip(seed) {
+        *byte = b;
+    }
+    let (grammars, start) = match start % 3 {
+        0 => (vec![&*ISO], "Script"),
+        1 => (vec![&*ISO], "Module"),
+        _ => (vec![&*TS, &*ISO], "SourceFile"),
+    };
+    // The same constraints as `tests/grammar.rs`.
+    let text = Generator::new(&grammars)
+        .seed(u64::from_le_bytes(bytes))
+        .lexical("PrivateIdentifier")
+        .values(
+            "UnicodeEscapeSequence",
+            &[
+                "u0041", "u00e9", "u03c0", "u005f", "u{61}", "u{2135}", "u{10400}",
+            ],
+        )
+        .values(
+            "TemplateCharacter",
+            &[
+                "a",
+                "中",
+                "😀",
+                " ",
+                "$",
+                "\\`",
+                "\\${",
+                "\\x41",
+                "\\u{2028}",
+                "\\03",
+                "\\\n",
+                "\n",
+                "\r\n",
+                "\u{2028}",
+            ],
+        )
+        .values(
+            "RegularExpressionLiteral",
+            &["/a/g", "/[/]+/", "/(a|b)*?/iu", "/x{2,}$/m", "/é+/v"],
+        )
+        .weight("TripleSlashDirective", 0)
+        .generate(start)
+        .unwrap();
+    if let Some(e) = lex_error(&text) {
+        panic!("{}\n{:?}", e, text);
+    }
+});
+
+/// The first error lexing `text`. `yield`, `await` and `of` may also be
+/// names, as in `yield /= 2`, where only a parser can tell that the `/`
+/// divides. So if an error follows a regular expression after one of them,
+/// that word is read as a name instead and the whole text is lexed again.
+fn lex_error(text: &str) -> Option<LexerError> {
+    let mut lexer = Lexer::new(text);
+    lexer.set_language_variant(LanguageVariant::Standard);
+    let mut previous: Option<Token> = None;
+    let mut ambiguous: Option<Token> = None;
+    for token in lexer.lex() {
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                let Some(name) = ambiguous else {
+                    return Some(e);
+                };
+                // A name of the same length keeps every offset in place.
+                let mut text = text.to_string();
+                let (start, end) = (name.start as usize, name.end as usize);
+                text.replace_range(start..end, &"_".repeat(end - start));
+                return lex_error(&text);
+            }
+        };
+        if token.token_type == TokenType::RegularExpressionLiteral
+            && let Some(previous) = previous
+            && matches!(
+                previous.token_type,
+                TokenType::YieldKeyword | TokenType::AwaitKeyword | TokenType::OfKeyword
+            )
+        {
+            ambiguous = Some(previous);
+        }
+        previous = Some(token);
+    }
+    None
+}
@@ -0,0 +1,57 @@
+//! Lexes arbitrary UTF-8 in every variant and scan mode, checking the same
+//! properties as `tests/properties.rs`.
+//!
+//! cargo +nightly fuzz run lexer
+//!
+//! Save any crash found as a regression case:
+//!
+//! cp fuzz/artifacts/lexer/crash-<hash> tests/regressions/
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhi::{LanguageVariant, Lexer, ScanMode, TokenFlags};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    for variant in [LanguageVariant::Standard, LanguageVariant::Jsx] {
+        for scan_mode in [ScanMode::Simd, ScanMode::Scalar] {
+            let mut lexer = Lexer::with_scan_mode(text, scan_mode);
+            lexer.set_language_variant(variant);
+            let mut tokens = lexer.lex();
+            let mut offset = tokens.lexer().offset();
+            let mut end = 0;
+            while let Some(result) = tokens.next() {
+                // An unterminated token follows its error without consuming
+                // more input.
+                let unterminated =
+                    matches!(&result, Ok(token) if token.flags.contains(TokenFlags::UNTERMINATED));
+                assert!(
+                    tokens.lexer().offset() > offset || unterminated,
+                    "no progress at {}",
+                    offset
+                );
+                offset = tokens.lexer().offset();
+                if let Ok(token) = result {
+                    let (start, stop) = (token.start as usize, token.end as usize);
+                    assert!(end <= start && start <= stop && stop <= offset);
+                    assert!(text.is_char_boundary(start) && text.is_char_boundary(stop));
+                    end = stop;
+                }
+            }
+            assert_eq!(tokens.lexer().offset(), text.len());
+
+            let mut lexer = Lexer::with_scan_mode(text, scan_mode);
+            lexer.set_language_variant(variant);
+            let (tokens, _) = lexer.lex_with_trivia();
+            let mut pos = 0;
+            for token in &tokens {
+                assert_eq!(token.start as usize, pos);
+                pos = token.end as usize;
+            }
+            assert_eq!(pos, text.len());
+        }
+    }
+});
@@ -0,0 +1,108 @@
+#!/usr/bin/env node
+// Prints the tokens TypeScript's scanner produces for a file, in the format
+// of `rhi tokens --format typescript`, for the golden files in
+// tests/typescript.
+//
+// usage: node scripts/ts-tokens.mjs <file> > tests/typescript/<file name>.tokens
+//
+// Needs the `typescript` version pinned in scripts/package.json, from
+// `npm install --prefix scripts`.
+//
+// The scanner on its own cannot tell a regular expression from a division,
+// or the `}` that continues a template from a plain one: TypeScript's parser
+// decides and asks the scanner to rescan. This script decides with the same
+// context-free rules as rhi's lexer (`regex_may_follow` in src/lexer.rs) and
+// then lets TypeScript rescan, so that the output compares how tokens are
+// scanned rather than how they are parsed. For the same reason `>` is always
+// rescanned into `>>`, `>=` and so on. JSX children and tags are not
+// followed, so inputs should not contain JSX elements.
+
+import { readFileSync } from "node:fs";
+import { createRequire } from "node:module";
+
+const require = createRequire(import.meta.url);
+const ts = require("typescript");
+const K = ts.SyntaxKind;
+
+const path = process.argv[2];
+if (!path) {
+  console.error("usage: node scripts/ts-tokens.mjs <file>");
+  process.exit(2);
+}
+const text = readFileSync(path, "utf8");
+const variant = /\.[mc]?ts$/.test(path)
+  ? ts.LanguageVariant.Standard
+  : ts.LanguageVariant.JSX;
+const scanner = ts.createScanner(ts.ScriptTarget.Latest, true, variant, text);
+
+// The enum's reverse mapping names some kinds after markers such as
+// `FirstPunctuation`, so build one from the first real name of each value.
+const names = new Map();
+for (const [name, value] of Object.entries(K)) {
+  if (typeof value === "number" && !/^(First|Last)/.test(name) && !names.has(value)) {
+    names.set(value, name);
+  }
+}
+
+const noRegexAfter = new Set([
+  K.Identifier,
+  K.PrivateIdentifier,
+  K.NumericLiteral,
+  K.BigIntLiteral,
+  K.StringLiteral,
+  K.RegularExpressionLiteral,
+  K.NoSubstitutionTemplateLiteral,
+  K.TemplateTail,
+  K.CloseParenToken,
+  K.CloseBracketToken,
+  K.CloseBraceToken,
+  K.PlusPlusToken,
+  K.MinusMinusToken,
+]);
+const regexAfterKeyword = new Set([
+  K.ReturnKeyword,
+  K.TypeOfKeyword,
+  K.InstanceOfKeyword,
+  K.InKeyword,
+  K.OfKeyword,
+  K.NewKeyword,
+  K.DeleteKeyword,
+  K.VoidKeyword,
+  K.ThrowKeyword,
+  K.CaseKeyword,
+  K.DoKeyword,
+  K.ElseKeyword,
+  K.YieldKeyword,
+  K.AwaitKeyword,
+]);
+const isKeyword = (kind) => kind >= K.FirstKeyword && kind <= K.LastKeyword;
+const regexMayFollow = (kind) =>
+  !noRegexAfter.has(kind) && (regexAfterKeyword.has(kind) || !isKeyword(kind));
+
+const start = () => (scanner.getTokenStart ? scanner.getTokenStart() : scanner.getTokenPos());
+
+// One entry per open `{`: whether it is a template's `${`.
+const braces = [];
+let regexAllowed = true;
+const lines = [`# typescript ${ts.version}`];
+for (let kind = scanner.scan(); kind !== K.EndOfFileToken; kind = scanner.scan()) {
+  if ((kind === K.SlashToken || kind === K.SlashEqualsToken) && regexAllowed) {
+    kind = scanner.reScanSlashToken();
+  } else if (kind === K.GreaterThanToken) {
+    kind = scanner.reScanGreaterToken();
+  } else if (kind === K.CloseBraceToken && braces.pop()) {
+    kind = scanner.reScanTemplateToken(false);
+  }
+
+  if (kind === K.OpenBraceToken) {
+    braces.push(false);
+  } else if (kind === K.TemplateHead || kind === K.TemplateMiddle) {
+    braces.push(true);
+  }
+  regexAllowed = regexMayFollow(kind);
+
+  lines.push(
+    [names.get(kind), start(), JSON.stringify(scanner.getTokenText()), scanner.getTokenFlags()].join("\t"),
+  );
+}
+console.log(lines.join("\n"));
@@ -0,0 +1,912 @@
+//! `rhi grammar`: tools for the EBNF grammars in `docs/`.
+
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+use rhi::ebnf::convert::{self, Format};
+use rhi::ebnf::coverage::Coverage;
+use rhi::ebnf::diagram;
+use rhi::ebnf::generate::Generator;
+use rhi::ebnf::lint::{self, Check, Lint};
+use rhi::ebnf::{Dialect, Grammar};
+use rhi::{ColumnEncoding, SourceFile};
+
+use crate::tokens::{read, variant};
+use crate::{EXIT_DIAGNOSTICS, EXIT_FAILURE};
+
+const USAGE: &str = "\
+usage: rhi grammar <command> [options]
+
+commands:
+  lint      report mistakes in ISO or W3C EBNF grammars
+  generate  print random sentences of a grammar
+  convert   write a grammar in another dialect, or as ABNF
+  diagram   draw productions as SVG railroad diagrams
+  coverage  report productions that sources never use
+
+Run `rhi grammar <command> --help` for a command's options.
+";
+
+const LINT_USAGE: &str = "\
+usage: rhi grammar lint [options] <file>... (`-` reads stdin)
+
+options:
+  --start <name>    a production the language starts from; may be repeated,
+                    and defaults to the first production
+  --allow <check>   do not report a check; may be repeated
+  --compare <file>  also report productions that differ from this grammar
+  -h, --help        print this help
+
+Checks: undefined, duplicate-production and empty-grammar are errors;
+unused, unreachable, duplicate-alternative, left-recursion, region and
+mismatch are warnings. The dialect is W3C if the file contains `::=`, and
+ISO otherwise. Exits with 1 if anything was reported, and 2 for bad
+arguments or unreadable files.
+";
+
+const GENERATE_USAGE: &str = "\
+usage: rhi grammar generate [options] <file>... (`-` reads stdin)
+
+options:
+  --start <name>        the production to generate; defaults to the first
+  --count <n>           how many sentences to print, one per line (default 1)
+  --seed <n>            seed for the random choices (default 0)
+  --depth <n>           how deeply productions may nest (default 60)
+  --size <n>            roughly how many productions a sentence expands
+                        (default 1000)
+  --weight <name>=<w>   weight alternatives that use a production; may be
+                        repeated, and 0 avoids it where anything else fits
+  --value <name>=<text> generate only the given texts for a production;
+                        may be repeated
+  --lexical <name>      generate a production as one token; may be repeated
+  -h, --help            print this help
+
+A production defined in more than one file is taken from the first, so a
+grammar that extends another comes before it. Exits with 2 for bad
+arguments, unreadable files or grammars that cannot be generated.
+";
+
+const CONVERT_USAGE: &str = "\
+usage: rhi grammar convert [options] --to <format> <file> (`-` reads stdin)
+
+options:
+  --from <dialect>  iso or w3c; defaults to W3C if the file contains `::=`,
+                    and ISO otherwise
+  --to <format>     iso (one production to a line), iso-pretty (one
+                    alternative to a line), w3c or abnf
+  -h, --help        print this help
+
+Comments, blank lines and parentheses are kept, so converting to the other
+dialect and back gives the text started from. Exits with 1 for grammars
+that cannot be parsed or have no form in the target, and 2 for bad
+arguments or unreadable files.
+";
+
+const DIAGRAM_USAGE: &str = "\
+usage: rhi grammar diagram [options] <file> (`-` reads stdin)
+
+options:
+  --production <name>  draw a production; may be repeated, and defaults to
+                       all of them
+  --output <dir>       write each diagram to <dir>/<name>.svg
+  -h, --help           print this help
+
+Without --output, the one production given is printed. Nonterminals link
+to the diagrams of their productions. Exits with 1 for grammars that
+cannot be parsed, and 2 for bad arguments, unknown productions or files
+that cannot be read or written.
+";
+
+const COVERAGE_USAGE: &str = "\
+usage: rhi grammar coverage [options] --grammar <file>... <source>...
+
+options:
+  --grammar <file>  a grammar to measure; may be repeated, and a grammar
+                    that extends another comes before it
+  --html <file>     also write the grammars to an HTML page, with what was
+                    never used highlighted
+  -h, --help        print this help
+
+Sources are lexed, and each lexical production the lexer matches, such as
+`StringLiteral` for a string or `HexEscapeSequence` for an escape in it,
+is derived from the grammar down to the characters. Directories are
+searched for .js, .mjs, .cjs, .jsx, .ts, .mts, .cts and .tsx files.
+Productions and alternatives that were never used are listed, and text
+the lexer matched as a production that the grammar does not match is
+reported as a warning. Syntactic productions are only used once a parser
+records them. Exits with 2 for bad arguments or unreadable files.
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    match args.first().map(String::as_str) {
+        Some("lint") => run_lint(&args[1..]),
+        Some("generate") => run_generate(&args[1..]),
+        Some("convert") => run_convert(&args[1..]),
+        Some("diagram") => run_diagram(&args[1..]),
+        Some("coverage") => run_coverage(&args[1..]),
+        Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Some(command) => {
+            eprintln!("rhi grammar: unknown command '{}'", command);
+            eprint!("{}", USAGE);
+            ExitCode::from(EXIT_FAILURE)
+        }
+        None => {
+            eprint!("{}", USAGE);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LintOptions {
+    starts: Vec<String>,
+    allowed: Vec<Check>,
+    compare: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_lint_args(args: &[String]) -> Result<LintOptions, String> {
+    let mut options = LintOptions {
+        starts: Vec::new(),
+        allowed: Vec::new(),
+        compare: None,
+        files: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        match name {
+            "--start" => options.starts.push(value(name)?),
+            "--allow" => {
+                let value = value(name)?;
+                let check = Check::from_name(&value).ok_or(format!("unknown check '{}'", value))?;
+                options.allowed.push(check);
+            }
+            "--compare" => options.compare = Some(value(name)?),
+            "-" => options.files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    Ok(options)
+}
+
+fn run_lint(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", LINT_USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_lint_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi grammar lint: {}", message);
+            eprint!("{}", LINT_USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut failed = false;
+    let mut reported = false;
+
+    let compare = match options.compare.as_deref().map(load).transpose() {
+        Ok(compare) => compare,
+        Err(message) => {
+            eprintln!("rhi grammar lint: {}", message);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    let compare = match &compare {
+        Some((source, Ok(grammar))) => Some((source, grammar)),
+        Some((source, Err(e))) => {
+            let _ = report(&mut out, source, e.span.start, "error", &e.message);
+            let _ = out.flush();
+            return ExitCode::from(EXIT_DIAGNOSTICS);
+        }
+        None => None,
+    };
+
+    let starts: Vec<&str> = options.starts.iter().map(String::as_str).collect();
+    for path in &options.files {
+        let (source, grammar) = match load(path) {
+            Ok(loaded) => loaded,
+            Err(message) => {
+                eprintln!("rhi grammar lint: {}", message);
+                failed = true;
+                continue;
+            }
+        };
+        let grammar = match grammar {
+            Ok(grammar) => grammar,
+            Err(e) => {
+                reported = true;
+                let _ = report(&mut out, &source, e.span.start, "error", &e.message);
+                continue;
+            }
+        };
+
+        let mut lints = lint::lint(&grammar, &starts);
+        if let Some((other_source, other)) = compare {
+            lints.extend(lint::compare(&grammar, other, other_source.name()));
+            lints.sort_by_key(|lint| lint.span.start);
+        }
+        for lint in lints
+            .iter()
+            .filter(|lint| !options.allowed.contains(&lint.check))
+        {
+            reported = true;
+            let related = match lint.check {
+                Check::Mismatch => compare.map(|(other_source, _)| other_source),
+                _ => Some(&source),
+            };
+            if print_lint(&mut out, &source, related, lint).is_err() {
+                break;
+            }
+        }
+    }
+    let _ = out.flush();
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else if reported {
+        ExitCode::from(EXIT_DIAGNOSTICS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[derive(Debug)]
+struct GenerateOptions {
+    start: Option<String>,
+    count: usize,
+    seed: u64,
+    depth: Option<usize>,
+    size: Option<usize>,
+    weights: Vec<(String, u32)>,
+    values: Vec<(String, Vec<String>)>,
+    lexical: Vec<String>,
+    files: Vec<String>,
+}
+
+fn parse_generate_args(args: &[String]) -> Result<GenerateOptions, String> {
+    let mut options = GenerateOptions {
+        start: None,
+        count: 1,
+        seed: 0,
+        depth: None,
+        size: None,
+        weights: Vec::new(),
+        values: Vec::new(),
+        lexical: Vec::new(),
+        files: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        fn number<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("{} must be a number, not '{}'", name, value))
+        }
+        fn pair(name: &str, value: String) -> Result<(String, String), String> {
+            match value.split_once('=') {
+                Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+                _ => Err(format!("{} must be <name>=<value>, not '{}'", name, value)),
+            }
+        }
+        match name {
+            "--start" => options.start = Some(value(name)?),
+            "--count" => options.count = number(name, value(name)?)?,
+            "--seed" => options.seed = number(name, value(name)?)?,
+            "--depth" => options.depth = Some(number(name, value(name)?)?),
+            "--size" => options.size = Some(number(name, value(name)?)?),
+            "--weight" => {
+                let (key, weight) = pair(name, value(name)?)?;
+                options.weights.push((key, number(name, weight)?));
+            }
+            "--value" => {
+                let (key, text) = pair(name, value(name)?)?;
+                match options.values.iter_mut().find(|(other, _)| *other == key) {
+                    Some((_, texts)) => texts.push(text),
+                    None => options.values.push((key, vec![text])),
+                }
+            }
+            "--lexical" => options.lexical.push(value(name)?),
+            "-" => options.files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    Ok(options)
+}
+
+fn run_generate(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", GENERATE_USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_generate_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi grammar generate: {}", message);
+            eprint!("{}", GENERATE_USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let mut grammars = Vec::new();
+    for path in &options.files {
+        match load(path) {
+            Ok((_, Ok(grammar))) => grammars.push(grammar),
+            Ok((source, Err(e))) => {
+                let _ = report(
+                    &mut io::stderr(),
+                    &source,
+                    e.span.start,
+                    "error",
+                    &e.message,
+                );
+                return ExitCode::from(EXIT_FAILURE);
+            }
+            Err(message) => {
+                eprintln!("rhi grammar generate: {}", message);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    }
+    let Some(start) = options
+        .start
+        .clone()
+        .or_else(|| Some(grammars[0].productions.first()?.name.clone()))
+    else {
+        eprintln!("rhi grammar generate: {}: no productions", options.files[0]);
+        return ExitCode::from(EXIT_FAILURE);
+    };
+
+    let grammars: Vec<&Grammar> = grammars.iter().collect();
+    let mut generator = Generator::new(&grammars).seed(options.seed);
+    if let Some(depth) = options.depth {
+        generator = generator.max_depth(depth);
+    }
+    if let Some(size) = options.size {
+        generator = generator.max_size(size);
+    }
+    for (name, weight) in &options.weights {
+        generator = generator.weight(name, *weight);
+    }
+    for (name, texts) in &options.values {
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        generator = generator.values(name, &texts);
+    }
+    for name in &options.lexical {
+        generator = generator.lexical(name);
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for _ in 0..options.count {
+        match generator.generate(&start) {
+            Ok(text) => {
+                if writeln!(out, "{}", text).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                let _ = out.flush();
+                eprintln!("rhi grammar generate: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    }
+    let _ = out.flush();
+    ExitCode::SUCCESS
+}
+
+#[derive(Debug)]
+struct ConvertOptions {
+    from: Option<Dialect>,
+    to: Format,
+    file: String,
+}
+
+fn parse_convert_args(args: &[String]) -> Result<ConvertOptions, String> {
+    let mut from = None;
+    let mut to = None;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        match name {
+            "--from" => {
+                from = Some(match value(name)?.as_str() {
+                    "iso" => Dialect::Iso,
+                    "w3c" => Dialect::W3c,
+                    other => return Err(format!("unknown dialect '{}'", other)),
+                })
+            }
+            "--to" => {
+                let value = value(name)?;
+                to = Some(Format::from_name(&value).ok_or(format!("unknown format '{}'", value))?);
+            }
+            "-" => files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    let to = to.ok_or("--to is required")?;
+    match <[String; 1]>::try_from(files) {
+        Ok([file]) => Ok(ConvertOptions { from, to, file }),
+        Err(files) if files.is_empty() => Err("no input file".to_string()),
+        Err(_) => Err("only one file can be converted at a time".to_string()),
+    }
+}
+
+fn run_convert(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", CONVERT_USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_convert_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi grammar convert: {}", message);
+            eprint!("{}", CONVERT_USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let source = match read(&options.file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("rhi grammar convert: {}: {}", options.file, e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    let dialect = options
+        .from
+        .unwrap_or_else(|| Dialect::detect(source.source()));
+    let converted = Grammar::parse(source.source(), dialect)
+        .map_err(|e| (e.span, e.message))
+        .and_then(|grammar| {
+            convert::convert(&grammar, source.source(), options.to).map_err(|e| (e.span, e.message))
+        });
+    match converted {
+        Ok(text) => {
+            print!("{}", text);
+            ExitCode::SUCCESS
+        }
+        Err((span, message)) => {
+            let _ = report(&mut io::stderr(), &source, span.start, "error", &message);
+            ExitCode::from(EXIT_DIAGNOSTICS)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DiagramOptions {
+    productions: Vec<String>,
+    output: Option<String>,
+    file: String,
+}
+
+fn parse_diagram_args(args: &[String]) -> Result<DiagramOptions, String> {
+    let mut productions = Vec::new();
+    let mut output = None;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        match name {
+            "--production" => productions.push(value(name)?),
+            "--output" => output = Some(value(name)?),
+            "-" => files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if output.is_none() && productions.len() != 1 {
+        return Err("without --output, give exactly one --production".to_string());
+    }
+    match <[String; 1]>::try_from(files) {
+        Ok([file]) => Ok(DiagramOptions {
+            productions,
+            output,
+            file,
+        }),
+        Err(files) if files.is_empty() => Err("no input file".to_string()),
+        Err(_) => Err("only one file can be drawn at a time".to_string()),
+    }
+}
+
+fn run_diagram(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", DIAGRAM_USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_diagram_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi grammar diagram: {}", message);
+            eprint!("{}", DIAGRAM_USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let grammar = match load(&options.file) {
+        Ok((_, Ok(grammar))) => grammar,
+        Ok((source, Err(e))) => {
+            let _ = report(
+                &mut io::stderr(),
+                &source,
+                e.span.start,
+                "error",
+                &e.message,
+            );
+            return ExitCode::from(EXIT_DIAGNOSTICS);
+        }
+        Err(message) => {
+            eprintln!("rhi grammar diagram: {}", message);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    let productions = if options.productions.is_empty() {
+        grammar.productions.iter().collect()
+    } else {
+        let mut productions = Vec::new();
+        for name in &options.productions {
+            match grammar.production(name) {
+                Some(production) => productions.push(production),
+                None => {
+                    eprintln!("rhi grammar diagram: '{}' is not defined", name);
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+            }
+        }
+        productions
+    };
+
+    let Some(dir) = &options.output else {
+        print!("{}", diagram::diagram(productions[0]));
+        return ExitCode::SUCCESS;
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        eprintln!("rhi grammar diagram: {}: {}", dir, e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    for production in productions {
+        let path = std::path::Path::new(dir).join(format!("{}.svg", production.name));
+        if let Err(e) = std::fs::write(&path, diagram::diagram(production)) {
+            eprintln!("rhi grammar diagram: {}: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[derive(Debug)]
+struct CoverageOptions {
+    grammars: Vec<String>,
+    html: Option<String>,
+    sources: Vec<String>,
+}
+
+fn parse_coverage_args(args: &[String]) -> Result<CoverageOptions, String> {
+    let mut options = CoverageOptions {
+        grammars: Vec::new(),
+        html: None,
+        sources: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        match name {
+            "--grammar" => options.grammars.push(value(name)?),
+            "--html" => options.html = Some(value(name)?),
+            "-" => options.sources.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.sources.push(arg.clone()),
+        }
+    }
+
+    if options.grammars.is_empty() {
+        return Err("no grammars".to_string());
+    }
+    if options.sources.is_empty() {
+        return Err("no sources".to_string());
+    }
+    Ok(options)
+}
+
+fn run_coverage(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", COVERAGE_USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_coverage_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi grammar coverage: {}", message);
+            eprint!("{}", COVERAGE_USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let mut sources = Vec::new();
+    let mut grammars = Vec::new();
+    for path in &options.grammars {
+        match load(path) {
+            Ok((source, Ok(grammar))) => {
+                sources.push(source);
+                grammars.push(grammar);
+            }
+            Ok((source, Err(e))) => {
+                let _ = report(
+                    &mut io::stderr(),
+                    &source,
+                    e.span.start,
+                    "error",
+                    &e.message,
+                );
+                return ExitCode::from(EXIT_FAILURE);
+            }
+            Err(message) => {
+                eprintln!("rhi grammar coverage: {}", message);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    }
+    let mut files = Vec::new();
+    for path in &options.sources {
+        if let Err(e) = source_files(path, &mut files) {
+            eprintln!("rhi grammar coverage: {}: {}", path, e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    }
+
+    let grammar_refs: Vec<&Grammar> = grammars.iter().collect();
+    let mut coverage = Coverage::new(&grammar_refs);
+    for path in &files {
+        match read(path) {
+            Ok(source) => coverage.record_tokens(source.source(), variant(path, false, false)),
+            Err(e) => {
+                eprintln!("rhi grammar coverage: {}: {}", path, e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let _ = print_coverage(&mut out, &coverage, &sources);
+    let _ = out.flush();
+
+    if let Some(path) = &options.html {
+        let names: Vec<(&str, &str)> = sources
+            .iter()
+            .map(|source| (source.name(), source.source()))
+            .collect();
+        if let Err(e) = std::fs::write(path, coverage.html(&names)) {
+            eprintln!("rhi grammar coverage: {}: {}", path, e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// `path`, or the JavaScript and TypeScript files under it, in order.
+fn source_files(path: &str, files: &mut Vec<String>) -> io::Result<()> {
+    if path == "-" || !std::fs::metadata(path)?.is_dir() {
+        files.push(path.to_string());
+        return Ok(());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for entry in entries {
+        let Some(entry) = entry.to_str() else {
+            continue;
+        };
+        let extension = std::path::Path::new(entry)
+            .extension()
+            .and_then(|e| e.to_str());
+        if std::fs::metadata(entry)?.is_dir() {
+            source_files(entry, files)?;
+        } else if matches!(
+            extension,
+            Some("js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx")
+        ) {
+            files.push(entry.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn print_coverage(
+    out: &mut impl Write,
+    coverage: &Coverage,
+    sources: &[SourceFile],
+) -> io::Result<()> {
+    for (grammar, source) in sources.iter().enumerate() {
+        let summary = coverage.summary(grammar);
+        writeln!(
+            out,
+            "{}: {} of {} productions used, {} of {} alternatives",
+            source.name(),
+            summary.covered_productions,
+            summary.productions,
+            summary.covered_alternatives,
+            summary.alternatives,
+        )?;
+    }
+    if coverage.skipped() > 0 {
+        writeln!(
+            out,
+            "{} tokens were too long to match, and were skipped",
+            coverage.skipped()
+        )?;
+    }
+    for production in coverage.productions() {
+        let source = &sources[production.grammar];
+        let name = &production.production.name;
+        if production.uses == 0 {
+            let message = format!("'{}' is never used", name);
+            report(
+                out,
+                source,
+                production.production.span.start,
+                "note",
+                &message,
+            )?;
+            continue;
+        }
+        if production.alternatives.len() < 2 {
+            continue;
+        }
+        for (i, (expr, uses)) in production.alternatives.iter().enumerate() {
+            if *uses == 0 {
+                let message = format!("alternative {} of '{}' is never used", i + 1, name);
+                report(out, source, expr.span.start, "note", &message)?;
+            }
+        }
+    }
+    for mismatch in coverage.mismatches() {
+        let source = &sources[mismatch.grammar];
+        let offset = coverage
+            .productions()
+            .iter()
+            .find(|p| p.grammar == mismatch.grammar && p.production.name == mismatch.production)
+            .map_or(0, |p| p.production.span.start);
+        let message = format!(
+            "'{}' does not match what the lexer matched as it {} time{}, in {:?} tokens, \
+             such as {:?}",
+            mismatch.production,
+            mismatch.count,
+            if mismatch.count == 1 { "" } else { "s" },
+            mismatch.token_type,
+            mismatch.sample,
+        );
+        report(out, source, offset, "warning", &message)?;
+    }
+    Ok(())
+}
+
+/// Reads and parses a grammar, failing only if it cannot be read.
+fn load(path: &str) -> Result<(SourceFile, Result<Grammar, rhi::ebnf::ParseError>), String> {
+    let source = read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let grammar = Grammar::parse(source.source(), Dialect::detect(source.source()));
+    Ok((source, grammar))
+}
+
+fn print_lint(
+    out: &mut impl Write,
+    source: &SourceFile,
+    related_source: Option<&SourceFile>,
+    lint: &Lint,
+) -> io::Result<()> {
+    let message = format!("{} [{}]", lint.message, lint.check.name());
+    report(
+        out,
+        source,
+        lint.span.start,
+        lint.check.severity().as_str(),
+        &message,
+    )?;
+    if let (Some(span), Some(related_source)) = (&lint.related, related_source) {
+        let note = match lint.check {
+            Check::DuplicateProduction => "first defined here",
+            Check::DuplicateAlternative => "first listed here",
+            _ => "defined here",
+        };
+        report(out, related_source, span.start, "note", note)?;
+    }
+    Ok(())
+}
+
+fn report(
+    out: &mut impl Write,
+    source: &SourceFile,
+    offset: usize,
+    severity: &str,
+    message: &str,
+) -> io::Result<()> {
+    let position = source.position(offset, ColumnEncoding::Utf16);
+    writeln!(
+        out,
+        "{}:{}:{}: {}: {}",
+        source.name(),
+        position.line,
+        position.column,
+        severity,
+        message
+    )
+}
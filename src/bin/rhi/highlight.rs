@@ -0,0 +1,198 @@
+//! `rhi highlight`: prints input files with syntax highlighting.
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+use rhi::{Highlighter, Theme};
+
+use crate::EXIT_FAILURE;
+use crate::tokens::{read, variant};
+
+const USAGE: &str = "\
+usage: rhi highlight [options] <file>... (`-` reads stdin)
+
+options:
+  --format <format>  ansi (default) for terminals, or html
+  --theme <file>     styles to use instead of the defaults, one class per
+                     line: name, SGR parameters (`-` for none), then CSS
+  --standalone       with --format html, write a whole page with its
+                     stylesheet rather than a <pre> element per file
+  --jsx              scan JSX even in TypeScript
+  --ts               treat the input as TypeScript
+  -h, --help         print this help
+
+Classes: keyword, contextual-keyword, number, string, regex, template,
+comment, doc-tag, punctuation, jsx-tag, jsx-attribute, jsx-text, invalid.
+Text the lexer rejects is shown as invalid rather than reported. Exits with 2
+for bad arguments or unreadable files.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ansi,
+    Html,
+}
+
+#[derive(Debug)]
+struct Options {
+    format: Format,
+    theme: Option<String>,
+    standalone: bool,
+    jsx: bool,
+    ts: bool,
+    files: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Ansi,
+        theme: None,
+        standalone: false,
+        jsx: false,
+        ts: false,
+        files: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| match value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .cloned()
+                .ok_or(format!("{} needs a value", name)),
+        };
+        match name {
+            "--format" => {
+                let value = value(name)?;
+                options.format = match value.as_str() {
+                    "ansi" => Format::Ansi,
+                    "html" => Format::Html,
+                    _ => return Err(format!("unknown format '{}'", value)),
+                };
+            }
+            "--theme" => options.theme = Some(value(name)?),
+            "--standalone" => options.standalone = true,
+            "--jsx" => options.jsx = true,
+            "--ts" => options.ts = true,
+            "-" => options.files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    if options.standalone && options.format != Format::Html {
+        return Err("--standalone needs --format html".to_string());
+    }
+    Ok(options)
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi highlight: {}", message);
+            eprint!("{}", USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    let theme = match options.theme.as_deref().map(load_theme).transpose() {
+        Ok(theme) => theme.unwrap_or_default(),
+        Err(message) => {
+            eprintln!("rhi highlight: {}", message);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut failed = false;
+
+    // A broken pipe just means nobody is reading any more.
+    if options.standalone {
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <style>\n{}</style>\n</head>\n<body>\n",
+            theme.css()
+        );
+    }
+    for path in &options.files {
+        let source = match read(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("rhi highlight: {}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+        let highlighter = Highlighter::new()
+            .variant(variant(path, options.jsx, options.ts))
+            .theme(theme.clone());
+        let result = match options.format {
+            Format::Ansi => write!(out, "{}", highlighter.ansi(source.source())),
+            Format::Html => writeln!(
+                out,
+                "<pre class=\"rhi\"><code>{}</code></pre>",
+                highlighter.html(source.source())
+            ),
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    if options.standalone {
+        let _ = writeln!(out, "</body>\n</html>");
+    }
+    let _ = out.flush();
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn load_theme(path: &str) -> Result<Theme, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Theme::parse(&text).map_err(|message| format!("{}: {}", path, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_args() {
+        let args =
+            |args: &[&str]| parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+
+        let options = args(&["--format=html", "--theme", "t.txt", "--standalone", "a.js"]).unwrap();
+        assert_eq!(options.format, Format::Html);
+        assert_eq!(options.theme.as_deref(), Some("t.txt"));
+        assert!(options.standalone);
+        assert_eq!(options.files, ["a.js"]);
+
+        assert_eq!(
+            args(&["--standalone", "a.js"]).unwrap_err(),
+            "--standalone needs --format html"
+        );
+        assert_eq!(args(&["--theme"]).unwrap_err(), "--theme needs a value");
+        assert_eq!(
+            args(&["--format", "svg", "a.js"]).unwrap_err(),
+            "unknown format 'svg'"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! `rhi lsp`: runs the language server on stdin and stdout.
+
+use std::io;
+use std::process::ExitCode;
+
+use crate::EXIT_FAILURE;
+
+const USAGE: &str = "\
+usage: rhi lsp [--stdio]
+
+Runs a language server for JavaScript and TypeScript, speaking the Language
+Server Protocol on stdin and stdout. It reports lexer errors and unbalanced
+brackets, and answers requests for semantic tokens, folding ranges and
+document symbols. `--stdio`, which editors pass, is the only transport.
+
+Exits with 0 after a shutdown request and an exit notification, with 1 on an
+exit without a shutdown, and with 2 for bad arguments or broken streams.
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if let Some(arg) = args.iter().find(|arg| *arg != "--stdio") {
+        eprintln!("rhi lsp: unknown option '{}'", arg);
+        eprint!("{}", USAGE);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    match rhi::lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("rhi lsp: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! The `rhi` command-line tool.
+
+mod grammar;
+mod highlight;
+mod json;
+mod lsp;
+mod tokens;
+
+use std::env;
+use std::process::ExitCode;
+
+/// Exit status when a file had lexer diagnostics.
+const EXIT_DIAGNOSTICS: u8 = 1;
+/// Exit status for bad arguments and unreadable files.
+const EXIT_FAILURE: u8 = 2;
+
+const USAGE: &str = "\
+usage: rhi <command> [options]
+
+commands:
+  tokens     print the tokens of JavaScript and TypeScript files
+  highlight  print files with syntax highlighting, for terminals or HTML
+  lsp        run a language server on stdin and stdout
+  grammar    check the EBNF grammars the lexer is written from
+
+Run `rhi <command> --help` for a command's options.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("tokens") => tokens::run(&args[1..]),
+        Some("highlight") => highlight::run(&args[1..]),
+        Some("lsp") => lsp::run(&args[1..]),
+        Some("grammar") => grammar::run(&args[1..]),
+        Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Some(command) => {
+            eprintln!("rhi: unknown command '{}'", command);
+            eprint!("{}", USAGE);
+            ExitCode::from(EXIT_FAILURE)
+        }
+        None => {
+            eprint!("{}", USAGE);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
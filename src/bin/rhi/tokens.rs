@@ -0,0 +1,426 @@
+//! `rhi tokens`: prints the tokens of each input file.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use rhi::{
+    ColumnEncoding, LanguageVariant, Lexer, LexerError, Renderer, SourceFile, Token, typescript,
+};
+
+use crate::json;
+use crate::{EXIT_DIAGNOSTICS, EXIT_FAILURE};
+
+const USAGE: &str = "\
+usage: rhi tokens [options] <file>... (`-` reads stdin)
+
+options:
+  --format <format>  text (default), json, jsonl, table, or typescript for
+                     the format of scripts/ts-tokens.mjs
+  --trivia           include comments, whitespace and line breaks
+  --jsx              scan JSX even in TypeScript; JavaScript, .jsx and .tsx
+                     files always allow it
+  --ts               treat the input as TypeScript, which has no JSX without
+                     --jsx; implied by .ts, .mts, .cts and .tsx files
+  -h, --help         print this help
+
+Lexer diagnostics are printed to stderr. Exits with 1 if there were any, and
+with 2 for bad arguments or unreadable files.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Jsonl,
+    Table,
+    TypeScript,
+}
+
+#[derive(Debug)]
+struct Options {
+    format: Format,
+    trivia: bool,
+    jsx: bool,
+    ts: bool,
+    files: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Text,
+        trivia: false,
+        jsx: false,
+        ts: false,
+        files: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        match name {
+            "--format" => {
+                let value = match value {
+                    Some(value) => value,
+                    None => args.next().ok_or("--format needs a value")?.clone(),
+                };
+                options.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "jsonl" => Format::Jsonl,
+                    "table" => Format::Table,
+                    "typescript" => Format::TypeScript,
+                    _ => return Err(format!("unknown format '{}'", value)),
+                };
+            }
+            "--trivia" => options.trivia = true,
+            "--jsx" => options.jsx = true,
+            "--ts" => options.ts = true,
+            "-" => options.files.push(arg.clone()),
+            _ if name.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    Ok(options)
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("rhi tokens: {}", message);
+            eprint!("{}", USAGE);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let renderer = Renderer::for_stderr();
+    let mut failed = false;
+    let mut diagnostics = false;
+    // Whether a JSON object was written, so the next one needs a comma.
+    let mut wrote_any = false;
+
+    if options.format == Format::Json {
+        // A broken pipe just means nobody is reading any more.
+        let _ = writeln!(out, "[");
+    }
+    for path in &options.files {
+        let source = match read(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("rhi tokens: {}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let variant = variant(path, options.jsx, options.ts);
+        let (tokens, errors) = lex(&source, variant, options.trivia);
+        for error in &errors {
+            eprint!(
+                "{}",
+                source.render(&renderer, &error.to_diagnostic(source.source()))
+            );
+        }
+        diagnostics |= !errors.is_empty();
+
+        let result = match options.format {
+            Format::Text => write_text(&mut out, &source, &tokens),
+            Format::Jsonl => write_jsonl(&mut out, &source, &tokens),
+            Format::Table => write_table(&mut out, &source, &tokens, options.files.len() > 1),
+            Format::Json => write_json(&mut out, &source, &tokens, &errors, !wrote_any),
+            Format::TypeScript => {
+                if options.files.len() > 1 {
+                    let _ = writeln!(out, "# {}", source.name());
+                }
+                write!(out, "{}", typescript::dump(source.source(), variant))
+            }
+        };
+        if result.is_err() {
+            break;
+        }
+        wrote_any = true;
+    }
+    if options.format == Format::Json {
+        let _ = writeln!(out, "\n]");
+    }
+    let _ = out.flush();
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else if diagnostics {
+        ExitCode::from(EXIT_DIAGNOSTICS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+pub fn read(path: &str) -> io::Result<SourceFile> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(SourceFile::new("<stdin>", text))
+    } else {
+        Ok(SourceFile::new(path, fs::read_to_string(path)?))
+    }
+}
+
+/// JSX is allowed unless the input is TypeScript, where `<` in expression
+/// position is a type assertion, as TypeScript decides by file extension.
+pub fn variant(path: &str, jsx: bool, ts: bool) -> LanguageVariant {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str());
+    let ts = ts || matches!(extension, Some("ts" | "mts" | "cts" | "tsx"));
+    if jsx || !ts || matches!(extension, Some("tsx")) {
+        LanguageVariant::Jsx
+    } else {
+        LanguageVariant::Standard
+    }
+}
+
+/// The tokens of `source`, without comments unless `trivia` is set, in
+/// which case the whitespace in between is included as well.
+fn lex(
+    source: &SourceFile,
+    variant: LanguageVariant,
+    trivia: bool,
+) -> (Vec<Token>, Vec<LexerError>) {
+    let mut lexer = Lexer::new(source.source());
+    lexer.set_language_variant(variant);
+    if trivia {
+        return lexer.lex_with_trivia();
+    }
+
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for result in lexer.lex() {
+        match result {
+            Ok(token) if !token.token_type.is_comment() => tokens.push(token),
+            Ok(_) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    (tokens, errors)
+}
+
+fn position(source: &SourceFile, offset: u32) -> (usize, usize) {
+    let position = source.position(offset as usize, ColumnEncoding::Utf16);
+    (position.line, position.column)
+}
+
+fn flag_names(token: &Token) -> Vec<&'static str> {
+    token.flags.names().collect()
+}
+
+fn write_text(out: &mut impl Write, source: &SourceFile, tokens: &[Token]) -> io::Result<()> {
+    for token in tokens {
+        let (line, column) = position(source, token.start);
+        write!(
+            out,
+            "{}:{}:{} {:?} {}..{} {:?}",
+            source.name(),
+            line,
+            column,
+            token.token_type,
+            token.start,
+            token.end,
+            source.text(token)
+        )?;
+        if !token.flags.is_empty() {
+            write!(out, " [{}]", flag_names(token).join(", "))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_table(
+    out: &mut impl Write,
+    source: &SourceFile,
+    tokens: &[Token],
+    header: bool,
+) -> io::Result<()> {
+    let rows: Vec<[String; 5]> = tokens
+        .iter()
+        .map(|token| {
+            let (line, column) = position(source, token.start);
+            [
+                format!("{}:{}", line, column),
+                format!("{}..{}", token.start, token.end),
+                format!("{:?}", token.token_type),
+                format!("{:?}", source.text(token)),
+                flag_names(token).join(", "),
+            ]
+        })
+        .collect();
+    let titles = ["POSITION", "SPAN", "KIND", "TEXT", "FLAGS"];
+    let mut widths = titles.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    if header {
+        writeln!(out, "==> {} <==", source.name())?;
+    }
+    let mut write_row = |cells: [&str; 5]| -> io::Result<()> {
+        let mut line = String::new();
+        for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            if i + 1 == cells.len() {
+                line.push_str(cell);
+            } else {
+                write!(line, "{:width$}  ", cell, width = width).unwrap();
+            }
+        }
+        writeln!(out, "{}", line.trim_end())
+    };
+    write_row(titles)?;
+    for row in &rows {
+        write_row(row.each_ref().map(String::as_str))?;
+    }
+    Ok(())
+}
+
+/// A token as a JSON object, with `file` first if given.
+fn token_json(source: &SourceFile, token: &Token, file: bool) -> String {
+    let mut out = String::from("{");
+    if file {
+        out.push_str("\"file\":");
+        json::write_string(&mut out, source.name());
+        out.push(',');
+    }
+    let (line, column) = position(source, token.start);
+    out.push_str("\"kind\":");
+    json::write_string(&mut out, &format!("{:?}", token.token_type));
+    write!(
+        out,
+        ",\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"text\":",
+        token.start, token.end, line, column
+    )
+    .unwrap();
+    json::write_string(&mut out, source.text(token));
+    out.push_str(",\"flags\":[");
+    for (i, name) in token.flags.names().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json::write_string(&mut out, name);
+    }
+    out.push_str("]}");
+    out
+}
+
+fn write_jsonl(out: &mut impl Write, source: &SourceFile, tokens: &[Token]) -> io::Result<()> {
+    for token in tokens {
+        writeln!(out, "{}", token_json(source, token, true))?;
+    }
+    Ok(())
+}
+
+fn write_json(
+    out: &mut impl Write,
+    source: &SourceFile,
+    tokens: &[Token],
+    errors: &[LexerError],
+    first: bool,
+) -> io::Result<()> {
+    let mut text = String::new();
+    if !first {
+        text.push_str(",\n");
+    }
+    text.push_str("  {\n    \"file\": ");
+    json::write_string(&mut text, source.name());
+    text.push_str(",\n    \"tokens\": [");
+    for (i, token) in tokens.iter().enumerate() {
+        text.push_str(if i == 0 { "\n      " } else { ",\n      " });
+        text.push_str(&token_json(source, token, false));
+    }
+    text.push_str(if tokens.is_empty() { "]" } else { "\n    ]" });
+    text.push_str(",\n    \"errors\": [");
+    for (i, error) in errors.iter().enumerate() {
+        let (line, column) = position(source, error.pos());
+        text.push_str(if i == 0 { "\n      " } else { ",\n      " });
+        write!(text, "{{\"code\":{},\"message\":", error.code()).unwrap();
+        json::write_string(&mut text, error.message());
+        write!(
+            text,
+            ",\"start\":{},\"line\":{},\"column\":{}}}",
+            error.pos(),
+            line,
+            column
+        )
+        .unwrap();
+    }
+    text.push_str(if errors.is_empty() { "]" } else { "\n    ]" });
+    text.push_str("\n  }");
+    write!(out, "{}", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhi::TokenType;
+
+    fn kinds(text: &str, trivia: bool) -> Vec<(TokenType, String)> {
+        let source = SourceFile::new("test.js", text);
+        let (tokens, _) = lex(&source, LanguageVariant::Jsx, trivia);
+        tokens
+            .iter()
+            .map(|t| (t.token_type, source.text(t).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens_trivia() {
+        assert_eq!(
+            kinds("#!node\r\na # // c\n", true),
+            [
+                (TokenType::SingleLineCommentTrivia, "#!node".into()),
+                (TokenType::NewLineTrivia, "\r\n".into()),
+                (TokenType::Identifier, "a".into()),
+                (TokenType::WhitespaceTrivia, " ".into()),
+                (TokenType::Unknown, "#".into()),
+                (TokenType::WhitespaceTrivia, " ".into()),
+                (TokenType::SingleLineCommentTrivia, "// c".into()),
+                (TokenType::NewLineTrivia, "\n".into()),
+            ]
+        );
+        assert_eq!(
+            kinds("a // c\n", false),
+            [(TokenType::Identifier, "a".into())]
+        );
+    }
+
+    #[test]
+    fn test_tokens_variant() {
+        let options = parse_args(&["a.ts".to_string()]).unwrap();
+        assert!(!options.ts && !options.jsx);
+        assert_eq!(variant("a.ts", false, false), LanguageVariant::Standard);
+        assert_eq!(variant("a.tsx", false, false), LanguageVariant::Jsx);
+        assert_eq!(variant("a.js", false, false), LanguageVariant::Jsx);
+        assert_eq!(variant("a.ts", true, false), LanguageVariant::Jsx);
+
+        let options = parse_args(&["--ts".into(), "--format=jsonl".into(), "-".into()]).unwrap();
+        assert_eq!(options.format, Format::Jsonl);
+        assert_eq!(
+            variant("-", options.jsx, options.ts),
+            LanguageVariant::Standard
+        );
+        assert!(parse_args(&["--format".into(), "xml".into(), "a.js".into()]).is_err());
+    }
+}
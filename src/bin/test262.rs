@@ -0,0 +1,575 @@
+//! Runs the lexer over a local checkout of test262 and checks that it
+//! accepts or rejects each test as the test's frontmatter requires.
+//!
+//! usage: test262 <test262 dir> [--all] [--filter <text>]
+//!                [--allowlist <file>] [--write-allowlist]
+//!
+//! A lexer cannot see most early errors, so by default only the directories
+//! of `test/language` that test the lexical grammar are run. Failures listed
+//! in the allowlist are expected; any other failure makes the run fail.
+//!
+//! The allowlist records the test262 revision it was written for, and a run
+//! against a checkout at any other revision fails.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rhi::{Interner, Lexer, LiteralValue, TokenFlags, TokenType};
+
+const USAGE: &str = "\
+usage: test262 <test262 dir> [options]
+
+options:
+  --all                run all of test/, not just the lexical grammar tests
+  --filter <text>      only run tests whose path contains <text>
+  --allowlist <file>   known failures, one path per line
+                       (default: tests/test262/known-failures.txt)
+  --write-allowlist    replace the allowlist with this run's failures, and
+                       pin it to the checkout's revision
+";
+
+/// The allowlist line that pins it to a test262 revision.
+const REVISION_PREFIX: &str = "# test262 revision: ";
+
+const DEFAULT_ALLOWLIST: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/test262/known-failures.txt"
+);
+
+/// The parts of `test/language` about the lexical grammar.
+const LEXICAL_DIRS: [&str; 10] = [
+    "comments",
+    "future-reserved-words",
+    "identifiers",
+    "keywords",
+    "line-terminators",
+    "literals",
+    "punctuators",
+    "reserved-words",
+    "source-text",
+    "white-space",
+];
+
+/// The commit a test262 git checkout is at, read from `.git` without
+/// running git.
+fn revision(root: &Path) -> Option<String> {
+    let git = root.join(".git");
+    let head = fs::read_to_string(git.join("HEAD")).ok()?;
+    let head = head.trim();
+    let Some(name) = head.strip_prefix("ref: ") else {
+        return Some(head.to_string());
+    };
+    if let Ok(commit) = fs::read_to_string(git.join(name)) {
+        return Some(commit.trim().to_string());
+    }
+    // The ref may only be in `packed-refs`, as `<commit> <name>` lines.
+    fs::read_to_string(git.join("packed-refs"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_suffix(name)?.strip_suffix(' ').map(String::from))
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Frontmatter {
+    /// `negative.phase`, e.g. `parse`.
+    negative_phase: Option<String>,
+    flags: Vec<String>,
+}
+
+impl Frontmatter {
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+/// Reads the YAML between `/*---` and `---*/`. Only the keys the runner
+/// needs are understood.
+fn parse_frontmatter(source: &str) -> Option<Frontmatter> {
+    let start = source.find("/*---")? + 5;
+    let end = start + source[start..].find("---*/")?;
+
+    let mut frontmatter = Frontmatter::default();
+    let mut key = "";
+    for line in source[start..end].lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            let (k, value) = trimmed.split_once(':').unwrap_or((trimmed, ""));
+            key = k.trim();
+            let value = value.trim();
+            if key == "flags" && value.starts_with('[') {
+                frontmatter.flags = value
+                    .trim_matches(['[', ']'])
+                    .split(',')
+                    .map(|flag| flag.trim().to_string())
+                    .filter(|flag| !flag.is_empty())
+                    .collect();
+            }
+        } else if key == "flags" {
+            if let Some(flag) = trimmed.strip_prefix('-') {
+                frontmatter.flags.push(flag.trim().to_string());
+            }
+        } else if key == "negative"
+            && let Some(phase) = trimmed.strip_prefix("phase:")
+        {
+            frontmatter.negative_phase = Some(phase.trim().to_string());
+        }
+    }
+    Some(frontmatter)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Sloppy,
+    Strict,
+    Module,
+}
+
+/// The modes test262 runs a test in, as its flags require.
+fn modes(frontmatter: &Frontmatter) -> &'static [Mode] {
+    if frontmatter.has_flag("module") {
+        &[Mode::Module]
+    } else if frontmatter.has_flag("onlyStrict") {
+        &[Mode::Strict]
+    } else if frontmatter.has_flag("noStrict") || frontmatter.has_flag("raw") {
+        &[Mode::Sloppy]
+    } else {
+        &[Mode::Sloppy, Mode::Strict]
+    }
+}
+
+/// The first lexical error in `text` for `mode`: an error from the lexer, or
+/// an early error that depends only on the tokens, such as a legacy octal
+/// literal in strict code or an escaped keyword.
+fn lexical_error(text: &str, mode: Mode) -> Option<String> {
+    let strict = mode != Mode::Sloppy;
+    let interner = Interner::new();
+    let (tokens, errors) = Lexer::new(text).tokenize(&interner);
+    if let Some(error) = errors.first() {
+        return Some(error.to_string());
+    }
+
+    let mut previous = TokenType::Unknown;
+    // For each open template, whether it is tagged.
+    let mut templates = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let kind = token.token_type;
+        if kind.is_comment() {
+            continue;
+        }
+        let token_text = &text[token.start as usize..token.end as usize];
+        let error = match kind {
+            TokenType::NumericLiteral | TokenType::BigIntLiteral
+                if strict
+                    && token
+                        .flags
+                        .intersects(TokenFlags::OCTAL | TokenFlags::CONTAINS_LEADING_ZERO) =>
+            {
+                Some("legacy octal literal in strict code")
+            }
+            TokenType::StringLiteral
+                if token.flags.contains(TokenFlags::CONTAINS_INVALID_ESCAPE) =>
+            {
+                if has_malformed_escape(token_text) {
+                    Some("malformed escape sequence")
+                } else if strict {
+                    Some("legacy octal escape in strict code")
+                } else {
+                    None
+                }
+            }
+            TokenType::NoSubstitutionTemplateLiteral
+            | TokenType::TemplateHead
+            | TokenType::TemplateMiddle
+            | TokenType::TemplateTail => {
+                let tagged = match kind {
+                    TokenType::NoSubstitutionTemplateLiteral => may_be_tag(previous),
+                    TokenType::TemplateHead => {
+                        templates.push(may_be_tag(previous));
+                        templates[templates.len() - 1]
+                    }
+                    TokenType::TemplateMiddle => templates.last().copied().unwrap_or(false),
+                    _ => templates.pop().unwrap_or(false),
+                };
+                (!tagged && token.flags.contains(TokenFlags::CONTAINS_INVALID_ESCAPE))
+                    .then_some("invalid escape in an untagged template")
+            }
+            TokenType::Identifier
+                if token.flags.intersects(
+                    TokenFlags::UNICODE_ESCAPE | TokenFlags::EXTENDED_UNICODE_ESCAPE,
+                ) =>
+            {
+                match tokens.literal(i) {
+                    Some(LiteralValue::Identifier(atom)) => {
+                        escaped_identifier_error(&interner.resolve(atom), mode)
+                    }
+                    _ => None,
+                }
+            }
+            TokenType::RegularExpressionLiteral => {
+                let flags = &token_text[token_text.rfind('/').unwrap() + 1..];
+                (!valid_regex_flags(flags)).then_some("invalid regular expression flags")
+            }
+            _ => None,
+        };
+        if let Some(error) = error {
+            return Some(format!("[{}] {}", token.start, error));
+        }
+        previous = kind;
+    }
+    None
+}
+
+/// Whether a template after `kind` may be tagged, e.g. after `f` or `)`.
+fn may_be_tag(kind: TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::Identifier
+            | TokenType::RightParenToken
+            | TokenType::RightBracketToken
+            | TokenType::NoSubstitutionTemplateLiteral
+            | TokenType::TemplateTail
+            | TokenType::ThisKeyword
+            | TokenType::SuperKeyword
+    ) || kind.is_contextual_keyword()
+        || kind.is_literal()
+}
+
+/// Whether a string literal has a `\x` or `\u` escape that is malformed,
+/// rather than only legacy octal escapes.
+fn has_malformed_escape(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        let hex = |from: usize, n: usize| {
+            bytes
+                .get(from..from + n)
+                .is_some_and(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+        };
+        match bytes.get(i + 1) {
+            Some(b'x') if !hex(i + 2, 2) => return true,
+            Some(b'u') if bytes.get(i + 2) == Some(&b'{') => {
+                let digits = text[i + 3..].split('}').next().unwrap_or_default();
+                let closed = text[i + 3..].contains('}');
+                if !closed
+                    || digits.is_empty()
+                    || !u32::from_str_radix(digits, 16).is_ok_and(|c| c <= 0x10ffff)
+                {
+                    return true;
+                }
+            }
+            Some(b'u') if !hex(i + 2, 4) => return true,
+            _ => {}
+        }
+        i += 2;
+    }
+    false
+}
+
+/// Early errors for an identifier written with escapes: it must still be
+/// an identifier, and must not spell a reserved word.
+fn escaped_identifier_error(name: &str, mode: Mode) -> Option<&'static str> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '$' || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '$' | '_' | '\u{200C}' | '\u{200D}'));
+    if !valid {
+        return Some("escape sequence is not an identifier character");
+    }
+
+    let kind = rhi::Lexer::new(name).lex().next()?.ok()?.token_type;
+    let reserved = (TokenType::FIRST_RESERVED_WORD..=TokenType::LAST_RESERVED_WORD).contains(&kind)
+        || (mode != Mode::Sloppy
+            && (TokenType::FIRST_FUTURE_RESERVED_WORD..=TokenType::LAST_FUTURE_RESERVED_WORD)
+                .contains(&kind))
+        || (mode == Mode::Module && kind == TokenType::AwaitKeyword);
+    reserved.then_some("keywords cannot contain escape characters")
+}
+
+fn valid_regex_flags(flags: &str) -> bool {
+    let mut seen = BTreeSet::new();
+    flags
+        .chars()
+        .all(|c| "dgimsuvy".contains(c) && seen.insert(c))
+        && !(seen.contains(&'u') && seen.contains(&'v'))
+}
+
+/// Why `source` fails, or `None` if lexing it agrees with its frontmatter.
+fn run_test(source: &str) -> Option<String> {
+    let Some(frontmatter) = parse_frontmatter(source) else {
+        return Some("no frontmatter".to_string());
+    };
+    let expect_error = frontmatter.negative_phase.as_deref() == Some("parse");
+
+    for &mode in modes(&frontmatter) {
+        match (lexical_error(source, mode), expect_error) {
+            (Some(error), false) => return Some(format!("{:?}: {}", mode, error)),
+            (None, true) => {
+                return Some(format!(
+                    "{:?}: expected a SyntaxError, but lexing succeeded",
+                    mode
+                ));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn collect(dir: &Path, tests: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, tests)?;
+        } else if path.extension().is_some_and(|e| e == "js")
+            && !path.to_string_lossy().contains("_FIXTURE")
+        {
+            tests.push(path);
+        }
+    }
+    Ok(())
+}
+
+struct Options {
+    root: PathBuf,
+    all: bool,
+    filter: Option<String>,
+    allowlist: PathBuf,
+    write_allowlist: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut root = None;
+    let mut options = Options {
+        root: PathBuf::new(),
+        all: false,
+        filter: None,
+        allowlist: PathBuf::from(DEFAULT_ALLOWLIST),
+        write_allowlist: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => options.all = true,
+            "--write-allowlist" => options.write_allowlist = true,
+            "--filter" => {
+                options.filter = Some(args.next().ok_or("--filter needs a value")?.clone())
+            }
+            "--allowlist" => {
+                options.allowlist = PathBuf::from(args.next().ok_or("--allowlist needs a value")?)
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if root.is_none() => root = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    options.root = root.ok_or("no test262 directory given")?;
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("test262: {}", message);
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let dirs: Vec<PathBuf> = if options.all {
+        vec![options.root.join("test")]
+    } else {
+        LEXICAL_DIRS
+            .iter()
+            .map(|dir| options.root.join("test/language").join(dir))
+            .collect()
+    };
+    let mut tests = Vec::new();
+    for dir in &dirs {
+        if let Err(e) = collect(dir, &mut tests) {
+            eprintln!("test262: {}: {}", dir.display(), e);
+            return ExitCode::from(2);
+        }
+    }
+    tests.sort();
+
+    let allowlist = fs::read_to_string(&options.allowlist).unwrap_or_default();
+    let checkout = revision(&options.root);
+    let pinned = allowlist
+        .lines()
+        .find_map(|line| line.strip_prefix(REVISION_PREFIX));
+    if !options.write_allowlist
+        && let (Some(pinned), Some(checkout)) = (pinned, &checkout)
+        && pinned != checkout
+    {
+        eprintln!(
+            "test262: {} is for test262 {}, but the checkout is at {}",
+            options.allowlist.display(),
+            pinned,
+            checkout
+        );
+        return ExitCode::from(2);
+    }
+    let known: BTreeSet<String> = allowlist
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+
+    let mut total = 0;
+    let mut failures = BTreeSet::new();
+    let mut new_failures = Vec::new();
+    for path in &tests {
+        let name = path
+            .strip_prefix(&options.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if options.filter.as_ref().is_some_and(|f| !name.contains(f)) {
+            continue;
+        }
+        total += 1;
+        let result = match fs::read_to_string(path) {
+            Ok(source) => run_test(&source),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(reason) = result {
+            if !known.contains(&name) {
+                new_failures.push(format!("{}: {}", name, reason));
+            }
+            failures.insert(name);
+        }
+    }
+    let fixed: Vec<_> = known
+        .iter()
+        .filter(|name| !failures.contains(*name))
+        .collect();
+
+    if options.write_allowlist {
+        let mut text = String::from(
+            "# Known test262 failures of the lexer, one path per line.\n\
+             # Regenerate with `cargo run --bin test262 -- <test262 dir> --write-allowlist`.\n",
+        );
+        if let Some(checkout) = &checkout {
+            text.push_str(REVISION_PREFIX);
+            text.push_str(checkout);
+            text.push('\n');
+        }
+        for name in &failures {
+            text.push_str(name);
+            text.push('\n');
+        }
+        if let Err(e) = fs::write(&options.allowlist, text) {
+            eprintln!("test262: {}: {}", options.allowlist.display(), e);
+            return ExitCode::from(2);
+        }
+        println!(
+            "wrote {} known failures to {}",
+            failures.len(),
+            options.allowlist.display()
+        );
+    }
+
+    for failure in &new_failures {
+        println!("FAIL {}", failure);
+    }
+    for name in &fixed {
+        println!("FIXED {} (remove it from the allowlist)", name);
+    }
+    println!(
+        "test262: {} tests, {} passed, {} known failures, {} new failures, {} fixed",
+        total,
+        total - failures.len(),
+        failures.len() - new_failures.len(),
+        new_failures.len(),
+        fixed.len()
+    );
+
+    if new_failures.is_empty() || options.write_allowlist {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frontmatter() {
+        let source = "/*---\nesid: sec-x\nnegative:\n  phase: parse\n  type: SyntaxError\n\
+                      flags: [onlyStrict, raw]\n---*/\n$DONOTEVALUATE();\n";
+        assert_eq!(
+            parse_frontmatter(source),
+            Some(Frontmatter {
+                negative_phase: Some("parse".to_string()),
+                flags: vec!["onlyStrict".to_string(), "raw".to_string()],
+            })
+        );
+
+        let source = "/*---\nflags:\n  - module\ndescription: >\n  phase: parse\n---*/";
+        let frontmatter = parse_frontmatter(source).unwrap();
+        assert_eq!(frontmatter.flags, ["module"]);
+        assert_eq!(frontmatter.negative_phase, None);
+        assert_eq!(modes(&frontmatter), [Mode::Module]);
+    }
+
+    #[test]
+    fn test_revision() {
+        let root = env::temp_dir().join(format!("rhi-test262-revision-{}", std::process::id()));
+        let git = root.join(".git");
+        fs::create_dir_all(git.join("refs/heads")).unwrap();
+        assert_eq!(revision(&root), None);
+
+        fs::write(git.join("HEAD"), "0123abc\n").unwrap();
+        assert_eq!(revision(&root).as_deref(), Some("0123abc"));
+        fs::write(git.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(
+            git.join("packed-refs"),
+            "# pack-refs\n4567def refs/heads/main\n",
+        )
+        .unwrap();
+        assert_eq!(revision(&root).as_deref(), Some("4567def"));
+        fs::write(git.join("refs/heads/main"), "89abcde\n").unwrap();
+        assert_eq!(revision(&root).as_deref(), Some("89abcde"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_lexical_errors() {
+        assert_eq!(lexical_error("var a = 017;", Mode::Sloppy), None);
+        assert!(lexical_error("var a = 017;", Mode::Strict).is_some());
+        assert!(lexical_error("'\\08'", Mode::Strict).is_some());
+        assert_eq!(lexical_error("'\\08'", Mode::Sloppy), None);
+        assert!(lexical_error("'\\x0g'", Mode::Sloppy).is_some());
+        assert!(lexical_error("`\\unicode`", Mode::Sloppy).is_some());
+        assert_eq!(lexical_error("tag`\\unicode`", Mode::Sloppy), None);
+        assert_eq!(lexical_error("f()`${a}\\u`", Mode::Sloppy), None);
+        assert!(lexical_error("var \\u0076ar;", Mode::Sloppy).is_some());
+        assert_eq!(lexical_error("var l\\u0065t;", Mode::Sloppy), None);
+        assert!(lexical_error("var l\\u0065t;", Mode::Strict).is_some());
+        assert!(lexical_error("a\\u0020b", Mode::Sloppy).is_some());
+        assert!(lexical_error("/a/gg", Mode::Sloppy).is_some());
+        assert!(lexical_error("'a", Mode::Sloppy).is_some());
+    }
+}
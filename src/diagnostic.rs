@@ -0,0 +1,432 @@
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::ops::Range;
+
+use crate::line_map::{ColumnEncoding, LineMap};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Range<usize>, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// A diagnostic in the shape TypeScript reports them: a severity, a `TSxxxx`
+/// code, a message, and any number of labelled byte spans into the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: u32,
+    pub message: String,
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn error(code: u32, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, span: Range<usize>, message: impl Into<String>) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    /// The byte offset the diagnostic points at, i.e. the start of its first label.
+    pub fn offset(&self) -> usize {
+        self.labels.first().map_or(0, |label| label.span.start)
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    /// Source snippet with a line-number gutter and underline carets.
+    Snippet,
+    /// One `file:line:col: error TSxxxx: message` line per diagnostic, for editors.
+    Plain,
+}
+
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    mode: RenderMode,
+    colors: bool,
+    tab_width: usize,
+    column_encoding: ColumnEncoding,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self {
+            mode: RenderMode::Snippet,
+            colors: false,
+            tab_width: 4,
+            column_encoding: ColumnEncoding::Utf16,
+        }
+    }
+
+    /// A snippet renderer that uses colors only when stderr is a terminal
+    /// and `NO_COLOR` is not set.
+    pub fn for_stderr() -> Self {
+        let colors = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        Self::new().colors(colors)
+    }
+
+    pub fn mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// How the reported `line:col` counts columns. Defaults to UTF-16 code
+    /// units so positions agree with VS Code.
+    pub fn column_encoding(mut self, column_encoding: ColumnEncoding) -> Self {
+        self.column_encoding = column_encoding;
+        self
+    }
+
+    pub fn render(&self, file_name: &str, source: &str, diagnostic: &Diagnostic) -> String {
+        let line_map = LineMap::new(source);
+        match self.mode {
+            RenderMode::Plain => self.render_plain(file_name, source, &line_map, diagnostic),
+            RenderMode::Snippet => self.render_snippet(file_name, source, &line_map, diagnostic),
+        }
+    }
+
+    fn paint(&self, out: &mut String, color: &str, text: &str) {
+        if self.colors {
+            _ = write!(out, "{}{}{}", color, text, RESET);
+        } else {
+            out.push_str(text);
+        }
+    }
+
+    fn render_plain(
+        &self,
+        file_name: &str,
+        source: &str,
+        line_map: &LineMap,
+        diagnostic: &Diagnostic,
+    ) -> String {
+        let position = line_map.position(source, diagnostic.offset(), self.column_encoding);
+        format!(
+            "{}:{}:{}: {} TS{}: {}\n",
+            file_name,
+            position.line,
+            position.column,
+            diagnostic.severity.as_str(),
+            diagnostic.code,
+            diagnostic.message
+        )
+    }
+
+    fn render_snippet(
+        &self,
+        file_name: &str,
+        source: &str,
+        line_map: &LineMap,
+        diagnostic: &Diagnostic,
+    ) -> String {
+        let mut out = String::new();
+
+        let header = format!("{} TS{}", diagnostic.severity.as_str(), diagnostic.code);
+        self.paint(&mut out, diagnostic.severity.color(), &header);
+        if self.colors {
+            _ = writeln!(out, "{}: {}{}", BOLD, diagnostic.message, RESET);
+        } else {
+            _ = writeln!(out, ": {}", diagnostic.message);
+        }
+
+        let position = line_map.position(source, diagnostic.offset(), self.column_encoding);
+        let last_line = diagnostic
+            .labels
+            .iter()
+            .map(|label| label_lines(source, line_map, label).1)
+            .max()
+            .unwrap_or(position.line);
+        let gutter = last_line.to_string().len();
+
+        out.push_str(&" ".repeat(gutter));
+        self.paint(&mut out, BLUE, "-->");
+        _ = writeln!(out, " {}:{}:{}", file_name, position.line, position.column);
+
+        for label in &diagnostic.labels {
+            self.render_label(
+                &mut out,
+                source,
+                line_map,
+                label,
+                diagnostic.severity,
+                gutter,
+            );
+        }
+
+        out
+    }
+
+    fn render_label(
+        &self,
+        out: &mut String,
+        source: &str,
+        line_map: &LineMap,
+        label: &Label,
+        severity: Severity,
+        gutter: usize,
+    ) {
+        let start = label.span.start.min(source.len());
+        let end = label.span.end.clamp(start, source.len());
+
+        self.gutter(out, gutter, None);
+        out.push('\n');
+
+        let (first_line, last_line) = label_lines(source, line_map, label);
+
+        for line in first_line..=last_line {
+            let range = line_map.line_range(source, line);
+            let text = &source[range.clone()];
+
+            self.gutter(out, gutter, Some(line));
+            out.push(' ');
+            out.push_str(&self.expand(text));
+            out.push('\n');
+
+            // The part of the span that falls on this line, relative to the line.
+            let from = floor_char_boundary(text, start.max(range.start) - range.start);
+            let to = floor_char_boundary(text, end.min(range.end).max(range.start) - range.start)
+                .max(from);
+            let pad = self.display_width(&text[..from]);
+            let carets = self.display_width(&text[from..to]).max(1);
+
+            self.gutter(out, gutter, None);
+            out.push(' ');
+            out.push_str(&" ".repeat(pad));
+            let mut marker = "^".repeat(carets);
+            if line == last_line && !label.message.is_empty() {
+                marker.push(' ');
+                marker.push_str(&label.message);
+            }
+            self.paint(out, severity.color(), &marker);
+            out.push('\n');
+        }
+    }
+
+    fn gutter(&self, out: &mut String, width: usize, line: Option<usize>) {
+        let number = line.map_or(String::new(), |line| line.to_string());
+        self.paint(out, BLUE, &format!("{:>width$} |", number, width = width));
+    }
+
+    fn expand(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut column = 0;
+        for c in text.chars() {
+            if c == '\t' {
+                let spaces = self.tab_width - column % self.tab_width;
+                out.push_str(&" ".repeat(spaces));
+                column += spaces;
+            } else {
+                out.push(c);
+                column += char_width(c);
+            }
+        }
+        out
+    }
+
+    fn display_width(&self, text: &str) -> usize {
+        text.chars().fold(0, |column, c| {
+            if c == '\t' {
+                column + self.tab_width - column % self.tab_width
+            } else {
+                column + char_width(c)
+            }
+        })
+    }
+}
+
+/// The 1-based first and last lines a label's span is drawn on.
+fn label_lines(source: &str, line_map: &LineMap, label: &Label) -> (usize, usize) {
+    let start = label.span.start.min(source.len());
+    let end = label.span.end.clamp(start, source.len());
+    // A span that ends right after a line terminator does not reach into the next line.
+    (
+        line_map.line_index(start) + 1,
+        line_map.line_index(end.saturating_sub(1).max(start)) + 1,
+    )
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Number of terminal columns a character occupies: 0 for combining marks and
+/// zero-width characters, 2 for East Asian wide and fullwidth characters.
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0xFEFF => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_plain() {
+        let source = "let a;\nlet b = #;\n";
+        let diagnostic = Diagnostic::error(1127, "Invalid character.").with_label(15..16, "");
+        let rendered =
+            Renderer::new()
+                .mode(RenderMode::Plain)
+                .render("main.ts", source, &diagnostic);
+
+        assert_eq!(rendered, "main.ts:2:9: error TS1127: Invalid character.\n");
+    }
+
+    #[test]
+    fn test_render_snippet() {
+        let source = "let a;\nlet b = #;\n";
+        let diagnostic = Diagnostic::error(1127, "Invalid character.")
+            .with_label(15..16, "unexpected character");
+        let rendered = Renderer::new().render("main.ts", source, &diagnostic);
+
+        assert_eq!(
+            rendered,
+            "error TS1127: Invalid character.\n\
+             \x20--> main.ts:2:9\n\
+             \x20 |\n\
+             2 | let b = #;\n\
+             \x20 |         ^ unexpected character\n"
+        );
+    }
+
+    #[test]
+    fn test_render_snippet_tabs_and_wide_chars() {
+        let source = "\t\"한글\" @";
+        let diagnostic = Diagnostic::error(1127, "Invalid character.").with_label(10..11, "");
+        let rendered = Renderer::new().render("main.ts", source, &diagnostic);
+
+        assert!(rendered.contains("1 |     \"한글\" @\n"));
+        assert!(rendered.contains("  |            ^\n"));
+    }
+
+    #[test]
+    fn test_render_snippet_multi_line_span() {
+        let source = "/* a\nbc */ x";
+        let diagnostic =
+            Diagnostic::error(1010, "'*/' expected.").with_label(0..10, "comment starts here");
+        let rendered = Renderer::new().render("main.ts", source, &diagnostic);
+
+        assert!(rendered.contains("1 | /* a\n  | ^^^^\n"));
+        assert!(rendered.contains("2 | bc */ x\n  | ^^^^^ comment starts here\n"));
+    }
+
+    #[test]
+    fn test_render_snippet_span_ending_at_line_break() {
+        let source = "\n".repeat(8) + "let b = #;\n";
+        let diagnostic = Diagnostic::error(1127, "Invalid character.").with_label(8..19, "");
+        let rendered = Renderer::new().render("main.ts", &source, &diagnostic);
+
+        assert_eq!(
+            rendered,
+            "error TS1127: Invalid character.\n\
+             \x20--> main.ts:9:1\n\
+             \x20 |\n\
+             9 | let b = #;\n\
+             \x20 | ^^^^^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_colors() {
+        let diagnostic = Diagnostic::error(1127, "Invalid character.").with_label(0..1, "");
+        let rendered = Renderer::new()
+            .colors(true)
+            .render("main.ts", "#", &diagnostic);
+
+        assert!(rendered.starts_with("\x1b[1;31merror TS1127\x1b[0m"));
+    }
+}
@@ -0,0 +1,1010 @@
+//! EBNF grammars, in the two dialects the grammars in `docs/` are written
+//! in.
+//!
+//! ISO 14977 EBNF writes `Name = a, [ b ], { c } | d - e ;`, with `? ... ?`
+//! special sequences described in prose, `n * a` repetition factors and the
+//! common `{ a }-` extension for one or more repetitions. W3C EBNF, as in
+//! the XML specification, writes `Name ::= a b? c* | d - e`, with `c+`,
+//! `#xN` code points, `[a-z]` character classes, and productions ending
+//! where the next one starts.
+//!
+//! Both are parsed into the same `Grammar`, with byte spans throughout.
+//! Comments are kept, and `(* region Name *)` ... `(* endregion *)` markers
+//! in them group productions into regions.
+
+pub mod convert;
+pub mod coverage;
+pub mod diagram;
+pub mod generate;
+pub mod lint;
+mod recognize;
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Iso,
+    W3c,
+}
+
+impl Dialect {
+    /// W3C if any production is defined with `::=`, ISO otherwise.
+    pub fn detect(text: &str) -> Dialect {
+        if text.contains("::=") {
+            Dialect::W3c
+        } else {
+            Dialect::Iso
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Iso => "iso",
+            Dialect::W3c => "w3c",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grammar {
+    pub dialect: Dialect,
+    pub productions: Vec<Production>,
+    /// Every comment, in order, regions markers included.
+    pub comments: Vec<Comment>,
+    /// Regions in the order they start. Regions can nest.
+    pub regions: Vec<Region>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Production {
+    pub name: String,
+    pub name_span: Range<usize>,
+    pub expr: Expr,
+    /// From the name to the terminating `;`, if any.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The text between the delimiters, trimmed.
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    /// From the start of the `region` comment to the end of the matching
+    /// `endregion` comment, or to the end of the file if there is none.
+    pub span: Range<usize>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// Nothing, as in `A = ;` or `A = b | ;`.
+    Empty,
+    /// A quoted string.
+    Terminal(String),
+    /// A production name.
+    Nonterminal(String),
+    /// An ISO `? ... ?` special sequence, or a W3C production whose body is
+    /// only a comment. The text is trimmed.
+    Special(String),
+    /// A W3C `#xN` code point.
+    Char(char),
+    /// A W3C `[...]` or `[^...]` class, or a bare `#xN-#xM` range.
+    CharClass {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    Sequence(Vec<Expr>),
+    Alternation(Vec<Expr>),
+    /// `[ a ]` or `a?`.
+    Optional(Box<Expr>),
+    /// `{ a }` or `a*`.
+    ZeroOrMore(Box<Expr>),
+    /// `{ a }-` or `a+`.
+    OneOrMore(Box<Expr>),
+    /// ISO `n * a`.
+    Repeat(u32, Box<Expr>),
+    /// `a - b`: what matches `a` but not `b`.
+    Exception(Box<Expr>, Box<Expr>),
+}
+
+impl Grammar {
+    pub fn parse(text: &str, dialect: Dialect) -> Result<Grammar, ParseError> {
+        let (tokens, comments) = tokenize(text, dialect)?;
+        let regions = regions(&comments, text.len());
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            dialect,
+            comments: &comments,
+        };
+        let mut productions = Vec::new();
+        while parser.peek().kind != Tok::Eof {
+            productions.push(parser.production()?);
+        }
+        Ok(Grammar {
+            dialect,
+            productions,
+            comments,
+            regions,
+        })
+    }
+
+    pub fn production(&self, name: &str) -> Option<&Production> {
+        self.productions.iter().find(|p| p.name == name)
+    }
+
+    /// The innermost region containing `offset`, if any.
+    pub fn region_at(&self, offset: usize) -> Option<&Region> {
+        self.regions
+            .iter()
+            .filter(|r| r.span.contains(&offset))
+            .min_by_key(|r| r.span.len())
+    }
+}
+
+impl Expr {
+    /// Calls `visit` with this expression and every one inside it, parents
+    /// before children.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Expr)) {
+        visit(self);
+        match &self.kind {
+            ExprKind::Sequence(items) | ExprKind::Alternation(items) => {
+                for item in items {
+                    item.walk(visit);
+                }
+            }
+            ExprKind::Optional(inner)
+            | ExprKind::ZeroOrMore(inner)
+            | ExprKind::OneOrMore(inner)
+            | ExprKind::Repeat(_, inner) => inner.walk(visit),
+            ExprKind::Exception(base, except) => {
+                base.walk(visit);
+                except.walk(visit);
+            }
+            ExprKind::Empty
+            | ExprKind::Terminal(_)
+            | ExprKind::Nonterminal(_)
+            | ExprKind::Special(_)
+            | ExprKind::Char(_)
+            | ExprKind::CharClass { .. } => {}
+        }
+    }
+
+    /// The alternatives of an alternation, or just this expression.
+    pub fn alternatives(&self) -> &[Expr] {
+        match &self.kind {
+            ExprKind::Alternation(items) => items,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.span.start, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn error(message: impl Into<String>, span: Range<usize>) -> ParseError {
+    ParseError {
+        message: message.into(),
+        span,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Literal(String),
+    Special(String),
+    Integer(u32),
+    Char(char),
+    CharClass(bool, Vec<(char, char)>),
+    /// `=` or `::=`.
+    Define,
+    /// `;` or `.`, ISO only.
+    Terminator,
+    Alt,
+    /// `,`, ISO only.
+    Concat,
+    Except,
+    LParen,
+    RParen,
+    /// `[` and `]`, ISO only: W3C brackets make a `CharClass`.
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Question,
+    Star,
+    Plus,
+    Eof,
+}
+
+impl Tok {
+    fn describe(&self) -> String {
+        match self {
+            Tok::Ident(name) => format!("'{}'", name),
+            Tok::Literal(text) => format!("\"{}\"", text),
+            Tok::Special(_) => "special sequence".to_string(),
+            Tok::Integer(n) => format!("'{}'", n),
+            Tok::Char(c) => format!("#x{:X}", *c as u32),
+            Tok::CharClass(..) => "character class".to_string(),
+            Tok::Define => "'='".to_string(),
+            Tok::Terminator => "';'".to_string(),
+            Tok::Alt => "'|'".to_string(),
+            Tok::Concat => "','".to_string(),
+            Tok::Except => "'-'".to_string(),
+            Tok::LParen => "'('".to_string(),
+            Tok::RParen => "')'".to_string(),
+            Tok::LBracket => "'['".to_string(),
+            Tok::RBracket => "']'".to_string(),
+            Tok::LBrace => "'{'".to_string(),
+            Tok::RBrace => "'}'".to_string(),
+            Tok::Question => "'?'".to_string(),
+            Tok::Star => "'*'".to_string(),
+            Tok::Plus => "'+'".to_string(),
+            Tok::Eof => "end of file".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Tok,
+    span: Range<usize>,
+}
+
+fn tokenize(text: &str, dialect: Dialect) -> Result<(Vec<Token>, Vec<Comment>), ParseError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = text[i..].chars().next().unwrap();
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        let kind = match c {
+            '(' if bytes.get(i + 1) == Some(&b'*') => {
+                i = comment_end(text, i, "(*", "*)", dialect == Dialect::Iso)?;
+                comments.push(comment(text, start..i));
+                continue;
+            }
+            '/' if dialect == Dialect::W3c && bytes.get(i + 1) == Some(&b'*') => {
+                i = comment_end(text, i, "/*", "*/", false)?;
+                comments.push(comment(text, start..i));
+                continue;
+            }
+            '"' | '\'' => {
+                let Some(len) = text[i + 1..].find(c) else {
+                    return Err(error("unterminated terminal string", i..text.len()));
+                };
+                i += len + 2;
+                let literal = &text[start + 1..i - 1];
+                if literal.is_empty() {
+                    return Err(error("empty terminal string", start..i));
+                }
+                Tok::Literal(literal.to_string())
+            }
+            '?' if dialect == Dialect::Iso => {
+                let Some(len) = text[i + 1..].find('?') else {
+                    return Err(error("unterminated special sequence", i..text.len()));
+                };
+                i += len + 2;
+                Tok::Special(text[start + 1..i - 1].trim().to_string())
+            }
+            '#' if dialect == Dialect::W3c => {
+                let first = char_code(text, &mut i)?;
+                if text[i..].starts_with("-#x") {
+                    i += 1;
+                    let last = char_code(text, &mut i)?;
+                    Tok::CharClass(false, vec![(first, last)])
+                } else {
+                    Tok::Char(first)
+                }
+            }
+            '[' if dialect == Dialect::W3c => {
+                i += 1;
+                let (negated, ranges) = char_class(text, &mut i)?;
+                Tok::CharClass(negated, ranges)
+            }
+            '0'..='9' if dialect == Dialect::Iso => {
+                while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                    i += 1;
+                }
+                let n = text[start..i]
+                    .parse()
+                    .map_err(|_| error("repetition count too large", start..i))?;
+                Tok::Integer(n)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                i = identifier_end(text, i);
+                // ISO meta identifiers may be several words.
+                if dialect == Dialect::Iso {
+                    loop {
+                        let rest = &text[i..];
+                        let gap = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+                        if gap > 0
+                            && rest[gap..]
+                                .chars()
+                                .next()
+                                .is_some_and(|c| c.is_ascii_alphabetic())
+                        {
+                            i = identifier_end(text, i + gap);
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                let words: Vec<&str> = text[start..i].split_whitespace().collect();
+                Tok::Ident(words.join(" "))
+            }
+            _ => {
+                let (kind, len) = match (dialect, &text[i..]) {
+                    (Dialect::W3c, s) if s.starts_with("::=") => (Tok::Define, 3),
+                    (Dialect::Iso, s) if s.starts_with('=') => (Tok::Define, 1),
+                    (Dialect::Iso, s) if s.starts_with(';') || s.starts_with('.') => {
+                        (Tok::Terminator, 1)
+                    }
+                    (Dialect::Iso, s) if s.starts_with(',') => (Tok::Concat, 1),
+                    (Dialect::Iso, s) if s.starts_with('[') => (Tok::LBracket, 1),
+                    (Dialect::Iso, s) if s.starts_with(']') => (Tok::RBracket, 1),
+                    (Dialect::Iso, s) if s.starts_with('{') => (Tok::LBrace, 1),
+                    (Dialect::Iso, s) if s.starts_with('}') => (Tok::RBrace, 1),
+                    (Dialect::W3c, s) if s.starts_with('?') => (Tok::Question, 1),
+                    (Dialect::W3c, s) if s.starts_with('+') => (Tok::Plus, 1),
+                    (_, s) if s.starts_with('*') => (Tok::Star, 1),
+                    (_, s) if s.starts_with('|') => (Tok::Alt, 1),
+                    (_, s) if s.starts_with('-') => (Tok::Except, 1),
+                    (_, s) if s.starts_with('(') => (Tok::LParen, 1),
+                    (_, s) if s.starts_with(')') => (Tok::RParen, 1),
+                    _ => {
+                        return Err(error(
+                            format!("unexpected character '{}'", c),
+                            i..i + c.len_utf8(),
+                        ));
+                    }
+                };
+                i += len;
+                kind
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: start..i,
+        });
+    }
+    tokens.push(Token {
+        kind: Tok::Eof,
+        span: text.len()..text.len(),
+    });
+    Ok((tokens, comments))
+}
+
+fn identifier_end(text: &str, mut i: usize) -> usize {
+    let bytes = text.as_bytes();
+    while bytes
+        .get(i)
+        .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+    {
+        i += 1;
+    }
+    i
+}
+
+/// The end of a comment starting at `start`, counting nested comments if
+/// `nested`.
+fn comment_end(
+    text: &str,
+    start: usize,
+    open: &str,
+    close: &str,
+    nested: bool,
+) -> Result<usize, ParseError> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < text.len() {
+        if text[i..].starts_with(open) && (nested || depth == 0) {
+            depth += 1;
+            i += open.len();
+        } else if text[i..].starts_with(close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return Ok(i);
+            }
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    Err(error("unterminated comment", start..text.len()))
+}
+
+fn comment(text: &str, span: Range<usize>) -> Comment {
+    Comment {
+        text: text[span.start + 2..span.end - 2].trim().to_string(),
+        span,
+    }
+}
+
+/// Regions from the `region` and `endregion` comments. An `endregion`
+/// closes the innermost open region; one with no open region is ignored.
+fn regions(comments: &[Comment], len: usize) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut open = Vec::new();
+    for comment in comments {
+        let mut words = comment.text.splitn(2, char::is_whitespace);
+        match words.next() {
+            Some("region") => {
+                open.push(regions.len());
+                regions.push(Region {
+                    name: words.next().unwrap_or_default().trim().to_string(),
+                    span: comment.span.start..len,
+                    closed: false,
+                });
+            }
+            Some("endregion") => {
+                if let Some(index) = open.pop() {
+                    let region: &mut Region = &mut regions[index];
+                    region.span.end = comment.span.end;
+                    region.closed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    regions
+}
+
+/// A `#xN` code point, with `i` at the `#`.
+fn char_code(text: &str, i: &mut usize) -> Result<char, ParseError> {
+    let start = *i;
+    if !text[start..].starts_with("#x") {
+        return Err(error("expected '#x'", start..start + 1));
+    }
+    let digits = text[start + 2..]
+        .bytes()
+        .take_while(u8::is_ascii_hexdigit)
+        .count();
+    *i = start + 2 + digits;
+    u32::from_str_radix(&text[start + 2..*i], 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| error("invalid code point", start..*i))
+}
+
+/// The ranges of a `[...]` class, with `i` just after the `[`.
+fn char_class(text: &str, i: &mut usize) -> Result<(bool, Vec<(char, char)>), ParseError> {
+    let start = *i - 1;
+    let negated = text[*i..].starts_with('^');
+    if negated {
+        *i += 1;
+    }
+    let mut ranges = Vec::new();
+    let next = |i: &mut usize| -> Result<char, ParseError> {
+        match text[*i..].chars().next() {
+            None => Err(error("unterminated character class", start..text.len())),
+            Some('#') if text[*i..].starts_with("#x") => char_code(text, i),
+            Some(c) => {
+                *i += c.len_utf8();
+                Ok(c)
+            }
+        }
+    };
+    while !text[*i..].starts_with(']') {
+        let first = next(i)?;
+        let last = if text[*i..].starts_with('-') && !text[*i + 1..].starts_with(']') {
+            *i += 1;
+            next(i)?
+        } else {
+            first
+        };
+        if last < first {
+            return Err(error("character range out of order", start..*i));
+        }
+        ranges.push((first, last));
+    }
+    *i += 1;
+    if ranges.is_empty() {
+        return Err(error("empty character class", start..*i));
+    }
+    Ok((negated, ranges))
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    dialect: Dialect,
+    comments: &'a [Comment],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != Tok::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: Tok) -> Option<Token> {
+        (self.peek().kind == kind).then(|| self.next())
+    }
+
+    fn expect(&mut self, kind: Tok, context: &str) -> Result<Token, ParseError> {
+        let token = self.peek();
+        if token.kind == kind {
+            return Ok(self.next());
+        }
+        Err(error(
+            format!(
+                "expected {} {}, found {}",
+                kind.describe(),
+                context,
+                token.kind.describe()
+            ),
+            token.span.clone(),
+        ))
+    }
+
+    /// Whether the next tokens start a W3C production.
+    fn at_w3c_production(&self) -> bool {
+        matches!(self.peek().kind, Tok::Ident(_))
+            && self.tokens.get(self.pos + 1).map(|t| &t.kind) == Some(&Tok::Define)
+    }
+
+    fn production(&mut self) -> Result<Production, ParseError> {
+        let token = self.next();
+        let Tok::Ident(name) = token.kind else {
+            return Err(error(
+                format!(
+                    "expected a production name, found {}",
+                    token.kind.describe()
+                ),
+                token.span,
+            ));
+        };
+        let define = self.expect(Tok::Define, &format!("after '{}'", name))?;
+        let expr = self.alternation()?;
+        let end = match self.dialect {
+            Dialect::Iso => {
+                let terminator =
+                    self.expect(Tok::Terminator, &format!("at the end of '{}'", name))?;
+                terminator.span.end
+            }
+            Dialect::W3c => {
+                if !self.at_w3c_production() && self.peek().kind != Tok::Eof {
+                    let token = self.peek();
+                    return Err(error(
+                        format!("unexpected {} in '{}'", token.kind.describe(), name),
+                        token.span.clone(),
+                    ));
+                }
+                expr.span.end.max(define.span.end)
+            }
+        };
+        // A W3C production with only a comment for a body describes it in
+        // prose, as a special sequence would.
+        let expr = match (&expr.kind, self.dialect) {
+            (ExprKind::Empty, Dialect::W3c) => {
+                let next = self.peek().span.start;
+                match self
+                    .comments
+                    .iter()
+                    .find(|c| c.span.start >= define.span.end && c.span.end <= next)
+                {
+                    Some(comment) => Expr {
+                        kind: ExprKind::Special(comment.text.clone()),
+                        span: comment.span.clone(),
+                    },
+                    None => expr,
+                }
+            }
+            _ => expr,
+        };
+        let end = end.max(expr.span.end);
+        Ok(Production {
+            name,
+            name_span: token.span.clone(),
+            expr,
+            span: token.span.start..end,
+        })
+    }
+
+    fn alternation(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.sequence()?];
+        while self.eat(Tok::Alt).is_some() {
+            items.push(self.sequence()?);
+        }
+        Ok(combine(items, ExprKind::Alternation))
+    }
+
+    fn sequence(&mut self) -> Result<Expr, ParseError> {
+        let mut items = Vec::new();
+        match self.dialect {
+            Dialect::Iso => {
+                items.push(self.iso_term()?);
+                while self.eat(Tok::Concat).is_some() {
+                    items.push(self.iso_term()?);
+                }
+            }
+            Dialect::W3c => {
+                while !self.at_w3c_production()
+                    && !matches!(self.peek().kind, Tok::Alt | Tok::RParen | Tok::Eof)
+                {
+                    items.push(self.w3c_term()?);
+                }
+                if items.is_empty() {
+                    let at = self.tokens[self.pos - 1].span.end;
+                    items.push(empty(at));
+                }
+            }
+        }
+        Ok(combine(items, ExprKind::Sequence))
+    }
+
+    /// `[n *] primary [- primary]`
+    fn iso_term(&mut self) -> Result<Expr, ParseError> {
+        let factor = if let Tok::Integer(n) = self.peek().kind {
+            let start = self.next().span.start;
+            self.expect(Tok::Star, "after a repetition count")?;
+            let primary = self.iso_primary()?;
+            Expr {
+                span: start..primary.span.end,
+                kind: ExprKind::Repeat(n, Box::new(primary)),
+            }
+        } else {
+            self.iso_primary()?
+        };
+        self.exception(factor, Self::iso_primary)
+    }
+
+    fn iso_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.peek().clone();
+        let kind = match token.kind {
+            Tok::Concat
+            | Tok::Alt
+            | Tok::Terminator
+            | Tok::Except
+            | Tok::RParen
+            | Tok::RBracket
+            | Tok::RBrace
+            | Tok::Eof => return Ok(empty(token.span.start)),
+            Tok::LBracket => {
+                self.next();
+                let inner = self.alternation()?;
+                let end = self.expect(Tok::RBracket, "to close '['")?;
+                return Ok(Expr {
+                    kind: ExprKind::Optional(Box::new(inner)),
+                    span: token.span.start..end.span.end,
+                });
+            }
+            Tok::LBrace => {
+                self.next();
+                let inner = Box::new(self.alternation()?);
+                let mut end = self.expect(Tok::RBrace, "to close '{'")?.span.end;
+                // `{ a }-` is one or more: a `-` with nothing after it to
+                // make an exception.
+                let one_or_more = self.peek().kind == Tok::Except
+                    && matches!(
+                        self.tokens[self.pos + 1].kind,
+                        Tok::Terminator
+                            | Tok::Alt
+                            | Tok::Concat
+                            | Tok::RParen
+                            | Tok::RBracket
+                            | Tok::RBrace
+                            | Tok::Eof
+                    );
+                let kind = if one_or_more {
+                    end = self.next().span.end;
+                    ExprKind::OneOrMore(inner)
+                } else {
+                    ExprKind::ZeroOrMore(inner)
+                };
+                return Ok(Expr {
+                    kind,
+                    span: token.span.start..end,
+                });
+            }
+            Tok::Special(text) => ExprKind::Special(text),
+            _ => return self.common_primary(),
+        };
+        self.next();
+        Ok(Expr {
+            kind,
+            span: token.span,
+        })
+    }
+
+    /// `primary ('?' | '*' | '+')* [- primary]`
+    fn w3c_term(&mut self) -> Result<Expr, ParseError> {
+        let primary = self.w3c_postfix()?;
+        self.exception(primary, Self::w3c_postfix)
+    }
+
+    fn w3c_postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = match self.peek().kind.clone() {
+            Tok::Char(c) => {
+                let span = self.next().span;
+                Expr {
+                    kind: ExprKind::Char(c),
+                    span,
+                }
+            }
+            Tok::CharClass(negated, ranges) => {
+                let span = self.next().span;
+                Expr {
+                    kind: ExprKind::CharClass { negated, ranges },
+                    span,
+                }
+            }
+            _ => self.common_primary()?,
+        };
+        loop {
+            let wrap: fn(Box<Expr>) -> ExprKind = match self.peek().kind {
+                Tok::Question => ExprKind::Optional,
+                Tok::Star => ExprKind::ZeroOrMore,
+                Tok::Plus => ExprKind::OneOrMore,
+                _ => return Ok(expr),
+            };
+            let end = self.next().span.end;
+            expr = Expr {
+                span: expr.span.start..end,
+                kind: wrap(Box::new(expr)),
+            };
+        }
+    }
+
+    /// Names, terminals and parenthesized groups, which both dialects share.
+    fn common_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.next();
+        let kind = match token.kind {
+            Tok::Ident(name) => ExprKind::Nonterminal(name),
+            Tok::Literal(text) => ExprKind::Terminal(text),
+            Tok::LParen => {
+                let mut inner = self.alternation()?;
+                let end = self.expect(Tok::RParen, "to close '('")?;
+                // Keep the parentheses in the span, so that it covers
+                // everything the expression was written with.
+                inner.span = token.span.start..end.span.end;
+                return Ok(inner);
+            }
+            kind => {
+                return Err(error(format!("unexpected {}", kind.describe()), token.span));
+            }
+        };
+        Ok(Expr {
+            kind,
+            span: token.span,
+        })
+    }
+
+    fn exception(
+        &mut self,
+        base: Expr,
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        if self.eat(Tok::Except).is_none() {
+            return Ok(base);
+        }
+        let except = operand(self)?;
+        if except.kind == ExprKind::Empty {
+            let at = self.peek();
+            return Err(error(
+                format!(
+                    "expected an exception after '-', found {}",
+                    at.kind.describe()
+                ),
+                at.span.clone(),
+            ));
+        }
+        Ok(Expr {
+            span: base.span.start..except.span.end,
+            kind: ExprKind::Exception(Box::new(base), Box::new(except)),
+        })
+    }
+}
+
+fn empty(at: usize) -> Expr {
+    Expr {
+        kind: ExprKind::Empty,
+        span: at..at,
+    }
+}
+
+/// A sequence or alternation of `items`, or the item itself if there is
+/// only one.
+fn combine(mut items: Vec<Expr>, kind: fn(Vec<Expr>) -> ExprKind) -> Expr {
+    if items.len() == 1 {
+        return items.pop().unwrap();
+    }
+    let span = items[0].span.start..items[items.len() - 1].span.end;
+    Expr {
+        kind: kind(items),
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compact rendering of an expression, for comparing trees.
+    fn show(expr: &Expr) -> String {
+        let list = |items: &[Expr], sep: &str| items.iter().map(show).collect::<Vec<_>>().join(sep);
+        match &expr.kind {
+            ExprKind::Empty => "ε".to_string(),
+            ExprKind::Terminal(text) => format!("{:?}", text),
+            ExprKind::Nonterminal(name) => name.clone(),
+            ExprKind::Special(text) => format!("?{}?", text),
+            ExprKind::Char(c) => format!("#x{:X}", *c as u32),
+            ExprKind::CharClass { negated, ranges } => {
+                let ranges: Vec<_> = ranges.iter().map(|(a, b)| format!("{}-{}", a, b)).collect();
+                format!("[{}{}]", if *negated { "^" } else { "" }, ranges.join(""))
+            }
+            ExprKind::Sequence(items) => format!("({})", list(items, " ")),
+            ExprKind::Alternation(items) => format!("({})", list(items, " | ")),
+            ExprKind::Optional(inner) => format!("{}?", show(inner)),
+            ExprKind::ZeroOrMore(inner) => format!("{}*", show(inner)),
+            ExprKind::OneOrMore(inner) => format!("{}+", show(inner)),
+            ExprKind::Repeat(n, inner) => format!("{}*{}", n, show(inner)),
+            ExprKind::Exception(base, except) => format!("({} - {})", show(base), show(except)),
+        }
+    }
+
+    fn parse(text: &str, dialect: Dialect) -> Vec<String> {
+        let grammar = Grammar::parse(text, dialect).unwrap();
+        grammar
+            .productions
+            .iter()
+            .map(|p| format!("{} = {}", p.name, show(&p.expr)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_iso() {
+        let text = r#"
+(* region Digits *)
+Digits = { Digit }- ;
+Letters = { Letter } - | { Letter } - "x" ;
+Digit = ? U+0030 to U+0039 ? ;
+(* endregion *)
+(* nested (* comments *) *)
+Number = [ "-" ], Digits, [ ".", { Digit } ], 3 * "x" | ;
+NotQuote = SourceCharacter - ( '"' | "\" ) .
+syntax rule = meta identifier, "=" ;
+"#;
+        assert_eq!(
+            parse(text, Dialect::Iso),
+            [
+                "Digits = Digit+",
+                r#"Letters = (Letter+ | (Letter* - "x"))"#,
+                "Digit = ?U+0030 to U+0039?",
+                r#"Number = (("-"? Digits ("." Digit*)? 3*"x") | ε)"#,
+                r#"NotQuote = (SourceCharacter - ("\"" | "\\"))"#,
+                r#"syntax rule = (meta identifier "=")"#,
+            ]
+        );
+
+        let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+        assert_eq!(grammar.comments.len(), 3);
+        assert_eq!(grammar.comments[2].text, "nested (* comments *)");
+        assert_eq!(grammar.regions.len(), 1);
+        assert!(grammar.regions[0].closed);
+        let digit = grammar.production("Digit").unwrap();
+        assert_eq!(&text[digit.span.clone()], "Digit = ? U+0030 to U+0039 ? ;");
+        assert_eq!(&text[digit.name_span.clone()], "Digit");
+        assert_eq!(grammar.region_at(digit.span.start).unwrap().name, "Digits");
+        let number = grammar.production("Number").unwrap();
+        assert_eq!(
+            &text[number.expr.alternatives()[0].span.clone()],
+            r#"[ "-" ], Digits, [ ".", { Digit } ], 3 * "x""#
+        );
+    }
+
+    #[test]
+    fn test_parse_w3c() {
+        let text = r#"
+(* Lexical grammar *)
+Hex ::= "0" [xX] [0-9a-fA-F]+
+Source ::= #x0000-#x10FFFF
+Space ::= (* Any character in category "Zs" *)
+NotSlash ::= Source - ("/" | #x002A)
+List ::= Item ("," Item)* ","?
+  | [^a]
+"#;
+        assert_eq!(
+            parse(text, Dialect::W3c),
+            [
+                r#"Hex = ("0" [x-xX-X] [0-9a-fA-F]+)"#,
+                "Source = [\u{0}-\u{10FFFF}]",
+                r#"Space = ?Any character in category "Zs"?"#,
+                r#"NotSlash = (Source - ("/" | #x2A))"#,
+                r#"List = ((Item ("," Item)* ","?) | [^a-a])"#,
+            ]
+        );
+        let grammar = Grammar::parse(text, Dialect::W3c).unwrap();
+        let list = grammar.production("List").unwrap();
+        assert_eq!(
+            &text[list.span.clone()],
+            "List ::= Item (\",\" Item)* \",\"?\n  | [^a]"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text: &str, dialect| Grammar::parse(text, dialect).unwrap_err();
+        assert_eq!(
+            error("A = b", Dialect::Iso).message,
+            "expected ';' at the end of 'A', found end of file"
+        );
+        assert_eq!(
+            error("A = \"b ;", Dialect::Iso),
+            ParseError {
+                message: "unterminated terminal string".to_string(),
+                span: 4..8,
+            }
+        );
+        assert_eq!(
+            error("A = (* b ;", Dialect::Iso).message,
+            "unterminated comment"
+        );
+        assert_eq!(
+            error("A = b - ;", Dialect::Iso).message,
+            "expected an exception after '-', found ';'"
+        );
+        assert_eq!(
+            error("A ::= b ) c", Dialect::W3c).message,
+            "unexpected ')' in 'A'"
+        );
+        assert_eq!(error("A ::= [z-a]", Dialect::W3c).span, 6..10);
+        assert_eq!(Dialect::detect("A ::= b"), Dialect::W3c);
+        assert_eq!(Dialect::detect("A = b;"), Dialect::Iso);
+    }
+
+    #[test]
+    fn test_parse_bundled_grammars() {
+        let docs = concat!(env!("CARGO_MANIFEST_DIR"), "/docs");
+        for entry in std::fs::read_dir(docs).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "ebnf") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            let grammar = Grammar::parse(&text, Dialect::detect(&text))
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            for production in &grammar.productions {
+                assert!(text[production.span.clone()].starts_with(&production.name));
+            }
+        }
+    }
+}
@@ -0,0 +1,943 @@
+//! Writing a grammar out again, in either dialect or as ABNF.
+//!
+//! Comments stay where they were, and so do blank lines between
+//! productions and comments, and parentheses the grammar was written with
+//! even where they are not needed. A comment inside a production is written
+//! after the item it follows, or before the item it precedes if a `|`, `,`
+//! or `-` comes between them. Converting from one dialect to the other and
+//! back gives the text that was started from, for grammars that are written
+//! the way `iso` and `iso-pretty` write them, with two exceptions. W3C has
+//! no `,`, so a comment after one comes back before it. And W3C and ABNF
+//! cannot write an empty item in a sequence, as in `a, , b`, so it is left
+//! out, which does not change what the production matches.
+//!
+//! Code points are written `? U+0009 ?` in ISO and `#x0009` in W3C. A W3C
+//! character class becomes an ISO special sequence holding the class, and
+//! a W3C production described by a comment an ISO one that is a special
+//! sequence; both convert back. A special sequence anywhere else has no
+//! W3C form, and is an error. ABNF (RFC 5234, with RFC 7405 `%s` for
+//! case-sensitive strings) is only written, never read: special sequences
+//! and exceptions become prose, `<...>`.
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
+use std::ops::Range;
+
+use super::{Comment, Dialect, Expr, ExprKind, Grammar, Production};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// ISO EBNF, one production to a line.
+    Iso,
+    /// ISO EBNF with each alternative on its own line.
+    IsoPretty,
+    W3c,
+    Abnf,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Iso, Format::IsoPretty, Format::W3c, Format::Abnf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Iso => "iso",
+            Format::IsoPretty => "iso-pretty",
+            Format::W3c => "w3c",
+            Format::Abnf => "abnf",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        Format::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    fn is_iso(self) -> bool {
+        matches!(self, Format::Iso | Format::IsoPretty)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    pub message: String,
+    /// What could not be converted, in the source.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ConvertError {}
+
+fn error(message: impl Into<String>, span: Range<usize>) -> ConvertError {
+    ConvertError {
+        message: message.into(),
+        span,
+    }
+}
+
+/// Binding strength: what an expression can appear in without parentheses.
+const ALTERNATION: u8 = 0;
+const SEQUENCE: u8 = 1;
+const TERM: u8 = 2;
+const PRIMARY: u8 = 3;
+
+/// Writes `grammar`, parsed from `source`, in `format`.
+pub fn convert(grammar: &Grammar, source: &str, format: Format) -> Result<String, ConvertError> {
+    let writer = Writer {
+        source,
+        dialect: grammar.dialect,
+        format,
+        comments: &grammar.comments,
+    };
+    let mut out = String::new();
+    let mut previous: Option<(Item, usize)> = None;
+    for (item, start, end) in items(grammar, &writer) {
+        if let Some((previous, previous_end)) = previous {
+            let gap = &source[previous_end.min(start)..start];
+            let blank = gap.matches('\n').count() >= 2
+                || (format == Format::IsoPretty && matches!(previous, Item::Production(_)));
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        match item {
+            Item::Comment(comment) => writer.comment(comment, &mut out),
+            Item::Production(production) => writer.production(production, &mut out)?,
+        }
+        previous = Some((item, end));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Copy)]
+enum Item<'g> {
+    Comment(&'g Comment),
+    Production(&'g Production),
+}
+
+/// Productions and comments in order, with their spans. Comments inside a
+/// production are left to the production to write.
+fn items<'g>(grammar: &'g Grammar, writer: &Writer) -> Vec<(Item<'g>, usize, usize)> {
+    let mut items: Vec<(Item, usize, usize)> = Vec::new();
+    let mut comments = grammar.comments.iter().peekable();
+    for production in &grammar.productions {
+        let end = writer.production_end(production);
+        while let Some(comment) = comments.next_if(|c| c.span.start < end) {
+            if comment.span.start < production.span.start {
+                items.push((Item::Comment(comment), comment.span.start, comment.span.end));
+            }
+        }
+        items.push((Item::Production(production), production.span.start, end));
+    }
+    for comment in comments {
+        items.push((Item::Comment(comment), comment.span.start, comment.span.end));
+    }
+    items
+}
+
+struct Writer<'a> {
+    source: &'a str,
+    dialect: Dialect,
+    format: Format,
+    /// All comments in the source, in order.
+    comments: &'a [Comment],
+}
+
+impl<'a> Writer<'a> {
+    fn comment(&self, comment: &Comment, out: &mut String) {
+        if self.format == Format::Abnf {
+            let lines: Vec<&str> = comment.text.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                let _ = write!(out, "; {}", line.trim_end());
+            }
+        } else {
+            let _ = write!(out, "(* {} *)", comment.text);
+        }
+    }
+
+    /// Where `production` ends, with the comments after it on the same line
+    /// if it is W3C, which has no `;` to put them before.
+    fn production_end(&self, production: &Production) -> usize {
+        let mut end = production.span.end;
+        if self.dialect == Dialect::W3c {
+            let first = self.comments.partition_point(|c| c.span.start < end);
+            for comment in &self.comments[first..] {
+                if !self.source[end..comment.span.start]
+                    .trim_matches([' ', '\t'])
+                    .is_empty()
+                {
+                    break;
+                }
+                end = comment.span.end;
+            }
+        }
+        end
+    }
+
+    /// The comments that start in `range`.
+    fn comments_in(&self, range: Range<usize>) -> &'a [Comment] {
+        let start = self
+            .comments
+            .partition_point(|c| c.span.start < range.start);
+        let end = self.comments.partition_point(|c| c.span.start < range.end);
+        &self.comments[start..end.max(start)]
+    }
+
+    /// Writes the comments in `range` before an item, or after one.
+    fn inline_comments(&self, range: Range<usize>, before: bool, out: &mut String) {
+        for comment in self.comments_in(range) {
+            if !before && !out.is_empty() && !out.ends_with([' ', '\n']) {
+                out.push(' ');
+            }
+            self.comment(comment, out);
+            // An ABNF comment runs to the end of the line.
+            if self.format == Format::Abnf {
+                out.push_str("\n    ");
+            } else if before {
+                out.push(' ');
+            }
+        }
+    }
+
+    /// Where in `gap`, between two items, the first of `separators` is,
+    /// outside comments; the end of the gap if there is none.
+    fn separator(&self, gap: Range<usize>, separators: &[char]) -> usize {
+        let mut i = gap.start;
+        for comment in self.comments_in(gap.clone()) {
+            if let Some(n) = self.source[i..comment.span.start].find(separators) {
+                return i + n;
+            }
+            i = comment.span.end;
+        }
+        match self.source[i..gap.end].find(separators) {
+            Some(n) => i + n,
+            None => gap.end,
+        }
+    }
+
+    /// Writes the comments in `gap`, between two items: those before the
+    /// separator after the first item, then `separator`, then the rest
+    /// before the second, unless it is empty.
+    fn gap(
+        &self,
+        gap: Range<usize>,
+        separators: &[char],
+        separator: &str,
+        empty: bool,
+        out: &mut String,
+    ) {
+        let split = self.separator(gap.clone(), separators);
+        self.inline_comments(gap.start..split, false, out);
+        out.push_str(separator);
+        if empty && self.format != Format::Abnf && out.ends_with(' ') {
+            // `a | ;` rather than `a |  ;`.
+            out.pop();
+        }
+        self.inline_comments(split..gap.end, !empty, out);
+    }
+
+    fn production(&self, production: &Production, out: &mut String) -> Result<(), ConvertError> {
+        let name = self.name(&production.name, production.name_span.clone())?;
+        let expr = &production.expr;
+        let mut body = String::new();
+        self.inline_comments(production.name_span.end..expr.span.start, true, &mut body);
+        match (&expr.kind, self.format) {
+            // Prose for a whole production is a W3C comment.
+            (ExprKind::Special(text), Format::W3c)
+                if code_point(text).is_none() && char_class_text(text).is_none() =>
+            {
+                let _ = write!(body, "(* {} *)", text);
+            }
+            _ => self.expr(expr, ALTERNATION, &mut body)?,
+        }
+        let end = self.production_end(production);
+        self.inline_comments(expr.span.end..end, false, &mut body);
+        if self.format == Format::Abnf {
+            body.truncate(body.trim_end().len());
+        }
+        // A body that starts with an empty alternative starts with its `|`.
+        let body = body.trim_start_matches(' ');
+        let space = if body.is_empty() || body.starts_with('\n') {
+            ""
+        } else {
+            " "
+        };
+        match self.format {
+            Format::Iso => {
+                let _ = write!(out, "{} ={}{} ;", name, space, body);
+            }
+            Format::IsoPretty => {
+                // `{ a }-;` would read as a `-` with nothing after it.
+                let end = if body.ends_with('-') { " ;" } else { ";" };
+                let _ = write!(out, "{}\n    ={}{}{}", name, space, body, end);
+            }
+            Format::W3c => {
+                let _ = write!(out, "{} ::={}{}", name, space, body);
+            }
+            Format::Abnf => {
+                let _ = write!(out, "{} ={}{}", name, space, body);
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self, name: &str, span: Range<usize>) -> Result<String, ConvertError> {
+        match self.format {
+            Format::Iso | Format::IsoPretty => Ok(name.to_string()),
+            Format::W3c if name.contains(' ') => Err(error(
+                format!("'{}' has spaces, which W3C names cannot", name),
+                span,
+            )),
+            Format::W3c => Ok(name.to_string()),
+            Format::Abnf => Ok(name.replace([' ', '_'], "-")),
+        }
+    }
+
+    /// How strongly `expr` binds when written.
+    fn level(&self, expr: &Expr) -> u8 {
+        match &expr.kind {
+            ExprKind::Alternation(_) => ALTERNATION,
+            ExprKind::Sequence(_) => SEQUENCE,
+            ExprKind::Exception(..) if self.format == Format::Abnf => PRIMARY,
+            ExprKind::Exception(..) | ExprKind::Repeat(..) => TERM,
+            ExprKind::ZeroOrMore(_) | ExprKind::OneOrMore(_) if self.format == Format::Abnf => TERM,
+            _ => PRIMARY,
+        }
+    }
+
+    /// Whether `expr` was written in parentheses.
+    fn parenthesized(&self, expr: &Expr) -> bool {
+        let text = &self.source[expr.span.clone()];
+        if !text.starts_with('(') || text.starts_with("(*") {
+            return false;
+        }
+        let first = match &expr.kind {
+            ExprKind::Sequence(items) | ExprKind::Alternation(items) => items[0].span.start,
+            ExprKind::Exception(base, _) => base.span.start,
+            ExprKind::Optional(inner)
+            | ExprKind::ZeroOrMore(inner)
+            | ExprKind::OneOrMore(inner)
+                if self.dialect == Dialect::W3c =>
+            {
+                inner.span.start
+            }
+            _ => return true,
+        };
+        first != expr.span.start
+    }
+
+    /// Writes `expr` where it must bind at least as strongly as `level`,
+    /// in parentheses if it does not or was written in them.
+    fn operand(&self, expr: &Expr, level: u8, out: &mut String) -> Result<(), ConvertError> {
+        let parens = self.level(expr) < level || self.parenthesized(expr);
+        self.group(expr, parens, out)
+    }
+
+    /// Writes what brackets or a postfix operator in `outer` apply to, where
+    /// the grouping is their own and parentheses are only written if needed.
+    fn inner(
+        &self,
+        outer: &Expr,
+        expr: &Expr,
+        level: u8,
+        out: &mut String,
+    ) -> Result<(), ConvertError> {
+        let before = outer.span.start..expr.span.start;
+        let after = expr.span.end..outer.span.end;
+        // Comments stay inside a postfix operator's operand.
+        let brackets = self.format.is_iso()
+            || matches!(outer.kind, ExprKind::Optional(_)) && self.format == Format::Abnf;
+        let comments = !self.comments_in(before.clone()).is_empty()
+            || !self.comments_in(after.clone()).is_empty();
+        let parens = self.level(expr) < level || (comments && !brackets);
+        let spaced = self.format.is_iso();
+        if parens {
+            out.push_str(if spaced { "( " } else { "(" });
+        }
+        self.inline_comments(before, true, out);
+        self.leaf(expr, out)?;
+        self.inline_comments(after, false, out);
+        if parens {
+            out.push_str(if spaced { " )" } else { ")" });
+        }
+        Ok(())
+    }
+
+    fn group(&self, expr: &Expr, parens: bool, out: &mut String) -> Result<(), ConvertError> {
+        let spaced = self.format.is_iso();
+        if parens {
+            out.push_str(if spaced { "( " } else { "(" });
+        }
+        self.leaf(expr, out)?;
+        if parens {
+            out.push_str(if spaced { " )" } else { ")" });
+        }
+        Ok(())
+    }
+
+    /// Writes `expr`, and if it is a single item in parentheses, the
+    /// comments in them.
+    fn leaf(&self, expr: &Expr, out: &mut String) -> Result<(), ConvertError> {
+        let composite = matches!(
+            expr.kind,
+            ExprKind::Sequence(_)
+                | ExprKind::Alternation(_)
+                | ExprKind::Optional(_)
+                | ExprKind::ZeroOrMore(_)
+                | ExprKind::OneOrMore(_)
+                | ExprKind::Repeat(..)
+                | ExprKind::Exception(..)
+        );
+        // A W3C production described by a comment is that comment.
+        if composite || self.source[expr.span.clone()].starts_with("(*") {
+            return self.expr(expr, ALTERNATION, out);
+        }
+        let mut start = expr.span.start;
+        loop {
+            let rest = &self.source[start..expr.span.end];
+            let skipped = rest.trim_start_matches(|c: char| c == '(' || c.is_whitespace());
+            start += rest.len() - skipped.len();
+            match self.comments_in(start..start + 1) {
+                [comment] => start = comment.span.end,
+                _ => break,
+            }
+        }
+        self.inline_comments(expr.span.start..start, true, out);
+        self.expr(expr, ALTERNATION, out)?;
+        self.inline_comments(start..expr.span.end, false, out);
+        Ok(())
+    }
+
+    fn expr(&self, expr: &Expr, _level: u8, out: &mut String) -> Result<(), ConvertError> {
+        let iso = self.format.is_iso();
+        match &expr.kind {
+            ExprKind::Empty => {
+                if self.format == Format::Abnf {
+                    out.push_str("\"\"");
+                }
+            }
+            ExprKind::Terminal(text) => self.terminal(text, expr.span.clone(), out)?,
+            ExprKind::Nonterminal(name) => out.push_str(&self.name(name, expr.span.clone())?),
+            ExprKind::Special(text) => self.special(text, expr.span.clone(), out)?,
+            ExprKind::Char(c) => self.char(*c, out),
+            ExprKind::CharClass { negated, ranges } => self.char_class(*negated, ranges, out),
+            ExprKind::Sequence(items) => {
+                let separator = if iso { ", " } else { " " };
+                let mut first = true;
+                let mut end = expr.span.start;
+                for item in items {
+                    let gap = end..item.span.start;
+                    end = item.span.end;
+                    // W3C and ABNF have no way to write an empty item.
+                    if item.kind == ExprKind::Empty && !iso {
+                        self.inline_comments(gap, first, out);
+                        continue;
+                    }
+                    if first {
+                        self.inline_comments(gap, true, out);
+                    } else {
+                        self.gap(gap, &[','], separator, false, out);
+                    }
+                    first = false;
+                    self.operand(item, TERM, out)?;
+                }
+                self.inline_comments(end..expr.span.end, false, out);
+            }
+            ExprKind::Alternation(items) => {
+                let separator = match self.format {
+                    Format::IsoPretty => "\n    | ",
+                    Format::Abnf => " / ",
+                    _ => " | ",
+                };
+                let mut end = expr.span.start;
+                for (i, item) in items.iter().enumerate() {
+                    let gap = end..item.span.start;
+                    end = item.span.end;
+                    if i == 0 {
+                        self.inline_comments(gap, true, out);
+                    } else {
+                        let empty = item.kind == ExprKind::Empty;
+                        self.gap(gap, &['|'], separator, empty, out);
+                    }
+                    self.operand(item, SEQUENCE, out)?;
+                }
+                self.inline_comments(end..expr.span.end, false, out);
+            }
+            ExprKind::Optional(inner) if iso || self.format == Format::Abnf => {
+                out.push_str(if iso { "[ " } else { "[" });
+                self.inner(expr, inner, ALTERNATION, out)?;
+                out.push_str(if iso { " ]" } else { "]" });
+            }
+            ExprKind::ZeroOrMore(inner) | ExprKind::OneOrMore(inner) if iso => {
+                out.push_str("{ ");
+                self.inner(expr, inner, ALTERNATION, out)?;
+                out.push_str(" }");
+                if let ExprKind::OneOrMore(_) = expr.kind {
+                    out.push_str(if self.format == Format::IsoPretty {
+                        " -"
+                    } else {
+                        "-"
+                    });
+                }
+            }
+            ExprKind::ZeroOrMore(inner) | ExprKind::OneOrMore(inner)
+                if self.format == Format::Abnf =>
+            {
+                out.push_str(if let ExprKind::ZeroOrMore(_) = expr.kind {
+                    "*"
+                } else {
+                    "1*"
+                });
+                self.inner(expr, inner, PRIMARY, out)?;
+            }
+            ExprKind::Optional(inner)
+            | ExprKind::ZeroOrMore(inner)
+            | ExprKind::OneOrMore(inner) => {
+                self.inner(expr, inner, PRIMARY, out)?;
+                out.push(match expr.kind {
+                    ExprKind::Optional(_) => '?',
+                    ExprKind::ZeroOrMore(_) => '*',
+                    _ => '+',
+                });
+            }
+            ExprKind::Repeat(n, inner) => match self.format {
+                Format::Iso | Format::IsoPretty => {
+                    let _ = write!(out, "{} * ", n);
+                    self.inner(expr, inner, PRIMARY, out)?;
+                }
+                Format::Abnf => {
+                    let _ = write!(out, "{}", n);
+                    self.inner(expr, inner, PRIMARY, out)?;
+                }
+                Format::W3c => {
+                    return Err(error("W3C EBNF has no repetition count", expr.span.clone()));
+                }
+            },
+            ExprKind::Exception(base, except) => {
+                if self.format == Format::Abnf {
+                    let iso = Writer {
+                        format: Format::Iso,
+                        ..*self
+                    };
+                    let mut text = String::new();
+                    iso.expr(expr, ALTERNATION, &mut text)?;
+                    out.push_str(&prose(&text));
+                    return Ok(());
+                }
+                // An ISO repetition count can precede the `-`.
+                let base_level = if matches!(base.kind, ExprKind::Repeat(..)) {
+                    TERM
+                } else {
+                    PRIMARY
+                };
+                self.inline_comments(expr.span.start..base.span.start, true, out);
+                self.operand(base, base_level, out)?;
+                self.gap(base.span.end..except.span.start, &['-'], " - ", false, out);
+                self.operand(except, PRIMARY, out)?;
+                self.inline_comments(except.span.end..expr.span.end, false, out);
+            }
+        }
+        Ok(())
+    }
+
+    fn terminal(
+        &self,
+        text: &str,
+        span: Range<usize>,
+        out: &mut String,
+    ) -> Result<(), ConvertError> {
+        if self.format == Format::Abnf {
+            out.push_str(&abnf_string(text));
+            return Ok(());
+        }
+        let quote = if !text.contains('"') {
+            '"'
+        } else if !text.contains('\'') {
+            '\''
+        } else {
+            return Err(error("a terminal cannot hold both kinds of quote", span));
+        };
+        let _ = write!(out, "{}{}{}", quote, text, quote);
+        Ok(())
+    }
+
+    fn special(
+        &self,
+        text: &str,
+        span: Range<usize>,
+        out: &mut String,
+    ) -> Result<(), ConvertError> {
+        let class = char_class_text(text);
+        match self.format {
+            Format::Iso | Format::IsoPretty => {
+                if text.contains('?') {
+                    return Err(error("an ISO special sequence cannot hold a '?'", span));
+                }
+                let _ = write!(out, "? {} ?", text);
+            }
+            Format::W3c => match (code_point(text), class) {
+                (Some(c), _) => self.char(c, out),
+                (None, Some(_)) => out.push_str(text),
+                (None, None) => {
+                    return Err(error(
+                        format!(
+                            "W3C EBNF can only describe whole productions in prose, not '? {} ?'",
+                            text
+                        ),
+                        span,
+                    ));
+                }
+            },
+            Format::Abnf => match (code_point(text), class) {
+                (Some(c), _) => self.char(c, out),
+                (None, Some((negated, ranges))) => self.char_class(negated, &ranges, out),
+                (None, None) => out.push_str(&prose(text)),
+            },
+        }
+        Ok(())
+    }
+
+    fn char(&self, c: char, out: &mut String) {
+        match self.format {
+            Format::Iso | Format::IsoPretty => {
+                let _ = write!(out, "? U+{:04X} ?", c as u32);
+            }
+            Format::W3c => {
+                let _ = write!(out, "#x{:04X}", c as u32);
+            }
+            Format::Abnf => {
+                let _ = write!(out, "%x{:02X}", c as u32);
+            }
+        }
+    }
+
+    fn char_class(&self, negated: bool, ranges: &[(char, char)], out: &mut String) {
+        match self.format {
+            Format::Iso | Format::IsoPretty => {
+                let _ = write!(out, "? {} ?", w3c_class(negated, ranges));
+            }
+            Format::W3c => out.push_str(&w3c_class(negated, ranges)),
+            Format::Abnf if negated => {
+                out.push_str(&prose(&format!(
+                    "any character but {}",
+                    w3c_class(false, ranges)
+                )));
+            }
+            Format::Abnf => {
+                let values: Vec<String> = ranges
+                    .iter()
+                    .map(|&(first, last)| {
+                        if first == last {
+                            format!("%x{:02X}", first as u32)
+                        } else {
+                            format!("%x{:02X}-{:02X}", first as u32, last as u32)
+                        }
+                    })
+                    .collect();
+                if values.len() == 1 {
+                    out.push_str(&values[0]);
+                } else {
+                    let _ = write!(out, "({})", values.join(" / "));
+                }
+            }
+        }
+    }
+}
+
+/// The code point of a special sequence that is only `U+XXXX`.
+fn code_point(text: &str) -> Option<char> {
+    let digits = text.strip_prefix("U+")?;
+    if !(4..=6).contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(digits, 16).ok()?)
+}
+
+/// The class of a special sequence holding a W3C character class.
+fn char_class_text(text: &str) -> Option<(bool, Vec<(char, char)>)> {
+    if !text.starts_with(['[', '#']) {
+        return None;
+    }
+    let grammar = Grammar::parse(&format!("A ::= {}", text), Dialect::W3c).ok()?;
+    match &grammar.productions[..] {
+        [production] => match &production.expr.kind {
+            ExprKind::CharClass { negated, ranges } => Some((*negated, ranges.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A W3C character class: a bare `#xN-#xM` for one range of code points
+/// that are not printable ASCII, `[...]` otherwise.
+pub(super) fn w3c_class(negated: bool, ranges: &[(char, char)]) -> String {
+    let printable = |c: char| c.is_ascii_graphic() && !matches!(c, ']' | '^' | '-' | '#');
+    if let [(first, last)] = ranges
+        && !negated
+        && first != last
+        && !(printable(*first) && printable(*last))
+    {
+        return format!("#x{:04X}-#x{:04X}", *first as u32, *last as u32);
+    }
+    let write = |c: char| {
+        if printable(c) {
+            c.to_string()
+        } else {
+            format!("#x{:04X}", c as u32)
+        }
+    };
+    let mut text = String::from(if negated { "[^" } else { "[" });
+    for &(first, last) in ranges {
+        text.push_str(&write(first));
+        if first != last {
+            text.push('-');
+            text.push_str(&write(last));
+        }
+    }
+    text.push(']');
+    text
+}
+
+/// An ABNF string: `%s"..."` where case matters, with the characters a
+/// quoted string cannot hold as `%x` values.
+fn abnf_string(text: &str) -> String {
+    let quotable = |c: char| matches!(c, ' '..='~') && c != '"';
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let run = rest.find(|c| !quotable(c)).unwrap_or(rest.len());
+        if run > 0 {
+            let run_text = &rest[..run];
+            let case = if run_text.bytes().any(|b| b.is_ascii_alphabetic()) {
+                "%s"
+            } else {
+                ""
+            };
+            parts.push(format!("{}\"{}\"", case, run_text));
+            rest = &rest[run..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            parts.push(format!("%x{:02X}", c as u32));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if parts.len() == 1 {
+        parts.pop().unwrap()
+    } else {
+        format!("({})", parts.join(" "))
+    }
+}
+
+/// ABNF prose, `<...>`, which can only hold printable ASCII other than `>`.
+fn prose(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| {
+            if matches!(c, ' '..='~') && c != '>' {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect();
+    format!("<{}>", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_text(text: &str, from: Dialect, to: Format) -> String {
+        let grammar = Grammar::parse(text, from).unwrap();
+        convert(&grammar, text, to).unwrap()
+    }
+
+    #[test]
+    fn test_convert() {
+        let iso = r#"(* Numbers *)
+Number = [ "-" ], Digits, [ ".", { Digit } ] | "0", ( "x" | "X" ), { Hex }- ;
+Digits = { Digit }- ;
+
+Digit = ? U+0030 ? | "1" | Other - ( "2" ) ;
+Space = ? any character in general category Zs ? ;
+Quote = '"' | ;
+Empty = | "e" ;
+"#;
+        assert_eq!(convert_text(iso, Dialect::Iso, Format::Iso), iso);
+        assert_eq!(
+            convert_text(iso, Dialect::Iso, Format::IsoPretty),
+            r#"(* Numbers *)
+Number
+    = [ "-" ], Digits, [ ".", { Digit } ]
+    | "0", ( "x"
+    | "X" ), { Hex } - ;
+
+Digits
+    = { Digit } - ;
+
+Digit
+    = ? U+0030 ?
+    | "1"
+    | Other - ( "2" );
+
+Space
+    = ? any character in general category Zs ?;
+
+Quote
+    = '"'
+    |;
+
+Empty
+    =
+    | "e";
+"#
+        );
+        let w3c = r#"(* Numbers *)
+Number ::= "-"? Digits ("." Digit*)? | "0" ("x" | "X") Hex+
+Digits ::= Digit+
+
+Digit ::= #x0030 | "1" | Other - ("2")
+Space ::= (* any character in general category Zs *)
+Quote ::= '"' |
+Empty ::= | "e"
+"#;
+        assert_eq!(convert_text(iso, Dialect::Iso, Format::W3c), w3c);
+        assert_eq!(convert_text(w3c, Dialect::W3c, Format::Iso), iso);
+        assert_eq!(
+            convert_text(iso, Dialect::Iso, Format::Abnf),
+            r#"; Numbers
+Number = ["-"] Digits ["." *Digit] / "0" (%s"x" / %s"X") 1*Hex
+Digits = 1*Digit
+
+Digit = %x30 / "1" / <Other - ( "2" )>
+Space = <any character in general category Zs>
+Quote = %x22 / ""
+Empty = "" / %s"e"
+"#
+        );
+    }
+
+    #[test]
+    fn test_convert_comments() {
+        let iso = r#"Space
+    = (* before *) ? U+0009 ? (* tab *)
+    | (* empty *)
+    | [ (* in brackets *) "a" ], "b" (* before the comma *)
+    , "c" - (* except *) "d"
+    | ( "e" (* in parentheses *) ) (* last *);
+"#;
+        assert_eq!(
+            convert_text(iso, Dialect::Iso, Format::IsoPretty),
+            r#"Space
+    = (* before *) ? U+0009 ? (* tab *)
+    | (* empty *)
+    | [ (* in brackets *) "a" ], "b" (* before the comma *), "c" - (* except *) "d"
+    | ( "e" (* in parentheses *) ) (* last *);
+"#
+        );
+        let w3c = convert_text(iso, Dialect::Iso, Format::W3c);
+        assert_eq!(
+            w3c,
+            r#"Space ::= (* before *) #x0009 (* tab *) | (* empty *) | ((* in brackets *) "a")? "b" (* before the comma *) "c" - (* except *) "d" | ("e" (* in parentheses *)) (* last *)
+"#
+        );
+        let compact = convert_text(iso, Dialect::Iso, Format::Iso);
+        assert_eq!(convert_text(&w3c, Dialect::W3c, Format::Iso), compact);
+        assert_eq!(
+            convert_text(iso, Dialect::Iso, Format::Abnf),
+            r#"Space = ; before
+    %x09 ; tab
+     / ; empty
+    "" / [; in brackets
+    %s"a"] %s"b" ; before the comma
+     <"c" - (* except *) "d"> / (%s"e" ; in parentheses
+    ) ; last
+"#
+        );
+    }
+
+    #[test]
+    fn test_convert_char_classes() {
+        let w3c = "Source ::= #x0000-#x10FFFF\nHex ::= [0-9a-fA-F] | [^#x000A\"]\n";
+        let iso = convert_text(w3c, Dialect::W3c, Format::Iso);
+        assert_eq!(
+            iso,
+            "Source = ? #x0000-#x10FFFF ? ;\nHex = ? [0-9a-fA-F] ? | ? [^#x000A\"] ? ;\n"
+        );
+        assert_eq!(convert_text(&iso, Dialect::Iso, Format::W3c), w3c);
+        assert_eq!(
+            convert_text(w3c, Dialect::W3c, Format::Abnf),
+            "Source = %x00-10FFFF\n\
+             Hex = (%x30-39 / %x61-66 / %x41-46) / <any character but [#x000A\"]>\n"
+        );
+    }
+
+    #[test]
+    fn test_convert_errors() {
+        let error = |text: &str, to| {
+            let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+            convert(&grammar, text, to).unwrap_err()
+        };
+        assert_eq!(
+            error("A = b, ? prose ? ;", Format::W3c),
+            ConvertError {
+                message: "W3C EBNF can only describe whole productions in prose, not '? prose ?'"
+                    .to_string(),
+                span: 7..16,
+            }
+        );
+        assert_eq!(
+            error("A = 3 * b ;", Format::W3c).message,
+            "W3C EBNF has no repetition count"
+        );
+        assert_eq!(
+            error("two words = b ;", Format::W3c).message,
+            "'two words' has spaces, which W3C names cannot"
+        );
+    }
+
+    /// The bundled grammars are written the way `convert` writes them, and
+    /// the W3C and compact ISO ones are generated from the ISO one:
+    ///
+    /// rhi grammar convert --to w3c docs/grammar_iso.ebnf > docs/grammar_w3c.ebnf
+    /// rhi grammar convert --to iso docs/grammar_iso.ebnf > docs/grammar-bak.ebnf
+    #[test]
+    fn test_convert_bundled_grammars() {
+        let docs = concat!(env!("CARGO_MANIFEST_DIR"), "/docs/");
+        let read = |name: &str| std::fs::read_to_string(format!("{}{}", docs, name)).unwrap();
+
+        let iso = read("grammar_iso.ebnf");
+        assert_eq!(
+            convert_text(&iso, Dialect::Iso, Format::IsoPretty),
+            format!("{}\n", iso)
+        );
+        let w3c = convert_text(&iso, Dialect::Iso, Format::W3c);
+        assert_eq!(
+            convert_text(&w3c, Dialect::W3c, Format::IsoPretty),
+            format!("{}\n", iso)
+        );
+        let compact = convert_text(&iso, Dialect::Iso, Format::Iso);
+        assert_eq!(
+            convert_text(&compact, Dialect::Iso, Format::IsoPretty),
+            format!("{}\n", iso)
+        );
+        convert_text(&iso, Dialect::Iso, Format::Abnf);
+
+        assert_eq!(w3c, read("grammar_w3c.ebnf"));
+
+        // The JavaScript grammar has comments inside productions, and empty
+        // items that W3C leaves out.
+        let js = convert_text(&read("grammar-js.ebnf"), Dialect::Iso, Format::W3c);
+        let pretty = convert_text(&js, Dialect::W3c, Format::IsoPretty);
+        assert_eq!(convert_text(&pretty, Dialect::Iso, Format::W3c), js);
+        assert_eq!(compact, read("grammar-bak.ebnf"));
+    }
+}
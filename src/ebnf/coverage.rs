@@ -0,0 +1,495 @@
+//! Which productions of a grammar the tests exercise.
+//!
+//! `Coverage::record_tokens` lexes a source file with the coverage set as
+//! the lexer's `Recorder`, so it is told each lexical production the lexer
+//! matches, such as `StringLiteral` for a string or `HexEscapeSequence` for
+//! an escape in it, along with the text matched. It derives that text from
+//! the production, down to the characters, and records the productions and
+//! alternatives the derivation uses. Where a production does not match the
+//! text the lexer matched as it, the grammar and the lexer disagree, and the
+//! text is kept as a `Mismatch`. A parser can record the productions it
+//! matches with `Coverage::record`.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use super::recognize::{Index, Recognizer, Step};
+use super::{Expr, Grammar, Production};
+use crate::{LanguageVariant, Lexer, Recorder, TokenType};
+
+/// Longest sample of a mismatched token kept, in characters.
+const SAMPLE_LENGTH: usize = 40;
+
+/// Whether a production matched a text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Match {
+    Yes,
+    No,
+    /// The text was too long to tell.
+    TooLong,
+}
+
+pub struct Coverage<'g> {
+    index: Index<'g>,
+    /// Times each production was used.
+    uses: Vec<u64>,
+    /// Times each top-level alternative of each production was taken.
+    alternatives: Vec<Vec<u64>>,
+    /// How each text recorded under a production was derived, if it was.
+    derivations: HashMap<(usize, String), (Match, Vec<Step>)>,
+    mismatches: Vec<Mismatch>,
+    /// Matches too long to derive.
+    skipped: u64,
+    values: HashMap<String, Vec<String>>,
+}
+
+/// Text the lexer matched as a production that the production does not
+/// match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub grammar: usize,
+    /// The production that should have matched.
+    pub production: String,
+    /// The kind of token the text was part of.
+    pub token_type: TokenType,
+    /// The first such text, shortened.
+    pub sample: String,
+    pub count: u64,
+}
+
+/// How often a production and each of its alternatives were used.
+#[derive(Debug)]
+pub struct ProductionCoverage<'g> {
+    pub grammar: usize,
+    pub production: &'g Production,
+    pub uses: u64,
+    /// The production's top-level alternatives, with their uses.
+    pub alternatives: Vec<(&'g Expr, u64)>,
+}
+
+/// Counts for one grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub productions: usize,
+    pub covered_productions: usize,
+    /// Alternatives of productions that have more than one.
+    pub alternatives: usize,
+    pub covered_alternatives: usize,
+}
+
+impl<'g> Coverage<'g> {
+    pub fn new(grammars: &[&'g Grammar]) -> Self {
+        let index = Index::new(grammars);
+        let alternatives = index
+            .productions
+            .iter()
+            .map(|entry| vec![0; entry.production.expr.alternatives().len()])
+            .collect();
+        Coverage {
+            uses: vec![0; index.productions.len()],
+            alternatives,
+            index,
+            derivations: HashMap::new(),
+            mismatches: Vec::new(),
+            skipped: 0,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Records that `text` was matched as `name`, in every grammar that
+    /// defines it. Returns whether any of them derives `text` from it.
+    pub fn record(&mut self, name: &str, text: &str) -> bool {
+        let ids: Vec<usize> = self
+            .index
+            .grammar_names
+            .iter()
+            .filter_map(|names| names.get(name).copied())
+            .collect();
+        let mut matched = false;
+        for id in ids {
+            matched |= self.record_id(id, text) == Match::Yes;
+        }
+        matched
+    }
+
+    /// Lexes `source` and records the productions the lexer matches in it,
+    /// including comments, whitespace and line terminators.
+    pub fn record_tokens(&mut self, source: &str, variant: LanguageVariant) {
+        let mut lexer = Lexer::new(source);
+        lexer.set_language_variant(variant);
+        lexer.set_recorder(self);
+        for _ in lexer.lex() {}
+    }
+
+    fn record_id(&mut self, id: usize, text: &str) -> Match {
+        let key = (id, text.to_string());
+        if !self.derivations.contains_key(&key) {
+            let mut steps = Vec::new();
+            let mut recognizer = Recognizer::new(&self.index, &self.values, text);
+            let result = if recognizer.derive(id, &mut steps) {
+                Match::Yes
+            } else if recognizer.exhausted() {
+                Match::TooLong
+            } else {
+                Match::No
+            };
+            self.derivations.insert(key.clone(), (result, steps));
+        }
+        let (result, steps) = &self.derivations[&key];
+        if *result != Match::Yes {
+            return *result;
+        }
+        for step in steps {
+            match *step {
+                Step::Production(id) => {
+                    self.uses[id] += 1;
+                    if let [only] = &mut self.alternatives[id][..] {
+                        *only += 1;
+                    }
+                }
+                Step::Alternative(id, i) => self.alternatives[id][i] += 1,
+            }
+        }
+        Match::Yes
+    }
+
+    fn mismatch(&mut self, grammar: usize, id: usize, token_type: TokenType, text: &str) {
+        let production = &self.index.productions[id].production.name;
+        if let Some(mismatch) = self.mismatches.iter_mut().find(|mismatch| {
+            mismatch.grammar == grammar
+                && mismatch.production == *production
+                && mismatch.token_type == token_type
+        }) {
+            mismatch.count += 1;
+            return;
+        }
+        let mut sample: String = text.chars().take(SAMPLE_LENGTH).collect();
+        if sample.len() < text.len() {
+            sample.push('…');
+        }
+        self.mismatches.push(Mismatch {
+            grammar,
+            production: production.clone(),
+            token_type,
+            sample,
+            count: 1,
+        });
+    }
+
+    /// Every production, in the order of the grammars and then of the
+    /// productions in them.
+    pub fn productions(&self) -> Vec<ProductionCoverage<'g>> {
+        self.index
+            .productions
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| ProductionCoverage {
+                grammar: entry.grammar,
+                production: entry.production,
+                uses: self.uses[id],
+                alternatives: entry
+                    .production
+                    .expr
+                    .alternatives()
+                    .iter()
+                    .zip(&self.alternatives[id])
+                    .map(|(expr, &uses)| (expr, uses))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// Matches too long for their production to be derived, which are
+    /// neither used nor mismatched.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    pub fn summary(&self, grammar: usize) -> Summary {
+        let mut summary = Summary {
+            productions: 0,
+            covered_productions: 0,
+            alternatives: 0,
+            covered_alternatives: 0,
+        };
+        for production in self.productions() {
+            if production.grammar != grammar {
+                continue;
+            }
+            summary.productions += 1;
+            summary.covered_productions += (production.uses > 0) as usize;
+            if production.alternatives.len() > 1 {
+                summary.alternatives += production.alternatives.len();
+                summary.covered_alternatives += production
+                    .alternatives
+                    .iter()
+                    .filter(|(_, uses)| *uses > 0)
+                    .count();
+            }
+        }
+        summary
+    }
+
+    /// A page showing each grammar's source, given by `(name, text)` in the
+    /// order the grammars were given, with what was not used highlighted.
+    pub fn html(&self, sources: &[(&str, &str)]) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Grammar coverage</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; }\n\
+             td, th { padding: 0.2em 0.8em; text-align: right; }\n\
+             td:first-child, th:first-child { text-align: left; }\n\
+             pre { font-size: 13px; line-height: 1.4; }\n\
+             .covered { background: #e6f4e6; }\n\
+             .uncovered { background: #f8d8d8; }\n\
+             </style>\n</head>\n<body>\n<h1>Grammar coverage</h1>\n\
+             <table>\n<tr><th>Grammar</th><th>Productions</th><th>Alternatives</th></tr>\n",
+        );
+        for (grammar, (name, _)) in sources.iter().enumerate() {
+            let summary = self.summary(grammar);
+            let _ = writeln!(
+                out,
+                "<tr><td><a href=\"#grammar-{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+                grammar,
+                escape(name),
+                fraction(summary.covered_productions, summary.productions),
+                fraction(summary.covered_alternatives, summary.alternatives),
+            );
+        }
+        out.push_str("</table>\n");
+
+        let productions = self.productions();
+        for (grammar, (name, text)) in sources.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "<h2 id=\"grammar-{}\">{}</h2>\n<pre>",
+                grammar,
+                escape(name)
+            );
+            // Spans to highlight, none overlapping except alternatives
+            // inside their production.
+            let mut marks: Vec<(usize, usize, &str, String)> = Vec::new();
+            for production in productions.iter().filter(|p| p.grammar == grammar) {
+                let span = &production.production.span;
+                let class = if production.uses > 0 {
+                    "covered"
+                } else {
+                    "uncovered"
+                };
+                marks.push((span.start, span.end, class, uses(production.uses)));
+                if production.uses > 0 && production.alternatives.len() > 1 {
+                    for (expr, count) in &production.alternatives {
+                        if *count == 0 && !expr.span.is_empty() {
+                            marks.push((expr.span.start, expr.span.end, "uncovered", uses(0)));
+                        }
+                    }
+                }
+            }
+            marks.sort_by_key(|&(start, end, ..)| (start, std::cmp::Reverse(end)));
+            let mut pos = 0;
+            let mut open: Vec<usize> = Vec::new();
+            for (start, end, class, title) in marks {
+                while let Some(&close) = open.last()
+                    && close <= start
+                {
+                    escape_into(&mut out, &text[pos..close]);
+                    out.push_str("</span>");
+                    pos = close;
+                    open.pop();
+                }
+                escape_into(&mut out, &text[pos..start]);
+                let _ = write!(out, "<span class=\"{}\" title=\"{}\">", class, title);
+                pos = start;
+                open.push(end);
+            }
+            while let Some(close) = open.pop() {
+                escape_into(&mut out, &text[pos..close]);
+                out.push_str("</span>");
+                pos = close;
+            }
+            escape_into(&mut out, &text[pos..]);
+            out.push_str("</pre>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+impl Recorder for Coverage<'_> {
+    fn matched(&mut self, production: &'static str, token_type: TokenType, text: &str) {
+        for grammar in 0..self.index.grammar_names.len() {
+            let Some(&id) = self.index.grammar_names[grammar].get(production) else {
+                continue;
+            };
+            match self.record_id(id, text) {
+                Match::Yes => {}
+                Match::No => self.mismatch(grammar, id, token_type, text),
+                Match::TooLong => self.skipped += 1,
+            }
+        }
+    }
+}
+
+fn uses(count: u64) -> String {
+    match count {
+        1 => "1 use".to_string(),
+        _ => format!("{} uses", count),
+    }
+}
+
+fn fraction(covered: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{} / {} ({}%)", covered, total, covered * 100 / total)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    escape_into(&mut escaped, text);
+    escaped
+}
+
+fn escape_into(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebnf::Dialect;
+
+    const GRAMMAR: &str = r#"Comment = "//", { ? any character ? } ;
+WhiteSpace = " " | ? U+0009 ? ;
+LineTerminatorSequence = ? U+000A ? | ? U+000D ?, ? U+000A ? ;
+IdentifierName = Letter, { Letter } ;
+Letter = "a" | "b" | "c" ;
+Punctuator = "=" | ";" | "(" ;
+NumericLiteral = Digit, { Digit } | "0x", Digit ;
+Digit = "0" | "1" ;
+Statement = IdentifierName, "=", NumericLiteral, ";" ;
+"#;
+
+    #[test]
+    fn test_coverage() {
+        let grammar = Grammar::parse(GRAMMAR, Dialect::Iso).unwrap();
+        let mut coverage = Coverage::new(&[&grammar]);
+        coverage.record_tokens("ab = 10; // c\n", LanguageVariant::Standard);
+
+        let uses: Vec<(&str, u64, Vec<u64>)> = coverage
+            .productions()
+            .iter()
+            .map(|p| {
+                let alternatives = p.alternatives.iter().map(|(_, uses)| *uses).collect();
+                (p.production.name.as_str(), p.uses, alternatives)
+            })
+            .collect();
+        assert_eq!(
+            uses,
+            [
+                ("Comment", 1, vec![1]),
+                ("WhiteSpace", 3, vec![3, 0]),
+                ("LineTerminatorSequence", 1, vec![1, 0]),
+                ("IdentifierName", 1, vec![1]),
+                ("Letter", 2, vec![1, 1, 0]),
+                ("Punctuator", 2, vec![1, 1, 0]),
+                ("NumericLiteral", 1, vec![1, 0]),
+                ("Digit", 2, vec![1, 1]),
+                ("Statement", 0, vec![0]),
+            ]
+        );
+        assert_eq!(
+            coverage.summary(0),
+            Summary {
+                productions: 9,
+                covered_productions: 8,
+                alternatives: 14,
+                covered_alternatives: 9,
+            }
+        );
+
+        assert!(coverage.record("Statement", "ab=10;"));
+        assert!(!coverage.record("Statement", "ab = 10;"));
+        assert_eq!(coverage.summary(0).covered_productions, 9);
+    }
+
+    #[test]
+    fn test_coverage_mismatches() {
+        let grammar = Grammar::parse(GRAMMAR, Dialect::Iso).unwrap();
+        let mut coverage = Coverage::new(&[&grammar]);
+        coverage.record_tokens("d = 2; e = 3; /* f */", LanguageVariant::Standard);
+        let mismatches: Vec<(&str, TokenType, &str, u64)> = coverage
+            .mismatches()
+            .iter()
+            .map(|m| {
+                (
+                    m.production.as_str(),
+                    m.token_type,
+                    m.sample.as_str(),
+                    m.count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            mismatches,
+            [
+                ("IdentifierName", TokenType::Identifier, "d", 2),
+                ("NumericLiteral", TokenType::NumericLiteral, "2", 2),
+                ("Comment", TokenType::MultiLineCommentTrivia, "/* f */", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coverage_long_tokens() {
+        let grammar = Grammar::parse(
+            r#"Comment = "/*", [ Chars ], "*/" ;
+Chars = Char, [ Chars ] ;
+Char = ? any character ? - "*" ;"#,
+            Dialect::Iso,
+        )
+        .unwrap();
+        let mut coverage = Coverage::new(&[&grammar]);
+        coverage.record_tokens(
+            &format!("/*{}*/", "a".repeat(500)),
+            LanguageVariant::Standard,
+        );
+        assert_eq!(coverage.productions()[1].uses, 500);
+        coverage.record_tokens(
+            &format!("/*{}*/", "a".repeat(5000)),
+            LanguageVariant::Standard,
+        );
+        assert_eq!(coverage.skipped(), 1);
+        assert!(coverage.mismatches().is_empty());
+    }
+
+    #[test]
+    fn test_coverage_html() {
+        let grammar = Grammar::parse(GRAMMAR, Dialect::Iso).unwrap();
+        let mut coverage = Coverage::new(&[&grammar]);
+        coverage.record_tokens("a", LanguageVariant::Standard);
+        let html = coverage.html(&[("<js>", GRAMMAR)]);
+        assert!(html.contains(
+            "<a href=\"#grammar-0\">&lt;js&gt;</a></td><td>2 / 9 (22%)</td><td>1 / 14 (7%)</td>"
+        ));
+        assert!(html.contains(
+            "<span class=\"covered\" title=\"1 use\">Letter = &quot;a&quot; | \
+             <span class=\"uncovered\" title=\"0 uses\">&quot;b&quot;</span> | "
+        ));
+        assert!(html.contains("<span class=\"uncovered\" title=\"0 uses\">Statement = "));
+        assert!(html.ends_with("</pre>\n</body>\n</html>\n"));
+    }
+}
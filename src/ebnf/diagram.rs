@@ -0,0 +1,462 @@
+//! Railroad diagrams of productions, as standalone SVG.
+//!
+//! Terminals are drawn in rounded boxes, and nonterminals in square ones
+//! that link to `Name.svg`, so that a directory of diagrams written by the
+//! CLI can be browsed. Special sequences and character classes are drawn
+//! in dashed boxes, and an exception as a dashed frame around what it
+//! restricts, with what is excluded written under it.
+
+use std::fmt::Write as _;
+
+use super::convert::w3c_class;
+use super::{Expr, ExprKind, Production};
+
+/// Radius of the curves where tracks split and join.
+const ARC: f64 = 10.0;
+/// Vertical space between the tracks of a choice or loop.
+const GAP: f64 = 8.0;
+/// Horizontal track between the items of a sequence.
+const SPACE: f64 = 10.0;
+const BOX_HEIGHT: f64 = 22.0;
+/// Width of a character in the 13px monospace font the labels use.
+const CHAR_WIDTH: f64 = 8.0;
+/// Space around the diagram, and the height of the title above it.
+const MARGIN: f64 = 10.0;
+const TITLE: f64 = 24.0;
+
+const STYLE: &str = "\
+svg { background: white; }
+path { fill: none; stroke: #333; stroke-width: 1.5; }
+rect { fill: #f4f4f4; stroke: #333; stroke-width: 1.5; }
+rect.special, rect.exception { stroke-dasharray: 4 3; }
+rect.exception { fill: none; }
+text { font: 13px monospace; fill: #111; text-anchor: middle; }
+text.title { font-weight: bold; text-anchor: start; }
+text.caption { font-size: 11px; fill: #555; }
+a rect { fill: #e8eef8; }
+";
+
+/// Draws `production` as a railroad diagram.
+pub fn diagram(production: &Production) -> String {
+    let node = Node::from_expr(&production.expr);
+    let width = node.width + 2 * MARGIN as usize + 4 * SPACE as usize;
+    let title_width = production.name.chars().count() as f64 * CHAR_WIDTH + 2.0 * MARGIN;
+    let width = (width as f64).max(title_width);
+    let y = MARGIN + TITLE + node.up;
+    let height = y + node.down + MARGIN;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height,
+    );
+    let _ = writeln!(out, "<title>{}</title>", escape(&production.name));
+    let _ = writeln!(out, "<style>\n{}</style>", STYLE);
+    let _ = writeln!(
+        out,
+        "<text class=\"title\" x=\"{}\" y=\"{}\">{}</text>",
+        MARGIN,
+        MARGIN + 14.0,
+        escape(&production.name)
+    );
+    // The track starts and ends at a bar.
+    let start = MARGIN;
+    let end = MARGIN + 4.0 * SPACE + node.width as f64;
+    let _ = writeln!(
+        out,
+        "<path d=\"M{s} {t}v16M{s} {y}h{sp}M{e} {t}v16M{e2} {y}h{sp}\"/>",
+        s = start,
+        e = end,
+        e2 = end - 2.0 * SPACE,
+        t = y - 8.0,
+        y = y,
+        sp = 2.0 * SPACE,
+    );
+    node.draw(start + 2.0 * SPACE, y, &mut out);
+    out.push_str("</svg>\n");
+    out
+}
+
+enum Kind {
+    Terminal(String),
+    Nonterminal(String),
+    Special(String),
+    /// An empty track.
+    Skip,
+    Sequence(Vec<Node>),
+    /// Alternatives, drawn above the one on the main line and then below.
+    Choice(Vec<Node>, usize),
+    /// What is repeated on the main line, and what comes between the
+    /// repetitions on the track back.
+    Loop(Box<Node>, Box<Node>),
+    /// An exception, and what it excludes.
+    Exception(Box<Node>, String),
+}
+
+/// A part of a diagram, and the room it needs around its main line.
+struct Node {
+    kind: Kind,
+    width: usize,
+    up: f64,
+    down: f64,
+}
+
+impl Node {
+    fn new(kind: Kind) -> Node {
+        let half = BOX_HEIGHT / 2.0;
+        let label = |text: &str| text.chars().count() * CHAR_WIDTH as usize + 2 * SPACE as usize;
+        let (width, up, down) = match &kind {
+            Kind::Terminal(text) | Kind::Nonterminal(text) | Kind::Special(text) => {
+                (label(text), half, half)
+            }
+            Kind::Skip => (0, 0.0, 0.0),
+            Kind::Sequence(items) => (
+                items.iter().map(|item| item.width).sum::<usize>()
+                    + SPACE as usize * items.len().saturating_sub(1),
+                items.iter().map(|item| item.up).fold(0.0, f64::max),
+                items.iter().map(|item| item.down).fold(0.0, f64::max),
+            ),
+            Kind::Choice(items, main) => {
+                let offsets = choice_offsets(items, *main);
+                let up = items
+                    .iter()
+                    .zip(&offsets)
+                    .map(|(item, offset)| item.up - offset)
+                    .fold(0.0, f64::max);
+                let down = items
+                    .iter()
+                    .zip(&offsets)
+                    .map(|(item, offset)| item.down + offset)
+                    .fold(0.0, f64::max);
+                let width = items.iter().map(|item| item.width).max().unwrap_or(0);
+                (width + 4 * ARC as usize, up, down)
+            }
+            Kind::Loop(item, back) => (
+                item.width.max(back.width) + 2 * ARC as usize,
+                item.up,
+                loop_offset(item, back) + back.down,
+            ),
+            Kind::Exception(item, caption) => (
+                item.width.max(label(caption)) + 2 * SPACE as usize,
+                item.up + GAP,
+                item.down + GAP + 14.0,
+            ),
+        };
+        Node {
+            kind,
+            width,
+            up,
+            down,
+        }
+    }
+
+    fn from_expr(expr: &Expr) -> Node {
+        let kind = match &expr.kind {
+            ExprKind::Empty => Kind::Skip,
+            ExprKind::Terminal(text) => Kind::Terminal(text.clone()),
+            ExprKind::Nonterminal(name) => Kind::Nonterminal(name.clone()),
+            ExprKind::Special(text) => Kind::Special(text.clone()),
+            ExprKind::Char(c) => Kind::Special(format!("U+{:04X}", *c as u32)),
+            ExprKind::CharClass { negated, ranges } => Kind::Special(w3c_class(*negated, ranges)),
+            ExprKind::Sequence(items) => {
+                let items: Vec<Node> = items
+                    .iter()
+                    .map(Node::from_expr)
+                    .filter(|item| !matches!(item.kind, Kind::Skip))
+                    .collect();
+                match items.len() {
+                    0 => Kind::Skip,
+                    1 => return items.into_iter().next().unwrap(),
+                    _ => Kind::Sequence(items),
+                }
+            }
+            ExprKind::Alternation(items) => {
+                Kind::Choice(items.iter().map(Node::from_expr).collect(), 0)
+            }
+            ExprKind::Optional(inner) => optional(Node::from_expr(inner)),
+            ExprKind::ZeroOrMore(inner) => optional(repeated(Node::from_expr(inner))),
+            ExprKind::OneOrMore(inner) => return repeated(Node::from_expr(inner)),
+            ExprKind::Repeat(n, inner) => match n {
+                0 => Kind::Skip,
+                1 => return Node::from_expr(inner),
+                _ => Kind::Sequence((0..*n).map(|_| Node::from_expr(inner)).collect()),
+            },
+            ExprKind::Exception(base, except) => Kind::Exception(
+                Box::new(Node::from_expr(base)),
+                format!("except {}", describe(except)),
+            ),
+        };
+        Node::new(kind)
+    }
+
+    /// Draws the node with its main line from `(x, y)` to `(x + width, y)`.
+    fn draw(&self, x: f64, y: f64, out: &mut String) {
+        let width = self.width as f64;
+        match &self.kind {
+            Kind::Terminal(text) => draw_box(x, y, width, text, "terminal", out),
+            Kind::Nonterminal(name) => {
+                let _ = write!(out, "<a href=\"{}.svg\">", escape(name));
+                draw_box(x, y, width, name, "nonterminal", out);
+                out.push_str("</a>\n");
+            }
+            Kind::Special(text) => draw_box(x, y, width, text, "special", out),
+            Kind::Skip => {}
+            Kind::Sequence(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        let _ = writeln!(out, "<path d=\"M{} {}h{}\"/>", x, y, SPACE);
+                        x += SPACE;
+                    }
+                    item.draw(x, y, out);
+                    x += item.width as f64;
+                }
+            }
+            Kind::Choice(items, main) => {
+                let inner = width - 4.0 * ARC;
+                for (item, offset) in items.iter().zip(choice_offsets(items, *main)) {
+                    let item_x = x + 2.0 * ARC;
+                    let rest = inner - item.width as f64;
+                    if offset == 0.0 {
+                        let _ = writeln!(
+                            out,
+                            "<path d=\"M{} {y}h{a}M{} {y}h{}\"/>",
+                            x,
+                            item_x + item.width as f64,
+                            rest + 2.0 * ARC,
+                            y = y,
+                            a = 2.0 * ARC,
+                        );
+                    } else {
+                        // Down (or up) from the main line, along, and back.
+                        let (sign, into, out_of) = if offset > 0.0 {
+                            (1.0, 1, 0)
+                        } else {
+                            (-1.0, 0, 1)
+                        };
+                        let v = offset - 2.0 * ARC * sign;
+                        let _ = writeln!(
+                            out,
+                            "<path d=\"M{x} {y}a{r} {r} 0 0 {into} {r} {dr}v{v}a{r} {r} 0 0 {out_of} {r} {dr}\
+                             M{ex} {iy}h{rest}a{r} {r} 0 0 {out_of} {r} {ndr}v{nv}a{r} {r} 0 0 {into} {r} {ndr}\"/>",
+                            x = x,
+                            y = y,
+                            r = ARC,
+                            dr = ARC * sign,
+                            ndr = -ARC * sign,
+                            v = v,
+                            nv = -v,
+                            ex = item_x + item.width as f64,
+                            iy = y + offset,
+                            rest = rest,
+                            into = into,
+                            out_of = out_of,
+                        );
+                    }
+                    item.draw(item_x, y + offset, out);
+                }
+            }
+            Kind::Loop(item, back) => {
+                let inner = width - 2.0 * ARC;
+                let offset = loop_offset(item, back);
+                let end = x + ARC + inner;
+                let back_x = x + ARC + (inner - back.width as f64) / 2.0;
+                let _ = writeln!(
+                    out,
+                    "<path d=\"M{x} {y}h{r}M{ie} {y}H{w}\
+                     M{end} {y}a{r} {r} 0 0 1 {r} {r}v{v}a{r} {r} 0 0 1 -{r} {r}H{be}\
+                     M{bx} {by}H{xr}a{r} {r} 0 0 1 -{r} -{r}v-{v}a{r} {r} 0 0 1 {r} -{r}\"/>",
+                    x = x,
+                    y = y,
+                    r = ARC,
+                    ie = x + ARC + item.width as f64,
+                    w = x + width,
+                    end = end,
+                    v = offset - 2.0 * ARC,
+                    be = back_x + back.width as f64,
+                    bx = back_x,
+                    by = y + offset,
+                    xr = x + ARC,
+                );
+                item.draw(x + ARC, y, out);
+                back.draw(back_x, y + offset, out);
+            }
+            Kind::Exception(item, caption) => {
+                let _ = writeln!(
+                    out,
+                    "<rect class=\"exception\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                    x,
+                    y - self.up,
+                    width,
+                    self.up + self.down - 14.0,
+                );
+                let _ = writeln!(
+                    out,
+                    "<text class=\"caption\" x=\"{}\" y=\"{}\">{}</text>",
+                    x + width / 2.0,
+                    y + self.down - 3.0,
+                    escape(caption)
+                );
+                let item_x = x + (width - item.width as f64) / 2.0;
+                let _ = writeln!(
+                    out,
+                    "<path d=\"M{} {y}H{}M{} {y}H{}\"/>",
+                    x,
+                    item_x,
+                    item_x + item.width as f64,
+                    x + width,
+                    y = y,
+                );
+                item.draw(item_x, y, out);
+            }
+        }
+    }
+}
+
+fn optional(node: Node) -> Kind {
+    Kind::Choice(vec![Node::new(Kind::Skip), node], 1)
+}
+
+fn repeated(node: Node) -> Node {
+    Node::new(Kind::Loop(Box::new(node), Box::new(Node::new(Kind::Skip))))
+}
+
+/// How far each alternative of a choice is drawn below its main line.
+fn choice_offsets(items: &[Node], main: usize) -> Vec<f64> {
+    let mut offsets = vec![0.0; items.len()];
+    let mut edge = items[main].down;
+    for i in main + 1..items.len() {
+        offsets[i] = (edge + GAP + items[i].up).max(2.0 * ARC);
+        edge = offsets[i] + items[i].down;
+    }
+    let mut edge = items[main].up;
+    for i in (0..main).rev() {
+        offsets[i] = -(edge + GAP + items[i].down).max(2.0 * ARC);
+        edge = -offsets[i] + items[i].up;
+    }
+    offsets
+}
+
+/// How far below its main line a loop's track back is.
+fn loop_offset(item: &Node, back: &Node) -> f64 {
+    (item.down + GAP + back.up).max(2.0 * ARC)
+}
+
+fn draw_box(x: f64, y: f64, width: f64, text: &str, class: &str, out: &mut String) {
+    let radius = if class == "terminal" {
+        BOX_HEIGHT / 2.0
+    } else {
+        0.0
+    };
+    let _ = writeln!(
+        out,
+        "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>",
+        class,
+        x,
+        y - BOX_HEIGHT / 2.0,
+        width,
+        BOX_HEIGHT,
+        radius
+    );
+    let _ = writeln!(
+        out,
+        "<text x=\"{}\" y=\"{}\">{}</text>",
+        x + width / 2.0,
+        y + 4.0,
+        escape(text)
+    );
+}
+
+/// A short text for what an exception excludes.
+fn describe(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Empty => "nothing".to_string(),
+        ExprKind::Terminal(text) => format!("\"{}\"", text),
+        ExprKind::Nonterminal(name) => name.clone(),
+        ExprKind::Special(text) => text.clone(),
+        ExprKind::Char(c) => format!("U+{:04X}", *c as u32),
+        ExprKind::CharClass { negated, ranges } => w3c_class(*negated, ranges),
+        ExprKind::Alternation(items) => items.iter().map(describe).collect::<Vec<_>>().join(" | "),
+        ExprKind::Sequence(items) => items.iter().map(describe).collect::<Vec<_>>().join(" "),
+        ExprKind::Optional(inner) => format!("[{}]", describe(inner)),
+        ExprKind::ZeroOrMore(inner) => format!("{{{}}}", describe(inner)),
+        ExprKind::OneOrMore(inner) => format!("{{{}}}-", describe(inner)),
+        ExprKind::Repeat(n, inner) => format!("{} * {}", n, describe(inner)),
+        ExprKind::Exception(base, except) => format!("{} - {}", describe(base), describe(except)),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebnf::{Dialect, Grammar};
+
+    fn draw(text: &str) -> String {
+        let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+        diagram(&grammar.productions[0])
+    }
+
+    #[test]
+    fn test_diagram() {
+        let svg = draw(r#"If = "if", "(", Expression, ")", [ "else", Statement ] ;"#);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains("<title>If</title>"));
+        assert!(svg.contains("<a href=\"Expression.svg\">"));
+        assert_eq!(svg.matches("class=\"terminal\"").count(), 4);
+        assert_eq!(svg.matches("class=\"nonterminal\"").count(), 2);
+    }
+
+    #[test]
+    fn test_diagram_layout() {
+        let node = |text: &str| {
+            let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+            Node::from_expr(&grammar.productions[0].expr)
+        };
+        // "ab" is 16 wide, and 10 either side.
+        let terminal = node(r#"A = "ab" ;"#);
+        assert_eq!(
+            (terminal.width, terminal.up, terminal.down),
+            (36, 11.0, 11.0)
+        );
+        let sequence = node(r#"A = "ab", "ab" ;"#);
+        assert_eq!(sequence.width, 82);
+        let choice = node(r#"A = "ab" | "ab" ;"#);
+        assert_eq!((choice.width, choice.up, choice.down), (76, 11.0, 41.0));
+        let optional = node(r#"A = [ "ab" ] ;"#);
+        assert_eq!(
+            (optional.width, optional.up, optional.down),
+            (76, 20.0, 11.0)
+        );
+        let repeated = node(r#"A = { "ab" }- ;"#);
+        assert_eq!(
+            (repeated.width, repeated.up, repeated.down),
+            (56, 11.0, 20.0)
+        );
+        let except = node(r#"A = "ab" - "c" ;"#);
+        assert_eq!(except.width, 120);
+    }
+
+    #[test]
+    fn test_diagram_escapes() {
+        let svg = draw(r#"A = '<&">', B - ( "x" | C ) ;"#);
+        assert!(svg.contains(">&lt;&amp;&quot;&gt;</text>"));
+        assert!(svg.contains(">except &quot;x&quot; | C</text>"));
+    }
+}
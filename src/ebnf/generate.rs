@@ -0,0 +1,772 @@
+//! Random sentences of a grammar, for fuzzing.
+//!
+//! The generator walks productions from a start symbol, choosing among
+//! alternatives at random, within a budget on how deeply productions nest.
+//! Each production's minimal derivation depth is computed up front, so an
+//! alternative is only taken if it can be finished within the budget, and
+//! generation always ends.
+//!
+//! Productions of the lexical grammar, those before a
+//! `(* === SYNTACTIC GRAMMAR === *)` comment and any made only of them,
+//! produce one token each, with no spaces inside; everything else is
+//! separated by a space. Special sequences are replaced by characters that
+//! fit their description, `? a standard JavaScript Name ?` by `Name` from
+//! another grammar, and `a - b` is retried until it does not match `b`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use super::recognize::{Class, Index, Recognizer, Special, special};
+use super::{Expr, ExprKind, Grammar};
+
+const SYNTACTIC_GRAMMAR: &str = "=== SYNTACTIC GRAMMAR ===";
+
+/// How deeply productions nest unless set with `max_depth`.
+const DEFAULT_MAX_DEPTH: usize = 60;
+/// How many productions are expanded unless set with `max_size`.
+const DEFAULT_MAX_SIZE: usize = 1000;
+/// Most repetitions of `{ a }`, each taken with even odds.
+const MAX_REPEAT: usize = 4;
+/// Tries at `a - b` before giving up.
+const ATTEMPTS: usize = 100;
+/// The height of what cannot be generated at all.
+const INFINITE: usize = usize::MAX;
+
+/// Characters for the special sequences, beyond ASCII where they allow it.
+const LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZéπжℵ";
+const DIGITS: &str = "0123456789٣";
+const SPACES: &str = " \u{a0}\u{1680}\u{2003}\u{202f}\u{3000}";
+const SOURCE: &str =
+    " !\"#$%&'()*+,-./0123456789:;<=>?@ABCXYZ[\\]^_`abcxyz{|}~\t\né中😀\u{a0}\u{2028}";
+
+pub struct Generator<'g> {
+    index: Index<'g>,
+    lexical: Vec<bool>,
+    max_depth: usize,
+    max_size: usize,
+    /// Productions expanded so far in the current sentence.
+    size: usize,
+    weights: HashMap<String, u32>,
+    values: HashMap<String, Vec<String>>,
+    rng: Rng,
+    /// Minimal derivation depth of each production, once computed.
+    heights: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerateError {
+    pub message: String,
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for GenerateError {}
+
+fn error(message: String) -> GenerateError {
+    GenerateError { message }
+}
+
+/// Characters to generate for a class.
+fn pool(class: &Class) -> Vec<char> {
+    match class {
+        Class::Listed(chars) => chars.clone(),
+        Class::Space => SPACES.chars().collect(),
+        Class::IdStart { dollar, underscore } => {
+            let mut chars: Vec<char> = LETTERS.chars().collect();
+            chars.extend(dollar.then_some('$'));
+            chars.extend(underscore.then_some('_'));
+            chars
+        }
+        Class::IdContinue => LETTERS.chars().chain(DIGITS.chars()).chain(['_']).collect(),
+        Class::Digit => DIGITS.chars().collect(),
+        Class::Any => SOURCE.chars().collect(),
+    }
+}
+
+/// Text generated so far, and whether it is inside a token.
+struct Output {
+    text: String,
+    lexical: bool,
+}
+
+impl Output {
+    fn new(lexical: bool) -> Self {
+        Output {
+            text: String::new(),
+            lexical,
+        }
+    }
+
+    fn emit(&mut self, text: &str) {
+        if !self.lexical && !self.text.is_empty() && !text.is_empty() {
+            self.text.push(' ');
+        }
+        self.text.push_str(text);
+    }
+}
+
+impl<'g> Generator<'g> {
+    /// A generator over `grammars`. A name defined in more than one is
+    /// taken from the first, so a grammar that extends another comes
+    /// before it.
+    pub fn new(grammars: &[&'g Grammar]) -> Self {
+        let index = Index::new(grammars);
+        let syntactic: Vec<Option<usize>> = grammars
+            .iter()
+            .map(|grammar| {
+                grammar
+                    .comments
+                    .iter()
+                    .find(|comment| comment.text == SYNTACTIC_GRAMMAR)
+                    .map(|comment| comment.span.start)
+            })
+            .collect();
+        let lexical_names: BTreeSet<&str> = index
+            .productions
+            .iter()
+            .filter(|entry| {
+                syntactic[entry.grammar].is_some_and(|start| entry.production.span.start < start)
+            })
+            .map(|entry| entry.production.name.as_str())
+            .collect();
+        let lexical = index
+            .productions
+            .iter()
+            .map(|entry| lexical_names.contains(entry.production.name.as_str()))
+            .collect();
+        let mut generator = Generator {
+            index,
+            lexical,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_size: DEFAULT_MAX_SIZE,
+            size: 0,
+            weights: HashMap::new(),
+            values: HashMap::new(),
+            rng: Rng::new(0),
+            heights: None,
+        };
+        generator.find_lexical();
+        generator
+    }
+
+    /// Seeds the random choices; the same seed gives the same sentences.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// How deeply productions may nest.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Roughly how many productions a sentence expands. Past that, each
+    /// choice takes the shortest way to finish, as nesting alone does not
+    /// keep sentences small where a production repeats itself.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Generates `name` as a single token, for lexical productions that the
+    /// grammar does not show to be lexical.
+    pub fn lexical(mut self, name: &str) -> Self {
+        for names in &self.index.grammar_names {
+            if let Some(&id) = names.get(name) {
+                self.lexical[id] = true;
+            }
+        }
+        self.find_lexical();
+        self
+    }
+
+    /// Makes alternatives that use `name` more or less likely. Each
+    /// alternative is weighted by the product of the weights of the names
+    /// in it, 1 unless given; with a weight of 0, `name` is only used where
+    /// nothing else fits.
+    pub fn weight(mut self, name: &str, weight: u32) -> Self {
+        self.weights.insert(name.to_string(), weight);
+        self.heights = None;
+        self
+    }
+
+    /// Generates one of `values` for a production, or for a special
+    /// sequence with this text, instead of what the grammar says.
+    pub fn values(mut self, name: &str, values: &[&str]) -> Self {
+        self.values.insert(
+            name.to_string(),
+            values.iter().map(|value| value.to_string()).collect(),
+        );
+        self.heights = None;
+        self
+    }
+
+    /// A random sentence of the production `start`.
+    pub fn generate(&mut self, start: &str) -> Result<String, GenerateError> {
+        if self.heights.is_none() {
+            self.heights = Some(self.compute_heights());
+        }
+        let id = *self
+            .index
+            .names
+            .get(start)
+            .ok_or_else(|| error(format!("'{}' is not defined", start)))?;
+        let mut out = Output::new(false);
+        if self.choose_value(start, &mut out) {
+            return Ok(out.text);
+        }
+        let height = self.heights.as_ref().unwrap()[id];
+        if height == INFINITE {
+            return Err(error(format!("'{}' cannot be generated", start)));
+        }
+        if height >= self.max_depth {
+            return Err(error(format!(
+                "'{}' needs a depth of at least {}",
+                start,
+                height + 1
+            )));
+        }
+        self.size = 0;
+        self.production(id, self.max_depth - 1, &mut out)?;
+        Ok(out.text)
+    }
+
+    /// Marks productions lexical if everything they refer to is, and they
+    /// either describe characters themselves or only choose between tokens,
+    /// as `Identifier = IdentifierName - ReservedWord` does, or if a
+    /// production of the same name in another grammar is lexical.
+    fn find_lexical(&mut self) {
+        loop {
+            let mut changed = false;
+            for id in 0..self.index.productions.len() {
+                if self.lexical[id] {
+                    continue;
+                }
+                let entry = &self.index.productions[id];
+                let name = entry.production.name.as_str();
+                if self
+                    .index
+                    .grammar_names
+                    .iter()
+                    .any(|names| names.get(name).is_some_and(|&other| self.lexical[other]))
+                {
+                    self.lexical[id] = true;
+                    changed = true;
+                    continue;
+                }
+                let (mut references, mut all_lexical, mut chars) = (0, true, false);
+                entry.production.expr.walk(&mut |expr| {
+                    let target = match &expr.kind {
+                        ExprKind::Nonterminal(name) => self.index.names.get(name.as_str()).copied(),
+                        ExprKind::Special(text) => match special(text) {
+                            Special::Chars(_) => {
+                                chars = true;
+                                return;
+                            }
+                            Special::Reference(name) => {
+                                self.index.resolve_other(name, entry.grammar)
+                            }
+                            Special::Unknown => None,
+                        },
+                        _ => return,
+                    };
+                    if let Some(target) = target {
+                        references += 1;
+                        all_lexical &= self.lexical[target];
+                    }
+                });
+                let choice = entry
+                    .production
+                    .expr
+                    .alternatives()
+                    .iter()
+                    .all(|item| !matches!(item.kind, ExprKind::Sequence(_)));
+                if all_lexical && (chars || (references > 0 && choice)) {
+                    self.lexical[id] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn compute_heights(&self) -> Vec<usize> {
+        let mut heights = vec![INFINITE; self.index.productions.len()];
+        loop {
+            let mut changed = false;
+            for id in 0..self.index.productions.len() {
+                let entry = &self.index.productions[id];
+                let height = self.height(&heights, &entry.production.expr, entry.grammar);
+                if height < heights[id] {
+                    heights[id] = height;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return heights;
+            }
+        }
+    }
+
+    /// How deep a derivation of `expr` must go at least.
+    fn height(&self, heights: &[usize], expr: &Expr, grammar: usize) -> usize {
+        let below = |id: Option<usize>| match id.map(|id| heights[id]) {
+            Some(INFINITE) | None => INFINITE,
+            Some(height) => height + 1,
+        };
+        match &expr.kind {
+            ExprKind::Empty
+            | ExprKind::Terminal(_)
+            | ExprKind::Char(_)
+            | ExprKind::CharClass { .. }
+            | ExprKind::Optional(_)
+            | ExprKind::ZeroOrMore(_)
+            | ExprKind::Repeat(0, _) => 0,
+            ExprKind::Nonterminal(name) => {
+                if self.values.contains_key(name) {
+                    1
+                } else if self.weights.get(name) == Some(&0) {
+                    INFINITE
+                } else {
+                    below(self.index.names.get(name.as_str()).copied())
+                }
+            }
+            ExprKind::Special(text) => {
+                if self.values.contains_key(text) {
+                    return 0;
+                }
+                match special(text) {
+                    Special::Chars(_) => 0,
+                    Special::Reference(name) => below(self.index.resolve_other(name, grammar)),
+                    Special::Unknown => INFINITE,
+                }
+            }
+            ExprKind::Sequence(items) => items
+                .iter()
+                .map(|item| self.height(heights, item, grammar))
+                .max()
+                .unwrap_or(0),
+            ExprKind::Alternation(items) => items
+                .iter()
+                .map(|item| self.height(heights, item, grammar))
+                .min()
+                .unwrap_or(INFINITE),
+            ExprKind::OneOrMore(inner)
+            | ExprKind::Repeat(_, inner)
+            | ExprKind::Exception(inner, _) => self.height(heights, inner, grammar),
+        }
+    }
+
+    /// The product of the weights of the names in `expr`.
+    fn weight_of(&self, expr: &Expr) -> u64 {
+        let mut weight = 1u64;
+        expr.walk(&mut |expr| {
+            if let ExprKind::Nonterminal(name) = &expr.kind
+                && let Some(&w) = self.weights.get(name)
+            {
+                weight = weight.saturating_mul(w as u64);
+            }
+        });
+        weight
+    }
+
+    /// Whether `expr` fits in `depth` and is not weighted out.
+    fn fits(&self, expr: &Expr, grammar: usize, depth: usize) -> bool {
+        let heights = self.heights.as_ref().unwrap();
+        self.height(heights, expr, grammar) <= depth && self.weight_of(expr) > 0
+    }
+
+    /// Whether optional parts may still be added.
+    fn growing(&self) -> bool {
+        self.size < self.max_size
+    }
+
+    fn production(
+        &mut self,
+        id: usize,
+        depth: usize,
+        out: &mut Output,
+    ) -> Result<(), GenerateError> {
+        self.size += 1;
+        let entry = &self.index.productions[id];
+        let (expr, grammar) = (&entry.production.expr, entry.grammar);
+        if self.lexical[id] == out.lexical {
+            return self.expr(expr, grammar, depth, out);
+        }
+        // A token among others, or tokens inside one, as in the expressions
+        // of a template.
+        let mut inner = Output::new(self.lexical[id]);
+        self.expr(expr, grammar, depth, &mut inner)?;
+        if out.lexical {
+            out.text.push_str(&inner.text);
+        } else {
+            out.emit(&inner.text);
+        }
+        Ok(())
+    }
+
+    fn choose_value(&mut self, name: &str, out: &mut Output) -> bool {
+        let Some(values) = self.values.get(name) else {
+            return false;
+        };
+        let value = &values[self.rng.below(values.len())];
+        out.emit(value);
+        true
+    }
+
+    fn expr(
+        &mut self,
+        expr: &'g Expr,
+        grammar: usize,
+        depth: usize,
+        out: &mut Output,
+    ) -> Result<(), GenerateError> {
+        match &expr.kind {
+            ExprKind::Empty => {}
+            ExprKind::Terminal(text) => out.emit(text),
+            ExprKind::Nonterminal(name) => {
+                if !self.choose_value(name, out) {
+                    self.production(self.index.names[name.as_str()], depth - 1, out)?;
+                }
+            }
+            ExprKind::Special(text) => {
+                if self.choose_value(text, out) {
+                    return Ok(());
+                }
+                match special(text) {
+                    Special::Chars(class) => {
+                        let pool = pool(&class);
+                        let c = pool[self.rng.below(pool.len())];
+                        out.emit(c.encode_utf8(&mut [0; 4]));
+                    }
+                    Special::Reference(name) => {
+                        let id = self.index.resolve_other(name, grammar).unwrap();
+                        self.production(id, depth - 1, out)?;
+                    }
+                    Special::Unknown => {
+                        unreachable!("special sequences that cannot be generated are never chosen")
+                    }
+                }
+            }
+            ExprKind::Char(c) => out.emit(c.encode_utf8(&mut [0; 4])),
+            ExprKind::CharClass { negated, ranges } => {
+                let pool: Vec<char> = if *negated {
+                    SOURCE
+                        .chars()
+                        .filter(|c| {
+                            !ranges
+                                .iter()
+                                .any(|(first, last)| (first..=last).contains(&c))
+                        })
+                        .collect()
+                } else {
+                    ranges
+                        .iter()
+                        .flat_map(|&(first, last)| [first, last])
+                        .collect()
+                };
+                if !pool.is_empty() {
+                    let c = pool[self.rng.below(pool.len())];
+                    out.emit(c.encode_utf8(&mut [0; 4]));
+                }
+            }
+            ExprKind::Sequence(items) => {
+                for item in items {
+                    self.expr(item, grammar, depth, out)?;
+                }
+            }
+            ExprKind::Alternation(items) => {
+                let candidates: Vec<(&'g Expr, u64)> = items
+                    .iter()
+                    .filter(|item| self.fits(item, grammar, depth))
+                    .map(|item| (item, self.weight_of(item)))
+                    .collect();
+                let heights = self.heights.as_ref().unwrap();
+                let item = if candidates.is_empty() {
+                    // Only alternatives weighted out are left.
+                    items
+                        .iter()
+                        .min_by_key(|item| self.height(heights, item, grammar))
+                        .unwrap()
+                } else if !self.growing() {
+                    candidates
+                        .iter()
+                        .min_by_key(|(item, _)| self.height(heights, item, grammar))
+                        .unwrap()
+                        .0
+                } else {
+                    let total: u64 = candidates.iter().map(|(_, w)| w).sum();
+                    let mut pick = self.rng.next() % total;
+                    candidates
+                        .iter()
+                        .find(|(_, w)| {
+                            let found = pick < *w;
+                            pick = pick.saturating_sub(*w);
+                            found
+                        })
+                        .unwrap()
+                        .0
+                };
+                self.expr(item, grammar, depth, out)?;
+            }
+            ExprKind::Optional(inner) => {
+                if self.growing() && self.fits(inner, grammar, depth) && self.rng.below(2) == 0 {
+                    self.expr(inner, grammar, depth, out)?;
+                }
+            }
+            ExprKind::ZeroOrMore(inner) => self.repeat(inner, grammar, depth, 0, out)?,
+            ExprKind::OneOrMore(inner) => self.repeat(inner, grammar, depth, 1, out)?,
+            ExprKind::Repeat(count, inner) => {
+                for _ in 0..*count {
+                    self.expr(inner, grammar, depth, out)?;
+                }
+            }
+            ExprKind::Exception(base, except) => {
+                for _ in 0..ATTEMPTS {
+                    let mut trial = Output::new(true);
+                    self.expr(base, grammar, depth, &mut trial)?;
+                    if !Recognizer::new(&self.index, &self.values, &trial.text)
+                        .matches(except, grammar)
+                    {
+                        out.emit(&trial.text);
+                        return Ok(());
+                    }
+                }
+                return Err(error(format!(
+                    "no sentence of '{}' found that is not excluded",
+                    describe(base)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn repeat(
+        &mut self,
+        inner: &'g Expr,
+        grammar: usize,
+        depth: usize,
+        min: usize,
+        out: &mut Output,
+    ) -> Result<(), GenerateError> {
+        let mut count = 0;
+        while count < min
+            || (count < MAX_REPEAT
+                && self.growing()
+                && self.fits(inner, grammar, depth)
+                && self.rng.below(2) == 0)
+        {
+            self.expr(inner, grammar, depth, out)?;
+            count += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A short description of `expr` for errors.
+fn describe(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Nonterminal(name) => name.clone(),
+        ExprKind::Special(text) => format!("? {} ?", text),
+        _ => "an expression".to_string(),
+    }
+}
+
+/// A xorshift generator: small, and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Spread the seed with splitmix64, as xorshift needs a state that
+        // is not 0, and similar seeds should not start out alike.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebnf::Dialect;
+
+    fn parse(text: &str) -> Grammar {
+        Grammar::parse(text, Dialect::Iso).unwrap()
+    }
+
+    fn sentences(generator: &mut Generator, start: &str, n: usize) -> Vec<String> {
+        (0..n).map(|_| generator.generate(start).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_generate() {
+        let grammar = parse(
+            r#"Number = Digit, { Digit } ;
+Digit = "0" | "1" | "2" ;
+(* === SYNTACTIC GRAMMAR === *)
+Sum = Number, { "+", Number } ;
+List = "[", [ Sum, { ",", Sum } ], "]" ;
+"#,
+        );
+        let mut generator = Generator::new(&[&grammar]).seed(7);
+        let list = sentences(&mut generator, "List", 50);
+        for text in &list {
+            assert!(text.starts_with("[ ") && text.ends_with(" ]"), "{:?}", text);
+            // Numbers are single tokens; everything else is spaced out.
+            for token in text.split(' ') {
+                assert!(
+                    ["[", "]", ",", "+"].contains(&token)
+                        || token.chars().all(|c| matches!(c, '0'..='2')),
+                    "{:?} in {:?}",
+                    token,
+                    text
+                );
+            }
+        }
+        assert!(list.iter().any(|text| text.contains('+')));
+        assert!(list.iter().any(|text| text == "[ ]"));
+
+        // The same seed gives the same sentences.
+        let mut again = Generator::new(&[&grammar]).seed(7);
+        assert_eq!(sentences(&mut again, "List", 50), list);
+        let mut other = Generator::new(&[&grammar]).seed(8);
+        assert_ne!(sentences(&mut other, "List", 50), list);
+    }
+
+    #[test]
+    fn test_depth() {
+        let grammar = parse(
+            r#"E = "x" | "(", E, ")" ;
+Deep = "[", E, "]" ;
+Loop = "(", Loop, ")" ;
+"#,
+        );
+        let mut generator = Generator::new(&[&grammar]).max_depth(4).seed(1);
+        for text in sentences(&mut generator, "E", 100) {
+            assert!(text.matches('(').count() <= 3, "{:?}", text);
+        }
+        let mut shallow = Generator::new(&[&grammar]).max_depth(1);
+        assert_eq!(
+            shallow.generate("Deep"),
+            Err(error("'Deep' needs a depth of at least 2".to_string()))
+        );
+        assert_eq!(
+            generator.generate("Loop"),
+            Err(error("'Loop' cannot be generated".to_string()))
+        );
+        assert_eq!(
+            generator.generate("Missing"),
+            Err(error("'Missing' is not defined".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_weight_and_values() {
+        let grammar = parse(
+            r#"S = A | B | C ;
+A = "a" ;
+B = "b" ;
+C = "c" ;
+"#,
+        );
+        let mut generator = Generator::new(&[&grammar])
+            .weight("A", 0)
+            .weight("B", 20)
+            .values("C", &["x", "y"]);
+        let texts = sentences(&mut generator, "S", 200);
+        assert!(!texts.contains(&"a".to_string()));
+        assert!(!texts.contains(&"c".to_string()));
+        let b = texts.iter().filter(|text| *text == "b").count();
+        assert!(b > 150, "{} of 200", b);
+        assert!(texts.contains(&"x".to_string()) && texts.contains(&"y".to_string()));
+
+        // Weight 0 only keeps a name out where something else fits.
+        let mut generator = Generator::new(&[&grammar]).weight("A", 0);
+        assert_eq!(generator.generate("A").unwrap(), "a");
+    }
+
+    #[test]
+    fn test_specials_and_exceptions() {
+        let grammar = parse(
+            r#"Name = Start, { Part } ;
+Start = ? any Unicode code point with the Unicode property “ID_Start” ? | "$" ;
+Part = ? any Unicode code point with the Unicode property “ID_Continue” ? ;
+Word = Name - Keyword ;
+Keyword = "if" | "in" | "do" ;
+Short = "i", ( "f" | "n" | "x" ) ;
+NotKeyword = Short - Keyword ;
+Space = ? any Unicode character in general category Zs ? ;
+(* === SYNTACTIC GRAMMAR === *)
+"#,
+        );
+        let extension = parse(
+            r#"Name = ? a standard JavaScript Name ?, "!" ;
+Missing = ? a standard JavaScript Missing ? ;
+"#,
+        );
+        let mut generator = Generator::new(&[&grammar]).seed(3);
+        for text in sentences(&mut generator, "Name", 200) {
+            let mut chars = text.chars();
+            let first = chars.next().unwrap();
+            assert!(first == '$' || first.is_alphabetic(), "{:?}", text);
+            assert!(chars.all(|c| c.is_alphanumeric() || c == '_'), "{:?}", text);
+        }
+        assert_eq!(sentences(&mut generator, "NotKeyword", 20), vec!["ix"; 20]);
+        for text in sentences(&mut generator, "Word", 200) {
+            assert!(!["if", "in", "do"].contains(&text.as_str()));
+        }
+        for text in sentences(&mut generator, "Space", 20) {
+            assert!(SPACES.contains(&text), "{:?}", text);
+        }
+
+        // References to the same name in another grammar.
+        let mut generator = Generator::new(&[&extension, &grammar]).seed(3);
+        for text in sentences(&mut generator, "Name", 20) {
+            assert!(text.len() > 1 && text.ends_with('!'), "{:?}", text);
+        }
+        assert!(generator.generate("Missing").is_err());
+    }
+
+    #[test]
+    fn test_lexical() {
+        let grammar = parse(
+            r##"Private = "#", Name ;
+Name = "a" | "b" ;
+(* === SYNTACTIC GRAMMAR === *)
+Field = Private, ";" ;
+Chosen = "#", Name ;
+"##,
+        );
+        let mut generator = Generator::new(&[&grammar]).seed(2);
+        for text in sentences(&mut generator, "Field", 10) {
+            assert!(text == "#a ;" || text == "#b ;", "{:?}", text);
+        }
+        for text in sentences(&mut generator, "Chosen", 10) {
+            assert!(text == "# a" || text == "# b", "{:?}", text);
+        }
+        let mut generator = Generator::new(&[&grammar]).lexical("Chosen");
+        for text in sentences(&mut generator, "Chosen", 10) {
+            assert!(text == "#a" || text == "#b", "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,830 @@
+//! Checks for mistakes in a grammar, and for differences between two
+//! grammars that should describe the same language.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::{Expr, ExprKind, Grammar};
+use crate::diagnostic::Severity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// A name is used but never defined.
+    Undefined,
+    /// A name is defined more than once.
+    DuplicateProduction,
+    /// The grammar has no productions at all.
+    EmptyGrammar,
+    /// A production is never used, and is not a start symbol.
+    Unused,
+    /// A production is used, but only from productions that cannot be
+    /// reached from a start symbol or an unused production.
+    Unreachable,
+    /// An alternation lists the same alternative twice.
+    DuplicateAlternative,
+    /// A production can derive itself without consuming anything first.
+    LeftRecursion,
+    /// A `region` comment without an `endregion`, or the other way round.
+    Region,
+    /// A production differs from the one of the same name in the grammar
+    /// being compared against, or is missing from one of them.
+    Mismatch,
+}
+
+impl Check {
+    pub const ALL: [Check; 9] = [
+        Check::Undefined,
+        Check::DuplicateProduction,
+        Check::EmptyGrammar,
+        Check::Unused,
+        Check::Unreachable,
+        Check::DuplicateAlternative,
+        Check::LeftRecursion,
+        Check::Region,
+        Check::Mismatch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::Undefined => "undefined",
+            Check::DuplicateProduction => "duplicate-production",
+            Check::EmptyGrammar => "empty-grammar",
+            Check::Unused => "unused",
+            Check::Unreachable => "unreachable",
+            Check::DuplicateAlternative => "duplicate-alternative",
+            Check::LeftRecursion => "left-recursion",
+            Check::Region => "region",
+            Check::Mismatch => "mismatch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Check> {
+        Check::ALL.into_iter().find(|check| check.name() == name)
+    }
+
+    /// Errors make the grammar meaningless as written; warnings are likely
+    /// mistakes.
+    pub fn severity(self) -> Severity {
+        match self {
+            Check::Undefined | Check::DuplicateProduction | Check::EmptyGrammar => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub check: Check,
+    pub message: String,
+    pub span: Range<usize>,
+    /// A second place the lint refers to, such as the first definition of
+    /// a duplicate. For `Mismatch` it is in the other grammar.
+    pub related: Option<Range<usize>>,
+}
+
+impl Lint {
+    fn new(check: Check, message: String, span: Range<usize>) -> Self {
+        Lint {
+            check,
+            message,
+            span,
+            related: None,
+        }
+    }
+
+    fn related(mut self, span: Range<usize>) -> Self {
+        self.related = Some(span);
+        self
+    }
+}
+
+/// Lints `grammar`, in source order. `starts` are the productions the
+/// language starts from; without any, the first production is the start.
+pub fn lint(grammar: &Grammar, starts: &[&str]) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let Some(first) = grammar.productions.first() else {
+        lints.push(Lint::new(
+            Check::EmptyGrammar,
+            "the grammar has no productions".to_string(),
+            0..0,
+        ));
+        return lints;
+    };
+    let starts: Vec<&str> = if starts.is_empty() {
+        vec![&first.name]
+    } else {
+        starts.to_vec()
+    };
+
+    let mut definitions: HashMap<&str, usize> = HashMap::new();
+    for (i, production) in grammar.productions.iter().enumerate() {
+        if let Some(&first) = definitions.get(production.name.as_str()) {
+            lints.push(
+                Lint::new(
+                    Check::DuplicateProduction,
+                    format!("'{}' is already defined", production.name),
+                    production.name_span.clone(),
+                )
+                .related(grammar.productions[first].name_span.clone()),
+            );
+        } else {
+            definitions.insert(&production.name, i);
+        }
+    }
+    for name in &starts {
+        if !definitions.contains_key(name) {
+            lints.push(Lint::new(
+                Check::Undefined,
+                format!("the start symbol '{}' is not defined", name),
+                0..0,
+            ));
+        }
+    }
+
+    // What each production refers to, other than itself.
+    let mut references: Vec<Vec<usize>> = vec![Vec::new(); grammar.productions.len()];
+    let mut used = HashSet::new();
+    for (i, production) in grammar.productions.iter().enumerate() {
+        production.expr.walk(&mut |expr| {
+            let ExprKind::Nonterminal(name) = &expr.kind else {
+                return;
+            };
+            match definitions.get(name.as_str()) {
+                Some(&target) => {
+                    if *name != production.name {
+                        used.insert(name.as_str());
+                        references[i].push(target);
+                    }
+                }
+                None => lints.push(Lint::new(
+                    Check::Undefined,
+                    format!("'{}' is not defined", name),
+                    expr.span.clone(),
+                )),
+            }
+        });
+        for alternation in alternations(&production.expr) {
+            duplicate_alternatives(&production.name, alternation, &mut lints);
+        }
+    }
+
+    let mut reachable = vec![false; grammar.productions.len()];
+    let mut stack = Vec::new();
+    for (i, production) in grammar.productions.iter().enumerate() {
+        if definitions.get(production.name.as_str()) != Some(&i) {
+            continue;
+        }
+        let start = starts.contains(&production.name.as_str());
+        if !start && !used.contains(production.name.as_str()) {
+            lints.push(Lint::new(
+                Check::Unused,
+                format!("'{}' is never used", production.name),
+                production.name_span.clone(),
+            ));
+        } else if !start {
+            continue;
+        }
+        reachable[i] = true;
+        stack.push(i);
+    }
+    while let Some(i) = stack.pop() {
+        for &target in &references[i] {
+            if !reachable[target] {
+                reachable[target] = true;
+                stack.push(target);
+            }
+        }
+    }
+    for (i, production) in grammar.productions.iter().enumerate() {
+        if !reachable[i] && definitions.get(production.name.as_str()) == Some(&i) {
+            lints.push(Lint::new(
+                Check::Unreachable,
+                format!(
+                    "'{}' cannot be reached from a start symbol or an unused production",
+                    production.name
+                ),
+                production.name_span.clone(),
+            ));
+        }
+    }
+
+    left_recursion(grammar, &definitions, &mut lints);
+    regions(grammar, &mut lints);
+
+    lints.sort_by_key(|lint| lint.span.start);
+    lints
+}
+
+/// The differences between `grammar` and `other`: productions defined in
+/// only one of them, and productions that differ. Differences that are
+/// only a matter of notation, such as `[ a ]` against `a?`, `"0" | "1"`
+/// against `[01]` or the order of alternatives, do not count, and a special
+/// sequence matches anything, since its prose cannot be compared.
+///
+/// Spans are in `grammar`, except `related`, which is in `other`.
+pub fn compare(grammar: &Grammar, other: &Grammar, other_name: &str) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    // A name that one grammar spells out as characters and the other as
+    // prose, like `SourceCharacter`, is left as a name on both sides.
+    let (mut ours, mut theirs) = (char_sets(grammar), char_sets(other));
+    ours.retain(|name, _| other.production(name).is_none() || theirs.contains_key(name));
+    theirs.retain(|name, _| grammar.production(name).is_none() || ours.contains_key(name));
+    for production in &grammar.productions {
+        match other.production(&production.name) {
+            None => lints.push(Lint::new(
+                Check::Mismatch,
+                format!("'{}' is not defined in {}", production.name, other_name),
+                production.name_span.clone(),
+            )),
+            Some(other_production) => {
+                if !same(
+                    &shape(&production.expr, Some(&ours)),
+                    &shape(&other_production.expr, Some(&theirs)),
+                ) {
+                    lints.push(
+                        Lint::new(
+                            Check::Mismatch,
+                            format!(
+                                "'{}' is defined differently in {}",
+                                production.name, other_name
+                            ),
+                            production.name_span.clone(),
+                        )
+                        .related(other_production.name_span.clone()),
+                    );
+                }
+            }
+        }
+    }
+    let end = grammar.productions.last().map_or(0, |p| p.span.end);
+    for theirs in &other.productions {
+        if grammar.production(&theirs.name).is_none() {
+            lints.push(
+                Lint::new(
+                    Check::Mismatch,
+                    format!("'{}' from {} is not defined", theirs.name, other_name),
+                    end..end,
+                )
+                .related(theirs.name_span.clone()),
+            );
+        }
+    }
+    lints
+}
+
+fn alternations(expr: &Expr) -> Vec<&Expr> {
+    let mut found = Vec::new();
+    expr.walk(&mut |e| {
+        if matches!(e.kind, ExprKind::Alternation(_)) {
+            found.push(e);
+        }
+    });
+    found
+}
+
+fn duplicate_alternatives(name: &str, alternation: &Expr, lints: &mut Vec<Lint>) {
+    let items = alternation.alternatives();
+    let shapes: Vec<Shape> = items.iter().map(|item| shape(item, None)).collect();
+    for (i, item) in items.iter().enumerate() {
+        if let Some(first) = shapes[..i].iter().position(|s| *s == shapes[i]) {
+            lints.push(
+                Lint::new(
+                    Check::DuplicateAlternative,
+                    format!("duplicate alternative in '{}'", name),
+                    item.span.clone(),
+                )
+                .related(items[first].span.clone()),
+            );
+        }
+    }
+}
+
+/// One lint per set of mutually left-recursive productions, at the first of
+/// them.
+fn left_recursion(grammar: &Grammar, definitions: &HashMap<&str, usize>, lints: &mut Vec<Lint>) {
+    let productions = &grammar.productions;
+
+    // Which productions can derive the empty string, to a fixed point.
+    let mut nullable = vec![false; productions.len()];
+    loop {
+        let mut changed = false;
+        for (i, production) in productions.iter().enumerate() {
+            if !nullable[i] && is_nullable(&production.expr, definitions, &nullable) {
+                nullable[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let edges: Vec<Vec<usize>> = productions
+        .iter()
+        .map(|p| {
+            let mut corners = Vec::new();
+            left_corners(&p.expr, definitions, &nullable, &mut corners);
+            corners
+        })
+        .collect();
+
+    for component in strongly_connected(&edges) {
+        let first = *component.iter().min().unwrap();
+        if component.len() == 1 && !edges[first].contains(&first) {
+            continue;
+        }
+        let production = &productions[first];
+        let message = if component.len() == 1 {
+            format!("'{}' is left-recursive", production.name)
+        } else {
+            let mut others: Vec<usize> = component.into_iter().filter(|&i| i != first).collect();
+            others.sort();
+            let others: Vec<String> = others
+                .iter()
+                .map(|&i| format!("'{}'", productions[i].name))
+                .collect();
+            format!(
+                "'{}' is left-recursive through {}",
+                production.name,
+                others.join(", ")
+            )
+        };
+        lints.push(Lint::new(
+            Check::LeftRecursion,
+            message,
+            production.name_span.clone(),
+        ));
+    }
+}
+
+fn is_nullable(expr: &Expr, definitions: &HashMap<&str, usize>, nullable: &[bool]) -> bool {
+    match &expr.kind {
+        ExprKind::Empty | ExprKind::Optional(_) | ExprKind::ZeroOrMore(_) => true,
+        ExprKind::Terminal(_)
+        | ExprKind::Special(_)
+        | ExprKind::Char(_)
+        | ExprKind::CharClass { .. } => false,
+        ExprKind::Nonterminal(name) => definitions.get(name.as_str()).is_some_and(|&i| nullable[i]),
+        ExprKind::Sequence(items) => items
+            .iter()
+            .all(|item| is_nullable(item, definitions, nullable)),
+        ExprKind::Alternation(items) => items
+            .iter()
+            .any(|item| is_nullable(item, definitions, nullable)),
+        ExprKind::OneOrMore(inner) => is_nullable(inner, definitions, nullable),
+        ExprKind::Repeat(n, inner) => *n == 0 || is_nullable(inner, definitions, nullable),
+        ExprKind::Exception(base, _) => is_nullable(base, definitions, nullable),
+    }
+}
+
+/// The productions that can come first in `expr`.
+fn left_corners(
+    expr: &Expr,
+    definitions: &HashMap<&str, usize>,
+    nullable: &[bool],
+    corners: &mut Vec<usize>,
+) {
+    match &expr.kind {
+        ExprKind::Nonterminal(name) => {
+            if let Some(&i) = definitions.get(name.as_str())
+                && !corners.contains(&i)
+            {
+                corners.push(i);
+            }
+        }
+        ExprKind::Sequence(items) => {
+            for item in items {
+                left_corners(item, definitions, nullable, corners);
+                if !is_nullable(item, definitions, nullable) {
+                    break;
+                }
+            }
+        }
+        ExprKind::Alternation(items) => {
+            for item in items {
+                left_corners(item, definitions, nullable, corners);
+            }
+        }
+        ExprKind::Optional(inner)
+        | ExprKind::ZeroOrMore(inner)
+        | ExprKind::OneOrMore(inner)
+        | ExprKind::Repeat(_, inner)
+        | ExprKind::Exception(inner, _) => left_corners(inner, definitions, nullable, corners),
+        ExprKind::Empty
+        | ExprKind::Terminal(_)
+        | ExprKind::Special(_)
+        | ExprKind::Char(_)
+        | ExprKind::CharClass { .. } => {}
+    }
+}
+
+/// Tarjan's algorithm, without recursion since grammars can be deep.
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = edges.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_index = 0;
+
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // (node, next edge to follow)
+        let mut work = vec![(root, 0)];
+        index[root] = next_index;
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&mut (node, ref mut edge)) = work.last_mut() {
+            if let Some(&target) = edges[node].get(*edge) {
+                *edge += 1;
+                if index[target] == UNVISITED {
+                    index[target] = next_index;
+                    low[target] = next_index;
+                    next_index += 1;
+                    stack.push(target);
+                    on_stack[target] = true;
+                    work.push((target, 0));
+                } else if on_stack[target] {
+                    low[node] = low[node].min(index[target]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+fn regions(grammar: &Grammar, lints: &mut Vec<Lint>) {
+    for region in grammar.regions.iter().filter(|r| !r.closed) {
+        let start = region.span.start;
+        lints.push(Lint::new(
+            Check::Region,
+            format!("region '{}' is never closed", region.name),
+            start..start,
+        ));
+    }
+    let mut depth = 0usize;
+    for comment in &grammar.comments {
+        let keyword = comment.text.split_whitespace().next();
+        if keyword == Some("region") {
+            depth += 1;
+        } else if keyword == Some("endregion") {
+            if depth == 0 {
+                lints.push(Lint::new(
+                    Check::Region,
+                    "'endregion' without a region".to_string(),
+                    comment.span.clone(),
+                ));
+            }
+            depth = depth.saturating_sub(1);
+        }
+    }
+}
+
+/// An expression with its notation normalized away, for comparisons.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Empty,
+    Terminal(String),
+    Nonterminal(String),
+    Special(String),
+    Chars {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    Sequence(Vec<Shape>),
+    Alternation(Vec<Shape>),
+    Optional(Box<Shape>),
+    ZeroOrMore(Box<Shape>),
+    OneOrMore(Box<Shape>),
+    Repeat(u32, Box<Shape>),
+    Exception(Box<Shape>, Box<Shape>),
+}
+
+/// Names of productions that are only a set of characters, such as
+/// `DecimalDigit`, with the characters, so that comparisons see through them.
+type CharSets = HashMap<String, Vec<(char, char)>>;
+
+fn char_sets(grammar: &Grammar) -> CharSets {
+    let mut sets = CharSets::new();
+    loop {
+        let mut changed = false;
+        for production in &grammar.productions {
+            if sets.contains_key(&production.name) {
+                continue;
+            }
+            if let Shape::Chars {
+                negated: false,
+                ranges,
+            } = shape(&production.expr, Some(&sets))
+            {
+                sets.insert(production.name.clone(), ranges);
+                changed = true;
+            }
+        }
+        if !changed {
+            return sets;
+        }
+    }
+}
+
+/// The shape of `expr`. Single characters are character classes, so `"0"`
+/// and `#x30` agree. With `sets`, for comparing grammars, productions that
+/// are sets of characters are inlined, alternatives that are characters
+/// are merged into one class, and an empty alternative makes the rest
+/// optional.
+fn shape(expr: &Expr, sets: Option<&CharSets>) -> Shape {
+    let chars = |ranges: Vec<(char, char)>| Shape::Chars {
+        negated: false,
+        ranges,
+    };
+    match &expr.kind {
+        ExprKind::Empty => Shape::Empty,
+        ExprKind::Terminal(text) => {
+            let mut chars_iter = text.chars();
+            match (chars_iter.next(), chars_iter.next()) {
+                (Some(c), None) => chars(vec![(c, c)]),
+                _ => Shape::Terminal(text.clone()),
+            }
+        }
+        ExprKind::Nonterminal(name) => match sets.and_then(|sets| sets.get(name)) {
+            Some(ranges) => chars(ranges.clone()),
+            None => Shape::Nonterminal(name.clone()),
+        },
+        ExprKind::Special(text) => match code_point(text) {
+            Some(c) => chars(vec![(c, c)]),
+            None => Shape::Special(text.clone()),
+        },
+        ExprKind::Char(c) => chars(vec![(*c, *c)]),
+        ExprKind::CharClass { negated, ranges } => Shape::Chars {
+            negated: *negated,
+            ranges: ranges.clone(),
+        },
+        ExprKind::Sequence(items) => {
+            let mut shapes = Vec::new();
+            for item in items {
+                match shape(item, sets) {
+                    Shape::Sequence(inner) => shapes.extend(inner),
+                    Shape::Empty => {}
+                    other => shapes.push(other),
+                }
+            }
+            match shapes.len() {
+                0 => Shape::Empty,
+                1 => shapes.pop().unwrap(),
+                _ => Shape::Sequence(shapes),
+            }
+        }
+        ExprKind::Alternation(items) => {
+            let merge = sets.is_some();
+            let mut shapes = Vec::new();
+            let mut merged: Option<usize> = None;
+            let mut optional = false;
+            for item in items {
+                let inner = match shape(item, sets) {
+                    Shape::Alternation(inner) => inner,
+                    other => vec![other],
+                };
+                for s in inner {
+                    match (s, merged) {
+                        (Shape::Empty, _) if merge => optional = true,
+                        (
+                            Shape::Chars {
+                                negated: false,
+                                ranges,
+                            },
+                            Some(at),
+                        ) if merge => {
+                            if let Shape::Chars { ranges: into, .. } = &mut shapes[at] {
+                                into.extend(ranges);
+                            }
+                        }
+                        (s @ Shape::Chars { negated: false, .. }, None) if merge => {
+                            merged = Some(shapes.len());
+                            shapes.push(s);
+                        }
+                        (s, _) => shapes.push(s),
+                    }
+                }
+            }
+            if let Some(Shape::Chars { ranges, .. }) = merged.map(|at| &mut shapes[at]) {
+                *ranges = normalize_ranges(ranges);
+            }
+            let alternation = match shapes.len() {
+                0 => Shape::Empty,
+                1 => shapes.pop().unwrap(),
+                _ => Shape::Alternation(shapes),
+            };
+            if optional && alternation != Shape::Empty {
+                Shape::Optional(Box::new(alternation))
+            } else {
+                alternation
+            }
+        }
+        ExprKind::Optional(inner) => Shape::Optional(Box::new(shape(inner, sets))),
+        ExprKind::ZeroOrMore(inner) => Shape::ZeroOrMore(Box::new(shape(inner, sets))),
+        ExprKind::OneOrMore(inner) => Shape::OneOrMore(Box::new(shape(inner, sets))),
+        ExprKind::Repeat(n, inner) => Shape::Repeat(*n, Box::new(shape(inner, sets))),
+        ExprKind::Exception(base, except) => {
+            Shape::Exception(Box::new(shape(base, sets)), Box::new(shape(except, sets)))
+        }
+    }
+}
+
+/// The code point of a special sequence that is just `U+XXXX`.
+fn code_point(text: &str) -> Option<char> {
+    let digits = text.strip_prefix("U+")?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(digits, 16).ok()?)
+}
+
+/// Sorted, with overlapping and adjacent ranges joined.
+fn normalize_ranges(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut ranges = ranges.to_vec();
+    ranges.sort();
+    let mut out: Vec<(char, char)> = Vec::new();
+    for (first, last) in ranges {
+        if let Some(previous) = out.last_mut()
+            && (previous.1 as u32).saturating_add(1) >= first as u32
+        {
+            previous.1 = previous.1.max(last);
+            continue;
+        }
+        out.push((first, last));
+    }
+    out
+}
+
+/// Whether two shapes describe the same thing, taking special sequences to
+/// match anything and alternatives to be unordered.
+fn same(a: &Shape, b: &Shape) -> bool {
+    match (a, b) {
+        (Shape::Special(_), _) | (_, Shape::Special(_)) => true,
+        (
+            Shape::Chars {
+                negated: a_negated,
+                ranges: a_ranges,
+            },
+            Shape::Chars {
+                negated: b_negated,
+                ranges: b_ranges,
+            },
+        ) => a_negated == b_negated && normalize_ranges(a_ranges) == normalize_ranges(b_ranges),
+        (Shape::Sequence(a), Shape::Sequence(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (Shape::Alternation(a), Shape::Alternation(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|a| b.iter().any(|b| same(a, b)))
+                && b.iter().all(|b| a.iter().any(|a| same(a, b)))
+        }
+        (Shape::Optional(a), Shape::Optional(b))
+        | (Shape::ZeroOrMore(a), Shape::ZeroOrMore(b))
+        | (Shape::OneOrMore(a), Shape::OneOrMore(b)) => same(a, b),
+        (Shape::Repeat(n, a), Shape::Repeat(m, b)) => n == m && same(a, b),
+        (Shape::Exception(a, a_except), Shape::Exception(b, b_except)) => {
+            same(a, b) && same(a_except, b_except)
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebnf::Dialect;
+
+    fn lints(text: &str, starts: &[&str]) -> Vec<(Check, String)> {
+        let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+        lint(&grammar, starts)
+            .into_iter()
+            .map(|lint| (lint.check, text[lint.span].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let text = r#"(* region Top *)
+Start = A, B | A, B | Missing ;
+A = [ "a" ], Start | "x" | "y" | "x" ;
+B = B, "b" | C ;
+C = D ;
+D = C, "d" ;
+Orphan = "o" ;
+A = "again" ;
+(* endregion *)
+(* endregion *)
+"#;
+        assert_eq!(
+            lints(text, &[]),
+            [
+                // Through `A`, since `[ "a" ]` can be skipped.
+                (Check::LeftRecursion, "Start".to_string()),
+                (Check::DuplicateAlternative, "A, B".to_string()),
+                (Check::Undefined, "Missing".to_string()),
+                (Check::DuplicateAlternative, r#""x""#.to_string()),
+                (Check::LeftRecursion, "B".to_string()),
+                (Check::LeftRecursion, "C".to_string()),
+                (Check::Unused, "Orphan".to_string()),
+                (Check::DuplicateProduction, "A".to_string()),
+                (Check::Region, "(* endregion *)".to_string()),
+            ]
+        );
+        // Nothing refers to `Orphan`, and `Start` only refers to itself
+        // through `A`, so none of the others can be reached.
+        let lints = lints(text, &["Orphan"]);
+        assert!(lints.contains(&(Check::Unreachable, "Start".to_string())));
+        assert!(lints.contains(&(Check::Unreachable, "D".to_string())));
+        assert!(!lints.contains(&(Check::Unused, "Orphan".to_string())));
+
+        let grammar = Grammar::parse("", Dialect::Iso).unwrap();
+        assert_eq!(lint(&grammar, &[])[0].check, Check::EmptyGrammar);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let text = "S = \"s\" ; A = \"a\", B ; B = \"b\", [ A ] ; C = A ;";
+        let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+        // `C` is unused, so `A` and `B` count as reachable through it.
+        let checks: Vec<_> = lint(&grammar, &[]).iter().map(|l| l.check).collect();
+        assert_eq!(checks, [Check::Unused]);
+
+        let text = "S = \"s\" ; A = \"a\", B ; B = \"b\", A ;";
+        let grammar = Grammar::parse(text, Dialect::Iso).unwrap();
+        let lints: Vec<_> = lint(&grammar, &[])
+            .into_iter()
+            .map(|l| (l.check, text[l.span].to_string()))
+            .collect();
+        assert_eq!(
+            lints,
+            [
+                (Check::Unreachable, "A".to_string()),
+                (Check::Unreachable, "B".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let iso = Grammar::parse(
+            r#"Digit = "0" | "1" | ? U+0032 ? ;
+Number = Digit, { Digit } | [ "-" ], Digit ;
+Space = ? any space ? ;
+Comment = "/", Text, "/" ;
+Text = { Digit } ;
+Hex = Digit | "a" ;
+Sign = | "-" ;
+IsoOnly = "i" ;"#,
+            Dialect::Iso,
+        )
+        .unwrap();
+        let w3c = Grammar::parse(
+            r#"Digit ::= [0-2]
+Number ::= "-"? Digit | (Digit Digit*)
+Space ::= #x20 | #x09
+Comment ::= "/*" Text "*/"
+Text ::= Digit*
+Hex ::= [0-2a]
+Sign ::= "-"?
+W3cOnly ::= "w""#,
+            Dialect::W3c,
+        )
+        .unwrap();
+        let messages: Vec<_> = compare(&iso, &w3c, "w3c.ebnf")
+            .into_iter()
+            .map(|l| l.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "'Comment' is defined differently in w3c.ebnf",
+                "'IsoOnly' is not defined in w3c.ebnf",
+                "'W3cOnly' from w3c.ebnf is not defined",
+            ]
+        );
+    }
+}
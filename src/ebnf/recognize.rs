@@ -0,0 +1,803 @@
+//! Matching text against productions, for the generator's exceptions and
+//! for coverage.
+//!
+//! Text is matched as one token: there is no whitespace between items. A
+//! match computes every position it can end at, so ambiguous and
+//! left-recursive productions are handled without backtracking. Neither
+//! matching nor deriving recurses from one production into another, so
+//! long texts do not take a deep stack.
+
+use std::collections::hash_map::Entry as MapEntry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::slice;
+
+use super::{Expr, ExprKind, Grammar, Production};
+
+/// Most productions matched inside one another. Grammars spell repetition
+/// as recursion, `Chars = Char, [ Chars ]`, so this bounds the length of
+/// what such a production can match, and the work that matching it takes.
+pub(super) const MAX_NESTING: usize = 4096;
+
+pub(super) struct Entry<'g> {
+    pub(super) grammar: usize,
+    pub(super) production: &'g Production,
+}
+
+/// The productions of several grammars, numbered.
+pub(super) struct Index<'g> {
+    pub(super) productions: Vec<Entry<'g>>,
+    /// Each name's first definition, in the order the grammars were given.
+    pub(super) names: HashMap<&'g str, usize>,
+    /// Each grammar's own definitions, for references to another grammar.
+    pub(super) grammar_names: Vec<HashMap<&'g str, usize>>,
+}
+
+impl<'g> Index<'g> {
+    /// Numbers the productions of `grammars`. A name defined twice in one
+    /// grammar is taken from its first definition.
+    pub(super) fn new(grammars: &[&'g Grammar]) -> Self {
+        let mut productions = Vec::new();
+        let mut names = HashMap::new();
+        let mut grammar_names = Vec::new();
+        for (index, grammar) in grammars.iter().enumerate() {
+            let mut own = HashMap::new();
+            for production in &grammar.productions {
+                if own.contains_key(production.name.as_str()) {
+                    continue;
+                }
+                own.insert(production.name.as_str(), productions.len());
+                names
+                    .entry(production.name.as_str())
+                    .or_insert(productions.len());
+                productions.push(Entry {
+                    grammar: index,
+                    production,
+                });
+            }
+            grammar_names.push(own);
+        }
+        Index {
+            productions,
+            names,
+            grammar_names,
+        }
+    }
+
+    /// `name` as defined in a grammar other than `grammar`.
+    pub(super) fn resolve_other(&self, name: &str, grammar: usize) -> Option<usize> {
+        self.grammar_names
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != grammar)
+            .find_map(|(_, names)| names.get(name).copied())
+    }
+}
+
+/// What a special sequence stands for.
+pub(super) enum Special<'t> {
+    Chars(Class),
+    /// `? a standard JavaScript Name ?`.
+    Reference(&'t str),
+    Unknown,
+}
+
+pub(super) enum Class {
+    Listed(Vec<char>),
+    Space,
+    IdStart { dollar: bool, underscore: bool },
+    IdContinue,
+    Digit,
+    Any,
+}
+
+impl Class {
+    pub(super) fn contains(&self, c: char) -> bool {
+        match self {
+            Class::Listed(chars) => chars.contains(&c),
+            Class::Space => {
+                c.is_whitespace() && !"\t\n\u{b}\u{c}\r\u{85}\u{2028}\u{2029}".contains(c)
+            }
+            Class::IdStart { dollar, underscore } => {
+                c.is_alphabetic() || (*dollar && c == '$') || (*underscore && c == '_')
+            }
+            Class::IdContinue => c.is_alphanumeric() || c == '_',
+            Class::Digit => c.is_numeric(),
+            Class::Any => true,
+        }
+    }
+}
+
+pub(super) fn special(text: &str) -> Special<'_> {
+    if let Some(name) = text.strip_prefix("a standard JavaScript ") {
+        return Special::Reference(name);
+    }
+    let listed: Option<Vec<char>> = text
+        .split('|')
+        .map(|part| {
+            let digits = part.trim().strip_prefix("U+")?;
+            char::from_u32(u32::from_str_radix(digits, 16).ok()?)
+        })
+        .collect();
+    let class = if let Some(chars) = listed {
+        Class::Listed(chars)
+    } else if text.contains("Zs") {
+        Class::Space
+    } else if text.contains("ID_Start") || text.contains("letter") {
+        Class::IdStart {
+            dollar: text.contains("'$'"),
+            underscore: text.contains("'_'"),
+        }
+    } else if text.contains("ID_Continue") {
+        Class::IdContinue
+    } else if text.contains("digit") {
+        Class::Digit
+    } else if text.starts_with("any ") {
+        Class::Any
+    } else {
+        return Special::Unknown;
+    };
+    Special::Chars(class)
+}
+
+/// A step of a derivation, as reported by `Recognizer::derive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+    /// A production was used.
+    Production(usize),
+    /// An alternative of a production's top-level choice was taken.
+    Alternative(usize, usize),
+}
+
+/// What is known so far of where a production matched at a position can
+/// end.
+struct Memo {
+    ends: BTreeSet<usize>,
+    /// How many productions it is matched inside, counting itself.
+    depth: usize,
+    /// The matches that read it, which must be computed again when it grows.
+    readers: HashSet<(usize, usize)>,
+}
+
+/// The items of a sequence, or one item a number of times.
+#[derive(Clone, Copy)]
+enum Items<'e> {
+    Listed(&'e [Expr]),
+    Repeated(&'e Expr, u32),
+}
+
+impl<'e> Items<'e> {
+    fn split_first(self) -> Option<(&'e Expr, Items<'e>)> {
+        match self {
+            Items::Listed(items) => {
+                let (first, rest) = items.split_first()?;
+                Some((first, Items::Listed(rest)))
+            }
+            Items::Repeated(_, 0) => None,
+            Items::Repeated(item, count) => Some((item, Items::Repeated(item, count - 1))),
+        }
+    }
+}
+
+/// What a part of a derivation derives a span from.
+#[derive(Clone, Copy)]
+enum Goal<'g> {
+    Production(usize),
+    Expr(&'g Expr, usize),
+    Sequence(Items<'g>, usize),
+    /// Repetitions of `{ inner }` or `{ inner }-`, and whether there may be
+    /// none.
+    Repetition(&'g Expr, usize, bool),
+}
+
+/// A derivation waiting on one of its parts.
+enum Frame<'g> {
+    /// A production, to stop deriving once its expression is derived.
+    Production { id: usize, pos: usize, end: usize },
+    /// Options tried in turn until one derives the span.
+    Choice {
+        options: &'g [Expr],
+        grammar: usize,
+        pos: usize,
+        end: usize,
+        next: usize,
+        mark: usize,
+        /// The production whose top-level alternatives the options are.
+        production: Option<usize>,
+        /// Whether the span may also be empty, for `[ inner ]`.
+        optional: bool,
+    },
+    /// `first` derives the span up to a middle, and `rest` from there, for
+    /// each middle in turn.
+    Split {
+        first: Goal<'g>,
+        rest: Goal<'g>,
+        pos: usize,
+        end: usize,
+        middles: Vec<usize>,
+        next: usize,
+        mark: usize,
+        in_rest: bool,
+    },
+}
+
+/// What a frame does next.
+enum Next<'g> {
+    Derive(Goal<'g>, usize, usize),
+    Done(bool),
+}
+
+/// Matches one text against the productions of an `Index`.
+pub(super) struct Recognizer<'r, 'g> {
+    index: &'r Index<'g>,
+    /// Texts that stand for a production or special sequence instead of
+    /// what the grammar says.
+    values: &'r HashMap<String, Vec<String>>,
+    text: &'r str,
+    /// Productions matched at each position.
+    memo: HashMap<(usize, usize), Memo>,
+    /// Matches to compute again, as `(depth, production, position)`, the
+    /// deepest first.
+    pending: BTreeSet<(usize, usize, usize)>,
+    /// The match being computed.
+    current: Option<(usize, usize)>,
+    /// How many productions the text is matched inside.
+    base: usize,
+    /// Productions being derived over a span, which are not derived again
+    /// inside themselves.
+    deriving: HashSet<(usize, usize, usize)>,
+    /// Productions being derived, one inside the other.
+    nesting: usize,
+    /// Whether `MAX_NESTING` was reached, making the result unreliable.
+    exhausted: bool,
+}
+
+impl<'r, 'g> Recognizer<'r, 'g> {
+    pub(super) fn new(
+        index: &'r Index<'g>,
+        values: &'r HashMap<String, Vec<String>>,
+        text: &'r str,
+    ) -> Self {
+        Recognizer {
+            index,
+            values,
+            text,
+            memo: HashMap::new(),
+            pending: BTreeSet::new(),
+            current: None,
+            base: 0,
+            deriving: HashSet::new(),
+            nesting: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Whether the text needed productions nested deeper than
+    /// `MAX_NESTING`, in which case it was taken not to match.
+    pub(super) fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Whether all of the text is a sentence of `expr`.
+    pub(super) fn matches(&mut self, expr: &Expr, grammar: usize) -> bool {
+        self.ends(expr, grammar, 0).contains(&self.text.len())
+    }
+
+    /// Whether all of the text is a sentence of the production `id`, and if
+    /// so, the productions and alternatives of one way to derive it, in the
+    /// order they are used.
+    pub(super) fn derive(&mut self, id: usize, steps: &mut Vec<Step>) -> bool {
+        let end = self.text.len();
+        if !self.settled(|r| r.read(id, 0)).contains(&end) {
+            return false;
+        }
+        // Parts of the derivation waiting on others are kept here rather
+        // than on the stack, which would grow with the text.
+        let mut frames = Vec::new();
+        let mut derived = self.enter(Goal::Production(id), 0, end, steps, &mut frames);
+        while let Some(frame) = frames.last_mut() {
+            // A frame just entered starts as if a part had failed.
+            match self.resume(frame, derived == Some(true), steps) {
+                Next::Derive(goal, pos, end) => {
+                    derived = self.enter(goal, pos, end, steps, &mut frames);
+                }
+                Next::Done(done) => {
+                    frames.pop();
+                    derived = Some(done);
+                }
+            }
+        }
+        derived == Some(true)
+    }
+
+    /// Where a match of `expr` starting at `pos` can end.
+    fn ends(&mut self, expr: &Expr, grammar: usize, pos: usize) -> BTreeSet<usize> {
+        self.settled(|r| r.expr_ends(expr, grammar, pos))
+    }
+
+    /// Evaluates `f` until every production it reads has been matched.
+    fn settled(&mut self, mut f: impl FnMut(&mut Self) -> BTreeSet<usize>) -> BTreeSet<usize> {
+        loop {
+            let positions = f(self);
+            if !self.settle() {
+                return positions;
+            }
+        }
+    }
+
+    /// Matches pending productions, and those that read them, until none
+    /// changes. Left-recursive productions are matched by growing their
+    /// result until it stops changing. Returns whether any was pending.
+    fn settle(&mut self) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+        while let Some((_, id, pos)) = self.pending.pop_last() {
+            if self.exhausted {
+                self.pending.clear();
+                break;
+            }
+            let entry = &self.index.productions[id];
+            let (expr, grammar) = (&entry.production.expr, entry.grammar);
+            self.current = Some((id, pos));
+            let positions = self.expr_ends(expr, grammar, pos);
+            let memo = self.memo.get_mut(&(id, pos)).unwrap();
+            if positions == memo.ends {
+                continue;
+            }
+            memo.ends = positions;
+            let readers: Vec<(usize, usize)> = memo.readers.iter().copied().collect();
+            for reader in readers {
+                self.pending
+                    .insert((self.memo[&reader].depth, reader.0, reader.1));
+            }
+        }
+        self.current = None;
+        true
+    }
+
+    /// How many productions what is being matched is inside.
+    fn depth(&self) -> usize {
+        self.current
+            .map_or(self.base, |current| self.memo[&current].depth)
+    }
+
+    /// Where the production `id` matched at `pos` can end, as far as is
+    /// known. The match being computed is computed again when that grows.
+    fn read(&mut self, id: usize, pos: usize) -> BTreeSet<usize> {
+        let depth = self.depth() + 1;
+        let memo = match self.memo.entry((id, pos)) {
+            MapEntry::Occupied(entry) => entry.into_mut(),
+            MapEntry::Vacant(entry) => {
+                if depth > MAX_NESTING {
+                    self.exhausted = true;
+                    return BTreeSet::new();
+                }
+                self.pending.insert((depth, id, pos));
+                entry.insert(Memo {
+                    ends: BTreeSet::new(),
+                    depth,
+                    readers: HashSet::new(),
+                })
+            }
+        };
+        if let Some(current) = self.current {
+            memo.readers.insert(current);
+        }
+        memo.ends.clone()
+    }
+
+    /// Where a match of `expr` starting at `pos` can end, with what is
+    /// known of the productions it uses.
+    fn expr_ends(&mut self, expr: &Expr, grammar: usize, pos: usize) -> BTreeSet<usize> {
+        if self.exhausted {
+            return BTreeSet::new();
+        }
+        let text = self.text;
+        let one_char = |test: &dyn Fn(char) -> bool| {
+            text[pos..]
+                .chars()
+                .next()
+                .filter(|&c| test(c))
+                .map(|c| pos + c.len_utf8())
+                .into_iter()
+                .collect()
+        };
+        match &expr.kind {
+            ExprKind::Empty => BTreeSet::from([pos]),
+            ExprKind::Terminal(terminal) => {
+                if text[pos..].starts_with(terminal.as_str()) {
+                    BTreeSet::from([pos + terminal.len()])
+                } else {
+                    BTreeSet::new()
+                }
+            }
+            ExprKind::Nonterminal(name) => {
+                if let Some(values) = self.values.get(name) {
+                    return self.value_ends(values, pos);
+                }
+                match self.index.names.get(name.as_str()) {
+                    Some(&id) => self.read(id, pos),
+                    None => BTreeSet::new(),
+                }
+            }
+            ExprKind::Special(special_text) => {
+                if let Some(values) = self.values.get(special_text) {
+                    return self.value_ends(values, pos);
+                }
+                match special(special_text) {
+                    Special::Chars(class) => one_char(&|c| class.contains(c)),
+                    Special::Reference(name) => match self.index.resolve_other(name, grammar) {
+                        Some(id) => self.read(id, pos),
+                        None => BTreeSet::new(),
+                    },
+                    Special::Unknown => BTreeSet::new(),
+                }
+            }
+            ExprKind::Char(expected) => one_char(&|c| c == *expected),
+            ExprKind::CharClass { negated, ranges } => one_char(&|c| {
+                ranges
+                    .iter()
+                    .any(|(first, last)| (first..=last).contains(&&c))
+                    != *negated
+            }),
+            ExprKind::Sequence(items) => self.items_ends(Items::Listed(items), grammar, pos),
+            ExprKind::Alternation(items) => items
+                .iter()
+                .flat_map(|item| self.expr_ends(item, grammar, pos))
+                .collect(),
+            ExprKind::Optional(inner) => {
+                let mut positions = self.expr_ends(inner, grammar, pos);
+                positions.insert(pos);
+                positions
+            }
+            ExprKind::ZeroOrMore(inner) | ExprKind::OneOrMore(inner) => {
+                let mut positions = BTreeSet::new();
+                if matches!(expr.kind, ExprKind::ZeroOrMore(_)) {
+                    positions.insert(pos);
+                }
+                let mut frontier = BTreeSet::from([pos]);
+                while !frontier.is_empty() {
+                    let next: BTreeSet<usize> = frontier
+                        .into_iter()
+                        .flat_map(|p| self.expr_ends(inner, grammar, p))
+                        .collect();
+                    frontier = next.difference(&positions).copied().collect();
+                    positions.extend(next);
+                }
+                positions
+            }
+            ExprKind::Repeat(count, inner) => {
+                self.items_ends(Items::Repeated(inner, *count), grammar, pos)
+            }
+            ExprKind::Exception(base, except) => {
+                let depth = self.depth();
+                self.expr_ends(base, grammar, pos)
+                    .into_iter()
+                    .filter(|&end| {
+                        let mut excluded =
+                            Recognizer::new(self.index, self.values, &text[pos..end]);
+                        excluded.base = depth;
+                        let matched = excluded.matches(except, grammar);
+                        self.exhausted |= excluded.exhausted;
+                        !matched
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn items_ends(&mut self, mut items: Items, grammar: usize, pos: usize) -> BTreeSet<usize> {
+        let mut positions = BTreeSet::from([pos]);
+        while let Some((item, rest)) = items.split_first() {
+            positions = positions
+                .into_iter()
+                .flat_map(|p| self.expr_ends(item, grammar, p))
+                .collect();
+            items = rest;
+        }
+        positions
+    }
+
+    fn value_ends(&self, values: &[String], pos: usize) -> BTreeSet<usize> {
+        values
+            .iter()
+            .filter(|value| self.text[pos..].starts_with(value.as_str()))
+            .map(|value| pos + value.len())
+            .collect()
+    }
+
+    /// Starts deriving `text[pos..end]` from `goal`, which `ends` says can
+    /// match it. Returns whether it was derived, or `None` if that waits on
+    /// a frame pushed onto `frames`.
+    fn enter(
+        &mut self,
+        goal: Goal<'g>,
+        pos: usize,
+        end: usize,
+        steps: &mut Vec<Step>,
+        frames: &mut Vec<Frame<'g>>,
+    ) -> Option<bool> {
+        match goal {
+            Goal::Production(id) => {
+                if self.exhausted || self.nesting == MAX_NESTING {
+                    self.exhausted = true;
+                    return Some(false);
+                }
+                if !self.deriving.insert((id, pos, end)) {
+                    return Some(false);
+                }
+                self.nesting += 1;
+                let entry = &self.index.productions[id];
+                let (expr, grammar) = (&entry.production.expr, entry.grammar);
+                steps.push(Step::Production(id));
+                frames.push(Frame::Production { id, pos, end });
+                match &expr.kind {
+                    ExprKind::Alternation(items) => {
+                        frames.push(Frame::Choice {
+                            options: items,
+                            grammar,
+                            pos,
+                            end,
+                            next: 0,
+                            mark: steps.len(),
+                            production: Some(id),
+                            optional: false,
+                        });
+                        None
+                    }
+                    _ => self.enter(Goal::Expr(expr, grammar), pos, end, steps, frames),
+                }
+            }
+            Goal::Expr(expr, grammar) => match &expr.kind {
+                ExprKind::Nonterminal(name) => {
+                    if self.values.contains_key(name) {
+                        return Some(true);
+                    }
+                    let id = self.index.names[name.as_str()];
+                    self.enter(Goal::Production(id), pos, end, steps, frames)
+                }
+                ExprKind::Special(text) => match special(text) {
+                    Special::Reference(name) if !self.values.contains_key(text) => {
+                        let id = self.index.resolve_other(name, grammar).unwrap();
+                        self.enter(Goal::Production(id), pos, end, steps, frames)
+                    }
+                    _ => Some(true),
+                },
+                ExprKind::Sequence(items) => {
+                    let goal = Goal::Sequence(Items::Listed(items), grammar);
+                    self.enter(goal, pos, end, steps, frames)
+                }
+                ExprKind::Alternation(items) => {
+                    frames.push(Frame::Choice {
+                        options: items,
+                        grammar,
+                        pos,
+                        end,
+                        next: 0,
+                        mark: steps.len(),
+                        production: None,
+                        optional: false,
+                    });
+                    None
+                }
+                ExprKind::Optional(inner) => {
+                    frames.push(Frame::Choice {
+                        options: slice::from_ref(&**inner),
+                        grammar,
+                        pos,
+                        end,
+                        next: 0,
+                        mark: steps.len(),
+                        production: None,
+                        optional: true,
+                    });
+                    None
+                }
+                ExprKind::ZeroOrMore(_) | ExprKind::OneOrMore(_) => {
+                    let zero = matches!(expr.kind, ExprKind::ZeroOrMore(_));
+                    let goal = Goal::Repetition(expr, grammar, zero);
+                    self.enter(goal, pos, end, steps, frames)
+                }
+                ExprKind::Repeat(count, inner) => {
+                    let goal = Goal::Sequence(Items::Repeated(inner, *count), grammar);
+                    self.enter(goal, pos, end, steps, frames)
+                }
+                // What is excluded is not part of the derivation.
+                ExprKind::Exception(base, _) => {
+                    self.enter(Goal::Expr(base, grammar), pos, end, steps, frames)
+                }
+                ExprKind::Empty
+                | ExprKind::Terminal(_)
+                | ExprKind::Char(_)
+                | ExprKind::CharClass { .. } => Some(true),
+            },
+            Goal::Sequence(items, grammar) => {
+                let Some((first, rest)) = items.split_first() else {
+                    return Some(pos == end);
+                };
+                let middles: Vec<usize> = self
+                    .ends(first, grammar, pos)
+                    .into_iter()
+                    .filter(|&middle| {
+                        middle <= end
+                            && self
+                                .settled(|r| r.items_ends(rest, grammar, middle))
+                                .contains(&end)
+                    })
+                    .collect();
+                frames.push(Frame::Split {
+                    first: Goal::Expr(first, grammar),
+                    rest: Goal::Sequence(rest, grammar),
+                    pos,
+                    end,
+                    middles,
+                    next: 0,
+                    mark: steps.len(),
+                    in_rest: false,
+                });
+                None
+            }
+            // The first repetition, then the rest as a zero-or-more.
+            Goal::Repetition(expr, grammar, zero) => {
+                if pos == end && zero {
+                    return Some(true);
+                }
+                let (ExprKind::ZeroOrMore(inner) | ExprKind::OneOrMore(inner)) = &expr.kind else {
+                    unreachable!("only repetitions repeat");
+                };
+                let middles: Vec<usize> = self
+                    .ends(inner, grammar, pos)
+                    .into_iter()
+                    .filter(|&middle| {
+                        middle <= end
+                            && (middle == end
+                                || (middle > pos
+                                    && self.ends(expr, grammar, middle).contains(&end)))
+                    })
+                    .collect();
+                frames.push(Frame::Split {
+                    first: Goal::Expr(inner, grammar),
+                    rest: Goal::Repetition(expr, grammar, true),
+                    pos,
+                    end,
+                    middles,
+                    next: 0,
+                    mark: steps.len(),
+                    in_rest: false,
+                });
+                None
+            }
+        }
+    }
+
+    /// Continues `frame` once the part it waited on was derived or not.
+    fn resume(&mut self, frame: &mut Frame<'g>, derived: bool, steps: &mut Vec<Step>) -> Next<'g> {
+        match frame {
+            Frame::Production { id, pos, end } => {
+                self.deriving.remove(&(*id, *pos, *end));
+                self.nesting -= 1;
+                Next::Done(derived)
+            }
+            Frame::Choice {
+                options,
+                grammar,
+                pos,
+                end,
+                next,
+                mark,
+                production,
+                optional,
+            } => {
+                if derived {
+                    return Next::Done(true);
+                }
+                steps.truncate(*mark);
+                while let Some(option) = options.get(*next) {
+                    let i = *next;
+                    *next += 1;
+                    if self.ends(option, *grammar, *pos).contains(end) {
+                        if let Some(id) = *production {
+                            steps.push(Step::Alternative(id, i));
+                        }
+                        return Next::Derive(Goal::Expr(option, *grammar), *pos, *end);
+                    }
+                }
+                Next::Done(*optional && *pos == *end)
+            }
+            Frame::Split {
+                first,
+                rest,
+                pos,
+                end,
+                middles,
+                next,
+                mark,
+                in_rest,
+            } => {
+                if derived {
+                    if *in_rest {
+                        return Next::Done(true);
+                    }
+                    *in_rest = true;
+                    return Next::Derive(*rest, middles[*next - 1], *end);
+                }
+                steps.truncate(*mark);
+                let Some(&middle) = middles.get(*next) else {
+                    return Next::Done(false);
+                };
+                *next += 1;
+                *in_rest = false;
+                Next::Derive(*first, *pos, middle)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ebnf::Dialect;
+
+    #[test]
+    fn test_derive() {
+        let grammar = Grammar::parse(
+            r#"Name = Start | Name, Part ;
+Start = "a" | "b" ;
+Part = Start | { "0" }- ;
+Word = Name - "ab" ;"#,
+            Dialect::Iso,
+        )
+        .unwrap();
+        let index = Index::new(&[&grammar]);
+        let values = HashMap::new();
+        let derive = |name: &str, text: &str| {
+            let mut steps = Vec::new();
+            let mut recognizer = Recognizer::new(&index, &values, text);
+            recognizer
+                .derive(index.names[name], &mut steps)
+                .then_some(steps)
+        };
+        let (name, start, part) = (
+            Step::Production(0),
+            Step::Production(1),
+            Step::Production(2),
+        );
+        assert_eq!(
+            derive("Name", "b00"),
+            Some(vec![
+                name,
+                Step::Alternative(0, 1),
+                name,
+                Step::Alternative(0, 0),
+                start,
+                Step::Alternative(1, 1),
+                part,
+                Step::Alternative(2, 1),
+            ])
+        );
+        assert_eq!(derive("Name", "0"), None);
+        assert_eq!(derive("Word", "ab"), None);
+        assert!(derive("Word", "ba").is_some());
+        let mut recognizer = Recognizer::new(&index, &values, "aa0");
+        assert!(recognizer.matches(&index.productions[0].production.expr, 0));
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let grammar = Grammar::parse(r#"Nest = "(", [ Nest ], ")" ;"#, Dialect::Iso).unwrap();
+        let index = Index::new(&[&grammar]);
+        let values = HashMap::new();
+        let nested = |depth: usize| format!("{}{}", "(".repeat(depth), ")".repeat(depth));
+        // Far deeper than a test thread's stack would allow if matching
+        // recursed from one production into the next.
+        let text = nested(MAX_NESTING - 1);
+        let mut steps = Vec::new();
+        let mut recognizer = Recognizer::new(&index, &values, &text);
+        assert!(recognizer.derive(0, &mut steps));
+        assert_eq!(steps.len(), MAX_NESTING - 1);
+        let text = nested(MAX_NESTING);
+        let mut recognizer = Recognizer::new(&index, &values, &text);
+        assert!(!recognizer.derive(0, &mut steps));
+        assert!(recognizer.exhausted());
+    }
+}
@@ -1,28 +1,35 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::diagnostic::{Diagnostic, Renderer};
+use crate::token::{Token, TokenFlags, TokenType};
+
 #[derive(Debug, Clone)]
 pub enum LexerError {
-    UnexpectedCharacter { line: usize, column: usize },
-    StringNotTerminated { line: usize, column: usize },
-    InvalidNumber { line: usize, column: usize },
-    CommentNotTerminated { line: usize, column: usize },
+    UnexpectedCharacter { pos: u32 },
+    StringNotTerminated { pos: u32 },
+    TemplateNotTerminated { pos: u32 },
+    InvalidNumber { pos: u32 },
+    CommentNotTerminated { pos: u32 },
 }
 
 impl fmt::Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LexerError::UnexpectedCharacter { line, column } => {
-                write!(f, "[{}:{}] Unexpected Character", line, column)
+            LexerError::UnexpectedCharacter { pos } => {
+                write!(f, "[{}] Unexpected Character", pos)
+            }
+            LexerError::StringNotTerminated { pos } => {
+                write!(f, "[{}] Unterminated string literal", pos)
             }
-            LexerError::StringNotTerminated { line, column } => {
-                write!(f, "[{}:{}] Unterminated string literal", line, column)
+            LexerError::TemplateNotTerminated { pos } => {
+                write!(f, "[{}] Unterminated template literal", pos)
             }
-            LexerError::InvalidNumber { line, column } => {
-                write!(f, "[{}:{}] Invalid number literal", line, column)
+            LexerError::InvalidNumber { pos } => {
+                write!(f, "[{}] Invalid number literal", pos)
             }
-            LexerError::CommentNotTerminated { line, column } => {
-                write!(f, "[{}:{}] Unterminated comment", line, column)
+            LexerError::CommentNotTerminated { pos } => {
+                write!(f, "[{}] Unterminated comment", pos)
             }
         }
     }
@@ -31,7 +38,100 @@ impl fmt::Display for LexerError {
 impl Error for LexerError {}
 
 impl LexerError {
-    pub fn report(&self) {
-        eprintln!("{}", self);
+    /// The TypeScript diagnostic code for this error, as in `error TS1127`.
+    pub fn code(&self) -> u32 {
+        match self {
+            LexerError::UnexpectedCharacter { .. } => 1127,
+            LexerError::StringNotTerminated { .. } => 1002,
+            LexerError::TemplateNotTerminated { .. } => 1160,
+            LexerError::InvalidNumber { .. } => 1351,
+            LexerError::CommentNotTerminated { .. } => 1010,
+        }
+    }
+
+    /// The message TypeScript uses for this diagnostic code.
+    pub fn message(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedCharacter { .. } => "Invalid character.",
+            LexerError::StringNotTerminated { .. } => "Unterminated string literal.",
+            LexerError::TemplateNotTerminated { .. } => "Unterminated template literal.",
+            LexerError::InvalidNumber { .. } => {
+                "An identifier or keyword cannot immediately follow a numeric literal."
+            }
+            LexerError::CommentNotTerminated { .. } => "'*/' expected.",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedCharacter { .. } => "unexpected character",
+            LexerError::StringNotTerminated { .. } => "string starts here",
+            LexerError::TemplateNotTerminated { .. } => "template starts here",
+            LexerError::InvalidNumber { .. } => "invalid number literal",
+            LexerError::CommentNotTerminated { .. } => "comment starts here",
+        }
+    }
+
+    /// Byte offset at which the error was detected.
+    pub fn pos(&self) -> u32 {
+        match *self {
+            LexerError::UnexpectedCharacter { pos }
+            | LexerError::StringNotTerminated { pos }
+            | LexerError::TemplateNotTerminated { pos }
+            | LexerError::InvalidNumber { pos }
+            | LexerError::CommentNotTerminated { pos } => pos,
+        }
+    }
+
+    /// The same error, `by` bytes further into the file.
+    pub fn shifted(self, by: u32) -> Self {
+        let pos = self.pos() + by;
+        self.at(pos)
+    }
+
+    /// The same error, at `pos`.
+    pub fn at(self, pos: u32) -> Self {
+        match self {
+            LexerError::UnexpectedCharacter { .. } => LexerError::UnexpectedCharacter { pos },
+            LexerError::StringNotTerminated { .. } => LexerError::StringNotTerminated { pos },
+            LexerError::TemplateNotTerminated { .. } => LexerError::TemplateNotTerminated { pos },
+            LexerError::InvalidNumber { .. } => LexerError::InvalidNumber { pos },
+            LexerError::CommentNotTerminated { .. } => LexerError::CommentNotTerminated { pos },
+        }
+    }
+
+    /// The error to report for `token` if the lexer flagged it
+    /// `UNTERMINATED`.
+    pub(crate) fn unterminated(token: &Token) -> Option<LexerError> {
+        if !token.flags.contains(TokenFlags::UNTERMINATED) {
+            return None;
+        }
+        let pos = token.start;
+        Some(match token.token_type {
+            TokenType::StringLiteral => LexerError::StringNotTerminated { pos },
+            TokenType::MultiLineCommentTrivia => LexerError::CommentNotTerminated { pos },
+            _ => LexerError::TemplateNotTerminated { pos },
+        })
+    }
+
+    pub fn to_diagnostic(&self, source: &str) -> Diagnostic {
+        let mut start = (self.pos() as usize).min(source.len());
+        while !source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let end = source[start..]
+            .chars()
+            .next()
+            .map_or(start, |c| start + c.len_utf8());
+
+        Diagnostic::error(self.code(), self.message()).with_label(start..end, self.label())
+    }
+
+    pub fn report(&self, file_name: &str, source: &str) {
+        let diagnostic = self.to_diagnostic(source);
+        eprint!(
+            "{}",
+            Renderer::for_stderr().render(file_name, source, &diagnostic)
+        );
     }
 }
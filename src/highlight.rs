@@ -0,0 +1,592 @@
+//! Syntax highlighting from the token stream, as ANSI escapes for terminals
+//! or HTML for web pages. Either way, the source text comes back exactly
+//! once the escapes or markup are stripped.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::lexer::{LanguageVariant, Lexer};
+use crate::token::{Token, TokenType};
+use crate::trivia;
+
+/// Prefix of the class names `Highlighter::html` writes, as in
+/// `<span class="rhi-keyword">`.
+pub const HTML_CLASS_PREFIX: &str = "rhi-";
+
+/// What a span of source is, for choosing how to show it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightClass {
+    Keyword,
+    /// A keyword only in some positions, such as `let`, `async` or `type`.
+    ContextualKeyword,
+    Number,
+    String,
+    Regex,
+    /// The literal parts of a template, from `` ` `` or `}` to `${` or `` ` ``.
+    Template,
+    Comment,
+    /// A JSDoc tag such as `@param` in a `/** */` comment.
+    DocTag,
+    Punctuation,
+    JsxTag,
+    JsxAttribute,
+    JsxText,
+    /// Text the lexer rejected.
+    Invalid,
+}
+
+impl HighlightClass {
+    pub const ALL: [HighlightClass; 13] = [
+        HighlightClass::Keyword,
+        HighlightClass::ContextualKeyword,
+        HighlightClass::Number,
+        HighlightClass::String,
+        HighlightClass::Regex,
+        HighlightClass::Template,
+        HighlightClass::Comment,
+        HighlightClass::DocTag,
+        HighlightClass::Punctuation,
+        HighlightClass::JsxTag,
+        HighlightClass::JsxAttribute,
+        HighlightClass::JsxText,
+        HighlightClass::Invalid,
+    ];
+
+    /// The name used in themes and HTML class names, such as
+    /// `contextual-keyword`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HighlightClass::Keyword => "keyword",
+            HighlightClass::ContextualKeyword => "contextual-keyword",
+            HighlightClass::Number => "number",
+            HighlightClass::String => "string",
+            HighlightClass::Regex => "regex",
+            HighlightClass::Template => "template",
+            HighlightClass::Comment => "comment",
+            HighlightClass::DocTag => "doc-tag",
+            HighlightClass::Punctuation => "punctuation",
+            HighlightClass::JsxTag => "jsx-tag",
+            HighlightClass::JsxAttribute => "jsx-attribute",
+            HighlightClass::JsxText => "jsx-text",
+            HighlightClass::Invalid => "invalid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.name() == name)
+    }
+
+    /// The class of a token outside JSX tags, where names are plain.
+    fn of_token(kind: TokenType) -> Option<Self> {
+        let class = match kind {
+            _ if kind.is_comment() => HighlightClass::Comment,
+            TokenType::NumericLiteral | TokenType::BigIntLiteral => HighlightClass::Number,
+            TokenType::StringLiteral => HighlightClass::String,
+            TokenType::RegularExpressionLiteral => HighlightClass::Regex,
+            _ if kind.is_template() => HighlightClass::Template,
+            TokenType::JsxText | TokenType::JsxTextAllWhiteSpaces => HighlightClass::JsxText,
+            TokenType::Unknown => HighlightClass::Invalid,
+            _ if kind.is_contextual_keyword() => HighlightClass::ContextualKeyword,
+            _ if kind.is_keyword() => HighlightClass::Keyword,
+            _ if kind.is_punctuation() => HighlightClass::Punctuation,
+            _ => return None,
+        };
+        Some(class)
+    }
+}
+
+/// A run of source text with one class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub span: Range<usize>,
+    /// `None` for identifiers, whitespace and other unstyled text.
+    pub class: Option<HighlightClass>,
+}
+
+/// How a class is shown: SGR parameters such as `1;35` for terminals, and
+/// CSS declarations for HTML. Either may be empty for no style.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub ansi: String,
+    pub css: String,
+}
+
+/// A style for each `HighlightClass`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    styles: [Style; HighlightClass::ALL.len()],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::plain()
+            .with_style(HighlightClass::Keyword, "35", "color: #a626a4")
+            .with_style(HighlightClass::ContextualKeyword, "35", "color: #a626a4")
+            .with_style(HighlightClass::Number, "33", "color: #986801")
+            .with_style(HighlightClass::String, "32", "color: #50a14f")
+            .with_style(HighlightClass::Regex, "31", "color: #e45649")
+            .with_style(HighlightClass::Template, "32", "color: #50a14f")
+            .with_style(
+                HighlightClass::Comment,
+                "90",
+                "color: #a0a1a7; font-style: italic",
+            )
+            .with_style(
+                HighlightClass::DocTag,
+                "1;90",
+                "color: #a0a1a7; font-weight: bold",
+            )
+            .with_style(HighlightClass::JsxTag, "31", "color: #e45649")
+            .with_style(HighlightClass::JsxAttribute, "33", "color: #986801")
+            .with_style(
+                HighlightClass::Invalid,
+                "4;31",
+                "color: #e45649; text-decoration: underline wavy",
+            )
+    }
+}
+
+impl Theme {
+    /// A theme that styles nothing.
+    pub fn plain() -> Self {
+        Self {
+            styles: Default::default(),
+        }
+    }
+
+    pub fn style(&self, class: HighlightClass) -> &Style {
+        &self.styles[class as usize]
+    }
+
+    pub fn with_style(
+        mut self,
+        class: HighlightClass,
+        ansi: impl Into<String>,
+        css: impl Into<String>,
+    ) -> Self {
+        self.styles[class as usize] = Style {
+            ansi: ansi.into(),
+            css: css.into(),
+        };
+        self
+    }
+
+    /// Reads a theme with one class per line: its name, its SGR parameters
+    /// (`-` for none), and CSS declarations to the end of the line. Classes
+    /// the text leaves out keep their default style.
+    ///
+    /// ```text
+    /// # class    ansi  css
+    /// keyword    1;34  color: #0000ff; font-weight: bold
+    /// comment    -     color: gray
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut theme = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let class = HighlightClass::from_name(name)
+                .ok_or_else(|| format!("line {}: unknown class '{}'", i + 1, name))?;
+            let rest = rest.trim_start();
+            let (ansi, css) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if !ansi.chars().all(|c| c.is_ascii_digit() || c == ';') && ansi != "-" {
+                return Err(format!(
+                    "line {}: '{}' is not a list of SGR parameters",
+                    i + 1,
+                    ansi
+                ));
+            }
+            theme = theme.with_style(class, ansi.trim_start_matches('-'), css.trim());
+        }
+        Ok(theme)
+    }
+
+    /// A stylesheet for the class names `Highlighter::html` writes.
+    pub fn css(&self) -> String {
+        let mut out = String::new();
+        for class in HighlightClass::ALL {
+            let css = &self.style(class).css;
+            if !css.is_empty() {
+                let _ = writeln!(out, ".{}{} {{ {} }}", HTML_CLASS_PREFIX, class.name(), css);
+            }
+        }
+        out
+    }
+}
+
+/// Highlights JavaScript and TypeScript source.
+#[derive(Debug, Clone, Default)]
+pub struct Highlighter {
+    variant: LanguageVariant,
+    theme: Theme,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn variant(mut self, variant: LanguageVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Splits `text` into classed spans that cover it exactly, in order.
+    /// Adjacent spans of the same class are merged.
+    pub fn spans(&self, text: &str) -> Vec<HighlightSpan> {
+        let mut spans = Vec::new();
+        let mut gap = Vec::new();
+        let mut end = 0;
+        classify(text, self.variant, |token, class, _| {
+            // Rejected text is left in the gap, which marks it invalid.
+            gap.clear();
+            trivia::gap_trivia(text, end, token.start as usize, &mut gap);
+            for t in &gap {
+                push(&mut spans, t, gap_class(t.token_type));
+            }
+            end = token.end as usize;
+
+            if token.token_type == TokenType::MultiLineCommentTrivia {
+                let start = token.start as usize;
+                let mut from = start;
+                for tag in doc_tags(&text[start..end]) {
+                    let comment = Some(HighlightClass::Comment);
+                    push_span(&mut spans, from..start + tag.start, comment);
+                    push_span(
+                        &mut spans,
+                        start + tag.start..start + tag.end,
+                        Some(HighlightClass::DocTag),
+                    );
+                    from = start + tag.end;
+                }
+                push_span(&mut spans, from..end, class);
+            } else {
+                push(&mut spans, &token, class);
+            }
+        });
+        gap.clear();
+        trivia::gap_trivia(text, end, text.len(), &mut gap);
+        for t in &gap {
+            push(&mut spans, t, gap_class(t.token_type));
+        }
+        spans
+    }
+
+    /// `text` with ANSI escapes around each styled span. Styles are ended at
+    /// line breaks and started again after, as pagers such as `less -R`
+    /// expect.
+    pub fn ansi(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len() * 2);
+        for span in self.spans(text) {
+            let piece = &text[span.span];
+            let sgr = span
+                .class
+                .map(|class| self.theme.style(class).ansi.as_str())
+                .unwrap_or_default();
+            if sgr.is_empty() {
+                out.push_str(piece);
+                continue;
+            }
+            for (i, line) in piece.split('\n').enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                if !line.is_empty() {
+                    let _ = write!(out, "\x1b[{}m{}\x1b[0m", sgr, line);
+                }
+            }
+        }
+        out
+    }
+
+    /// `text` escaped for HTML, with each classed span in a `<span>` whose
+    /// class is the class name after `HTML_CLASS_PREFIX`. `Theme::css` gives
+    /// the matching stylesheet. The caller wraps the result in `<pre>`.
+    pub fn html(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len() * 2);
+        for span in self.spans(text) {
+            let piece = &text[span.span];
+            match span.class {
+                Some(class) => {
+                    let _ = write!(
+                        out,
+                        "<span class=\"{}{}\">",
+                        HTML_CLASS_PREFIX,
+                        class.name()
+                    );
+                    escape_html(&mut out, piece);
+                    out.push_str("</span>");
+                }
+                None => escape_html(&mut out, piece),
+            }
+        }
+        out
+    }
+}
+
+/// Calls `visit` with each token of `text`, its class, and whether it is
+/// part of a JSX tag, leaving out errors. Names in JSX tags are told apart
+/// from the same names in code.
+pub(crate) fn classify(
+    text: &str,
+    variant: LanguageVariant,
+    mut visit: impl FnMut(Token, Option<HighlightClass>, bool),
+) {
+    let mut lexer = Lexer::new(text);
+    lexer.set_language_variant(variant);
+
+    // Whether the next name in a JSX tag names the element, as after `<`,
+    // `</` or the `.` of `<a.b>`.
+    let mut tag_name_next = false;
+    let mut previous = None;
+    loop {
+        let in_tag = lexer.in_jsx_tag();
+        let Some(result) = lexer.lex().next() else {
+            break;
+        };
+        let Ok(token) = result else {
+            continue;
+        };
+
+        let kind = token.token_type;
+        let class = if in_tag && (kind == TokenType::Identifier || kind.is_keyword()) {
+            let class = if tag_name_next {
+                HighlightClass::JsxTag
+            } else {
+                HighlightClass::JsxAttribute
+            };
+            tag_name_next = false;
+            Some(class)
+        } else {
+            tag_name_next = match kind {
+                TokenType::LessThanToken | TokenType::LessThanSlashToken => true,
+                TokenType::DotToken | TokenType::ColonToken => {
+                    in_tag && previous == Some(HighlightClass::JsxTag)
+                }
+                _ => false,
+            };
+            HighlightClass::of_token(kind)
+        };
+        // The `<` or `</` that opens a tag is lexed before entering it.
+        visit(token, class, in_tag || lexer.in_jsx_tag());
+        previous = class;
+    }
+}
+
+/// The class of trivia between tokens: a `#!` line is a comment, and text
+/// the lexer rejected is invalid.
+fn gap_class(kind: TokenType) -> Option<HighlightClass> {
+    match kind {
+        TokenType::SingleLineCommentTrivia => Some(HighlightClass::Comment),
+        TokenType::Unknown => Some(HighlightClass::Invalid),
+        _ => None,
+    }
+}
+
+fn push(spans: &mut Vec<HighlightSpan>, token: &Token, class: Option<HighlightClass>) {
+    push_span(spans, token.start as usize..token.end as usize, class);
+}
+
+fn push_span(spans: &mut Vec<HighlightSpan>, span: Range<usize>, class: Option<HighlightClass>) {
+    if span.is_empty() {
+        return;
+    }
+    if let Some(last) = spans.last_mut()
+        && last.class == class
+        && last.span.end == span.start
+    {
+        last.span.end = span.end;
+        return;
+    }
+    spans.push(HighlightSpan { span, class });
+}
+
+/// The tags of a `/** */` comment, such as `@param`: an `@` and a name,
+/// after a space, `*` or `{`. Ranges are relative to `comment`.
+pub(crate) fn doc_tags(comment: &str) -> Vec<Range<usize>> {
+    let mut tags = Vec::new();
+    if !comment.starts_with("/**") || comment == "/**/" {
+        return tags;
+    }
+    let bytes = comment.as_bytes();
+    let mut i = 3;
+    while i < bytes.len() {
+        if bytes[i] == b'@'
+            && matches!(bytes[i - 1], b' ' | b'\t' | b'\n' | b'\r' | b'*' | b'{')
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_alphabetic)
+        {
+            let len = bytes[i + 1..]
+                .iter()
+                .position(|b| !b.is_ascii_alphanumeric())
+                .unwrap_or(bytes.len() - i - 1);
+            tags.push(i..i + 1 + len);
+            i += 1 + len;
+        } else {
+            i += 1;
+        }
+    }
+    tags
+}
+
+fn escape_html(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(text: &str, variant: LanguageVariant) -> Vec<(&str, Option<&'static str>)> {
+        Highlighter::new()
+            .variant(variant)
+            .spans(text)
+            .into_iter()
+            .filter(|s| !text[s.span.clone()].trim().is_empty())
+            .map(|s| (&text[s.span], s.class.map(HighlightClass::name)))
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_spans() {
+        let text = "#!node\n/** @param x {@link y} */\nasync a = `b${1n}` + /c/g; // d\n'e' @";
+        assert_eq!(
+            classes(text, LanguageVariant::Standard),
+            [
+                ("#!node", Some("comment")),
+                ("/** ", Some("comment")),
+                ("@param", Some("doc-tag")),
+                (" x {", Some("comment")),
+                ("@link", Some("doc-tag")),
+                (" y} */", Some("comment")),
+                ("async", Some("contextual-keyword")),
+                (" a ", None),
+                ("=", Some("punctuation")),
+                ("`b${", Some("template")),
+                ("1n", Some("number")),
+                ("}`", Some("template")),
+                ("+", Some("punctuation")),
+                ("/c/g", Some("regex")),
+                (";", Some("punctuation")),
+                ("// d", Some("comment")),
+                ("'e'", Some("string")),
+                ("@", Some("punctuation")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_jsx() {
+        let text = "<a.b class=\"c\" d:e={f}>g</a.b>";
+        assert_eq!(
+            classes(text, LanguageVariant::Jsx),
+            [
+                ("<", Some("punctuation")),
+                ("a", Some("jsx-tag")),
+                (".", Some("punctuation")),
+                ("b", Some("jsx-tag")),
+                ("class", Some("jsx-attribute")),
+                ("=", Some("punctuation")),
+                ("\"c\"", Some("string")),
+                ("d", Some("jsx-attribute")),
+                (":", Some("punctuation")),
+                ("e", Some("jsx-attribute")),
+                ("={", Some("punctuation")),
+                ("f", None),
+                ("}>", Some("punctuation")),
+                ("g", Some("jsx-text")),
+                ("</", Some("punctuation")),
+                ("a", Some("jsx-tag")),
+                (".", Some("punctuation")),
+                ("b", Some("jsx-tag")),
+                (">", Some("punctuation")),
+            ]
+        );
+        assert_eq!(
+            classes("a < b", LanguageVariant::Jsx),
+            [("a ", None), ("<", Some("punctuation")), (" b", None)]
+        );
+    }
+
+    #[test]
+    fn test_highlight_invalid() {
+        assert_eq!(
+            classes("a ℮ 'b\nc", LanguageVariant::Standard),
+            [
+                ("a ", None),
+                ("℮", Some("invalid")),
+                ("'b", Some("invalid")),
+                ("\nc", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_ansi() {
+        let text = "/* a\nb */ if";
+        let ansi = Highlighter::new().ansi(text);
+        assert_eq!(
+            ansi,
+            "\x1b[90m/* a\x1b[0m\n\x1b[90mb */\x1b[0m \x1b[35mif\x1b[0m"
+        );
+        assert_eq!(Highlighter::new().theme(Theme::plain()).ansi(text), text);
+    }
+
+    #[test]
+    fn test_highlight_html() {
+        let html = Highlighter::new().html("a<b&&\"c\"");
+        assert_eq!(
+            html,
+            "a<span class=\"rhi-punctuation\">&lt;</span>b\
+             <span class=\"rhi-punctuation\">&amp;&amp;</span>\
+             <span class=\"rhi-string\">&quot;c&quot;</span>"
+        );
+    }
+
+    #[test]
+    fn test_theme_parse() {
+        let theme =
+            Theme::parse("# c\nkeyword 1;34  color: blue; font-weight: bold\nnumber -\n").unwrap();
+        assert_eq!(
+            theme.style(HighlightClass::Keyword),
+            &Style {
+                ansi: "1;34".to_string(),
+                css: "color: blue; font-weight: bold".to_string(),
+            }
+        );
+        assert_eq!(theme.style(HighlightClass::Number), &Style::default());
+        assert_eq!(
+            theme.style(HighlightClass::String),
+            Theme::default().style(HighlightClass::String)
+        );
+        assert!(
+            theme
+                .css()
+                .contains(".rhi-keyword { color: blue; font-weight: bold }\n")
+        );
+
+        assert_eq!(
+            Theme::parse("keywords 1").unwrap_err(),
+            "line 1: unknown class 'keywords'"
+        );
+        assert_eq!(
+            Theme::parse("\nkeyword red").unwrap_err(),
+            "line 2: 'red' is not a list of SGR parameters"
+        );
+    }
+}
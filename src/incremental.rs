@@ -0,0 +1,319 @@
+//! Relexing after a text edit, for editors that keep a file's tokens up to
+//! date on every keystroke.
+//!
+//! `relex` restarts the lexer at a safe point shortly before the edit, lexes
+//! forward until its output lines up with the old tokens again, and reports
+//! only the tokens in between.
+
+use std::ops::Range;
+
+use crate::error::LexerError;
+use crate::lexer::Lexer;
+use crate::token::{Token, TokenType};
+
+/// How far past the end of a token the lexer may look before deciding where
+/// the token ends, e.g. `1e+` in `1e+x` or `?.` in `?.5`.
+const LOOKAHEAD: usize = 3;
+
+/// A replacement of the bytes in `range` by `text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        Self::new(offset..offset, text)
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, "")
+    }
+
+    /// The text that results from applying this edit to `old_text`.
+    pub fn apply(&self, old_text: &str) -> String {
+        let mut text = String::with_capacity(old_text.len() + self.text.len());
+        text.push_str(&old_text[..self.range.start]);
+        text.push_str(&self.text);
+        text.push_str(&old_text[self.range.end..]);
+        text
+    }
+
+    /// How much offsets after the edit move.
+    pub fn shift(&self) -> i64 {
+        self.text.len() as i64 - self.range.len() as i64
+    }
+}
+
+/// The effect of an edit on a file's tokens.
+#[derive(Debug, Clone)]
+pub struct TokenDelta {
+    /// Indices of the old tokens that were replaced.
+    pub old_range: Range<usize>,
+    /// The tokens that replace them, at offsets into the new text.
+    pub tokens: Vec<Token>,
+    /// Errors reported while relexing `tokens`.
+    pub errors: Vec<LexerError>,
+    /// How much the offsets of the old tokens after `old_range` move.
+    pub shift: i64,
+}
+
+impl TokenDelta {
+    /// Index range of the changed tokens in the new token list.
+    pub fn new_range(&self) -> Range<usize> {
+        self.old_range.start..self.old_range.start + self.tokens.len()
+    }
+
+    /// Updates `tokens`, the old token list, to the tokens of the new text.
+    pub fn apply(&self, tokens: &mut Vec<Token>) {
+        let end = self.new_range().end;
+        tokens.splice(self.old_range.clone(), self.tokens.iter().copied());
+        for token in &mut tokens[end..] {
+            token.start = (token.start as i64 + self.shift) as u32;
+            token.end = (token.end as i64 + self.shift) as u32;
+        }
+    }
+}
+
+/// Relexes the part of `old_text` that `edit` can affect.
+///
+/// `old_tokens` must be the tokens `Lexer::lex` produced for `old_text`,
+/// comments included and errors left out.
+pub fn relex(old_tokens: &[Token], old_text: &str, edit: &TextEdit) -> TokenDelta {
+    let new_text = edit.apply(old_text);
+    let shift = edit.shift();
+
+    let restart = restart_point(old_tokens, old_text, edit.range.start);
+    let (offset, previous) = match restart.checked_sub(1) {
+        Some(i) => (old_tokens[i].end as usize, Some(old_tokens[i].token_type)),
+        None => (0, None),
+    };
+
+    let mut lexer = Lexer::resume(&new_text, offset, previous);
+    // The template nesting the old tokens had, replayed from the restart
+    // point, to compare with the lexer's.
+    let mut old_braces = Vec::new();
+    let mut old = restart;
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    let edit_end = edit.range.start + edit.text.len();
+
+    loop {
+        lexer.skip();
+        let token = match lexer.next_token() {
+            Ok(token) if token.token_type == TokenType::EndOfFile => break,
+            Ok(token) => token,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        if token.start as usize >= edit_end {
+            let old_start = token.start as i64 - shift;
+            while old < old_tokens.len() && (old_tokens[old].start as i64) < old_start {
+                replay(&mut old_braces, old_tokens[old].token_type);
+                old += 1;
+            }
+            if old < old_tokens.len()
+                && old_tokens[old].start as usize >= edit.range.end
+                && same_token(&old_tokens[old], &token, shift)
+                && !token.token_type.is_comment()
+            {
+                replay(&mut old_braces, old_tokens[old].token_type);
+                if old_braces == lexer.braces() {
+                    // From here on, the lexer is in the same state it was in
+                    // after this token, and sees the same text.
+                    return TokenDelta {
+                        old_range: restart..old,
+                        tokens,
+                        errors,
+                        shift,
+                    };
+                }
+                old += 1;
+            }
+        }
+
+        errors.extend(LexerError::unterminated(&token));
+        tokens.push(token);
+    }
+
+    TokenDelta {
+        old_range: restart..old_tokens.len(),
+        tokens,
+        errors,
+        shift,
+    }
+}
+
+/// Index of the token to restart lexing at for an edit at `offset`.
+///
+/// The lexer restarts at the end of the token before it, so that the
+/// whitespace in between is rescanned for `PRECEDING_LINE_BREAK`. That token
+/// must not be a comment, which would carry flags over to the restart token,
+/// and the restart token must not be inside a template substitution, whose
+/// closing `}` depends on state from before the restart.
+fn restart_point(old_tokens: &[Token], old_text: &str, offset: usize) -> usize {
+    let mut first = old_tokens.partition_point(|t| t.end as usize + LOOKAHEAD < offset);
+
+    // A `/` the lexer took for a division may be the start of a regular
+    // expression that was unterminated until this edit, which can only close
+    // it on the same line.
+    let line_start = old_text[..offset.min(old_text.len())]
+        .rfind(['\n', '\r'])
+        .map_or(0, |i| i + 1);
+    let mut i = first;
+    while i > 0 && old_tokens[i - 1].end as usize > line_start {
+        i -= 1;
+        if matches!(
+            old_tokens[i].token_type,
+            TokenType::SlashToken | TokenType::SlashEqualsToken
+        ) {
+            first = i;
+        }
+    }
+
+    let mut braces = Vec::new();
+    let mut restart = 0;
+    for (i, token) in old_tokens[..first].iter().enumerate() {
+        replay(&mut braces, token.token_type);
+        if !braces.contains(&true) && !token.token_type.is_comment() {
+            restart = i + 1;
+        }
+    }
+    restart
+}
+
+/// Updates a template nesting stack the way `Lexer` does for `kind`.
+fn replay(braces: &mut Vec<bool>, kind: TokenType) {
+    match kind {
+        TokenType::LeftBraceToken => braces.push(false),
+        TokenType::TemplateHead => braces.push(true),
+        TokenType::RightBraceToken | TokenType::TemplateTail => {
+            braces.pop();
+        }
+        _ => {}
+    }
+}
+
+fn same_token(old: &Token, new: &Token, shift: i64) -> bool {
+    old.token_type == new.token_type
+        && old.flags == new.flags
+        && old.start as i64 + shift == new.start as i64
+        && old.end as i64 + shift == new.end as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(text: &str) -> Vec<Token> {
+        Lexer::new(text).lex().filter_map(Result::ok).collect()
+    }
+
+    fn check(text: &str, edit: &TextEdit) -> TokenDelta {
+        let mut tokens = lex(text);
+        let delta = relex(&tokens, text, edit);
+        delta.apply(&mut tokens);
+
+        assert_eq!(
+            tokens,
+            lex(&edit.apply(text)),
+            "{:?} applied to {:?}",
+            edit,
+            text
+        );
+        delta
+    }
+
+    #[test]
+    fn test_relex_reports_changed_range() {
+        let text = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+        let delta = check(text, &TextEdit::new(19..20, "22"));
+
+        // `b` and `=` are relexed too, since `2` is within their lookahead.
+        assert_eq!(delta.old_range, 6..9);
+        assert_eq!(delta.new_range(), 6..9);
+        assert_eq!(delta.tokens[2].start..delta.tokens[2].end, 19..21);
+        assert_eq!(delta.shift, 1);
+        assert!(delta.errors.is_empty());
+
+        let delta = check(text, &TextEdit::insert(19, "'"));
+        assert!(matches!(
+            delta.errors[..],
+            [LexerError::StringNotTerminated { pos: 19 }]
+        ));
+
+        // The unterminated string is not relexed, so its error is not
+        // reported again.
+        let delta = check("a; b; 'c", &TextEdit::insert(0, "x"));
+        assert_eq!(delta.new_range(), 0..1);
+        assert!(delta.errors.is_empty());
+    }
+
+    #[test]
+    fn test_relex_edge_cases() {
+        let cases = [
+            ("a + b", TextEdit::insert(1, "bc")),
+            ("a/b/c", TextEdit::insert(0, "return ")),
+            ("x = a / b; y", TextEdit::insert(12, "/")),
+            ("`a${b}c${d}e`", TextEdit::insert(7, "x")),
+            ("`a${ {b} }c` + d", TextEdit::delete(5..6)),
+            ("/* a */ b", TextEdit::delete(0..2)),
+            ("a /** doc */\nb", TextEdit::delete(12..13)),
+            ("'abc\nd", TextEdit::insert(4, "'")),
+            ("1 .5", TextEdit::delete(1..2)),
+            ("a ? .5 : b", TextEdit::delete(3..4)),
+            ("", TextEdit::insert(0, "a")),
+            ("abc", TextEdit::delete(0..3)),
+        ];
+
+        for (text, edit) in &cases {
+            check(text, edit);
+        }
+    }
+
+    #[test]
+    fn test_relex_matches_full_lex() {
+        let sources = [
+            include_str!("../benches/corpus/emitter.js"),
+            include_str!("../benches/corpus/store.ts"),
+        ];
+        const SNIPPETS: [&str; 16] = [
+            "", "a", " ", "\n", "/", "*", "'", "\"", "`", "${", "}", "{", "//", "/*", "*/", "0x",
+        ];
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+
+        for text in sources {
+            for _ in 0..300 {
+                let mut start = next(text.len() + 1);
+                while !text.is_char_boundary(start) {
+                    start -= 1;
+                }
+                let mut end = (start + next(8)).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                check(
+                    text,
+                    &TextEdit::new(start..end, SNIPPETS[next(SNIPPETS.len())]),
+                );
+            }
+        }
+    }
+}
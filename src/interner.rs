@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::{Arc, RwLock};
+
+/// An interned string: an index into an `Interner`.
+///
+/// Two atoms from the same interner are equal exactly when their strings are,
+/// so names can be compared and hashed as integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Atom(u32);
+
+impl Atom {
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The multiply-rotate hash from rustc's `FxHasher`. Names are short and not
+/// attacker-chosen, so SipHash's DoS resistance is not worth its cost here.
+#[derive(Default)]
+struct FxHasher(u64);
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    #[inline(always)]
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &b in chunks.remainder() {
+            self.add(b as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Default)]
+struct Table {
+    atoms: HashMap<Arc<str>, Atom, BuildHasherDefault<FxHasher>>,
+    strings: Vec<Arc<str>>,
+}
+
+/// A string table shared by every file in a compilation.
+///
+/// `Interner` is `Send + Sync`: lexers on different threads can intern into
+/// the same table through a shared reference. Lookups of strings that are
+/// already interned only take a read lock.
+#[derive(Default)]
+pub struct Interner {
+    table: RwLock<Table>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&self, text: &str) -> Atom {
+        if let Some(&atom) = self.table.read().unwrap().atoms.get(text) {
+            return atom;
+        }
+
+        let mut table = self.table.write().unwrap();
+        // Another thread may have interned `text` while we waited for the lock.
+        if let Some(&atom) = table.atoms.get(text) {
+            return atom;
+        }
+        let atom =
+            Atom(u32::try_from(table.strings.len()).expect("more than u32::MAX interned strings"));
+        let text: Arc<str> = Arc::from(text);
+        table.strings.push(text.clone());
+        table.atoms.insert(text, atom);
+        atom
+    }
+
+    /// The atom for `text`, if it has been interned.
+    pub fn get(&self, text: &str) -> Option<Atom> {
+        self.table.read().unwrap().atoms.get(text).copied()
+    }
+
+    /// The string `atom` stands for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `atom` came from a different interner.
+    pub fn resolve(&self, atom: Atom) -> Arc<str> {
+        self.table.read().unwrap().strings[atom.0 as usize].clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.read().unwrap().strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_interner_dedupes() {
+        let interner = Interner::new();
+        let a = interner.intern("foo");
+        let b = interner.intern("bar");
+
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("foo"), a);
+        assert_eq!(interner.get("bar"), Some(b));
+        assert_eq!(interner.get("baz"), None);
+        assert_eq!(&*interner.resolve(a), "foo");
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_interner_shared_across_threads() {
+        let interner = Interner::new();
+        let words: Vec<String> = (0..200).map(|i| format!("name{}", i % 50)).collect();
+
+        let atoms: Vec<Vec<Atom>> = thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| words.iter().map(|w| interner.intern(w)).collect()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(interner.len(), 50);
+        for thread_atoms in &atoms[1..] {
+            assert_eq!(thread_atoms, &atoms[0]);
+        }
+        for (word, atom) in words.iter().zip(&atoms[0]) {
+            assert_eq!(&*interner.resolve(*atom), word);
+        }
+    }
+}
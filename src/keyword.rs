@@ -0,0 +1,104 @@
+//! Keyword lookup through a perfect hash that build.rs generates from
+//! `keywords.txt`, so that the spellings and their `TokenType` variants come
+//! from a single list.
+
+use crate::token::TokenType;
+
+include!(concat!(env!("OUT_DIR"), "/keywords.rs"));
+
+/// Must match `hash` in build.rs.
+#[inline(always)]
+fn hash(word: u128) -> usize {
+    let folded = (word as u64) ^ ((word >> 64) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (folded.wrapping_mul(SEED) >> (64 - BITS)) as usize
+}
+
+/// The keyword whose spelling, packed by `pack`, is `word`.
+#[inline(always)]
+fn match_word(word: u128) -> Option<TokenType> {
+    let index = SLOTS[hash(word)] as usize;
+    (WORDS[index] == word).then(|| KEYWORDS[index].1)
+}
+
+/// The keyword spelled by `text`, if any.
+pub fn lookup(text: &[u8]) -> Option<TokenType> {
+    if !(MIN_LEN..=MAX_LEN).contains(&text.len()) {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    bytes[..text.len()].copy_from_slice(text);
+    match_word(u128::from_le_bytes(bytes))
+}
+
+/// The keyword spelled by `source[start..end]`, if any. Unlike `lookup`, this
+/// reads a whole 16-byte window where the source allows it and masks off the
+/// bytes past `end`, which avoids a variable-length copy in the lexer's hot
+/// loop.
+#[inline(always)]
+pub fn lookup_in(source: &[u8], start: usize, end: usize) -> Option<TokenType> {
+    let len = end - start;
+    if !(MIN_LEN..=MAX_LEN).contains(&len) {
+        return None;
+    }
+    match source.get(start..start + 16) {
+        Some(window) => {
+            let word = u128::from_le_bytes(window.try_into().unwrap());
+            match_word(word & ((1 << (len * 8)) - 1))
+        }
+        None => lookup(&source[start..end]),
+    }
+}
+
+impl TokenType {
+    /// The spelling of a keyword kind, or `None` for other kinds.
+    pub fn keyword_text(self) -> Option<&'static str> {
+        if !self.is_keyword() {
+            return None;
+        }
+        Some(KEYWORDS[(self as u8 - TokenType::FIRST_KEYWORD as u8) as usize].0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword_round_trip() {
+        let first = TokenType::FIRST_KEYWORD as u8;
+        let last = TokenType::LAST_KEYWORD as u8;
+        assert_eq!(KEYWORDS.len(), (last - first + 1) as usize);
+
+        for (i, &(text, kind)) in KEYWORDS.iter().enumerate() {
+            assert_eq!(kind as u8, first + i as u8, "{:?} is out of order", kind);
+            assert_eq!(lookup(text.as_bytes()), Some(kind));
+            let source = format!("{} x", text);
+            assert_eq!(lookup_in(source.as_bytes(), 0, text.len()), Some(kind));
+            assert_eq!(kind.keyword_text(), Some(text));
+        }
+    }
+
+    #[test]
+    fn test_keyword_lookup_rejects_non_keywords() {
+        for text in [
+            "",
+            "x",
+            "Break",
+            "breaks",
+            "instanceOf",
+            "o",
+            "ofx",
+            "constructors",
+        ] {
+            assert_eq!(lookup(text.as_bytes()), None, "{:?}", text);
+            let source = format!("{:<20}", text);
+            assert_eq!(
+                lookup_in(source.as_bytes(), 0, text.len()),
+                None,
+                "{:?}",
+                text
+            );
+        }
+        assert_eq!(TokenType::Identifier.keyword_text(), None);
+    }
+}
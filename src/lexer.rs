@@ -1,678 +1,1508 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::iter::FusedIterator;
 
 use crate::error::LexerError;
-use crate::token::{StrSpan, Token, TokenType};
+use crate::interner::Interner;
+use crate::keyword;
+use crate::scan::{self, ScanMode};
+use crate::token::{Token, TokenFlags, TokenType};
+use crate::token_buffer::{self, LiteralValue, TokenBuffer};
+use crate::trivia;
+
+// Character classes for the ASCII fast path. Bytes are classified through
+// `CHAR_CLASS` before falling back to UTF-8 decoding, which is only needed
+// for bytes >= 0x80. Whitespace, comment and string bodies are skipped with
+// the searches in `scan` instead.
+const LINE_BREAK: u8 = 1 << 0;
+const ID_START: u8 = 1 << 1;
+const ID_PART: u8 = 1 << 2;
+const DIGIT: u8 = 1 << 3;
+const HEX_DIGIT: u8 = 1 << 4;
+const NON_ASCII: u8 = 1 << 5;
+
+static CHAR_CLASS: [u8; 256] = char_class_table();
+
+const fn char_class_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let b = i as u8;
+        let mut class = 0;
+        if matches!(b, b'\n' | b'\r') {
+            class |= LINE_BREAK;
+        }
+        if b.is_ascii_alphabetic() || b == b'$' || b == b'_' {
+            class |= ID_START | ID_PART;
+        }
+        if b.is_ascii_digit() {
+            class |= DIGIT | ID_PART | HEX_DIGIT;
+        }
+        if matches!(b, b'a'..=b'f' | b'A'..=b'F') {
+            class |= HEX_DIGIT;
+        }
+        if b >= 0x80 {
+            class |= NON_ASCII;
+        }
+        table[i] = class;
+        i += 1;
+    }
+    table
+}
+
+#[inline(always)]
+fn is(b: u8, class: u8) -> bool {
+    CHAR_CLASS[b as usize] & class != 0
+}
+
+/// Number of leading bytes of `bytes` that are in `class`.
+#[inline(always)]
+fn count_while(bytes: &[u8], class: u8) -> usize {
+    bytes
+        .iter()
+        .position(|&b| !is(b, class))
+        .unwrap_or(bytes.len())
+}
+
+fn is_jsx_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\u{0B}' | '\u{0C}') || is_unicode_whitespace(c)
+}
+
+fn is_unicode_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00A0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200A}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202F}'
+                | '\u{205F}'
+                | '\u{3000}'
+                | '\u{FEFF}'
+    )
+}
+
+fn is_unicode_id_start(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+fn is_unicode_id_part(c: char) -> bool {
+    // ZWNJ and ZWJ are allowed in identifier parts, as are combining marks.
+    c.is_alphanumeric()
+        || matches!(c, '\u{200C}' | '\u{200D}' | '\u{0300}'..='\u{036F}' | '\u{20D0}'..='\u{20FF}')
+}
+
+/// Whether a `/` after `token_type` starts a regular expression rather than
+/// a division.
+fn regex_may_follow(token_type: TokenType) -> bool {
+    match token_type {
+        TokenType::Identifier
+        | TokenType::PrivateIdentifier
+        | TokenType::NumericLiteral
+        | TokenType::BigIntLiteral
+        | TokenType::StringLiteral
+        | TokenType::RegularExpressionLiteral
+        | TokenType::NoSubstitutionTemplateLiteral
+        | TokenType::TemplateTail
+        | TokenType::RightParenToken
+        | TokenType::RightBracketToken
+        | TokenType::RightBraceToken
+        | TokenType::PlusPlusToken
+        | TokenType::MinusMinusToken => false,
+        // Operator-like keywords are followed by an expression. Other keywords
+        // either end one (`this`, `null`) or are contextual and may be names.
+        TokenType::ReturnKeyword
+        | TokenType::TypeOfKeyword
+        | TokenType::InstanceOfKeyword
+        | TokenType::InKeyword
+        | TokenType::OfKeyword
+        | TokenType::NewKeyword
+        | TokenType::DeleteKeyword
+        | TokenType::VoidKeyword
+        | TokenType::ThrowKeyword
+        | TokenType::CaseKeyword
+        | TokenType::DoKeyword
+        | TokenType::ElseKeyword
+        | TokenType::YieldKeyword
+        | TokenType::AwaitKeyword => true,
+        _ => !token_type.is_keyword(),
+    }
+}
+
+/// Whether `<` in expression position starts a JSX element, as in
+/// TypeScript's `LanguageVariant`. JSX is off by default: in TypeScript files
+/// that `<` starts a type assertion instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LanguageVariant {
+    #[default]
+    Standard,
+    Jsx,
+}
+
+/// Where the lexer is within JSX.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Jsx {
+    /// Inside an opening tag, or a closing one (`</a>`, or the `/>` of a
+    /// self-closing tag) whose `>` ends the element.
+    Tag { closing: bool },
+    /// Between an element's opening and closing tags.
+    Children,
+    /// Inside a `{...}` in a tag or among children, which ends with the `}`
+    /// that leaves `braces` shorter than this.
+    Expression(usize),
+}
+
+/// The part of a `Lexer`'s state that carries over from one token to the
+/// next, so that lexing can stop and later continue in another buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerState {
+    /// One entry per open `{`: `true` if it was opened by a template's `${`,
+    /// in which case the matching `}` continues the template.
+    braces: Vec<bool>,
+    /// Whether a `/` after the last significant token starts a regular
+    /// expression.
+    regex_allowed: bool,
+    /// `PRECEDING_*` flags gathered from the whitespace and comments since
+    /// the last significant token.
+    preceding: TokenFlags,
+    /// Nothing has been scanned yet, so a `#!` line may follow.
+    at_start: bool,
+    /// The JSX nesting, innermost last. Always empty for
+    /// `LanguageVariant::Standard`.
+    jsx: Vec<Jsx>,
+}
+
+impl Default for LexerState {
+    fn default() -> Self {
+        Self {
+            braces: Vec::new(),
+            regex_allowed: true,
+            preceding: TokenFlags::NONE,
+            at_start: true,
+            jsx: Vec::new(),
+        }
+    }
+}
+
+impl LexerState {
+    /// Whether a `/` at this point is scanned as a regular expression.
+    pub fn regex_allowed(&self) -> bool {
+        self.regex_allowed
+    }
+
+    /// Whether nothing has been scanned yet, so that a `#!` line may come
+    /// next.
+    pub(crate) fn at_start(&self) -> bool {
+        self.at_start
+    }
+}
+
+/// Receives the productions of the lexical grammar that a `Lexer` matches,
+/// by their names in the ECMAScript specification, as it matches them. Set
+/// with `Lexer::set_recorder`.
+///
+/// A token reports its own production, such as `StringLiteral`, along with
+/// the parts the lexer told apart while scanning it, such as each
+/// `HexEscapeSequence`. Whitespace and line terminators are reported as the
+/// lexer skips them. Tokens the lexer rejects, or flags `UNTERMINATED`,
+/// report nothing.
+pub trait Recorder {
+    /// The lexer matched `text` as `production`, within or as a token of
+    /// `token_type`.
+    fn matched(&mut self, production: &'static str, token_type: TokenType, text: &str);
+}
+
+impl std::fmt::Debug for dyn Recorder + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Recorder")
+    }
+}
+
+/// The production a punctuator of `token_type` is matched as, if any.
+/// Punctuation of TypeScript and JSX only has none.
+fn punctuator_production(token_type: TokenType) -> Option<&'static str> {
+    match token_type {
+        TokenType::SlashToken | TokenType::SlashEqualsToken => Some("DivPunctuator"),
+        TokenType::RightBraceToken => Some("RightBracePunctuator"),
+        TokenType::QuestionDotToken => Some("OptionalChainingPunctuator"),
+        TokenType::AtToken
+        | TokenType::HashToken
+        | TokenType::BacktickToken
+        | TokenType::LessThanSlashToken => None,
+        _ => Some("Punctuator"),
+    }
+}
+
+/// Iterator over the tokens of a `Lexer`, returned by `Lexer::lex`.
+#[derive(Debug)]
+pub struct Tokens<'l, 'a> {
+    lexer: &'l mut Lexer<'a>,
+    done: bool,
+    /// An unterminated token, returned after its error.
+    unterminated: Option<Token>,
+}
+
+impl<'a> Tokens<'_, 'a> {
+    /// The lexer being iterated, to inspect its position between tokens.
+    pub fn lexer(&self) -> &Lexer<'a> {
+        self.lexer
+    }
+}
+
+impl Iterator for Tokens<'_, '_> {
+    type Item = Result<Token, LexerError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.unterminated.take() {
+            return Some(Ok(token));
+        }
+        if self.done {
+            return None;
+        }
+        self.lexer.skip();
+        match self.lexer.next_token() {
+            Ok(token) if token.token_type == TokenType::EndOfFile => {
+                self.done = true;
+                None
+            }
+            Ok(token) => match LexerError::unterminated(&token) {
+                Some(error) => {
+                    self.unterminated = Some(token);
+                    Some(Err(error))
+                }
+                None => Some(Ok(token)),
+            },
+            result => Some(result),
+        }
+    }
+}
+
+impl FusedIterator for Tokens<'_, '_> {}
 
+/// A byte-oriented scanner over UTF-8 source text.
+///
+/// The cursor is a plain byte index into the source, and tokens carry `u32`
+/// byte offsets, so sources are limited to 4 GiB.
 #[derive(Debug)]
 pub struct Lexer<'a> {
-    source: Peekable<Chars<'a>>,
+    text: &'a str,
+    source: &'a [u8],
 
-    line: usize,
+    /// Byte offset of the first byte of the token being scanned.
     start: usize,
+    /// Byte offset of the next byte to be consumed.
     current: usize,
+
+    state: LexerState,
+    /// Flags of the token being scanned.
+    flags: TokenFlags,
+
+    scan_mode: ScanMode,
+    variant: LanguageVariant,
+
+    recorder: Option<&'a mut dyn Recorder>,
+    /// The productions matched so far in the token being scanned, as byte
+    /// ranges, kept only while a `recorder` is set.
+    matched: Vec<(&'static str, usize, usize)>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
+        Self::with_scan_mode(source, ScanMode::Simd)
+    }
+
+    pub fn with_scan_mode(source: &'a str, scan_mode: ScanMode) -> Self {
+        let mut lexer = Self::with_state(source, 0, LexerState::default());
+        lexer.scan_mode = scan_mode;
+        lexer
+    }
+
+    /// Continues scanning at `offset` in `source`, in `state`, as taken from
+    /// another lexer by `into_state`.
+    pub fn with_state(source: &'a str, offset: usize, state: LexerState) -> Self {
+        assert!(
+            source.len() <= u32::MAX as usize,
+            "source files are limited to 4 GiB"
+        );
         Self {
-            source: source.chars().peekable(),
-            line: 1,
-            current: 0,
-            start: 0,
-        }
+            text: source,
+            source: source.as_bytes(),
+            start: offset,
+            current: offset,
+            state,
+            flags: TokenFlags::NONE,
+            scan_mode: ScanMode::Simd,
+            variant: LanguageVariant::Standard,
+            recorder: None,
+            matched: Vec::new(),
+        }
+    }
+
+    /// Starts scanning `source` at `offset`, the end of a token of kind
+    /// `previous` (or the start of the file, for `None`), outside any
+    /// template substitution. Used to relex part of a file after an edit.
+    pub fn resume(source: &'a str, offset: usize, previous: Option<TokenType>) -> Self {
+        let state = match previous {
+            Some(previous) => LexerState {
+                regex_allowed: regex_may_follow(previous),
+                at_start: false,
+                ..LexerState::default()
+            },
+            None => LexerState::default(),
+        };
+        Self::with_state(source, offset, state)
     }
 
-    pub fn process(&mut self) -> impl IntoIterator<Item = Result<Token, LexerError>> {
-        self.lex().into_iter()
+    pub fn set_language_variant(&mut self, variant: LanguageVariant) {
+        self.variant = variant;
     }
 
-    pub fn lex(&mut self) -> impl Iterator<Item = Result<Token, LexerError>> {
-        gen {
-            loop {
-                self.skip();
+    /// Reports the productions matched from here on to `recorder`.
+    pub fn set_recorder(&mut self, recorder: &'a mut dyn Recorder) {
+        self.recorder = Some(recorder);
+    }
 
-                match self.next_token() {
-                    Ok(token) => {
-                        if token.token_type == TokenType::EndOfFile {
-                            return;
-                        }
+    /// Byte offset of the next byte to be scanned.
+    pub fn offset(&self) -> usize {
+        self.current
+    }
 
-                        yield Ok(token);
-                    }
-                    Err(e) => yield Err(e),
+    pub fn into_state(self) -> LexerState {
+        self.state
+    }
+
+    /// The template nesting state: one entry per open `{` or `${`.
+    pub fn braces(&self) -> &[bool] {
+        &self.state.braces
+    }
+
+    /// Whether the next token is inside a JSX tag: a tag or attribute name,
+    /// an attribute value, or the tag's own punctuation.
+    pub(crate) fn in_jsx_tag(&self) -> bool {
+        matches!(self.state.jsx.last(), Some(Jsx::Tag { .. }))
+    }
+
+    pub fn process(&mut self) -> impl IntoIterator<Item = Result<Token, LexerError>> {
+        self.lex()
+    }
+
+    /// The remaining tokens, with errors interleaved where they occur. Ends
+    /// before `EndOfFile`. An unterminated string, template or comment is a
+    /// token flagged `UNTERMINATED`, right after its error.
+    pub fn lex(&mut self) -> Tokens<'_, 'a> {
+        Tokens {
+            lexer: self,
+            done: false,
+            unterminated: None,
+        }
+    }
+
+    /// Scans the whole source into a `TokenBuffer`, computing literal values
+    /// as it goes. Identifier names and cooked string values are interned
+    /// into `interner`. Errors are collected rather than interleaved with
+    /// tokens.
+    pub fn tokenize(&mut self, interner: &Interner) -> (TokenBuffer, Vec<LexerError>) {
+        let mut buffer = TokenBuffer::with_capacity(self.source.len() / 4);
+        let mut errors = Vec::new();
+        let text = self.text;
+
+        for token in self.lex() {
+            let t = match token {
+                Ok(t) => t,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
                 }
+            };
+            let value = match t.token_type {
+                TokenType::NumericLiteral => LiteralValue::Number(token_buffer::numeric_value(
+                    &text[t.start as usize..t.end as usize],
+                    t.flags,
+                )),
+                TokenType::Identifier | TokenType::PrivateIdentifier => {
+                    let name = token_buffer::identifier_name(
+                        &text[t.start as usize..t.end as usize],
+                        t.flags,
+                    );
+                    LiteralValue::Identifier(interner.intern(&name))
+                }
+                TokenType::StringLiteral => {
+                    let body = t.value_span();
+                    let value =
+                        token_buffer::string_value(&text[body.start as usize..body.end as usize]);
+                    LiteralValue::String(interner.intern(&value))
+                }
+                _ => {
+                    buffer.push(t);
+                    continue;
+                }
+            };
+            buffer.push_literal(t, value);
+        }
+
+        (buffer, errors)
+    }
+
+    /// Scans the whole source, filling the gaps between tokens with
+    /// whitespace, line break and shebang trivia, and text the lexer rejected
+    /// with `Unknown` tokens. The token spans then cover the remaining source
+    /// exactly, in order.
+    pub fn lex_with_trivia(&mut self) -> (Vec<Token>, Vec<LexerError>) {
+        let text = self.text;
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        let mut end = self.current;
+        for result in self.lex() {
+            match result {
+                Ok(token) => {
+                    trivia::gap_trivia(text, end, token.start as usize, &mut tokens);
+                    end = token.end as usize;
+                    tokens.push(token);
+                }
+                Err(e) => errors.push(e),
             }
         }
+        trivia::gap_trivia(text, end, text.len(), &mut tokens);
+        (tokens, errors)
     }
 
-    pub fn skip(&mut self) {
-        while let Some(&c) = self.source.peek() {
-            match c {
-                ' ' | '\r' | '\t' => {
-                    self.current += 1;
-                    _ = self.source.next();
+    /// Skips whitespace, line terminators and a leading `#!` shebang line.
+    pub(crate) fn skip(&mut self) {
+        // Whitespace among JSX children is part of the text.
+        if self.state.jsx.last() == Some(&Jsx::Children) {
+            return;
+        }
+        if self.state.at_start {
+            self.state.at_start = false;
+            if self.source[self.current..].starts_with(b"#!") {
+                let from = self.current;
+                self.skip_line();
+                self.record_trivia("HashbangComment", TokenType::SingleLineCommentTrivia, from);
+            }
+        }
+
+        let from = self.current;
+        loop {
+            let len = scan::whitespace_len(&self.source[self.current..], self.scan_mode);
+            if self.source[self.current..self.current + len]
+                .iter()
+                .any(|&b| is(b, LINE_BREAK))
+            {
+                self.state.preceding |= TokenFlags::PRECEDING_LINE_BREAK;
+            }
+            self.current += len;
+
+            let Some(&b) = self.source.get(self.current) else {
+                break;
+            };
+            if is(b, NON_ASCII) {
+                let c = self.peek_char();
+                if !is_unicode_whitespace(c) {
+                    break;
                 }
-                '\n' => {
-                    self.current = 1;
-                    self.line += 1;
-                    _ = self.source.next();
+                if matches!(c, '\u{2028}' | '\u{2029}') {
+                    self.state.preceding |= TokenFlags::PRECEDING_LINE_BREAK;
                 }
-                _ => break,
+                self.current += c.len_utf8();
+            } else {
+                break;
             }
         }
+        if self.recorder.is_some() {
+            self.record_whitespace(from);
+        }
+    }
+
+    /// Reports the text from `from` to the cursor, which the lexer skipped
+    /// rather than returned as a token, as `production`.
+    fn record_trivia(&mut self, production: &'static str, token_type: TokenType, from: usize) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.matched(production, token_type, &self.text[from..self.current]);
+        }
     }
 
-    pub fn advance(&mut self) -> Option<char> {
-        if let Some(c) = self.source.next() {
-            self.current += c.len_utf8();
-            Some(c)
+    /// Reports each whitespace character and line terminator skipped since
+    /// `from`.
+    #[cold]
+    fn record_whitespace(&mut self, from: usize) {
+        let text = self.text;
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        let mut rest = &text[from..self.current];
+        while let Some(c) = rest.chars().next() {
+            let len = if rest.starts_with("\r\n") {
+                recorder.matched("LineTerminatorSequence", TokenType::NewLineTrivia, "\r\n");
+                2
+            } else if matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}') {
+                let line_break = &rest[..c.len_utf8()];
+                recorder.matched(
+                    "LineTerminatorSequence",
+                    TokenType::NewLineTrivia,
+                    line_break,
+                );
+                recorder.matched("LineTerminator", TokenType::NewLineTrivia, line_break);
+                c.len_utf8()
+            } else {
+                recorder.matched(
+                    "WhiteSpace",
+                    TokenType::WhitespaceTrivia,
+                    &rest[..c.len_utf8()],
+                );
+                c.len_utf8()
+            };
+            rest = &rest[len..];
+        }
+    }
+
+    /// Notes that the lexer matched `start..end` as `production`, to report
+    /// once the token being scanned is complete.
+    #[inline(always)]
+    fn record(&mut self, production: &'static str, start: usize, end: usize) {
+        if self.recorder.is_some() {
+            self.matched.push((production, start, end));
+        }
+    }
+
+    fn skip_line(&mut self) {
+        self.current += scan::find_line_break(&self.source[self.current..], self.scan_mode);
+    }
+
+    #[inline(always)]
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.current).copied()
+    }
+
+    #[inline(always)]
+    fn peek_at(&self, n: usize) -> Option<u8> {
+        self.source.get(self.current + n).copied()
+    }
+
+    /// Decodes the (non-ASCII) character at the cursor. Only called when
+    /// `current` is in bounds and on a character boundary.
+    fn peek_char(&self) -> char {
+        self.text[self.current..].chars().next().unwrap_or_default()
+    }
+
+    #[inline(always)]
+    fn match_byte(&mut self, expected: u8) -> bool {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            true
         } else {
-            None
+            false
         }
     }
 
-    pub fn match_char(&mut self, expected: char) -> bool {
-        match self.source.peek().copied() {
-            Some(c) if c == expected => {
-                // Consume the matched character and advance position counters
-                _ = self.source.next();
-                self.current += expected.len_utf8();
-                true
+    /// Builds the token that ends at the cursor. Comments keep the
+    /// `PRECEDING_*` flags pending for the next significant token.
+    fn new_token(&mut self, token_type: TokenType) -> Token {
+        if !token_type.is_comment() {
+            self.state.regex_allowed = regex_may_follow(token_type);
+        }
+
+        let flags = self.flags | self.state.preceding;
+        if !token_type.is_comment() {
+            self.state.preceding = TokenFlags::NONE;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            // An unterminated token is an error, not a production.
+            if !flags.contains(TokenFlags::UNTERMINATED) {
+                for &(production, start, end) in &self.matched {
+                    recorder.matched(production, token_type, &self.text[start..end]);
+                }
             }
-            _ => false,
+            self.matched.clear();
         }
+        Token::new(token_type, flags, self.start as u32, self.current as u32)
     }
 
-    pub fn new_token(
-        &self,
-        token_type: TokenType,
-        lexeme: Option<StrSpan>,
-        literal_value: Option<StrSpan>,
-    ) -> Token {
-        Token::new(
-            token_type,
-            self.line,
-            self.start,
-            self.current + 1,
-            lexeme,
-            literal_value,
-        )
+    fn punctuator(&mut self, token_type: TokenType) -> Result<Token, LexerError> {
+        if let Some(production) = punctuator_production(token_type) {
+            self.record(production, self.start, self.current);
+        }
+        Ok(self.new_token(token_type))
+    }
+
+    fn error_pos(&self) -> u32 {
+        self.start as u32
     }
 
-    pub fn next_token(&mut self) -> Result<Token, LexerError> {
-        self.start = self.current + 1;
+    pub(crate) fn next_token(&mut self) -> Result<Token, LexerError> {
+        self.start = self.current;
+        self.flags = TokenFlags::NONE;
+        // Left over from a token that was rejected.
+        self.matched.clear();
 
-        let Some(c) = self.advance() else {
-            return Ok(self.new_token(TokenType::EndOfFile, None, None));
+        let Some(b) = self.peek() else {
+            return Ok(self.new_token(TokenType::EndOfFile));
         };
+        match self.state.jsx.last() {
+            Some(&Jsx::Tag { closing }) => {
+                if let Some(token) = self.jsx_tag_token(b, closing) {
+                    return token;
+                }
+            }
+            Some(Jsx::Children) => return self.jsx_child_token(b),
+            _ => {}
+        }
+        self.current += 1;
 
-        match c {
-            ';' => Ok(self.new_token(TokenType::SemicolonToken, None, None)),
-            '(' => Ok(self.new_token(TokenType::LeftParenToken, None, None)),
-            ')' => Ok(self.new_token(TokenType::RightParenToken, None, None)),
-            '{' => Ok(self.new_token(TokenType::LeftBraceToken, None, None)),
-            '}' => Ok(self.new_token(TokenType::RightBraceToken, None, None)),
-            '[' => Ok(self.new_token(TokenType::LeftBracketToken, None, None)),
-            ']' => Ok(self.new_token(TokenType::RightBracketToken, None, None)),
-            ',' => Ok(self.new_token(TokenType::CommaToken, None, None)),
-            '~' => Ok(self.new_token(TokenType::TildeToken, None, None)),
-            ':' => Ok(self.new_token(TokenType::ColonToken, None, None)),
-            '@' => Ok(self.new_token(TokenType::AtToken, None, None)),
-            '.' => {
-                if self.match_char('.') {
-                    if self.match_char('.') {
-                        Ok(self.new_token(TokenType::DotDotDotToken, None, None))
-                    } else {
-                        Err(LexerError::UnexpectedCharacter {
-                            line: self.line,
-                            column: self.start,
-                        })
+        match b {
+            b';' => self.punctuator(TokenType::SemicolonToken),
+            b'(' => self.punctuator(TokenType::LeftParenToken),
+            b')' => self.punctuator(TokenType::RightParenToken),
+            b'{' => {
+                self.state.braces.push(false);
+                self.punctuator(TokenType::LeftBraceToken)
+            }
+            b'}' => {
+                if self.state.braces.pop() == Some(true) {
+                    self.template(false)
+                } else {
+                    if let Some(&Jsx::Expression(depth)) = self.state.jsx.last()
+                        && self.state.braces.len() < depth
+                    {
+                        self.state.jsx.pop();
                     }
+                    self.punctuator(TokenType::RightBraceToken)
+                }
+            }
+            b'[' => self.punctuator(TokenType::LeftBracketToken),
+            b']' => self.punctuator(TokenType::RightBracketToken),
+            b',' => self.punctuator(TokenType::CommaToken),
+            b'~' => self.punctuator(TokenType::TildeToken),
+            b':' => self.punctuator(TokenType::ColonToken),
+            b'@' => self.punctuator(TokenType::AtToken),
+            b'.' => {
+                if self.peek().is_some_and(|b| is(b, DIGIT)) {
+                    self.decimal(true)
+                } else if self.peek() == Some(b'.') && self.peek_at(1) == Some(b'.') {
+                    self.current += 2;
+                    self.punctuator(TokenType::DotDotDotToken)
                 } else {
-                    Ok(self.new_token(TokenType::DotToken, None, None))
+                    self.punctuator(TokenType::DotToken)
                 }
             }
-            '+' => {
-                if self.match_char('+') {
-                    Ok(self.new_token(TokenType::PlusPlusToken, None, None))
-                } else if self.match_char('=') {
-                    Ok(self.new_token(TokenType::PlusEqualsToken, None, None))
+            b'+' => {
+                if self.match_byte(b'+') {
+                    self.punctuator(TokenType::PlusPlusToken)
+                } else if self.match_byte(b'=') {
+                    self.punctuator(TokenType::PlusEqualsToken)
                 } else {
-                    Ok(self.new_token(TokenType::PlusToken, None, None))
+                    self.punctuator(TokenType::PlusToken)
                 }
             }
-            '-' => {
-                if self.match_char('-') {
-                    Ok(self.new_token(TokenType::MinusMinusToken, None, None))
-                } else if self.match_char('=') {
-                    Ok(self.new_token(TokenType::MinusEqualsToken, None, None))
+            b'-' => {
+                if self.match_byte(b'-') {
+                    self.punctuator(TokenType::MinusMinusToken)
+                } else if self.match_byte(b'=') {
+                    self.punctuator(TokenType::MinusEqualsToken)
                 } else {
-                    Ok(self.new_token(TokenType::MinusToken, None, None))
+                    self.punctuator(TokenType::MinusToken)
                 }
             }
-            '*' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::AsteriskEqualsToken, None, None))
-                } else if self.match_char('*') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::AsteriskAsteriskEqualsToken, None, None))
+            b'*' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::AsteriskEqualsToken)
+                } else if self.match_byte(b'*') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::AsteriskAsteriskEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::AsteriskAsteriskToken, None, None))
+                        self.punctuator(TokenType::AsteriskAsteriskToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::AsteriskToken, None, None))
+                    self.punctuator(TokenType::AsteriskToken)
                 }
             }
-            '/' => {
-                if self.match_char('/') {
-                    loop {
-                        let Some(next_c) = self.advance() else {
-                            return Ok(self.new_token(
-                                TokenType::MultiLineCommentTrivia,
-                                Some(StrSpan {
-                                    start: self.start + 2,
-                                    end: self.current + 1,
-                                }),
-                                Some(StrSpan {
-                                    start: self.start,
-                                    end: self.current + 1,
-                                }),
-                            ));
-                        };
-                        if next_c == '\n' {
-                            break;
-                        }
-                    }
-
-                    let new_token = self.new_token(
-                        TokenType::MultiLineCommentTrivia,
-                        Some(StrSpan {
-                            start: self.start + 2,
-                            end: self.current + 1,
-                        }),
-                        Some(StrSpan {
-                            start: self.start,
-                            end: self.current + 1,
-                        }),
-                    );
-                    self.line += 1;
-                    self.current = 0;
-                    Ok(new_token)
-                } else if self.match_char('*') {
-                    let start_line = self.line;
-                    let mut is_jsdoc = false;
-
-                    if self.match_char('*') {
-                        is_jsdoc = true;
-                    }
-
-                    loop {
-                        let Some(next_c) = self.advance() else {
-                            return Err(LexerError::CommentNotTerminated {
-                                line: start_line,
-                                column: self.current,
-                            });
-                        };
-
-                        if next_c == '\n' {
-                            self.current = 0;
-                            self.line += 1;
-                            continue;
-                        } else if next_c == '*' {
-                            if self.match_char('/') {
-                                break;
-                            }
-                        }
-                    }
-
-                    if is_jsdoc {
-                        Ok(Token::new(
-                            TokenType::JSDoc,
-                            start_line,
-                            self.start,
-                            self.current,
-                            Some(StrSpan {
-                                start: self.start + 3,
-                                end: self.current,
-                            }),
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current,
-                            }),
-                        ))
-                    } else {
-                        Ok(Token::new(
-                            TokenType::MultiLineCommentTrivia,
-                            start_line,
-                            self.start,
-                            self.current,
-                            Some(StrSpan {
-                                start: self.start + 2,
-                                end: self.current,
-                            }),
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current,
-                            }),
-                        ))
-                    }
-                } else if self.match_char('=') {
-                    Ok(self.new_token(TokenType::SlashEqualsToken, None, None))
+            b'/' => {
+                if self.match_byte(b'/') {
+                    self.single_line_comment()
+                } else if self.match_byte(b'*') {
+                    self.multi_line_comment()
+                } else if self.state.regex_allowed {
+                    self.regex()
+                } else if self.match_byte(b'=') {
+                    self.punctuator(TokenType::SlashEqualsToken)
                 } else {
-                    Ok(self.new_token(TokenType::SlashToken, None, None))
+                    self.punctuator(TokenType::SlashToken)
                 }
             }
-            '%' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::PercentEqualsToken, None, None))
+            b'%' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::PercentEqualsToken)
                 } else {
-                    Ok(self.new_token(TokenType::PercentToken, None, None))
+                    self.punctuator(TokenType::PercentToken)
                 }
             }
-            '=' => {
-                if self.match_char('=') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::EqualsEqualsEqualsToken, None, None))
+            b'=' => {
+                if self.match_byte(b'=') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::EqualsEqualsEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::EqualsEqualsToken, None, None))
+                        self.punctuator(TokenType::EqualsEqualsToken)
                     }
-                } else if self.match_char('>') {
-                    Ok(self.new_token(TokenType::EqualsGreaterThanToken, None, None))
+                } else if self.match_byte(b'>') {
+                    self.punctuator(TokenType::EqualsGreaterThanToken)
                 } else {
-                    Ok(self.new_token(TokenType::EqualsToken, None, None))
+                    self.punctuator(TokenType::EqualsToken)
                 }
             }
-            '!' => {
-                if self.match_char('=') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::ExclamationEqualsEqualsToken, None, None))
+            b'!' => {
+                if self.match_byte(b'=') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::ExclamationEqualsEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::ExclamationEqualsToken, None, None))
+                        self.punctuator(TokenType::ExclamationEqualsToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::ExclamationToken, None, None))
+                    self.punctuator(TokenType::ExclamationToken)
                 }
             }
-            '?' => {
-                if self.match_char('?') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::QuestionQuestionEqualsToken, None, None))
+            b'?' => {
+                if self.match_byte(b'?') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::QuestionQuestionEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::QuestionQuestionToken, None, None))
+                        self.punctuator(TokenType::QuestionQuestionToken)
                     }
-                } else if self.match_char('.') {
-                    Ok(self.new_token(TokenType::QuestionDotToken, None, None))
+                } else if self.peek() == Some(b'.')
+                    && !self.peek_at(1).is_some_and(|b| is(b, DIGIT))
+                {
+                    // `a?.5:b` is a conditional, not an optional chain.
+                    self.current += 1;
+                    self.punctuator(TokenType::QuestionDotToken)
                 } else {
-                    Ok(self.new_token(TokenType::QuestionToken, None, None))
+                    self.punctuator(TokenType::QuestionToken)
                 }
             }
-            '<' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::LessThanEqualsToken, None, None))
-                } else if self.match_char('/') {
-                    Ok(self.new_token(TokenType::LessThanSlashToken, None, None))
-                } else if self.match_char('<') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::LessThanLessThanEqualsToken, None, None))
+            b'<' => {
+                if self.variant == LanguageVariant::Jsx
+                    && self.state.regex_allowed
+                    && self.peek().is_some_and(|b| b == b'>' || is(b, ID_START))
+                {
+                    self.state.jsx.push(Jsx::Tag { closing: false });
+                    self.punctuator(TokenType::LessThanToken)
+                } else if self.match_byte(b'=') {
+                    self.punctuator(TokenType::LessThanEqualsToken)
+                } else if self.variant == LanguageVariant::Jsx
+                    && self.peek() == Some(b'/')
+                    && self.peek_at(1) != Some(b'*')
+                {
+                    self.current += 1;
+                    self.punctuator(TokenType::LessThanSlashToken)
+                } else if self.match_byte(b'<') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::LessThanLessThanEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::LessThanLessThanToken, None, None))
+                        self.punctuator(TokenType::LessThanLessThanToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::LessThanToken, None, None))
+                    self.punctuator(TokenType::LessThanToken)
                 }
             }
-            '>' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::GreaterThanEqualsToken, None, None))
-                } else if self.match_char('>') {
-                    if self.match_char('>') {
-                        if self.match_char('=') {
-                            Ok(self.new_token(
-                                TokenType::GreaterThanGreaterThanGreaterThanEqualsToken,
-                                None,
-                                None,
-                            ))
+            b'>' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::GreaterThanEqualsToken)
+                } else if self.match_byte(b'>') {
+                    if self.match_byte(b'>') {
+                        if self.match_byte(b'=') {
+                            self.punctuator(TokenType::GreaterThanGreaterThanGreaterThanEqualsToken)
                         } else {
-                            Ok(self.new_token(
-                                TokenType::GreaterThanGreaterThanGreaterThanToken,
-                                None,
-                                None,
-                            ))
+                            self.punctuator(TokenType::GreaterThanGreaterThanGreaterThanToken)
                         }
-                    } else if self.match_char('=') {
-                        Ok(
-                            self.new_token(
-                                TokenType::GreaterThanGreaterThanEqualsToken,
-                                None,
-                                None,
-                            ),
-                        )
+                    } else if self.match_byte(b'=') {
+                        self.punctuator(TokenType::GreaterThanGreaterThanEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::GreaterThanGreaterThanToken, None, None))
+                        self.punctuator(TokenType::GreaterThanGreaterThanToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::GreaterThanToken, None, None))
+                    self.punctuator(TokenType::GreaterThanToken)
                 }
             }
-            '&' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::AmpersandEqualsToken, None, None))
-                } else if self.match_char('&') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::AmpersandAmpersandEqualsToken, None, None))
+            b'&' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::AmpersandEqualsToken)
+                } else if self.match_byte(b'&') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::AmpersandAmpersandEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::AmpersandAmpersandToken, None, None))
+                        self.punctuator(TokenType::AmpersandAmpersandToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::AmpersandToken, None, None))
+                    self.punctuator(TokenType::AmpersandToken)
                 }
             }
-            '|' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::BarEqualsToken, None, None))
-                } else if self.match_char('|') {
-                    if self.match_char('=') {
-                        Ok(self.new_token(TokenType::BarBarEqualsToken, None, None))
+            b'|' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::BarEqualsToken)
+                } else if self.match_byte(b'|') {
+                    if self.match_byte(b'=') {
+                        self.punctuator(TokenType::BarBarEqualsToken)
                     } else {
-                        Ok(self.new_token(TokenType::BarBarToken, None, None))
+                        self.punctuator(TokenType::BarBarToken)
                     }
                 } else {
-                    Ok(self.new_token(TokenType::BarToken, None, None))
+                    self.punctuator(TokenType::BarToken)
                 }
             }
-            '^' => {
-                if self.match_char('=') {
-                    Ok(self.new_token(TokenType::CaretEqualsToken, None, None))
+            b'^' => {
+                if self.match_byte(b'=') {
+                    self.punctuator(TokenType::CaretEqualsToken)
                 } else {
-                    Ok(self.new_token(TokenType::CaretToken, None, None))
+                    self.punctuator(TokenType::CaretToken)
+                }
+            }
+            b'\'' | b'"' => self.string(b),
+            b'`' => self.template(true),
+            b'#' => {
+                if self.identifier_start() {
+                    self.identifier_rest();
+                    self.record("PrivateIdentifier", self.start, self.current);
+                    Ok(self.new_token(TokenType::PrivateIdentifier))
+                } else {
+                    Err(LexerError::UnexpectedCharacter {
+                        pos: self.error_pos(),
+                    })
+                }
+            }
+            b'0' => match self.peek() {
+                Some(b'b' | b'B') => self.radix(
+                    TokenFlags::BINARY_SPECIFIER,
+                    ["BinaryIntegerLiteral", "BinaryBigIntegerLiteral"],
+                    |b| matches!(b, b'0' | b'1'),
+                ),
+                Some(b'o' | b'O') => self.radix(
+                    TokenFlags::OCTAL_SPECIFIER,
+                    ["OctalIntegerLiteral", "OctalBigIntegerLiteral"],
+                    |b| matches!(b, b'0'..=b'7'),
+                ),
+                Some(b'x' | b'X') => self.radix(
+                    TokenFlags::HEX_SPECIFIER,
+                    ["HexIntegerLiteral", "HexBigIntegerLiteral"],
+                    |b| is(b, HEX_DIGIT),
+                ),
+                _ => self.decimal(false),
+            },
+            b'1'..=b'9' => self.decimal(false),
+            _ if is(b, ID_START) => {
+                self.identifier_rest();
+                // Escaped keywords like `\u0062reak` are left as identifiers.
+                let keyword = if self.flags.is_empty() {
+                    keyword::lookup_in(self.source, self.start, self.current)
+                } else {
+                    None
+                };
+                self.name(keyword.unwrap_or(TokenType::Identifier))
+            }
+            b'\\' => {
+                self.current = self.start;
+                if self.identifier_start() {
+                    self.identifier_rest();
+                    self.name(TokenType::Identifier)
+                } else {
+                    self.current = self.start + 1;
+                    Err(LexerError::UnexpectedCharacter {
+                        pos: self.error_pos(),
+                    })
+                }
+            }
+            _ if is(b, NON_ASCII) => {
+                self.current = self.start;
+                let c = self.peek_char();
+                self.current += c.len_utf8();
+                if is_unicode_id_start(c) {
+                    self.identifier_rest();
+                    self.name(TokenType::Identifier)
+                } else {
+                    Err(LexerError::UnexpectedCharacter {
+                        pos: self.error_pos(),
+                    })
                 }
             }
-            '0' => self.numeric_literal_starting_0(),
-            '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            }),
             _ => Err(LexerError::UnexpectedCharacter {
-                line: self.line,
-                column: self.start,
+                pos: self.error_pos(),
             }),
         }
     }
 
-    fn numeric_literal_starting_0(&mut self) -> Result<Token, LexerError> {
-        let Some(start_char) = self.source.peek() else {
-            return Ok(self.new_token(
-                TokenType::NumericLiteral,
-                Some(StrSpan {
-                    start: self.start,
-                    end: self.current,
-                }),
-                Some(StrSpan {
-                    start: self.start,
-                    end: self.current,
-                }),
-            ));
+    /// Builds the token for the name or keyword just scanned.
+    fn name(&mut self, token_type: TokenType) -> Result<Token, LexerError> {
+        self.record("IdentifierName", self.start, self.current);
+        let production = match token_type {
+            TokenType::Identifier => Some("Identifier"),
+            TokenType::TrueKeyword | TokenType::FalseKeyword => Some("BooleanLiteral"),
+            TokenType::NullKeyword => Some("NullLiteral"),
+            _ if token_type.is_contextual_keyword() => None,
+            _ => Some("ReservedWord"),
         };
-
-        match start_char {
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => self.decimal(),
-            'b' | 'B' => self.binary(),
-            'o' | 'O' => self.octal(),
-            'x' | 'X' => self.hexadecimal(),
-            // 'e' | 'E' => {}
-            _ => Ok(self.new_token(
-                TokenType::NumericLiteral,
-                Some(StrSpan {
-                    start: self.start,
-                    end: self.current,
-                }),
-                Some(StrSpan {
-                    start: self.start,
-                    end: self.current,
-                }),
-            )),
+        if let Some(production) = production {
+            self.record(production, self.start, self.current);
+        }
+        Ok(self.new_token(token_type))
+    }
+
+    /// Scans the token at `b` inside a JSX tag, or returns `None` for tokens
+    /// that are scanned as usual, such as comments.
+    #[inline(never)]
+    fn jsx_tag_token(&mut self, b: u8, closing: bool) -> Option<Result<Token, LexerError>> {
+        self.current += 1;
+        let token = match b {
+            b'>' => {
+                self.state.jsx.pop();
+                if !closing {
+                    self.state.jsx.push(Jsx::Children);
+                }
+                let token = self.new_token(TokenType::GreaterThanToken);
+                if closing && !matches!(self.state.jsx.last(), Some(Jsx::Children)) {
+                    // The element is an operand.
+                    self.state.regex_allowed = false;
+                }
+                Ok(token)
+            }
+            b'/' if !matches!(self.peek(), Some(b'/' | b'*')) => {
+                *self.state.jsx.last_mut().unwrap() = Jsx::Tag { closing: true };
+                self.punctuator(TokenType::SlashToken)
+            }
+            b'{' => {
+                self.state.braces.push(false);
+                self.state
+                    .jsx
+                    .push(Jsx::Expression(self.state.braces.len()));
+                self.punctuator(TokenType::LeftBraceToken)
+            }
+            b'\'' | b'"' => {
+                // Attribute strings have no escapes and may span lines.
+                match self.source[self.current..].iter().position(|&c| c == b) {
+                    Some(n) => self.current += n + 1,
+                    None => {
+                        self.current = self.source.len();
+                        self.flags |= TokenFlags::UNTERMINATED;
+                    }
+                }
+                self.record("JsxAttributeValue", self.start, self.current);
+                Ok(self.new_token(TokenType::StringLiteral))
+            }
+            _ if is(b, ID_START) => {
+                // Names may contain `-`, as in `data-id`.
+                self.identifier_rest();
+                let mut dashed = false;
+                while self.match_byte(b'-') {
+                    dashed = true;
+                    self.identifier_rest();
+                }
+                if dashed {
+                    return Some(Ok(self.new_token(TokenType::Identifier)));
+                }
+                let keyword = if self.flags.is_empty() {
+                    keyword::lookup_in(self.source, self.start, self.current)
+                } else {
+                    None
+                };
+                self.name(keyword.unwrap_or(TokenType::Identifier))
+            }
+            _ => {
+                self.current = self.start;
+                return None;
+            }
+        };
+        Some(token)
+    }
+
+    /// Scans the token at `b` among JSX children: a tag, an expression or
+    /// the text up to either.
+    #[inline(never)]
+    fn jsx_child_token(&mut self, b: u8) -> Result<Token, LexerError> {
+        self.current += 1;
+        match b {
+            b'<' => {
+                if self.match_byte(b'/') {
+                    self.state.jsx.pop();
+                    self.state.jsx.push(Jsx::Tag { closing: true });
+                    self.punctuator(TokenType::LessThanSlashToken)
+                } else {
+                    self.state.jsx.push(Jsx::Tag { closing: false });
+                    self.punctuator(TokenType::LessThanToken)
+                }
+            }
+            b'{' => {
+                self.state.braces.push(false);
+                self.state
+                    .jsx
+                    .push(Jsx::Expression(self.state.braces.len()));
+                self.punctuator(TokenType::LeftBraceToken)
+            }
+            _ => {
+                let rest = &self.source[self.start..];
+                let len = rest
+                    .iter()
+                    .position(|&c| c == b'<' || c == b'{')
+                    .unwrap_or(rest.len());
+                self.current = self.start + len;
+                let text = &self.text[self.start..self.current];
+                let token_type = if text.chars().all(is_jsx_whitespace) {
+                    TokenType::JsxTextAllWhiteSpaces
+                } else {
+                    TokenType::JsxText
+                };
+                self.record("JsxText", self.start, self.current);
+                Ok(self.new_token(token_type))
+            }
         }
     }
 
-    fn binary(&mut self) -> Result<Token, LexerError> {
-        _ = self.advance();
+    fn single_line_comment(&mut self) -> Result<Token, LexerError> {
+        // The line terminator is not part of the comment.
+        self.skip_line();
 
-        let Some(next_c) = self.source.peek() else {
-            return Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            });
-        };
+        self.record("SingleLineComment", self.start, self.current);
+        self.record("Comment", self.start, self.current);
+        Ok(self.new_token(TokenType::SingleLineCommentTrivia))
+    }
 
-        match *next_c {
-            '0' | '1' => {
-                _ = self.advance();
-
-                loop {
-                    let Some(next_next_c) = self.source.peek() else {
-                        return Ok(self.new_token(
-                            TokenType::NumericLiteral,
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                        ));
-                    };
+    fn multi_line_comment(&mut self) -> Result<Token, LexerError> {
+        // `/**/` is an empty comment, not the start of a JSDoc comment.
+        let is_jsdoc = self.peek() == Some(b'*') && self.peek_at(1) != Some(b'/');
 
-                    match next_next_c {
-                        ' ' | '\n' | '\r' | '\t' | ';' => break,
-                        '0' | '1' => {
-                            _ = self.advance();
-                        }
-                        _ => {
-                            self.current += 1;
-                            return Err(LexerError::InvalidNumber {
-                                line: self.line,
-                                column: self.current,
-                            });
-                        }
+        match scan::find_comment_end(&self.source[self.current..], self.scan_mode) {
+            Some(n) => {
+                let body = &self.source[self.current..self.current + n];
+                if body.iter().any(|&b| is(b, LINE_BREAK)) {
+                    self.state.preceding |= TokenFlags::PRECEDING_LINE_BREAK;
+                }
+                self.current += n + 2;
+            }
+            None => {
+                let body = &self.source[self.current..];
+                if body.iter().any(|&b| is(b, LINE_BREAK)) {
+                    self.state.preceding |= TokenFlags::PRECEDING_LINE_BREAK;
+                }
+                self.current = self.source.len();
+                self.flags |= TokenFlags::UNTERMINATED;
+            }
+        }
+
+        self.record("MultiLineComment", self.start, self.current);
+        self.record("Comment", self.start, self.current);
+        let token = self.new_token(TokenType::MultiLineCommentTrivia);
+        if is_jsdoc {
+            self.state.preceding |= TokenFlags::PRECEDING_JSDOC_COMMENT;
+        }
+        Ok(token)
+    }
+
+    /// Consumes an identifier start at the cursor, if there is one, including
+    /// `\uXXXX` and `\u{X}` escapes.
+    fn identifier_start(&mut self) -> bool {
+        match self.peek() {
+            Some(b) if is(b, ID_START) => {
+                self.current += 1;
+                true
+            }
+            Some(b'\\') => self.unicode_escape(),
+            Some(b) if is(b, NON_ASCII) => {
+                let c = self.peek_char();
+                if is_unicode_id_start(c) {
+                    self.current += c.len_utf8();
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn identifier_rest(&mut self) {
+        loop {
+            // ASCII fast path.
+            self.current += count_while(&self.source[self.current..], ID_PART);
+
+            match self.peek() {
+                Some(b'\\') => {
+                    if !self.unicode_escape() {
+                        return;
                     }
                 }
+                Some(b) if is(b, NON_ASCII) => {
+                    let c = self.peek_char();
+                    if !is_unicode_id_part(c) {
+                        return;
+                    }
+                    self.current += c.len_utf8();
+                }
+                _ => return,
+            }
+        }
+    }
 
-                Ok(self.new_token(
-                    TokenType::NumericLiteral,
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                ))
-            }
-            _ => Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            }),
+    fn unicode_escape(&mut self) -> bool {
+        let rest = &self.source[self.current..];
+        if !rest.starts_with(b"\\u") {
+            return false;
+        }
+
+        let len = if rest.get(2) == Some(&b'{') {
+            match rest[3..].iter().position(|&b| b == b'}') {
+                Some(n) if n > 0 && rest[3..3 + n].iter().all(|&b| is(b, HEX_DIGIT)) => {
+                    self.flags |= TokenFlags::EXTENDED_UNICODE_ESCAPE;
+                    n + 4
+                }
+                _ => return false,
+            }
+        } else if rest.len() >= 6 && rest[2..6].iter().all(|&b| is(b, HEX_DIGIT)) {
+            self.flags |= TokenFlags::UNICODE_ESCAPE;
+            6
+        } else {
+            return false;
+        };
+
+        self.record(
+            "UnicodeEscapeSequence",
+            self.current + 1,
+            self.current + len,
+        );
+        self.current += len;
+        true
+    }
+
+    /// Consumes exactly `n` hex digits, or nothing if there are fewer.
+    fn hex_digits(&mut self, n: usize) -> bool {
+        let rest = &self.source[self.current..];
+        if rest.len() >= n && rest[..n].iter().all(|&b| is(b, HEX_DIGIT)) {
+            self.current += n;
+            true
+        } else {
+            false
         }
     }
-    fn octal(&mut self) -> Result<Token, LexerError> {
-        _ = self.advance();
 
-        let Some(next_c) = self.source.peek() else {
+    fn digits(&mut self, is_digit: impl Fn(u8) -> bool) -> usize {
+        let from = self.current;
+        while let Some(b) = self.peek() {
+            if is_digit(b) {
+                self.current += 1;
+            } else if b == b'_' && self.peek_at(1).is_some_and(&is_digit) {
+                self.flags |= TokenFlags::CONTAINS_SEPARATOR;
+                self.current += 1;
+            } else {
+                break;
+            }
+        }
+        self.current - from
+    }
+
+    fn radix(
+        &mut self,
+        specifier: TokenFlags,
+        productions: [&'static str; 2],
+        is_digit: impl Fn(u8) -> bool,
+    ) -> Result<Token, LexerError> {
+        // Skip the `b`, `o` or `x` after the leading `0`.
+        self.current += 1;
+        self.flags |= specifier;
+
+        if self.digits(is_digit) == 0 {
             return Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
+                pos: self.current as u32,
             });
+        }
+
+        let [integer, big_integer] = productions;
+        let token_type = if self.match_byte(b'n') {
+            self.record(big_integer, self.start, self.current);
+            TokenType::BigIntLiteral
+        } else {
+            self.record(integer, self.start, self.current);
+            TokenType::NumericLiteral
         };
+        self.finish_number(token_type)
+    }
+
+    fn decimal(&mut self, leading_dot: bool) -> Result<Token, LexerError> {
+        let is_digit = |b| is(b, DIGIT);
+        let mut is_integer = !leading_dot;
+
+        self.digits(is_digit);
+        if !leading_dot && self.source[self.start] == b'0' && self.current - self.start > 1 {
+            // Legacy octal literals like `0777` are integers: as in TypeScript,
+            // `07.5` is `07` followed by `.5`. Decimals like `0888.5` go on.
+            if self.source[self.start + 1..self.current]
+                .iter()
+                .all(|b| matches!(b, b'0'..=b'7'))
+            {
+                self.flags |= TokenFlags::OCTAL;
+                self.record("LegacyOctalIntegerLiteral", self.start, self.current);
+                return self.finish_number(TokenType::NumericLiteral);
+            }
+            self.flags |= TokenFlags::CONTAINS_LEADING_ZERO;
+        }
+        if !leading_dot && self.peek() == Some(b'.') {
+            self.current += 1;
+            is_integer = false;
+            self.digits(is_digit);
+        }
+
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let offset = if matches!(self.peek_at(1), Some(b'+' | b'-')) {
+                2
+            } else {
+                1
+            };
+            if self.peek_at(offset).is_some_and(is_digit) {
+                let from = self.current;
+                self.current += offset;
+                self.flags |= TokenFlags::SCIENTIFIC;
+                is_integer = false;
+                self.digits(is_digit);
+                self.record("ExponentPart", from, self.current);
+            }
+        }
 
-        match *next_c {
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' => {
-                _ = self.advance();
-
-                loop {
-                    let Some(next_next_c) = self.source.peek() else {
-                        return Ok(self.new_token(
-                            TokenType::NumericLiteral,
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                        ));
-                    };
+        let token_type = if is_integer && self.match_byte(b'n') {
+            self.record("DecimalBigIntegerLiteral", self.start, self.current);
+            TokenType::BigIntLiteral
+        } else {
+            self.record("DecimalLiteral", self.start, self.current);
+            TokenType::NumericLiteral
+        };
+        self.finish_number(token_type)
+    }
 
-                    match next_next_c {
-                        ' ' | '\n' | '\r' | '\t' | ';' => break,
-                        '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' => {
-                            _ = self.advance();
-                        }
-                        _ => {
-                            self.current += 1;
-                            return Err(LexerError::InvalidNumber {
-                                line: self.line,
-                                column: self.current,
-                            });
-                        }
-                    }
-                }
+    fn finish_number(&mut self, token_type: TokenType) -> Result<Token, LexerError> {
+        // An identifier or keyword cannot immediately follow a numeric literal.
+        let pos = self.current;
+        if self.peek().is_some_and(|b| is(b, DIGIT)) || self.identifier_start() {
+            self.identifier_rest();
+            return Err(LexerError::InvalidNumber { pos: pos as u32 });
+        }
 
-                Ok(self.new_token(
-                    TokenType::NumericLiteral,
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                ))
-            }
-            _ => Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            }),
+        self.record("NumericLiteral", self.start, self.current);
+        if token_type == TokenType::BigIntLiteral {
+            self.record("BigIntLiteral", self.start, self.current);
         }
+        Ok(self.new_token(token_type))
     }
-    fn decimal(&mut self) -> Result<Token, LexerError> {
-        _ = self.advance();
 
+    fn string(&mut self, quote: u8) -> Result<Token, LexerError> {
         loop {
-            let Some(next_next_c) = self.source.peek() else {
-                return Ok(self.new_token(
-                    TokenType::NumericLiteral,
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                ));
-            };
+            self.current +=
+                scan::find_string_special(&self.source[self.current..], quote, self.scan_mode);
 
-            match next_next_c {
-                ' ' | '\n' | '\r' | '\t' | ';' => break,
-                '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                    _ = self.advance();
-                }
-                'b' => {
-                    _ = self.advance();
+            // A line break ends an unterminated string, and is not part of it.
+            match self.peek() {
+                Some(b) if b == quote => {
+                    self.current += 1;
                     break;
                 }
+                Some(b'\\') => self.escape(),
                 _ => {
-                    self.current += 1;
-                    return Err(LexerError::InvalidNumber {
-                        line: self.line,
-                        column: self.current,
-                    });
+                    self.flags |= TokenFlags::UNTERMINATED;
+                    break;
                 }
             }
         }
 
-        Ok(self.new_token(
-            TokenType::NumericLiteral,
-            Some(StrSpan {
-                start: self.start,
-                end: self.current + 1,
-            }),
-            Some(StrSpan {
-                start: self.start,
-                end: self.current + 1,
-            }),
-        ))
+        self.record("StringLiteral", self.start, self.current);
+        Ok(self.new_token(TokenType::StringLiteral))
     }
-    fn hexadecimal(&mut self) -> Result<Token, LexerError> {
-        _ = self.advance();
 
-        let Some(next_c) = self.source.peek() else {
-            return Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            });
+    /// Skips a backslash and the escape sequence after it, flagging escapes
+    /// the way TypeScript does. A `\r\n` line continuation is skipped as a
+    /// whole.
+    fn escape(&mut self) {
+        let from = self.current;
+        self.current += 1;
+        let production = match self.peek() {
+            Some(b'\r') if self.peek_at(1) == Some(b'\n') => {
+                self.current += 2;
+                "LineContinuation"
+            }
+            Some(b'x') => {
+                self.current += 1;
+                if self.hex_digits(2) {
+                    self.flags |= TokenFlags::HEX_ESCAPE;
+                    "HexEscapeSequence"
+                } else {
+                    self.flags |= TokenFlags::CONTAINS_INVALID_ESCAPE;
+                    return;
+                }
+            }
+            Some(b'u') => {
+                self.current += 1;
+                if self.match_byte(b'{') {
+                    let from = self.current;
+                    self.current += count_while(&self.source[self.current..], HEX_DIGIT);
+                    let digits = &self.text[from..self.current];
+                    let valid = !digits.is_empty()
+                        && u32::from_str_radix(digits, 16).is_ok_and(|c| c <= 0x10ffff);
+                    if valid && self.match_byte(b'}') {
+                        self.flags |= TokenFlags::EXTENDED_UNICODE_ESCAPE;
+                    } else {
+                        self.flags |= TokenFlags::CONTAINS_INVALID_ESCAPE;
+                        return;
+                    }
+                } else if self.hex_digits(4) {
+                    self.flags |= TokenFlags::UNICODE_ESCAPE;
+                } else {
+                    self.flags |= TokenFlags::CONTAINS_INVALID_ESCAPE;
+                    return;
+                }
+                "UnicodeEscapeSequence"
+            }
+            // `\0` is a null character unless a digit follows; any other digit
+            // is a legacy octal escape, or `\8` or `\9`.
+            Some(b'0') if !self.peek_at(1).is_some_and(|b| is(b, DIGIT)) => {
+                self.current += 1;
+                "EscapeSequence"
+            }
+            Some(b'0'..=b'9') => {
+                self.current += 1;
+                self.flags |= TokenFlags::CONTAINS_INVALID_ESCAPE;
+                return;
+            }
+            Some(_) if self.at_line_terminator() => {
+                self.current += self.peek_char().len_utf8();
+                "LineContinuation"
+            }
+            Some(b) if is(b, NON_ASCII) => {
+                self.current += self.peek_char().len_utf8();
+                "CharacterEscapeSequence"
+            }
+            Some(_) => {
+                self.current += 1;
+                "CharacterEscapeSequence"
+            }
+            None => return,
         };
+        // A line continuation includes its backslash; an escape sequence
+        // is what follows it.
+        let start = if production == "LineContinuation" {
+            from
+        } else {
+            from + 1
+        };
+        self.record(production, start, self.current);
+    }
 
-        match *next_c {
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | 'a' | 'b' | 'c' | 'd'
-            | 'e' | 'f' | 'A' | 'B' | 'C' | 'D' | 'E' | 'F' => {
-                _ = self.advance();
-
-                loop {
-                    let Some(next_next_c) = self.source.peek() else {
-                        return Ok(self.new_token(
-                            TokenType::NumericLiteral,
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                            Some(StrSpan {
-                                start: self.start,
-                                end: self.current + 1,
-                            }),
-                        ));
+    /// Scans the rest of a template after its opening `` ` `` (`head`) or
+    /// after the `}` that closes a substitution.
+    fn template(&mut self, head: bool) -> Result<Token, LexerError> {
+        loop {
+            self.current +=
+                scan::find_template_special(&self.source[self.current..], self.scan_mode);
+
+            match self.peek() {
+                Some(b'$') if self.peek_at(1) == Some(b'{') => {
+                    self.current += 2;
+                    self.state.braces.push(true);
+                    let (token_type, production) = if head {
+                        (TokenType::TemplateHead, "TemplateHead")
+                    } else {
+                        (TokenType::TemplateMiddle, "TemplateMiddle")
+                    };
+                    self.record(production, self.start, self.current);
+                    return Ok(self.new_token(token_type));
+                }
+                Some(b'\\') => self.escape(),
+                Some(b'`') | None => {
+                    // Only the end of the file ends an unterminated template.
+                    if !self.match_byte(b'`') {
+                        self.flags |= TokenFlags::UNTERMINATED;
+                    }
+                    let (token_type, production) = if head {
+                        (
+                            TokenType::NoSubstitutionTemplateLiteral,
+                            "NoSubstitutionTemplate",
+                        )
+                    } else {
+                        (TokenType::TemplateTail, "TemplateTail")
                     };
+                    self.record(production, self.start, self.current);
+                    if head {
+                        self.record("Template", self.start, self.current);
+                    }
+                    return Ok(self.new_token(token_type));
+                }
+                Some(_) => self.current += 1,
+            }
+        }
+    }
 
-                    match next_next_c {
-                        ' ' | '\n' | '\r' | '\t' | ';' => break,
-                        '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | 'a' | 'b'
-                        | 'c' | 'd' | 'e' | 'f' | 'A' | 'B' | 'C' | 'D' | 'E' | 'F' => {
-                            _ = self.advance();
-                        }
-                        _ => {
-                            self.current += 1;
-                            return Err(LexerError::InvalidNumber {
-                                line: self.line,
-                                column: self.current,
-                            });
-                        }
+    /// Whether the cursor is at a line terminator, `\u{2028}` and `\u{2029}`
+    /// included.
+    fn at_line_terminator(&self) -> bool {
+        match self.peek() {
+            Some(b) if is(b, LINE_BREAK) => true,
+            Some(b) if is(b, NON_ASCII) => matches!(self.peek_char(), '\u{2028}' | '\u{2029}'),
+            _ => false,
+        }
+    }
+
+    /// Scans a regular expression after its opening `/`. A `/` whose regular
+    /// expression would run into the end of the line is taken to be a division.
+    fn regex(&mut self) -> Result<Token, LexerError> {
+        // The start of the class being scanned, if any.
+        let mut class = None;
+
+        loop {
+            match self.peek() {
+                Some(b'\\') => {
+                    let from = self.current;
+                    self.current += 1;
+                    if self.peek().is_some() && !self.at_line_terminator() {
+                        self.current += self.peek_char().len_utf8();
+                        self.record("RegularExpressionBackslashSequence", from, self.current);
+                    }
+                }
+                Some(b'[') => {
+                    class.get_or_insert(self.current);
+                    self.current += 1;
+                }
+                Some(b']') => {
+                    self.current += 1;
+                    if let Some(from) = class.take() {
+                        self.record("RegularExpressionClass", from, self.current);
                     }
                 }
+                Some(b'/') if class.is_none() => {
+                    self.record("RegularExpressionBody", self.start + 1, self.current);
+                    self.current += 1;
+                    break;
+                }
+                Some(b) if !is(b, NON_ASCII | LINE_BREAK) => self.current += 1,
+                Some(_) if !self.at_line_terminator() => {
+                    self.current += self.peek_char().len_utf8();
+                }
+                _ => {
+                    self.current = self.start + 1;
+                    self.matched.clear();
+                    return if self.match_byte(b'=') {
+                        self.punctuator(TokenType::SlashEqualsToken)
+                    } else {
+                        self.punctuator(TokenType::SlashToken)
+                    };
+                }
+            }
+        }
 
-                Ok(self.new_token(
-                    TokenType::NumericLiteral,
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                    Some(StrSpan {
-                        start: self.start,
-                        end: self.current + 1,
-                    }),
-                ))
-            }
-            _ => Err(LexerError::InvalidNumber {
-                line: self.line,
-                column: self.start,
-            }),
+        // Flags are identifier parts, but never escapes.
+        let flags = self.current;
+        loop {
+            self.current += count_while(&self.source[self.current..], ID_PART);
+            match self.peek() {
+                Some(b) if is(b, NON_ASCII) && is_unicode_id_part(self.peek_char()) => {
+                    self.current += self.peek_char().len_utf8();
+                }
+                _ => break,
+            }
         }
+
+        if self.current > flags {
+            self.record("RegularExpressionFlags", flags, self.current);
+        }
+        self.record("RegularExpressionLiteral", self.start, self.current);
+        Ok(self.new_token(TokenType::RegularExpressionLiteral))
     }
-    // fn exponent(&mut self) -> Result<Token, LexerError> {}
 }
 
 #[cfg(test)]
@@ -1247,15 +2077,25 @@ mod tests {
     fn test_lexer_token_less_slash() -> Result<(), String> {
         let test_string = "</";
         let mut lexer = Lexer::new(test_string);
+        lexer.set_language_variant(LanguageVariant::Jsx);
         let token = lexer.next_token();
 
         match token {
             Ok(t) => {
                 assert_eq!(t.token_type, TokenType::LessThanSlashToken);
-                Ok(())
             }
-            Err(e) => Err(e.to_string()),
+            Err(e) => return Err(e.to_string()),
         }
+
+        // As in TypeScript, `</` is only one token in JSX, and `</*` never is.
+        assert_eq!(token_types("a </re/.test(b)")?[1], TokenType::LessThanToken);
+        let mut lexer = Lexer::new("</**/");
+        lexer.set_language_variant(LanguageVariant::Jsx);
+        assert_eq!(
+            lexer.next_token().unwrap().token_type,
+            TokenType::LessThanToken
+        );
+        Ok(())
     }
     #[test]
     fn test_lexer_token_less_less() -> Result<(), String> {
@@ -1535,4 +2375,522 @@ mod tests {
             Err(e) => Err(e.to_string()),
         }
     }
+
+    // =============
+    // = Positions =
+    // =============
+    #[test]
+    fn test_lexer_positions_are_byte_offsets() -> Result<(), String> {
+        let test_string = "(\n  // comment\r\n/* a\n b */ ;";
+        let mut lexer = Lexer::new(test_string);
+        let mut spans = Vec::new();
+
+        for token in lexer.lex() {
+            let t = token.map_err(|e| e.to_string())?;
+            spans.push((t.token_type, t.start, t.end));
+        }
+
+        assert_eq!(
+            spans,
+            vec![
+                (TokenType::LeftParenToken, 0, 1),
+                (TokenType::SingleLineCommentTrivia, 4, 14),
+                (TokenType::MultiLineCommentTrivia, 16, 26),
+                (TokenType::SemicolonToken, 27, 28),
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_unterminated_comment_position() {
+        let test_string = "; /* a\n b";
+        let mut lexer = Lexer::new(test_string);
+        let errors: Vec<u32> = lexer
+            .lex()
+            .filter_map(|t| t.err())
+            .map(|e| e.pos())
+            .collect();
+
+        assert_eq!(errors, vec![2]);
+    }
+
+    // ===============
+    // = Byte Lexing =
+    // ===============
+    fn token_types(source: &str) -> Result<Vec<TokenType>, String> {
+        let mut lexer = Lexer::new(source);
+        let mut token_types = Vec::new();
+
+        for token in lexer.lex() {
+            token_types.push(token.map_err(|e| e.to_string())?.token_type);
+        }
+
+        Ok(token_types)
+    }
+    #[test]
+    fn test_lexer_token_flags() -> Result<(), String> {
+        let test_string = "a /* x\n */ b /** doc */ c\n0x1 1_0 1e3 017 \\u{61}";
+        let mut lexer = Lexer::new(test_string);
+        let mut flags = Vec::new();
+
+        for token in lexer.lex() {
+            let t = token.map_err(|e| e.to_string())?;
+            if !t.token_type.is_comment() {
+                flags.push(t.flags);
+            }
+        }
+
+        assert_eq!(
+            flags,
+            vec![
+                TokenFlags::NONE,
+                TokenFlags::PRECEDING_LINE_BREAK,
+                TokenFlags::PRECEDING_JSDOC_COMMENT,
+                TokenFlags::PRECEDING_LINE_BREAK | TokenFlags::HEX_SPECIFIER,
+                TokenFlags::CONTAINS_SEPARATOR,
+                TokenFlags::SCIENTIFIC,
+                TokenFlags::OCTAL,
+                TokenFlags::EXTENDED_UNICODE_ESCAPE,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_escape_flags() -> Result<(), String> {
+        let cases = [
+            ("'\\n\\0'", TokenFlags::NONE),
+            ("'\\x41'", TokenFlags::HEX_ESCAPE),
+            ("'\\u0041'", TokenFlags::UNICODE_ESCAPE),
+            ("`\\u{41}`", TokenFlags::EXTENDED_UNICODE_ESCAPE),
+            ("'\\u{110000}'", TokenFlags::CONTAINS_INVALID_ESCAPE),
+            ("`\\x4`", TokenFlags::CONTAINS_INVALID_ESCAPE),
+            ("'\\01'", TokenFlags::CONTAINS_INVALID_ESCAPE),
+            ("'\\8'", TokenFlags::CONTAINS_INVALID_ESCAPE),
+        ];
+
+        for (text, flags) in cases {
+            let token = Lexer::new(text)
+                .lex()
+                .next()
+                .unwrap()
+                .map_err(|e| e.to_string())?;
+            assert_eq!(token.flags, flags, "{}", text);
+            assert_eq!(token.end as usize, text.len(), "{}", text);
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_identifiers() -> Result<(), String> {
+        assert_eq!(
+            token_types("foo $bar _baz1 #priv 한글 \\u0061bc")?,
+            vec![
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::PrivateIdentifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_numeric_literals() -> Result<(), String> {
+        assert_eq!(
+            token_types("0 12 1.5 .5 1e10 2.5E-3 1_000 0xFF 0o17 0b1 10n 0x1fn")?,
+            vec![
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::NumericLiteral,
+                TokenType::BigIntLiteral,
+                TokenType::BigIntLiteral,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_legacy_octal_literals() -> Result<(), String> {
+        use crate::token_buffer::numeric_value;
+
+        let text = "07.5 08.5";
+        let tokens = Lexer::new(text)
+            .lex()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let spans: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, &text[t.start as usize..t.end as usize]))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (TokenType::NumericLiteral, "07"),
+                (TokenType::NumericLiteral, ".5"),
+                (TokenType::NumericLiteral, "08.5"),
+            ]
+        );
+        assert_eq!(tokens[0].flags, TokenFlags::OCTAL);
+        assert_eq!(numeric_value("07", tokens[0].flags), 7.0);
+        assert_eq!(numeric_value("08.5", tokens[2].flags), 8.5);
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_numeric_literal_followed_by_identifier() {
+        let mut lexer = Lexer::new("3in");
+        let token = lexer.next_token();
+
+        assert!(matches!(token, Err(LexerError::InvalidNumber { pos: 1 })));
+    }
+    #[test]
+    fn test_lexer_string_literals() -> Result<(), String> {
+        let test_string = r#"'a\'b' "c\"d""#;
+        let mut lexer = Lexer::new(test_string);
+        let mut literals = Vec::new();
+
+        for token in lexer.lex() {
+            let t = token.map_err(|e| e.to_string())?;
+            assert_eq!(t.token_type, TokenType::StringLiteral);
+            let span = t.value_span();
+            literals.push(&test_string[span.start as usize..span.end as usize]);
+        }
+
+        assert_eq!(literals, vec![r"a\'b", r#"c\"d"#]);
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_string_not_terminated() {
+        let mut lexer = Lexer::new("'abc\n'");
+        let token = lexer.next_token().unwrap();
+
+        assert_eq!(token.token_type, TokenType::StringLiteral);
+        assert_eq!(token.flags, TokenFlags::UNTERMINATED);
+        assert_eq!((token.start, token.end), (0, 4));
+        assert_eq!(
+            token.value_span(),
+            crate::token::StrSpan { start: 1, end: 4 }
+        );
+
+        let results: Vec<_> = Lexer::new("'abc\n'").lex().collect();
+        assert!(matches!(
+            results[..],
+            [
+                Err(LexerError::StringNotTerminated { pos: 0 }),
+                Ok(Token {
+                    start: 0,
+                    end: 4,
+                    ..
+                }),
+                Err(LexerError::StringNotTerminated { pos: 5 }),
+                Ok(Token {
+                    start: 5,
+                    end: 6,
+                    ..
+                }),
+            ]
+        ));
+    }
+    #[test]
+    fn test_lexer_unterminated_tokens() {
+        let cases = [
+            ("`a${b}c", TokenType::TemplateTail, "}c"),
+            ("`abc", TokenType::NoSubstitutionTemplateLiteral, "`abc"),
+            ("/* a\n b", TokenType::MultiLineCommentTrivia, "/* a\n b"),
+        ];
+
+        for (text, token_type, token_text) in cases {
+            let results: Vec<_> = Lexer::new(text).lex().collect();
+            let [.., Err(error), Ok(token)] = &results[..] else {
+                panic!("{:?}: {:?}", text, results);
+            };
+            assert_eq!(token.token_type, token_type, "{:?}", text);
+            assert!(token.flags.contains(TokenFlags::UNTERMINATED), "{:?}", text);
+            assert_eq!(&text[token.start as usize..], token_text);
+            assert_eq!(error.pos(), token.start, "{:?}", text);
+        }
+
+        // Attribute strings run to the end of the file.
+        let mut lexer = Lexer::new("<a b='c>");
+        lexer.set_language_variant(LanguageVariant::Jsx);
+        let tokens: Vec<_> = lexer.lex().filter_map(Result::ok).collect();
+        assert_eq!(tokens[4].token_type, TokenType::StringLiteral);
+        assert_eq!(tokens[4].flags, TokenFlags::UNTERMINATED);
+        assert_eq!(tokens[4].end, 8);
+    }
+    #[test]
+    fn test_lexer_templates() -> Result<(), String> {
+        assert_eq!(
+            token_types("`a${b}c${ {d} }e` `f`")?,
+            vec![
+                TokenType::TemplateHead,
+                TokenType::Identifier,
+                TokenType::TemplateMiddle,
+                TokenType::LeftBraceToken,
+                TokenType::Identifier,
+                TokenType::RightBraceToken,
+                TokenType::TemplateTail,
+                TokenType::NoSubstitutionTemplateLiteral,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_regex_or_division() -> Result<(), String> {
+        assert_eq!(
+            token_types("a / b / c")?,
+            vec![
+                TokenType::Identifier,
+                TokenType::SlashToken,
+                TokenType::Identifier,
+                TokenType::SlashToken,
+                TokenType::Identifier,
+            ]
+        );
+        assert_eq!(
+            token_types("x = /[/]+/g; return /a/")?,
+            vec![
+                TokenType::Identifier,
+                TokenType::EqualsToken,
+                TokenType::RegularExpressionLiteral,
+                TokenType::SemicolonToken,
+                TokenType::ReturnKeyword,
+                TokenType::RegularExpressionLiteral,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_regex_flags() -> Result<(), String> {
+        // Flags are any identifier parts, but never escapes.
+        assert_eq!(
+            token_types("/é[😀]/g\u{663}\u{200d}; /a/\\u0067")?,
+            vec![
+                TokenType::RegularExpressionLiteral,
+                TokenType::SemicolonToken,
+                TokenType::RegularExpressionLiteral,
+                TokenType::Identifier,
+            ]
+        );
+        let tokens = Lexer::new("/a/g\u{663}")
+            .lex()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        assert_eq!(tokens[0].end as usize, "/a/g\u{663}".len());
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_regex_line_terminators() -> Result<(), String> {
+        // A regular expression cannot span lines, whatever ends them.
+        assert_eq!(
+            token_types("a = b / c\u{2028}/ d; e = /[\u{2029}]/")?,
+            vec![
+                TokenType::Identifier,
+                TokenType::EqualsToken,
+                TokenType::Identifier,
+                TokenType::SlashToken,
+                TokenType::Identifier,
+                TokenType::SlashToken,
+                TokenType::Identifier,
+                TokenType::SemicolonToken,
+                TokenType::Identifier,
+                TokenType::EqualsToken,
+                TokenType::SlashToken,
+                TokenType::LeftBracketToken,
+                TokenType::RightBracketToken,
+                TokenType::SlashToken,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_keywords() -> Result<(), String> {
+        assert_eq!(
+            token_types("const of = this / 2; typeof /x/; \\u0063onst constant")?,
+            vec![
+                TokenType::ConstKeyword,
+                TokenType::OfKeyword,
+                TokenType::EqualsToken,
+                TokenType::ThisKeyword,
+                TokenType::SlashToken,
+                TokenType::NumericLiteral,
+                TokenType::SemicolonToken,
+                TokenType::TypeOfKeyword,
+                TokenType::RegularExpressionLiteral,
+                TokenType::SemicolonToken,
+                TokenType::Identifier,
+                TokenType::Identifier,
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_jsx() {
+        let text = "x = <a b-c=\"d\" {...e}>\n  f {g}<br/></a> / 2";
+        let mut lexer = Lexer::new(text);
+        lexer.set_language_variant(LanguageVariant::Jsx);
+        let tokens: Vec<_> = lexer
+            .lex()
+            .map(|t| {
+                let t = t.unwrap();
+                (t.token_type, &text[t.start as usize..t.end as usize])
+            })
+            .collect();
+
+        assert_eq!(
+            tokens,
+            [
+                (TokenType::Identifier, "x"),
+                (TokenType::EqualsToken, "="),
+                (TokenType::LessThanToken, "<"),
+                (TokenType::Identifier, "a"),
+                (TokenType::Identifier, "b-c"),
+                (TokenType::EqualsToken, "="),
+                (TokenType::StringLiteral, "\"d\""),
+                (TokenType::LeftBraceToken, "{"),
+                (TokenType::DotDotDotToken, "..."),
+                (TokenType::Identifier, "e"),
+                (TokenType::RightBraceToken, "}"),
+                (TokenType::GreaterThanToken, ">"),
+                (TokenType::JsxText, "\n  f "),
+                (TokenType::LeftBraceToken, "{"),
+                (TokenType::Identifier, "g"),
+                (TokenType::RightBraceToken, "}"),
+                (TokenType::LessThanToken, "<"),
+                (TokenType::Identifier, "br"),
+                (TokenType::SlashToken, "/"),
+                (TokenType::GreaterThanToken, ">"),
+                (TokenType::LessThanSlashToken, "</"),
+                (TokenType::Identifier, "a"),
+                (TokenType::GreaterThanToken, ">"),
+                (TokenType::SlashToken, "/"),
+                (TokenType::NumericLiteral, "2"),
+            ]
+        );
+    }
+    #[test]
+    fn test_lexer_jsx_nesting() {
+        let text = "f(<>{c ? <b>{`${d}`}</b> : null}\n</>, a < b)";
+        let kinds = |variant| {
+            let mut lexer = Lexer::new(text);
+            lexer.set_language_variant(variant);
+            lexer
+                .lex()
+                .map(|t| t.unwrap().token_type)
+                .collect::<Vec<_>>()
+        };
+
+        let jsx = kinds(LanguageVariant::Jsx);
+        assert!(jsx.contains(&TokenType::JsxTextAllWhiteSpaces));
+        assert_eq!(
+            jsx[jsx.len() - 5..],
+            [
+                TokenType::CommaToken,
+                TokenType::Identifier,
+                TokenType::LessThanToken,
+                TokenType::Identifier,
+                TokenType::RightParenToken,
+            ]
+        );
+        assert!(!kinds(LanguageVariant::Standard).contains(&TokenType::JsxText));
+    }
+    #[test]
+    fn test_lexer_corpus() -> Result<(), String> {
+        token_types(include_str!("../benches/corpus/emitter.js"))?;
+        token_types(include_str!("../benches/corpus/store.ts"))?;
+        Ok(())
+    }
+    #[test]
+    fn test_lexer_simd_matches_scalar() {
+        let sources = [
+            include_str!("../benches/corpus/emitter.js").to_string(),
+            include_str!("../benches/corpus/store.ts").to_string(),
+            format!(
+                "/* {} */ '{}' `{}${{x}}{}` // {}",
+                "*".repeat(70),
+                "\\'".repeat(40),
+                " ".repeat(64),
+                "$".repeat(33),
+                "/".repeat(50)
+            ),
+            format!(
+                "{}'unterminated{}\n`unterminated {}",
+                " \t\n".repeat(40),
+                "a".repeat(40),
+                "b".repeat(40)
+            ),
+        ];
+
+        for source in &sources {
+            let simd: Vec<String> = Lexer::with_scan_mode(source, ScanMode::Simd)
+                .lex()
+                .map(|t| format!("{:?}", t))
+                .collect();
+            let scalar: Vec<String> = Lexer::with_scan_mode(source, ScanMode::Scalar)
+                .lex()
+                .map(|t| format!("{:?}", t))
+                .collect();
+
+            assert_eq!(simd, scalar);
+        }
+    }
+
+    #[derive(Default)]
+    struct Matches(Vec<(&'static str, TokenType, String)>);
+
+    impl Recorder for Matches {
+        fn matched(&mut self, production: &'static str, token_type: TokenType, text: &str) {
+            self.0.push((production, token_type, text.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_lexer_recorder() {
+        let mut matches = Matches::default();
+        let mut lexer = Lexer::new("x\\u0061 = '\\x41' / /[/]/g;\r\n0x1fn 'open");
+        lexer.set_recorder(&mut matches);
+        for _ in lexer.lex() {}
+
+        let matches: Vec<(&str, TokenType, &str)> = matches
+            .0
+            .iter()
+            .map(|(production, token_type, text)| (*production, *token_type, text.as_str()))
+            .collect();
+        use TokenType::*;
+        assert_eq!(
+            matches,
+            [
+                ("UnicodeEscapeSequence", Identifier, "u0061"),
+                ("IdentifierName", Identifier, "x\\u0061"),
+                ("Identifier", Identifier, "x\\u0061"),
+                ("WhiteSpace", WhitespaceTrivia, " "),
+                ("Punctuator", EqualsToken, "="),
+                ("WhiteSpace", WhitespaceTrivia, " "),
+                ("HexEscapeSequence", StringLiteral, "x41"),
+                ("StringLiteral", StringLiteral, "'\\x41'"),
+                ("WhiteSpace", WhitespaceTrivia, " "),
+                ("DivPunctuator", SlashToken, "/"),
+                ("WhiteSpace", WhitespaceTrivia, " "),
+                ("RegularExpressionClass", RegularExpressionLiteral, "[/]"),
+                ("RegularExpressionBody", RegularExpressionLiteral, "[/]"),
+                ("RegularExpressionFlags", RegularExpressionLiteral, "g"),
+                (
+                    "RegularExpressionLiteral",
+                    RegularExpressionLiteral,
+                    "/[/]/g"
+                ),
+                ("Punctuator", SemicolonToken, ";"),
+                ("LineTerminatorSequence", NewLineTrivia, "\r\n"),
+                ("HexBigIntegerLiteral", BigIntLiteral, "0x1fn"),
+                ("NumericLiteral", BigIntLiteral, "0x1fn"),
+                ("BigIntLiteral", BigIntLiteral, "0x1fn"),
+                ("WhiteSpace", WhitespaceTrivia, " "),
+            ]
+        );
+    }
 }
@@ -0,0 +1,52 @@
+//! A JavaScript and TypeScript lexer.
+//!
+//! `Lexer` turns UTF-8 source text into `Token`s, which carry a `TokenType`,
+//! `TokenFlags` and a byte span. Errors are reported as `LexerError`s
+//! interleaved with the tokens, and can be rendered as `Diagnostic`s against a
+//! `SourceFile`.
+//!
+//! ```
+//! use rhi::{Lexer, TokenType};
+//!
+//! let kinds: Vec<_> = Lexer::new("let x = 1;")
+//!     .lex()
+//!     .map(|t| t.unwrap().token_type)
+//!     .collect();
+//! assert_eq!(kinds[0], TokenType::LetKeyword);
+//! ```
+
+#![cfg_attr(feature = "nightly", feature(portable_simd))]
+
+mod diagnostic;
+pub mod ebnf;
+mod error;
+mod highlight;
+mod incremental;
+mod interner;
+mod json;
+mod keyword;
+mod lexer;
+mod line_map;
+pub mod lsp;
+mod outline;
+mod scan;
+pub mod semantic_tokens;
+mod source;
+mod streaming;
+mod token;
+mod token_buffer;
+mod trivia;
+pub mod typescript;
+
+pub use diagnostic::{Diagnostic, Label, RenderMode, Renderer, Severity};
+pub use error::LexerError;
+pub use highlight::{HTML_CLASS_PREFIX, HighlightClass, HighlightSpan, Highlighter, Style, Theme};
+pub use incremental::{TextEdit, TokenDelta, relex};
+pub use interner::{Atom, Interner};
+pub use lexer::{LanguageVariant, Lexer, LexerState, Recorder, Tokens};
+pub use line_map::{ColumnEncoding, LineMap, Position};
+pub use scan::ScanMode;
+pub use source::SourceFile;
+pub use streaming::{OwnedToken, StreamError, StreamingLexer};
+pub use token::{StrSpan, Token, TokenFlags, TokenType};
+pub use token_buffer::{LiteralValue, TokenBuffer};
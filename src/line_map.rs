@@ -0,0 +1,166 @@
+use std::ops::Range;
+
+/// How columns are counted within a line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnEncoding {
+    /// UTF-8 bytes, i.e. plain byte offsets from the start of the line.
+    Utf8,
+    /// UTF-16 code units, as used by VS Code and the Language Server Protocol.
+    Utf16,
+    /// Unicode scalar values.
+    Char,
+}
+
+/// A 1-based line and column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Start offsets of every line in a file, computed once so that byte offsets
+/// can be turned into line/column positions with a binary search.
+///
+/// Lines are terminated by `\n`, `\r\n` or a lone `\r`, the same set VS Code
+/// uses.
+#[derive(Debug, Clone)]
+pub struct LineMap {
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineMap {
+    pub fn new(source: &str) -> Self {
+        let bytes = source.as_bytes();
+        let mut line_starts = vec![0];
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\n' => line_starts.push(i + 1),
+                b'\r' => {
+                    if bytes.get(i + 1) == Some(&b'\n') {
+                        i += 1;
+                    }
+                    line_starts.push(i + 1);
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        Self {
+            line_starts,
+            len: source.len(),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// 0-based index of the line containing `offset`.
+    pub fn line_index(&self, offset: usize) -> usize {
+        let offset = offset.min(self.len);
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// Byte offset at which the 1-based `line` starts.
+    pub fn line_start(&self, line: usize) -> usize {
+        let index = line.saturating_sub(1).min(self.line_starts.len() - 1);
+        self.line_starts[index]
+    }
+
+    /// Byte range of the 1-based `line`, excluding its line terminator.
+    pub fn line_range(&self, source: &str, line: usize) -> Range<usize> {
+        let start = self.line_start(line);
+        let next = self
+            .line_starts
+            .get(line.max(1))
+            .copied()
+            .unwrap_or(source.len());
+        let text = source[start..next].trim_end_matches(['\n', '\r']);
+        start..start + text.len()
+    }
+
+    pub fn position(&self, source: &str, offset: usize, encoding: ColumnEncoding) -> Position {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = self.line_index(offset);
+        let prefix = &source[self.line_starts[line]..offset];
+        let column = match encoding {
+            ColumnEncoding::Utf8 => prefix.len(),
+            ColumnEncoding::Utf16 => prefix.chars().map(char::len_utf16).sum(),
+            ColumnEncoding::Char => prefix.chars().count(),
+        };
+
+        Position {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_map_line_terminators() {
+        let source = "a\nb\r\nc\rd";
+        let line_map = LineMap::new(source);
+
+        assert_eq!(line_map.line_count(), 4);
+        assert_eq!(line_map.line_start(2), 2);
+        assert_eq!(line_map.line_start(3), 5);
+        assert_eq!(line_map.line_start(4), 7);
+        assert_eq!(line_map.line_range(source, 2), 2..3);
+        assert_eq!(line_map.line_range(source, 4), 7..8);
+    }
+
+    #[test]
+    fn test_line_map_position() {
+        let source = "let a;\nlet b = #;";
+        let line_map = LineMap::new(source);
+
+        assert_eq!(
+            line_map.position(source, 0, ColumnEncoding::Utf8),
+            Position { line: 1, column: 1 }
+        );
+        assert_eq!(
+            line_map.position(source, 6, ColumnEncoding::Utf8),
+            Position { line: 1, column: 7 }
+        );
+        assert_eq!(
+            line_map.position(source, 15, ColumnEncoding::Utf8),
+            Position { line: 2, column: 9 }
+        );
+    }
+
+    #[test]
+    fn test_line_map_column_encodings() {
+        // "한" is 3 UTF-8 bytes and 1 UTF-16 unit, "😀" is 4 bytes and 2 units.
+        let source = "x\n한😀#";
+        let line_map = LineMap::new(source);
+        let offset = source.find('#').unwrap();
+
+        assert_eq!(
+            line_map.position(source, offset, ColumnEncoding::Utf8),
+            Position { line: 2, column: 8 }
+        );
+        assert_eq!(
+            line_map.position(source, offset, ColumnEncoding::Utf16),
+            Position { line: 2, column: 4 }
+        );
+        assert_eq!(
+            line_map.position(source, offset, ColumnEncoding::Char),
+            Position { line: 2, column: 3 }
+        );
+    }
+}
@@ -0,0 +1,712 @@
+//! A language server, speaking the Language Server Protocol over a pair of
+//! byte streams such as stdin and stdout.
+//!
+//! The server publishes lexer diagnostics, and when the lexer found nothing
+//! wrong, unbalanced brackets. It answers requests for semantic tokens,
+//! including deltas against the last result, folding ranges and document
+//! symbols. Documents are synced incrementally, and each change to a
+//! TypeScript document only relexes the tokens it can affect. JavaScript
+//! documents may hold JSX, which `relex` cannot restart inside, so they are
+//! relexed whole.
+//!
+//! Positions are in UTF-16 code units, the protocol's default encoding.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::LexerError;
+use crate::incremental::{TextEdit, relex};
+pub use crate::json::Json;
+use crate::lexer::{LanguageVariant, Lexer};
+use crate::line_map::{ColumnEncoding, LineMap};
+use crate::outline::{Outline, Symbol};
+use crate::semantic_tokens;
+use crate::token::Token;
+
+// JSON-RPC and LSP error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// The longest message body read, well above any source file the lexer is
+/// meant for, so that a bad `Content-Length` cannot exhaust memory.
+const MAX_CONTENT_LENGTH: usize = 64 << 20;
+
+/// An error response's code and message.
+type Error = (i64, String);
+
+/// Serves requests from `input` until an `exit` notification or the end of
+/// the input, writing responses and notifications to `output`. Returns
+/// whether a `shutdown` request came first, as it should.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<bool> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        let messages = match Json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(message) => vec![error_response(Json::Null, (PARSE_ERROR, message))],
+        };
+        for message in messages {
+            write!(
+                output,
+                "Content-Length: {}\r\n\r\n{}",
+                message.to_string().len(),
+                message
+            )?;
+        }
+        output.flush()?;
+        if server.exited {
+            break;
+        }
+    }
+    Ok(server.shut_down)
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut length = None;
+    let mut headers = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            if headers {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            return Ok(None);
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            if headers {
+                break;
+            }
+            continue;
+        }
+        headers = true;
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| invalid("invalid Content-Length"))?,
+            );
+        }
+    }
+    let length = length.ok_or_else(|| invalid("missing Content-Length"))?;
+    if length > MAX_CONTENT_LENGTH {
+        return Err(invalid(&format!(
+            "Content-Length {} is over the limit of {}",
+            length, MAX_CONTENT_LENGTH
+        )));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// The state of a session: its open documents and where it is in the
+/// protocol's lifecycle.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    initialized: bool,
+    shut_down: bool,
+    exited: bool,
+    /// The id of the next semantic tokens result.
+    next_result_id: u64,
+}
+
+#[derive(Debug)]
+struct Document {
+    text: String,
+    version: i64,
+    variant: LanguageVariant,
+    /// The tokens of `text`, comments included, and the lexer's errors.
+    tokens: Vec<Token>,
+    errors: Vec<LexerError>,
+    /// The id and data of the last semantic tokens result, for deltas.
+    semantic_tokens: Option<(String, Vec<u32>)>,
+}
+
+impl Document {
+    fn new(text: String, version: i64, variant: LanguageVariant) -> Self {
+        let mut document = Document {
+            text,
+            version,
+            variant,
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            semantic_tokens: None,
+        };
+        document.lex();
+        document
+    }
+
+    /// Lexes the whole text.
+    fn lex(&mut self) {
+        let mut lexer = Lexer::new(&self.text);
+        lexer.set_language_variant(self.variant);
+        self.tokens.clear();
+        self.errors.clear();
+        for result in lexer.lex() {
+            match result {
+                Ok(token) => self.tokens.push(token),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    /// Applies `edit` to the text, relexing only the tokens it can affect.
+    fn edit(&mut self, edit: &TextEdit) {
+        if self.variant == LanguageVariant::Jsx {
+            self.text = edit.apply(&self.text);
+            self.lex();
+            return;
+        }
+        let delta = relex(&self.tokens, &self.text, edit);
+        // The errors in the old text that was relexed are replaced, and
+        // those after it move with the tokens.
+        let start = match delta.old_range.start.checked_sub(1) {
+            Some(i) => self.tokens[i].end,
+            None => 0,
+        };
+        let end = self
+            .tokens
+            .get(delta.old_range.end)
+            .map_or(self.text.len() as u32, |token| token.start);
+        let old_errors = std::mem::take(&mut self.errors);
+        self.errors
+            .extend(old_errors.iter().filter(|e| e.pos() < start).cloned());
+        self.errors.extend(delta.errors.iter().cloned());
+        // At `end` itself, only the first token kept can be unterminated;
+        // any other error there came from the text before it.
+        let after = |e: &LexerError| {
+            e.pos() > end
+                || e.pos() == end
+                    && matches!(
+                        e,
+                        LexerError::StringNotTerminated { .. }
+                            | LexerError::TemplateNotTerminated { .. }
+                            | LexerError::CommentNotTerminated { .. }
+                    )
+        };
+        self.errors
+            .extend(old_errors.into_iter().filter(after).map(|e| {
+                let pos = (e.pos() as i64 + delta.shift) as u32;
+                e.at(pos)
+            }));
+        delta.apply(&mut self.tokens);
+        self.text = edit.apply(&self.text);
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a request or notification, returning the messages to send
+    /// back: the response to a request, or any notifications.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let params = &message["params"];
+        let Some(method) = message["method"].as_str() else {
+            // Responses to requests the server never sent are ignored.
+            return match message.get("id") {
+                Some(id) if message.get("result").is_none() && message.get("error").is_none() => {
+                    let error = (INVALID_REQUEST, "missing method".to_string());
+                    vec![error_response(id.clone(), error)]
+                }
+                _ => Vec::new(),
+            };
+        };
+        match message.get("id") {
+            Some(id) => vec![match self.request(method, params) {
+                Ok(result) => Json::object([
+                    ("jsonrpc", "2.0".into()),
+                    ("id", id.clone()),
+                    ("result", result),
+                ]),
+                Err(error) => error_response(id.clone(), error),
+            }],
+            None => self.notification(method, params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, Error> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server is shutting down".to_string()));
+        }
+        if !self.initialized && method != "initialize" {
+            return Err((SERVER_NOT_INITIALIZED, "not initialized".to_string()));
+        }
+        match method {
+            "initialize" if self.initialized => {
+                Err((INVALID_REQUEST, "already initialized".to_string()))
+            }
+            "initialize" => {
+                self.initialized = true;
+                Ok(capabilities())
+            }
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/semanticTokens/full" => {
+                let id = self.next_result_id();
+                let document = self.document(params)?;
+                let data = semantic_tokens::encode(&document.text, document.variant);
+                document.semantic_tokens = Some((id.clone(), data.clone()));
+                Ok(Json::object([
+                    ("resultId", id.into()),
+                    ("data", data.into()),
+                ]))
+            }
+            "textDocument/semanticTokens/full/delta" => {
+                let id = self.next_result_id();
+                let document = self.document(params)?;
+                let data = semantic_tokens::encode(&document.text, document.variant);
+                let previous = document.semantic_tokens.replace((id.clone(), data.clone()));
+                let result = match previous {
+                    Some((previous_id, previous))
+                        if params["previousResultId"].as_str() == Some(&previous_id) =>
+                    {
+                        let edits = semantic_tokens::delta(&previous, &data)
+                            .into_iter()
+                            .map(|edit| {
+                                Json::object([
+                                    ("start", edit.start.into()),
+                                    ("deleteCount", edit.delete_count.into()),
+                                    ("data", edit.data.into()),
+                                ])
+                            })
+                            .collect();
+                        ("edits", Json::Array(edits))
+                    }
+                    _ => ("data", data.into()),
+                };
+                Ok(Json::object([("resultId", id.into()), result]))
+            }
+            "textDocument/foldingRange" => {
+                let document = self.document(params)?;
+                let line_map = LineMap::new(&document.text);
+                let ranges = Outline::new(&document.text, document.tokens.clone())
+                    .folding_ranges(&line_map)
+                    .into_iter()
+                    .map(|range| {
+                        let mut members = vec![
+                            ("startLine", range.start_line.into()),
+                            ("endLine", range.end_line.into()),
+                        ];
+                        if let Some(kind) = range.kind {
+                            members.push(("kind", kind.into()));
+                        }
+                        Json::object(members)
+                    })
+                    .collect();
+                Ok(Json::Array(ranges))
+            }
+            "textDocument/documentSymbol" => {
+                let document = self.document(params)?;
+                let line_map = LineMap::new(&document.text);
+                let symbols = Outline::new(&document.text, document.tokens.clone()).symbols();
+                Ok(symbols_json(&document.text, &line_map, &symbols))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unhandled method '{}'", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        if method == "exit" {
+            self.exited = true;
+            return Vec::new();
+        }
+        if !self.initialized {
+            return Vec::new();
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let (Some(text), Some(version)) =
+                    (document["text"].as_str(), version(&document["version"]))
+                else {
+                    return Vec::new();
+                };
+                let variant = match document["languageId"].as_str() {
+                    Some("typescript") => LanguageVariant::Standard,
+                    _ => LanguageVariant::Jsx,
+                };
+                self.documents.insert(
+                    uri.to_string(),
+                    Document::new(text.to_string(), version, variant),
+                );
+                vec![self.publish_diagnostics(uri)]
+            }
+            "textDocument/didChange" => {
+                let Some(document) = self.documents.get_mut(uri) else {
+                    return Vec::new();
+                };
+                for change in params["contentChanges"].as_array().unwrap_or_default() {
+                    let Some(text) = change["text"].as_str() else {
+                        continue;
+                    };
+                    if change["range"].is_null() {
+                        document.text = text.to_string();
+                        document.lex();
+                        continue;
+                    }
+                    let line_map = LineMap::new(&document.text);
+                    let start = offset(&document.text, &line_map, &change["range"]["start"]);
+                    let end = offset(&document.text, &line_map, &change["range"]["end"]);
+                    if let (Some(start), Some(end)) = (start, end)
+                        && start <= end
+                    {
+                        document.edit(&TextEdit::new(start..end, text));
+                    }
+                }
+                if let Some(version) = version(&params["textDocument"]["version"]) {
+                    document.version = version;
+                }
+                vec![self.publish_diagnostics(uri)]
+            }
+            "textDocument/didClose" => {
+                if self.documents.remove(uri).is_none() {
+                    return Vec::new();
+                }
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    Json::object([
+                        ("uri", uri.into()),
+                        ("diagnostics", Json::Array(Vec::new())),
+                    ]),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn next_result_id(&mut self) -> String {
+        self.next_result_id += 1;
+        self.next_result_id.to_string()
+    }
+
+    fn document(&mut self, params: &Json) -> Result<&mut Document, Error> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        self.documents
+            .get_mut(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document '{}'", uri)))
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Json {
+        let document = &self.documents[uri];
+        let text = &document.text;
+        let mut diagnostics: Vec<Diagnostic> = document
+            .errors
+            .iter()
+            .map(|e| e.to_diagnostic(text))
+            .collect();
+        // Brackets are only worth checking once the tokens are right.
+        if diagnostics.is_empty()
+            && let Some(diagnostic) = Outline::new(text, document.tokens.clone()).diagnostic()
+        {
+            diagnostics.push(diagnostic.clone());
+        }
+
+        let line_map = LineMap::new(text);
+        let diagnostics = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic_json(uri, text, &line_map, diagnostic))
+            .collect();
+        notification(
+            "textDocument/publishDiagnostics",
+            Json::object([
+                ("uri", uri.into()),
+                ("version", Json::Number(document.version as f64)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+}
+
+fn capabilities() -> Json {
+    let legend = Json::object([
+        ("tokenTypes", semantic_tokens::TOKEN_TYPES.to_vec().into()),
+        (
+            "tokenModifiers",
+            semantic_tokens::TOKEN_MODIFIERS.to_vec().into(),
+        ),
+    ]);
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("positionEncoding", "utf-16".into()),
+                (
+                    "textDocumentSync",
+                    Json::object([("openClose", true.into()), ("change", 2u32.into())]),
+                ),
+                (
+                    "semanticTokensProvider",
+                    Json::object([
+                        ("legend", legend),
+                        ("full", Json::object([("delta", true.into())])),
+                    ]),
+                ),
+                ("foldingRangeProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "rhi".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+/// A document version: a JSON integer.
+fn version(version: &Json) -> Option<i64> {
+    match *version {
+        Json::Number(n) if n.fract() == 0.0 => Some(n as i64),
+        _ => None,
+    }
+}
+
+/// The byte offset of an LSP position in `text`. Positions past the end of
+/// a line or of the text are taken to mean its end.
+fn offset(text: &str, line_map: &LineMap, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    if line >= line_map.line_count() {
+        return Some(text.len());
+    }
+    let range = line_map.line_range(text, line + 1);
+    let mut units = 0;
+    for (i, c) in text[range.clone()].char_indices() {
+        if units >= character {
+            return Some(range.start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(range.end)
+}
+
+fn position_json(text: &str, line_map: &LineMap, offset: usize) -> Json {
+    let position = line_map.position(text, offset, ColumnEncoding::Utf16);
+    Json::object([
+        ("line", (position.line - 1).into()),
+        ("character", (position.column - 1).into()),
+    ])
+}
+
+fn range_json(text: &str, line_map: &LineMap, range: &Range<usize>) -> Json {
+    Json::object([
+        ("start", position_json(text, line_map, range.start)),
+        ("end", position_json(text, line_map, range.end)),
+    ])
+}
+
+/// A diagnostic at its first label, with any others as related
+/// information.
+fn diagnostic_json(uri: &str, text: &str, line_map: &LineMap, diagnostic: &Diagnostic) -> Json {
+    let span = diagnostic
+        .labels
+        .first()
+        .map_or(0..0, |label| label.span.clone());
+    let severity: u32 = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    let mut members = vec![
+        ("range", range_json(text, line_map, &span)),
+        ("severity", severity.into()),
+        ("code", diagnostic.code.into()),
+        ("source", "rhi".into()),
+        ("message", diagnostic.message.as_str().into()),
+    ];
+    if diagnostic.labels.len() > 1 {
+        let related = diagnostic.labels[1..]
+            .iter()
+            .map(|label| {
+                Json::object([
+                    (
+                        "location",
+                        Json::object([
+                            ("uri", uri.into()),
+                            ("range", range_json(text, line_map, &label.span)),
+                        ]),
+                    ),
+                    ("message", label.message.as_str().into()),
+                ])
+            })
+            .collect();
+        members.push(("relatedInformation", Json::Array(related)));
+    }
+    Json::object(members)
+}
+
+fn symbols_json(text: &str, line_map: &LineMap, symbols: &[Symbol]) -> Json {
+    Json::Array(
+        symbols
+            .iter()
+            .map(|symbol| {
+                Json::object([
+                    ("name", symbol.name.as_str().into()),
+                    ("kind", symbol.kind.into()),
+                    ("range", range_json(text, line_map, &symbol.range)),
+                    (
+                        "selectionRange",
+                        range_json(text, line_map, &symbol.selection_range),
+                    ),
+                    ("children", symbols_json(text, line_map, &symbol.children)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn error_response(id: Json, (code, message): Error) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([("code", code.into()), ("message", message.into())]),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset() {
+        let text = "a😀b\r\nc";
+        let line_map = LineMap::new(text);
+        let at = |line: u32, character: u32| {
+            let position = Json::object([("line", line.into()), ("character", character.into())]);
+            offset(text, &line_map, &position)
+        };
+        assert_eq!(at(0, 1), Some(1));
+        assert_eq!(at(0, 3), Some(5));
+        assert_eq!(at(0, 9), Some(6));
+        assert_eq!(at(1, 0), Some(8));
+        assert_eq!(at(5, 0), Some(text.len()));
+        assert_eq!(offset(text, &line_map, &Json::Null), None);
+        assert_eq!(
+            position_json(text, &line_map, 5).to_string(),
+            r#"{"line":0,"character":3}"#
+        );
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut input =
+            "content-length: 2\r\nContent-Type: x\r\n\r\n{}\r\nContent-Length: 2\r\n\r\n[]"
+                .as_bytes();
+        assert_eq!(read_message(&mut input).unwrap().as_deref(), Some("{}"));
+        assert_eq!(read_message(&mut input).unwrap().as_deref(), Some("[]"));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut input = "Content-Type: x\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+        let mut input = "Content-Length: 9\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+        let input = format!("Content-Length: {}\r\n\r\n{{}}", MAX_CONTENT_LENGTH + 1);
+        let error = read_message(&mut input.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_document_edit() {
+        let lexed = |document: &Document| {
+            let errors: Vec<String> = document.errors.iter().map(|e| e.to_string()).collect();
+            (document.tokens.clone(), errors)
+        };
+        let edits = [
+            TextEdit::insert(8, "'"),
+            TextEdit::insert(0, "1x "),
+            TextEdit::delete(8..9),
+            TextEdit::insert(20, "/*"),
+            TextEdit::delete(20..22),
+            TextEdit::new(0..3, "#"),
+            TextEdit::insert(4, "\n"),
+        ];
+        for variant in [LanguageVariant::Standard, LanguageVariant::Jsx] {
+            let mut document = Document::new("let a = 1;\nb = `c${d}`;\n".to_string(), 1, variant);
+            for edit in &edits {
+                document.edit(edit);
+                let lexed_whole = Document::new(document.text.clone(), 1, variant);
+                assert_eq!(
+                    lexed(&document),
+                    lexed(&lexed_whole),
+                    "{:?} in {:?}",
+                    edit,
+                    document.text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut server = Server::new();
+        let request = |method: &str| {
+            Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", 1u32.into()),
+                ("method", method.into()),
+            ])
+        };
+        let error_code = |responses: Vec<Json>| responses[0]["error"]["code"].clone();
+
+        assert_eq!(
+            error_code(server.handle(&request("shutdown"))),
+            Json::from(SERVER_NOT_INITIALIZED)
+        );
+        let responses = server.handle(&request("initialize"));
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["textDocumentSync"]["change"],
+            Json::from(2u32)
+        );
+        assert_eq!(
+            error_code(server.handle(&request("initialize"))),
+            Json::from(INVALID_REQUEST)
+        );
+        assert_eq!(
+            error_code(server.handle(&request("textDocument/hover"))),
+            Json::from(METHOD_NOT_FOUND)
+        );
+        assert!(server.handle(&request("shutdown"))[0]["result"].is_null());
+        assert_eq!(
+            error_code(server.handle(&request("shutdown"))),
+            Json::from(INVALID_REQUEST)
+        );
+        assert!(!server.exited);
+        assert_eq!(
+            server.handle(&Json::parse(r#"{"method":"exit"}"#).unwrap()),
+            []
+        );
+        assert!(server.exited);
+    }
+}
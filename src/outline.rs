@@ -0,0 +1,909 @@
+//! What the language server can tell about a file's structure from its
+//! tokens alone: bracket pairs and the diagnostics for unbalanced ones,
+//! folding ranges, and an outline of its declarations.
+//!
+//! There is no parser, so declarations are found by their leading keywords
+//! at the start of a statement, and statements end at a `;`, a closing
+//! bracket, or a line break that cannot continue them.
+
+use std::ops::Range;
+
+use crate::diagnostic::Diagnostic;
+use crate::line_map::LineMap;
+use crate::token::{Token, TokenType};
+
+/// A foldable range of 0-based lines.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FoldingRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    /// `comment`, `region`, or `None` for code.
+    pub kind: Option<&'static str>,
+}
+
+/// A declaration, as a node of the outline.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Symbol {
+    pub name: String,
+    /// An LSP `SymbolKind`.
+    pub kind: u32,
+    /// The whole declaration, including modifiers and any body.
+    pub range: Range<usize>,
+    /// The declaration's name.
+    pub selection_range: Range<usize>,
+    pub children: Vec<Symbol>,
+}
+
+// LSP `SymbolKind`s.
+const MODULE: u32 = 2;
+const NAMESPACE: u32 = 3;
+const CLASS: u32 = 5;
+const METHOD: u32 = 6;
+const PROPERTY: u32 = 7;
+const CONSTRUCTOR: u32 = 9;
+const ENUM: u32 = 10;
+const INTERFACE: u32 = 11;
+const FUNCTION: u32 = 12;
+const VARIABLE: u32 = 13;
+const CONSTANT: u32 = 14;
+const ENUM_MEMBER: u32 = 22;
+const TYPE_PARAMETER: u32 = 26;
+
+/// The tokens of a file with their brackets matched.
+pub(crate) struct Outline<'a> {
+    text: &'a str,
+    /// Every token, comments included.
+    tokens: Vec<Token>,
+    /// The tokens other than comments.
+    code: Vec<Token>,
+    /// For each token of `code` that opens a bracket or a template, the
+    /// index of the token that closes it, if any.
+    closers: Vec<Option<usize>>,
+    /// The first unbalanced bracket, if any. Later ones are usually
+    /// consequences of the first, so they are not reported.
+    diagnostic: Option<Diagnostic>,
+}
+
+impl<'a> Outline<'a> {
+    /// `tokens` are the tokens of `text`, comments included.
+    pub fn new(text: &'a str, tokens: Vec<Token>) -> Self {
+        let code: Vec<Token> = tokens
+            .iter()
+            .copied()
+            .filter(|t| !t.token_type.is_comment())
+            .collect();
+        let mut closers = vec![None; code.len()];
+        let mut diagnostic = None;
+        let mut open: Vec<usize> = Vec::new();
+
+        for (i, token) in code.iter().enumerate() {
+            let kind = token.token_type;
+            if closing(kind).is_some() {
+                open.push(i);
+                continue;
+            }
+            let expected = match kind {
+                TokenType::RightBraceToken
+                | TokenType::RightParenToken
+                | TokenType::RightBracketToken
+                | TokenType::TemplateTail => kind,
+                TokenType::TemplateMiddle => TokenType::TemplateTail,
+                _ => continue,
+            };
+            let span = token.start as usize..token.end as usize;
+            let Some(matched) = open
+                .iter()
+                .rposition(|&o| closing(code[o].token_type) == Some(expected))
+            else {
+                diagnostic.get_or_insert_with(|| {
+                    Diagnostic::error(1128, "Declaration or statement expected.")
+                        .with_label(span, "nothing to close here")
+                });
+                continue;
+            };
+            if matched + 1 < open.len() {
+                let unclosed = code[*open.last().unwrap()];
+                diagnostic.get_or_insert_with(|| expected_closer(unclosed, span));
+            }
+            open.truncate(matched + 1);
+            if kind != TokenType::TemplateMiddle {
+                closers[open.pop().unwrap()] = Some(i);
+            }
+        }
+        if let Some(&last) = open.last() {
+            let end = text.len();
+            diagnostic.get_or_insert_with(|| expected_closer(code[last], end..end));
+        }
+
+        Self {
+            text,
+            tokens,
+            code,
+            closers,
+            diagnostic,
+        }
+    }
+
+    /// The diagnostic for the first unbalanced bracket, if any.
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        self.diagnostic.as_ref()
+    }
+
+    /// Multi-line bracket pairs and templates, block comments, runs of line
+    /// comments, and `// #region` ... `// #endregion` regions, in order.
+    pub fn folding_ranges(&self, line_map: &LineMap) -> Vec<FoldingRange> {
+        let line = |offset: u32| line_map.line_index(offset as usize);
+        let mut ranges = Vec::new();
+
+        for (i, closer) in self.closers.iter().enumerate() {
+            let Some(c) = *closer else {
+                continue;
+            };
+            let start_line = line(self.code[i].start);
+            let mut end_line = line(self.code[c].start);
+            // Keep the closing bracket visible when it starts its line.
+            if self.starts_line(line_map, self.code[c].start as usize) {
+                end_line = end_line.saturating_sub(1);
+            }
+            if end_line > start_line {
+                ranges.push(FoldingRange {
+                    start_line,
+                    end_line,
+                    kind: None,
+                });
+            }
+        }
+
+        let mut regions = Vec::new();
+        // The first and last lines of the current run of line comments.
+        let mut run: Option<(usize, usize)> = None;
+        let end_run = |run: &mut Option<(usize, usize)>, ranges: &mut Vec<FoldingRange>| {
+            if let Some((start_line, end_line)) = run.take()
+                && end_line > start_line
+            {
+                ranges.push(FoldingRange {
+                    start_line,
+                    end_line,
+                    kind: Some("comment"),
+                });
+            }
+        };
+        for token in &self.tokens {
+            let start_line = line(token.start);
+            match token.token_type {
+                TokenType::SingleLineCommentTrivia => {
+                    let comment = self.text[token.start as usize + 2..token.end as usize].trim();
+                    if comment.starts_with("#region") {
+                        end_run(&mut run, &mut ranges);
+                        regions.push(start_line);
+                    } else if comment.starts_with("#endregion") {
+                        end_run(&mut run, &mut ranges);
+                        if let Some(region) = regions.pop() {
+                            ranges.push(FoldingRange {
+                                start_line: region,
+                                end_line: start_line,
+                                kind: Some("region"),
+                            });
+                        }
+                    } else if !self.starts_line(line_map, token.start as usize) {
+                        end_run(&mut run, &mut ranges);
+                    } else {
+                        match &mut run {
+                            Some((_, end_line)) if *end_line + 1 == start_line => {
+                                *end_line = start_line;
+                            }
+                            _ => {
+                                end_run(&mut run, &mut ranges);
+                                run = Some((start_line, start_line));
+                            }
+                        }
+                    }
+                }
+                TokenType::MultiLineCommentTrivia => {
+                    end_run(&mut run, &mut ranges);
+                    let end_line = line(token.end - 1);
+                    if end_line > start_line {
+                        ranges.push(FoldingRange {
+                            start_line,
+                            end_line,
+                            kind: Some("comment"),
+                        });
+                    }
+                }
+                _ => end_run(&mut run, &mut ranges),
+            }
+        }
+        end_run(&mut run, &mut ranges);
+
+        ranges.sort_by_key(|r| (r.start_line, r.end_line));
+        ranges
+    }
+
+    /// The declarations at the top level and in namespaces, with the members
+    /// of classes and enums, and the functions and classes declared inside
+    /// functions.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.statements(0..self.code.len(), true)
+    }
+
+    fn starts_line(&self, line_map: &LineMap, offset: usize) -> bool {
+        let line_start = line_map.line_start(line_map.line_index(offset) + 1);
+        self.text[line_start..offset].trim().is_empty()
+    }
+
+    fn kind(&self, i: usize, end: usize) -> Option<TokenType> {
+        (i < end).then(|| self.code[i].token_type)
+    }
+
+    fn span(&self, tokens: Range<usize>) -> Range<usize> {
+        self.code[tokens.start].start as usize..self.code[tokens.end - 1].end as usize
+    }
+
+    fn name(&self, i: usize) -> String {
+        let token = &self.code[i];
+        let span = if token.token_type == TokenType::StringLiteral {
+            token.value_span()
+        } else {
+            token.span()
+        };
+        self.text[span.start as usize..span.end as usize].to_string()
+    }
+
+    /// Whether a declaration could start at token `i`.
+    fn at_statement_start(&self, i: usize, start: usize) -> bool {
+        if i == start {
+            return true;
+        }
+        let previous = self.code[i - 1].token_type;
+        matches!(
+            previous,
+            TokenType::SemicolonToken | TokenType::LeftBraceToken | TokenType::RightBraceToken
+        ) || (self.code[i].has_preceding_line_break() && !continues_after(previous))
+    }
+
+    /// The declarations in `tokens`, or with `all` false, only the functions
+    /// and classes, looking into nested blocks for them.
+    fn statements(&self, tokens: Range<usize>, all: bool) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        let mut i = tokens.start;
+        while i < tokens.end {
+            if self.at_statement_start(i, tokens.start)
+                && let Some(end) = self.declaration(i, tokens.end, all, &mut symbols)
+            {
+                i = end + 1;
+                continue;
+            }
+            match self.closers[i] {
+                Some(closer) if all => i = closer + 1,
+                _ => i += 1,
+            }
+        }
+        symbols
+    }
+
+    /// Pushes the symbols of a declaration starting at token `start`, if
+    /// there is one, returning the index of its last token.
+    fn declaration(
+        &self,
+        start: usize,
+        end: usize,
+        all: bool,
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<usize> {
+        let mut i = start;
+        let mut default = false;
+        while let Some(
+            kind @ (TokenType::ExportKeyword
+            | TokenType::DefaultKeyword
+            | TokenType::DeclareKeyword
+            | TokenType::AsyncKeyword
+            | TokenType::AbstractKeyword),
+        ) = self.kind(i, end)
+        {
+            default |= kind == TokenType::DefaultKeyword;
+            i += 1;
+        }
+        let keyword = self.kind(i, end)?;
+        let next = self.kind(i + 1, end);
+
+        let (kind, name) = match keyword {
+            TokenType::FunctionKeyword | TokenType::ClassKeyword => {
+                let kind = if keyword == TokenType::FunctionKeyword {
+                    FUNCTION
+                } else {
+                    CLASS
+                };
+                let mut n = i + 1;
+                if self.kind(n, end) == Some(TokenType::AsteriskToken) {
+                    n += 1;
+                }
+                match self.kind(n, end) {
+                    Some(name) if is_binding_name(name) => (kind, n),
+                    // `export default function () {}` is named `default`.
+                    _ if default => (kind, i),
+                    _ => return None,
+                }
+            }
+            TokenType::ConstKeyword if next == Some(TokenType::EnumKeyword) => (ENUM, i + 2),
+            TokenType::EnumKeyword => (ENUM, i + 1),
+            TokenType::InterfaceKeyword if all => (INTERFACE, i + 1),
+            TokenType::TypeKeyword
+                if all
+                    && matches!(
+                        self.kind(i + 2, end),
+                        Some(TokenType::EqualsToken | TokenType::LessThanToken)
+                    ) =>
+            {
+                (TYPE_PARAMETER, i + 1)
+            }
+            TokenType::NamespaceKeyword | TokenType::ModuleKeyword | TokenType::GlobalKeyword
+                if all =>
+            {
+                return self.namespace(start, i, end, symbols);
+            }
+            TokenType::ConstKeyword | TokenType::LetKeyword | TokenType::VarKeyword if all => {
+                return self.variables(start, i, end, symbols);
+            }
+            _ => return None,
+        };
+        if !self.kind(name, end).is_some_and(is_binding_name) && name != i {
+            return None;
+        }
+
+        let (last, body) = self.body(name + 1, end);
+        let children = match (kind, body) {
+            (FUNCTION, Some(body)) => self.statements(body, false),
+            (CLASS, Some(body)) => self.members(body),
+            (ENUM, Some(body)) => self.enum_members(body),
+            _ => Vec::new(),
+        };
+        // A type alias ends with its type, not at a `{` in it.
+        let last = if kind == TYPE_PARAMETER {
+            self.statement_end(name, end)
+        } else {
+            last
+        };
+        symbols.push(Symbol {
+            name: if name == i {
+                "default".to_string()
+            } else {
+                self.name(name)
+            },
+            kind,
+            range: self.span(start..last + 1),
+            selection_range: self.span(name..name + 1),
+            children,
+        });
+        Some(last)
+    }
+
+    /// `namespace a.b {}`, `module "m" {}` or `declare global {}`.
+    fn namespace(
+        &self,
+        start: usize,
+        keyword: usize,
+        end: usize,
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<usize> {
+        let kind = self.code[keyword].token_type;
+        let mut i = keyword + 1;
+        if kind != TokenType::GlobalKeyword {
+            let name = self.kind(i, end)?;
+            if !(is_binding_name(name)
+                || kind == TokenType::ModuleKeyword && name == TokenType::StringLiteral)
+            {
+                return None;
+            }
+            while self.kind(i + 1, end) == Some(TokenType::DotToken)
+                && self.kind(i + 2, end).is_some_and(is_binding_name)
+            {
+                i += 2;
+            }
+            i += 1;
+        }
+        if self.kind(i, end) != Some(TokenType::LeftBraceToken) {
+            return None;
+        }
+        let name_tokens = if kind == TokenType::GlobalKeyword {
+            keyword..keyword + 1
+        } else {
+            keyword + 1..i
+        };
+        let name = if self.code[name_tokens.start].token_type == TokenType::StringLiteral {
+            self.name(name_tokens.start)
+        } else {
+            self.text[self.span(name_tokens.clone())].to_string()
+        };
+        let (last, body) = self.body(i, end);
+        symbols.push(Symbol {
+            name,
+            kind: if kind == TokenType::NamespaceKeyword {
+                NAMESPACE
+            } else {
+                MODULE
+            },
+            range: self.span(start..last + 1),
+            selection_range: self.span(name_tokens),
+            children: body.map_or_else(Vec::new, |body| self.statements(body, true)),
+        });
+        Some(last)
+    }
+
+    /// `const`, `let` and `var`, with a symbol per declared name. Names
+    /// in destructuring patterns are left out.
+    fn variables(
+        &self,
+        start: usize,
+        keyword: usize,
+        end: usize,
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<usize> {
+        let kind = if self.code[keyword].token_type == TokenType::ConstKeyword {
+            CONSTANT
+        } else {
+            VARIABLE
+        };
+        let last = self.statement_end(keyword, end);
+        let range = self.span(start..last + 1);
+        let mut i = keyword + 1;
+        let mut declarator_start = true;
+        while i <= last {
+            let token_type = self.code[i].token_type;
+            if declarator_start && is_binding_name(token_type) {
+                symbols.push(Symbol {
+                    name: self.name(i),
+                    kind,
+                    range: range.clone(),
+                    selection_range: self.span(i..i + 1),
+                    children: Vec::new(),
+                });
+            }
+            declarator_start = token_type == TokenType::CommaToken;
+            i = self.closers[i].map_or(i, |closer| closer.min(last)) + 1;
+        }
+        Some(last)
+    }
+
+    /// The methods, constructors and properties in a class body.
+    fn members(&self, tokens: Range<usize>) -> Vec<Symbol> {
+        let end = tokens.end;
+        let mut symbols = Vec::new();
+        let mut i = tokens.start;
+        while i < end {
+            let start = i;
+            match self.code[i].token_type {
+                TokenType::SemicolonToken => {
+                    i += 1;
+                    continue;
+                }
+                // Decorators: `@a.b(c)`.
+                TokenType::AtToken => {
+                    i += 1;
+                    while matches!(
+                        self.kind(i, end),
+                        Some(kind) if is_binding_name(kind) || kind == TokenType::DotToken
+                    ) {
+                        i += 1;
+                    }
+                    if let Some(closer) = self.closers.get(i).copied().flatten()
+                        && self.code[i].token_type == TokenType::LeftParenToken
+                    {
+                        i = closer + 1;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            // `static {}` blocks have no name.
+            if self.code[i].token_type == TokenType::StaticKeyword
+                && self.kind(i + 1, end) == Some(TokenType::LeftBraceToken)
+            {
+                i = self.closers[i + 1].map_or(end, |closer| closer + 1);
+                continue;
+            }
+
+            let mut accessor = false;
+            while self.kind(i, end).is_some_and(is_member_modifier)
+                && self
+                    .kind(i + 1, end)
+                    .is_some_and(|next| is_member_name(next) || next == TokenType::AsteriskToken)
+                && !self.code[i + 1].has_preceding_line_break()
+            {
+                accessor |= matches!(
+                    self.code[i].token_type,
+                    TokenType::GetKeyword | TokenType::SetKeyword
+                );
+                i += 1;
+            }
+            if self.kind(i, end) == Some(TokenType::AsteriskToken) {
+                i += 1;
+            }
+            let Some(kind) = self.kind(i, end) else {
+                break;
+            };
+            let name = i;
+            let name_end = match kind {
+                TokenType::LeftBracketToken => match self.closers[i] {
+                    Some(closer) if closer < end => closer,
+                    _ => break,
+                },
+                kind if is_member_name(kind) => i,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let mut after = name_end + 1;
+            if matches!(
+                self.kind(after, end),
+                Some(TokenType::QuestionToken | TokenType::ExclamationToken)
+            ) {
+                after += 1;
+            }
+            let method = matches!(
+                self.kind(after, end),
+                Some(TokenType::LeftParenToken | TokenType::LessThanToken)
+            );
+
+            let (last, children, kind) = if method {
+                let (last, body) = self.body(after, end);
+                let kind = if accessor {
+                    PROPERTY
+                } else if &self.text[self.span(name..name + 1)] == "constructor" {
+                    CONSTRUCTOR
+                } else {
+                    METHOD
+                };
+                let children = body.map_or_else(Vec::new, |body| self.statements(body, false));
+                (last, children, kind)
+            } else {
+                (self.statement_end(name_end, end), Vec::new(), PROPERTY)
+            };
+            symbols.push(Symbol {
+                name: if name == name_end {
+                    self.name(name)
+                } else {
+                    self.text[self.span(name..name_end + 1)].to_string()
+                },
+                kind,
+                range: self.span(start..last + 1),
+                selection_range: self.span(name..name_end + 1),
+                children,
+            });
+            i = last + 1;
+        }
+        symbols
+    }
+
+    fn enum_members(&self, tokens: Range<usize>) -> Vec<Symbol> {
+        let end = tokens.end;
+        let mut symbols = Vec::new();
+        let mut i = tokens.start;
+        while i < end {
+            let name = i;
+            while i < end && self.code[i].token_type != TokenType::CommaToken {
+                i = self.closers[i].map_or(i, |closer| closer.min(end - 1)) + 1;
+            }
+            if is_member_name(self.code[name].token_type) {
+                symbols.push(Symbol {
+                    name: self.name(name),
+                    kind: ENUM_MEMBER,
+                    range: self.span(name..i),
+                    selection_range: self.span(name..name + 1),
+                    children: Vec::new(),
+                });
+            }
+            i += 1;
+        }
+        symbols
+    }
+
+    /// Finds the body of a function, class or similar declaration from token
+    /// `i` on: the first `{` outside brackets, unless a `;` or another
+    /// declaration comes first. Returns the index of the declaration's last
+    /// token, and the tokens inside the body.
+    fn body(&self, mut i: usize, end: usize) -> (usize, Option<Range<usize>>) {
+        let from = i;
+        while i < end {
+            let token = self.code[i];
+            match token.token_type {
+                TokenType::LeftBraceToken => {
+                    return match self.closers[i] {
+                        Some(closer) if closer < end => (closer, Some(i + 1..closer)),
+                        _ => (end - 1, Some(i + 1..end)),
+                    };
+                }
+                TokenType::SemicolonToken => return (i, None),
+                kind if i > from
+                    && token.has_preceding_line_break()
+                    && starts_declaration(kind) =>
+                {
+                    return (i - 1, None);
+                }
+                _ => {}
+            }
+            match self.closers[i] {
+                Some(closer) if closer < end => i = closer + 1,
+                Some(_) => return (end - 1, None),
+                None => i += 1,
+            }
+        }
+        (end.max(from) - 1, None)
+    }
+
+    /// The index of the last token of a statement whose first tokens end at
+    /// token `i`.
+    fn statement_end(&self, mut i: usize, end: usize) -> usize {
+        loop {
+            i = match self.closers[i] {
+                Some(closer) if closer < end => closer,
+                Some(_) => return end - 1,
+                None => i,
+            };
+            let Some(next) = self.kind(i + 1, end) else {
+                return i;
+            };
+            match next {
+                TokenType::SemicolonToken => return i + 1,
+                TokenType::RightBraceToken
+                | TokenType::RightParenToken
+                | TokenType::RightBracketToken => return i,
+                _ if self.code[i + 1].has_preceding_line_break()
+                    && !continues_after(self.code[i].token_type)
+                    && !continues_before(next) =>
+                {
+                    return i;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+/// The token that closes a bracket opened by `kind`.
+fn closing(kind: TokenType) -> Option<TokenType> {
+    match kind {
+        TokenType::LeftBraceToken => Some(TokenType::RightBraceToken),
+        TokenType::LeftParenToken => Some(TokenType::RightParenToken),
+        TokenType::LeftBracketToken => Some(TokenType::RightBracketToken),
+        TokenType::TemplateHead => Some(TokenType::TemplateTail),
+        _ => None,
+    }
+}
+
+/// TS1005 for a bracket `opener` still open at `span`.
+fn expected_closer(opener: Token, span: Range<usize>) -> Diagnostic {
+    let closer = match opener.token_type {
+        TokenType::LeftParenToken => ")",
+        TokenType::LeftBracketToken => "]",
+        _ => "}",
+    };
+    let opener_span = opener.start as usize..opener.end as usize;
+    Diagnostic::error(1005, format!("'{}' expected.", closer))
+        .with_label(span, "expected before this")
+        .with_label(opener_span, "to close this")
+}
+
+/// Whether a line break after `kind` cannot end a statement, because
+/// `kind` needs something after it.
+fn continues_after(kind: TokenType) -> bool {
+    kind.is_punctuation()
+        && !matches!(
+            kind,
+            TokenType::RightBraceToken
+                | TokenType::RightParenToken
+                | TokenType::RightBracketToken
+                | TokenType::PlusPlusToken
+                | TokenType::MinusMinusToken
+        )
+}
+
+/// Whether a line break before `kind` cannot end a statement, because
+/// `kind` can only continue one, like `.` or `=`.
+fn continues_before(kind: TokenType) -> bool {
+    kind.is_punctuation()
+        && !matches!(
+            kind,
+            TokenType::LeftBraceToken
+                | TokenType::LeftParenToken
+                | TokenType::LeftBracketToken
+                | TokenType::PlusPlusToken
+                | TokenType::MinusMinusToken
+                | TokenType::ExclamationToken
+                | TokenType::TildeToken
+                | TokenType::LessThanToken
+                | TokenType::AtToken
+        )
+}
+
+/// Keywords after which a new declaration starts on a new line.
+fn starts_declaration(kind: TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::ExportKeyword
+            | TokenType::FunctionKeyword
+            | TokenType::ClassKeyword
+            | TokenType::InterfaceKeyword
+            | TokenType::EnumKeyword
+            | TokenType::TypeKeyword
+            | TokenType::NamespaceKeyword
+            | TokenType::DeclareKeyword
+            | TokenType::ConstKeyword
+            | TokenType::LetKeyword
+            | TokenType::VarKeyword
+    )
+}
+
+/// Names that can be declared. Contextual keywords like `type` are names
+/// too, in most places.
+fn is_binding_name(kind: TokenType) -> bool {
+    kind == TokenType::Identifier || kind.is_contextual_keyword()
+}
+
+/// Names of class and enum members.
+fn is_member_name(kind: TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::Identifier
+            | TokenType::PrivateIdentifier
+            | TokenType::StringLiteral
+            | TokenType::NumericLiteral
+            | TokenType::LeftBracketToken
+    ) || kind.is_keyword()
+}
+
+fn is_member_modifier(kind: TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::PublicKeyword
+            | TokenType::PrivateKeyword
+            | TokenType::ProtectedKeyword
+            | TokenType::StaticKeyword
+            | TokenType::ReadonlyKeyword
+            | TokenType::AbstractKeyword
+            | TokenType::AsyncKeyword
+            | TokenType::OverrideKeyword
+            | TokenType::DeclareKeyword
+            | TokenType::AccessorKeyword
+            | TokenType::GetKeyword
+            | TokenType::SetKeyword
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn outline(text: &str) -> Outline<'_> {
+        let tokens = Lexer::new(text).lex().filter_map(Result::ok).collect();
+        Outline::new(text, tokens)
+    }
+
+    /// Symbols as `kind name` lines, indented by depth.
+    fn symbols(text: &str) -> Vec<String> {
+        fn walk(text: &str, symbols: &[Symbol], depth: usize, out: &mut Vec<String>) {
+            for symbol in symbols {
+                assert!(symbol.range.start <= symbol.selection_range.start);
+                assert!(symbol.selection_range.end <= symbol.range.end);
+                out.push(format!(
+                    "{}{} {} `{}`",
+                    "  ".repeat(depth),
+                    symbol.kind,
+                    symbol.name,
+                    &text[symbol.range.clone()]
+                ));
+                walk(text, &symbol.children, depth + 1, out);
+            }
+        }
+        let mut out = Vec::new();
+        walk(text, &outline(text).symbols(), 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_bracket_diagnostics() {
+        let message = |text: &str| {
+            outline(text)
+                .diagnostic()
+                .map(|d| (d.code, d.message.clone(), d.labels[0].span.clone()))
+        };
+        assert_eq!(message("f({ a: [1, `${b}`] });"), None);
+        assert_eq!(
+            message("f(a];"),
+            Some((1128, "Declaration or statement expected.".to_string(), 3..4))
+        );
+        assert_eq!(
+            message("f({ a );"),
+            Some((1005, "'}' expected.".to_string(), 6..7))
+        );
+        assert_eq!(
+            message("if (a) {\n  b();\n"),
+            Some((1005, "'}' expected.".to_string(), 16..16))
+        );
+        assert_eq!(
+            message("`${ ( }`"),
+            Some((1005, "')' expected.".to_string(), 6..8))
+        );
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let text = "\
+// #region setup
+// one
+// two
+function f() {
+  return [
+    1,
+  ];
+}
+/*
+ */
+// #endregion
+";
+        let ranges = outline(text).folding_ranges(&LineMap::new(text));
+        let ranges: Vec<_> = ranges
+            .iter()
+            .map(|r| (r.start_line, r.end_line, r.kind))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (0, 10, Some("region")),
+                (1, 2, Some("comment")),
+                (3, 6, None),
+                (4, 5, None),
+                (8, 9, Some("comment")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let text = "\
+export default async function main(a) {
+  const inner = 1;
+  if (a) { function helper() {} }
+}
+export class Point<T> extends Base {
+  @dec() static readonly origin = new Point(0, 0)
+  #x: number;
+  constructor(x) { this.#x = x; }
+  get x() { return this.#x; }
+  async *[Symbol.iterator]() {}
+  static { init(); }
+}
+declare namespace a.b {
+  interface I { m(): void }
+  type T<U> = { u: U }
+    | null;
+  const enum E { A = 1, 'B' }
+}
+let x = f(1, 2), y, { z } = o;
+x = function notAStatement() {};
+";
+        assert_eq!(
+            symbols(text),
+            [
+                "12 main `export default async function main(a) {\n  const inner = 1;\n  if (a) { function helper() {} }\n}`",
+                "  12 helper `function helper() {}`",
+                "5 Point `export class Point<T> extends Base {\n  @dec() static readonly origin = new Point(0, 0)\n  #x: number;\n  constructor(x) { this.#x = x; }\n  get x() { return this.#x; }\n  async *[Symbol.iterator]() {}\n  static { init(); }\n}`",
+                "  7 origin `static readonly origin = new Point(0, 0)`",
+                "  7 #x `#x: number;`",
+                "  9 constructor `constructor(x) { this.#x = x; }`",
+                "  7 x `get x() { return this.#x; }`",
+                "  6 [Symbol.iterator] `async *[Symbol.iterator]() {}`",
+                "3 a.b `declare namespace a.b {\n  interface I { m(): void }\n  type T<U> = { u: U }\n    | null;\n  const enum E { A = 1, 'B' }\n}`",
+                "  11 I `interface I { m(): void }`",
+                "  26 T `type T<U> = { u: U }\n    | null;`",
+                "  10 E `const enum E { A = 1, 'B' }`",
+                "    22 A `A = 1`",
+                "    22 B `'B'`",
+                "13 x `let x = f(1, 2), y, { z } = o;`",
+                "13 y `let x = f(1, 2), y, { z } = o;`",
+            ]
+        );
+    }
+}
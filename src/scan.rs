@@ -0,0 +1,364 @@
+//! Searches for the next "interesting" byte in the lexer's hot loops:
+//! whitespace runs, comment bodies and string and template contents.
+//!
+//! Every search has a block implementation that compares `LANES` bytes at a
+//! time, and a scalar implementation that the block one falls back to for
+//! short inputs and for the tail of the input. Both must return the same
+//! result for every input.
+//!
+//! Blocks are 8-byte words compared with bit tricks on stable Rust, or
+//! 32-byte `std::simd` vectors with the `nightly` feature.
+
+use lanes::{Block, LANES, Matches};
+
+/// Whitespace runs shorter than this are skipped with the scalar search.
+const SHORT_RUN: usize = 16;
+
+/// Which implementation a `Lexer` uses to scan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanMode {
+    Simd,
+    Scalar,
+}
+
+#[cfg(feature = "nightly")]
+mod lanes {
+    use std::ops::{BitOr, Not};
+    use std::simd::cmp::SimdPartialEq;
+    use std::simd::{Mask, Simd};
+
+    pub const LANES: usize = 32;
+
+    #[derive(Clone, Copy)]
+    pub struct Block(Simd<u8, LANES>);
+
+    /// The lanes of a `Block` that matched.
+    #[derive(Clone, Copy)]
+    pub struct Matches(Mask<i8, LANES>);
+
+    impl Block {
+        #[inline(always)]
+        pub fn load(bytes: &[u8]) -> Self {
+            Block(Simd::from_slice(bytes))
+        }
+
+        #[inline(always)]
+        pub fn eq(self, b: u8) -> Matches {
+            Matches(self.0.simd_eq(Simd::splat(b)))
+        }
+    }
+
+    impl Matches {
+        #[inline(always)]
+        pub fn first(self) -> Option<usize> {
+            let bits = self.0.to_bitmask();
+            (bits != 0).then(|| bits.trailing_zeros() as usize)
+        }
+    }
+
+    impl BitOr for Matches {
+        type Output = Matches;
+
+        #[inline(always)]
+        fn bitor(self, other: Matches) -> Matches {
+            Matches(self.0 | other.0)
+        }
+    }
+
+    impl Not for Matches {
+        type Output = Matches;
+
+        #[inline(always)]
+        fn not(self) -> Matches {
+            Matches(!self.0)
+        }
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+mod lanes {
+    use std::ops::{BitOr, Not};
+
+    pub const LANES: usize = 8;
+
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const LOW: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+    const HIGH: u64 = 0x8080_8080_8080_8080;
+
+    /// Eight bytes in a little-endian word, so that the first byte is the
+    /// lowest.
+    #[derive(Clone, Copy)]
+    pub struct Block(u64);
+
+    /// The high bit of each byte of a `Block` that matched.
+    #[derive(Clone, Copy)]
+    pub struct Matches(u64);
+
+    impl Block {
+        #[inline(always)]
+        pub fn load(bytes: &[u8]) -> Self {
+            Block(u64::from_le_bytes(bytes.try_into().unwrap()))
+        }
+
+        #[inline(always)]
+        pub fn eq(self, b: u8) -> Matches {
+            // A byte of `x` is zero exactly when neither adding 0x7f to its
+            // low bits nor its own high bit sets its high bit. The additions
+            // cannot carry into the next byte, so unlike the usual
+            // `(x - ONES) & !x` test this is exact for every byte.
+            let x = self.0 ^ (ONES * b as u64);
+            Matches(!(((x & LOW) + LOW) | x) & HIGH)
+        }
+    }
+
+    impl Matches {
+        #[inline(always)]
+        pub fn first(self) -> Option<usize> {
+            (self.0 != 0).then(|| self.0.trailing_zeros() as usize / 8)
+        }
+    }
+
+    impl BitOr for Matches {
+        type Output = Matches;
+
+        #[inline(always)]
+        fn bitor(self, other: Matches) -> Matches {
+            Matches(self.0 | other.0)
+        }
+    }
+
+    impl Not for Matches {
+        type Output = Matches;
+
+        #[inline(always)]
+        fn not(self) -> Matches {
+            Matches(!self.0 & HIGH)
+        }
+    }
+}
+
+/// Runs `chunk` over every full `LANES`-byte chunk of `bytes` until it finds a
+/// match, then finishes the tail with `scalar`.
+#[inline(always)]
+fn search(
+    bytes: &[u8],
+    chunk: impl Fn(Block) -> Matches,
+    scalar: impl Fn(&[u8]) -> usize,
+) -> usize {
+    let mut offset = 0;
+    while offset + LANES <= bytes.len() {
+        let block = Block::load(&bytes[offset..offset + LANES]);
+        if let Some(i) = chunk(block).first() {
+            return offset + i;
+        }
+        offset += LANES;
+    }
+    offset + scalar(&bytes[offset..])
+}
+
+/// Length of the run of ASCII whitespace and line breaks at the start of `bytes`.
+pub fn whitespace_len(bytes: &[u8], mode: ScanMode) -> usize {
+    // Most runs are a line break and some indentation, which is not worth a
+    // vector compare, so only long runs take the block path.
+    let prefix = bytes.len().min(SHORT_RUN);
+    let len = scalar::whitespace_len(&bytes[..prefix]);
+    if mode == ScanMode::Scalar || len < SHORT_RUN {
+        return if len < prefix {
+            len
+        } else {
+            len + scalar::whitespace_len(&bytes[len..])
+        };
+    }
+    len + search(
+        &bytes[len..],
+        |block| {
+            let whitespace = block.eq(b' ')
+                | block.eq(b'\t')
+                | block.eq(b'\n')
+                | block.eq(b'\r')
+                | block.eq(0x0b)
+                | block.eq(0x0c);
+            !whitespace
+        },
+        scalar::whitespace_len,
+    )
+}
+
+/// Offset of the first `\n` or `\r` in `bytes`, or its length.
+pub fn find_line_break(bytes: &[u8], mode: ScanMode) -> usize {
+    if mode == ScanMode::Scalar || bytes.len() < LANES {
+        return scalar::find_line_break(bytes);
+    }
+    search(
+        bytes,
+        |block| block.eq(b'\n') | block.eq(b'\r'),
+        scalar::find_line_break,
+    )
+}
+
+/// Offset of the first `*/` in `bytes`.
+pub fn find_comment_end(bytes: &[u8], mode: ScanMode) -> Option<usize> {
+    if mode == ScanMode::Scalar || bytes.len() < LANES {
+        return scalar::find_comment_end(bytes);
+    }
+
+    let mut offset = 0;
+    loop {
+        let rest = &bytes[offset..];
+        let star = search(rest, |block| block.eq(b'*'), scalar::find_star);
+        if star + 1 >= rest.len() {
+            return None;
+        }
+        if rest[star + 1] == b'/' {
+            return Some(offset + star);
+        }
+        offset += star + 1;
+    }
+}
+
+/// Offset of the first `quote`, `\`, `\n` or `\r` in a string literal's body.
+pub fn find_string_special(bytes: &[u8], quote: u8, mode: ScanMode) -> usize {
+    if mode == ScanMode::Scalar || bytes.len() < LANES {
+        return scalar::find_string_special(bytes, quote);
+    }
+    search(
+        bytes,
+        |block| block.eq(quote) | block.eq(b'\\') | block.eq(b'\n') | block.eq(b'\r'),
+        |tail| scalar::find_string_special(tail, quote),
+    )
+}
+
+/// Offset of the first `` ` ``, `\` or `$` in a template's body.
+pub fn find_template_special(bytes: &[u8], mode: ScanMode) -> usize {
+    if mode == ScanMode::Scalar || bytes.len() < LANES {
+        return scalar::find_template_special(bytes);
+    }
+    search(
+        bytes,
+        |block| block.eq(b'`') | block.eq(b'\\') | block.eq(b'$'),
+        scalar::find_template_special,
+    )
+}
+
+fn is_ascii_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)
+}
+
+pub mod scalar {
+    use super::is_ascii_whitespace;
+
+    fn position(bytes: &[u8], f: impl Fn(u8) -> bool) -> usize {
+        bytes.iter().position(|&b| f(b)).unwrap_or(bytes.len())
+    }
+
+    pub fn whitespace_len(bytes: &[u8]) -> usize {
+        position(bytes, |b| !is_ascii_whitespace(b))
+    }
+
+    pub fn find_line_break(bytes: &[u8]) -> usize {
+        position(bytes, |b| b == b'\n' || b == b'\r')
+    }
+
+    pub(super) fn find_star(bytes: &[u8]) -> usize {
+        position(bytes, |b| b == b'*')
+    }
+
+    pub fn find_comment_end(bytes: &[u8]) -> Option<usize> {
+        bytes.windows(2).position(|pair| pair == b"*/")
+    }
+
+    pub fn find_string_special(bytes: &[u8], quote: u8) -> usize {
+        position(bytes, |b| {
+            b == quote || b == b'\\' || b == b'\n' || b == b'\r'
+        })
+    }
+
+    pub fn find_template_special(bytes: &[u8]) -> usize {
+        position(bytes, |b| b == b'`' || b == b'\\' || b == b'$')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator, so the differential tests need no dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random input biased towards the bytes the searches look for.
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            const ALPHABET: &[u8] = b"  \t\n\r*/*/'\"`\\$ab{}\x0b\x0c\xc3\xa9";
+            (0..len)
+                .map(|_| {
+                    let r = self.next();
+                    if r.is_multiple_of(4) {
+                        (r >> 8) as u8
+                    } else {
+                        ALPHABET[(r >> 8) as usize % ALPHABET.len()]
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_scan_simd_matches_scalar() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+        for i in 0..20_000 {
+            let len = (i % 200) + rng.next() as usize % 8;
+            let bytes = rng.bytes(len);
+
+            assert_eq!(
+                whitespace_len(&bytes, ScanMode::Simd),
+                scalar::whitespace_len(&bytes),
+                "whitespace_len({:?})",
+                bytes
+            );
+            assert_eq!(
+                find_line_break(&bytes, ScanMode::Simd),
+                scalar::find_line_break(&bytes),
+                "find_line_break({:?})",
+                bytes
+            );
+            assert_eq!(
+                find_comment_end(&bytes, ScanMode::Simd),
+                scalar::find_comment_end(&bytes),
+                "find_comment_end({:?})",
+                bytes
+            );
+            for quote in *b"'\"" {
+                assert_eq!(
+                    find_string_special(&bytes, quote, ScanMode::Simd),
+                    scalar::find_string_special(&bytes, quote),
+                    "find_string_special({:?})",
+                    bytes
+                );
+            }
+            assert_eq!(
+                find_template_special(&bytes, ScanMode::Simd),
+                scalar::find_template_special(&bytes),
+                "find_template_special({:?})",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_scan_long_runs() {
+        let mut bytes = vec![b' '; 100];
+        bytes.extend_from_slice(b"x*/");
+
+        assert_eq!(whitespace_len(&bytes, ScanMode::Simd), 100);
+        assert_eq!(find_comment_end(&bytes, ScanMode::Simd), Some(101));
+        assert_eq!(find_line_break(&bytes, ScanMode::Simd), bytes.len());
+    }
+}
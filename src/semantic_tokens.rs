@@ -0,0 +1,341 @@
+//! LSP semantic tokens, for `textDocument/semanticTokens/full` and its
+//! `/delta` variant.
+//!
+//! Tokens are encoded as the protocol asks: five numbers each, giving the
+//! line and start column relative to the previous token, the length, an
+//! index into `TOKEN_TYPES` and a bit set over `TOKEN_MODIFIERS`. Columns
+//! and lengths count UTF-16 code units, and tokens that span lines, such as
+//! block comments and templates, are split into one token per line.
+//!
+//! Identifiers and JSX are left to the client's grammar, which knows as
+//! much about them as the lexer does.
+
+use std::ops::Range;
+
+use crate::highlight::{self, HighlightClass};
+use crate::lexer::LanguageVariant;
+use crate::token::{Token, TokenType};
+
+/// The legend's token types, in index order.
+pub const TOKEN_TYPES: [&str; 7] = [
+    "keyword", "string", "number", "regexp", "comment", "operator", "property",
+];
+
+/// The legend's token modifiers, in bit order.
+pub const TOKEN_MODIFIERS: [&str; 2] = ["documentation", "contextual"];
+
+const KEYWORD: u32 = 0;
+const STRING: u32 = 1;
+const NUMBER: u32 = 2;
+const REGEXP: u32 = 3;
+const COMMENT: u32 = 4;
+const OPERATOR: u32 = 5;
+const PROPERTY: u32 = 6;
+
+/// Set on `/** */` comments and the tags in them.
+const DOCUMENTATION: u32 = 1 << 0;
+/// Set on keywords that are keywords only in some positions, such as `async`.
+const CONTEXTUAL: u32 = 1 << 1;
+
+/// One edit of a `/delta` result: `delete_count` numbers of the previous
+/// data, from index `start`, are replaced by `data`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub start: u32,
+    pub delete_count: u32,
+    pub data: Vec<u32>,
+}
+
+/// The semantic tokens of `text`, encoded.
+pub fn encode(text: &str, variant: LanguageVariant) -> Vec<u32> {
+    let mut encoder = Encoder {
+        text,
+        data: Vec::new(),
+        offset: 0,
+        line: 0,
+        column: 0,
+        previous_line: 0,
+        previous_column: 0,
+    };
+    highlight::classify(text, variant, |token, class, jsx_tag| {
+        if jsx_tag {
+            return;
+        }
+        let Some((token_type, modifiers)) = token_type(&token, class) else {
+            return;
+        };
+        let (start, end) = (token.start as usize, token.end as usize);
+        if token_type != COMMENT {
+            encoder.push(start..end, token_type, modifiers);
+            return;
+        }
+        // Doc tags are keywords inside the comment.
+        let modifiers = if text[start..end].starts_with("/**") {
+            DOCUMENTATION
+        } else {
+            0
+        };
+        let mut from = start;
+        for tag in highlight::doc_tags(&text[start..end]) {
+            encoder.push(from..start + tag.start, COMMENT, modifiers);
+            encoder.push(start + tag.start..start + tag.end, KEYWORD, modifiers);
+            from = start + tag.end;
+        }
+        encoder.push(from..end, COMMENT, modifiers);
+    });
+    encoder.data
+}
+
+/// The edits that turn `previous` into `current`: none if they are equal,
+/// and otherwise one replacing whatever lies between their common prefix
+/// and suffix. Both are kept to whole tokens.
+pub fn delta(previous: &[u32], current: &[u32]) -> Vec<Edit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == previous.len() && prefix == current.len() {
+        return Vec::new();
+    }
+    let prefix = prefix - prefix % 5;
+
+    let max_suffix = previous.len().min(current.len()) - prefix;
+    let suffix = previous
+        .iter()
+        .rev()
+        .zip(current.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = suffix - suffix % 5;
+
+    vec![Edit {
+        start: prefix as u32,
+        delete_count: (previous.len() - prefix - suffix) as u32,
+        data: current[prefix..current.len() - suffix].to_vec(),
+    }]
+}
+
+/// The type and modifiers of a token, or `None` to leave it out.
+fn token_type(token: &Token, class: Option<HighlightClass>) -> Option<(u32, u32)> {
+    let kind = token.token_type;
+    if kind == TokenType::PrivateIdentifier {
+        return Some((PROPERTY, 0));
+    }
+    let token_type = match class? {
+        HighlightClass::Keyword => KEYWORD,
+        HighlightClass::ContextualKeyword => return Some((KEYWORD, CONTEXTUAL)),
+        HighlightClass::Number => NUMBER,
+        HighlightClass::String | HighlightClass::Template => STRING,
+        HighlightClass::Regex => REGEXP,
+        HighlightClass::Comment => COMMENT,
+        HighlightClass::Punctuation if is_operator(kind) => OPERATOR,
+        _ => return None,
+    };
+    Some((token_type, 0))
+}
+
+/// Punctuators that are operators rather than delimiters.
+fn is_operator(kind: TokenType) -> bool {
+    !matches!(
+        kind,
+        TokenType::LeftBraceToken
+            | TokenType::RightBraceToken
+            | TokenType::LeftParenToken
+            | TokenType::RightParenToken
+            | TokenType::LeftBracketToken
+            | TokenType::RightBracketToken
+            | TokenType::DotToken
+            | TokenType::DotDotDotToken
+            | TokenType::SemicolonToken
+            | TokenType::CommaToken
+            | TokenType::AtToken
+            | TokenType::HashToken
+    )
+}
+
+/// Encodes tokens in order, keeping the position of the last one so each
+/// is only scanned once.
+struct Encoder<'a> {
+    text: &'a str,
+    data: Vec<u32>,
+    /// Byte offset of `line` and `column`.
+    offset: usize,
+    line: u32,
+    column: u32,
+    previous_line: u32,
+    previous_column: u32,
+}
+
+impl Encoder<'_> {
+    fn push(&mut self, span: Range<usize>, token_type: u32, modifiers: u32) {
+        let bytes = self.text.as_bytes();
+        let mut from = span.start;
+        let mut i = span.start;
+        while i < span.end {
+            match bytes[i] {
+                b'\n' | b'\r' => {
+                    self.push_line(from..i, token_type, modifiers);
+                    i += if bytes[i..].starts_with(b"\r\n") {
+                        2
+                    } else {
+                        1
+                    };
+                    from = i;
+                }
+                _ => i += 1,
+            }
+        }
+        self.push_line(from..span.end, token_type, modifiers);
+    }
+
+    /// Pushes a token that does not cross a line break.
+    fn push_line(&mut self, span: Range<usize>, token_type: u32, modifiers: u32) {
+        if span.is_empty() {
+            return;
+        }
+        self.advance(span.start);
+        let length = utf16_len(&self.text[span]);
+        let delta_line = self.line - self.previous_line;
+        let delta_column = if delta_line == 0 {
+            self.column - self.previous_column
+        } else {
+            self.column
+        };
+        self.data
+            .extend([delta_line, delta_column, length, token_type, modifiers]);
+        self.previous_line = self.line;
+        self.previous_column = self.column;
+    }
+
+    fn advance(&mut self, to: usize) {
+        let bytes = self.text.as_bytes();
+        for (i, c) in self.text[self.offset..to].char_indices() {
+            match c {
+                '\r' if bytes.get(self.offset + i + 1) == Some(&b'\n') => {}
+                '\n' | '\r' => {
+                    self.line += 1;
+                    self.column = 0;
+                }
+                c => self.column += c.len_utf16() as u32,
+            }
+        }
+        self.offset = to;
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let text = "/** @a\r\n */ async 𝑥 => `é${#p}`\n+ /r/;";
+        assert_eq!(
+            encode(text, LanguageVariant::Standard),
+            [
+                0,
+                0,
+                4,
+                COMMENT,
+                DOCUMENTATION, // "/** "
+                0,
+                4,
+                2,
+                KEYWORD,
+                DOCUMENTATION, // "@a"
+                1,
+                0,
+                3,
+                COMMENT,
+                DOCUMENTATION, // " */"
+                0,
+                4,
+                5,
+                KEYWORD,
+                CONTEXTUAL, // "async"
+                0,
+                9,
+                2,
+                OPERATOR,
+                0, // "=>" after the surrogate pair
+                0,
+                3,
+                4,
+                STRING,
+                0, // "`é${"
+                0,
+                4,
+                2,
+                PROPERTY,
+                0, // "#p"
+                0,
+                2,
+                2,
+                STRING,
+                0, // "}`"
+                1,
+                0,
+                1,
+                OPERATOR,
+                0, // "+"
+                0,
+                2,
+                3,
+                REGEXP,
+                0, // "/r/"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_jsx() {
+        // `class` is an attribute here, not a keyword, and the tag's
+        // punctuation is not operators.
+        let text = "<a class={b ? 1 : 2}>if</a>";
+        assert_eq!(
+            encode(text, LanguageVariant::Jsx),
+            [
+                0, 12, 1, OPERATOR, 0, 0, 2, 1, NUMBER, 0, 0, 2, 1, OPERATOR, 0, 0, 2, 1, NUMBER, 0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delta() {
+        let previous = encode("a = 1;\nb = 2;\n", LanguageVariant::Standard);
+        let current = encode("a = 1;\nb = 'x' + 2;\n", LanguageVariant::Standard);
+        assert_eq!(delta(&previous, &previous), []);
+
+        let edits = delta(&previous, &current);
+        assert_eq!(
+            edits,
+            [Edit {
+                start: 15,
+                delete_count: 0,
+                data: vec![0, 2, 3, STRING, 0, 0, 4, 1, OPERATOR, 0],
+            }]
+        );
+
+        let mut applied = previous.clone();
+        for edit in edits {
+            let start = edit.start as usize;
+            applied.splice(start..start + edit.delete_count as usize, edit.data);
+        }
+        assert_eq!(applied, current);
+
+        assert_eq!(
+            delta(&[], &previous),
+            [Edit {
+                start: 0,
+                delete_count: 0,
+                data: previous.clone(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::diagnostic::{Diagnostic, Renderer};
+use crate::line_map::{ColumnEncoding, LineMap, Position};
+use crate::token::{StrSpan, Token};
+
+/// A source file: its name, its text and the line map built from that text.
+///
+/// Tokens only store byte offsets, so `SourceFile` is what ties them back to
+/// the text they were scanned from.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    line_map: LineMap,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let line_map = LineMap::new(&text);
+        Self {
+            name: name.into(),
+            text,
+            line_map,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_map(&self) -> &LineMap {
+        &self.line_map
+    }
+
+    /// The text of `token`, exactly as written in the source.
+    pub fn text(&self, token: &Token) -> &str {
+        self.slice(token.start as usize, token.end as usize)
+    }
+
+    pub fn span_text(&self, span: &StrSpan) -> &str {
+        self.slice(span.start as usize, span.end as usize)
+    }
+
+    pub fn position(&self, offset: usize, encoding: ColumnEncoding) -> Position {
+        self.line_map.position(&self.text, offset, encoding)
+    }
+
+    pub fn render(&self, renderer: &Renderer, diagnostic: &Diagnostic) -> String {
+        renderer.render(&self.name, &self.text, diagnostic)
+    }
+
+    fn slice(&self, start: usize, end: usize) -> &str {
+        self.text.get(start..end).unwrap_or_default()
+    }
+}
+
+impl Token {
+    pub fn text<'s>(&self, source: &'s SourceFile) -> &'s str {
+        source.text(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::token::{TokenFlags, TokenType};
+
+    #[test]
+    fn test_source_file_token_text() -> Result<(), String> {
+        let source = SourceFile::new("main.ts", "0x1f >>>= /* 한글 */");
+        let mut lexer = Lexer::new(source.source());
+        let mut texts = Vec::new();
+
+        for token in lexer.lex() {
+            let t = token.map_err(|e| e.to_string())?;
+            texts.push(t.text(&source));
+        }
+
+        assert_eq!(texts, vec!["0x1f", ">>>=", "/* 한글 */"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_file_text_out_of_bounds() {
+        let source = SourceFile::new("main.ts", "abc");
+        let token = Token::new(TokenType::Identifier, TokenFlags::NONE, 2, 10);
+
+        assert_eq!(source.text(&token), "");
+    }
+}
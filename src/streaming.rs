@@ -0,0 +1,392 @@
+//! Lexing from an `io::Read`, for inputs too large to hold in memory as one
+//! string.
+//!
+//! `StreamingLexer` keeps a window of the input and runs an ordinary `Lexer`
+//! over it one token at a time. A token is only emitted once enough input
+//! past its end is buffered that more input could not have changed it;
+//! otherwise the window grows and the token is scanned again.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::mem;
+
+use crate::error::LexerError;
+use crate::lexer::{Lexer, LexerState};
+use crate::scan::{self, ScanMode};
+use crate::token::{Token, TokenType};
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How many bytes past the end of a token must be buffered before the token
+/// is known to be complete. This covers the lexer's lookahead, such as the
+/// `e+1` of an exponent or the `{...}` of a `\u{...}` escape.
+const MARGIN: usize = 64;
+
+/// A token along with its text, which the streaming lexer does not keep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedToken {
+    pub token: Token,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Lexer(LexerError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "{}", e),
+            StreamError::Lexer(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StreamError {}
+
+impl From<io::Error> for StreamError {
+    fn from(e: io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+/// A lexer over any `Read`, producing the same tokens and errors as `Lexer`
+/// does over the whole input, with absolute byte offsets.
+///
+/// Memory use is bounded by the chunk size and the longest token. Like
+/// `Lexer`, offsets are `u32`, so inputs are limited to 4 GiB.
+pub struct StreamingLexer<R> {
+    reader: R,
+    chunk_size: usize,
+    read_buffer: Vec<u8>,
+
+    /// Buffered input. `window[0]` is at absolute offset `base`.
+    window: String,
+    /// Bytes read past the end of `window` that do not form a whole
+    /// character yet.
+    partial: Vec<u8>,
+    base: usize,
+    /// Offset in `window` of the next byte to be scanned.
+    pos: usize,
+
+    state: LexerState,
+    /// `state` before the token being scanned, to scan it again from.
+    saved: LexerState,
+    /// An unterminated token, returned after its error.
+    unterminated: Option<OwnedToken>,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: Read> StreamingLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        Self {
+            reader,
+            chunk_size,
+            read_buffer: vec![0; chunk_size],
+            window: String::new(),
+            partial: Vec::new(),
+            base: 0,
+            pos: 0,
+            state: LexerState::default(),
+            saved: LexerState::default(),
+            unterminated: None,
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Reads at least `min` more bytes into the window, unless the input
+    /// ends first. Scanned text, up to the end of the last token or of the
+    /// whitespace after it, is dropped from the window first.
+    fn fill(&mut self, min: usize) -> io::Result<()> {
+        if self.pos >= self.chunk_size {
+            self.window.drain(..self.pos);
+            self.base += self.pos;
+            self.pos = 0;
+        }
+
+        let target = self.window.len() + min;
+        while self.window.len() < target && !self.eof {
+            let n = match self.reader.read(&mut self.read_buffer) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                self.eof = true;
+                if !self.partial.is_empty() {
+                    return Err(invalid_utf8());
+                }
+                break;
+            }
+
+            self.partial.extend_from_slice(&self.read_buffer[..n]);
+            let valid = match std::str::from_utf8(&self.partial) {
+                Ok(text) => text.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+            let text = std::str::from_utf8(&self.partial[..valid]).map_err(|_| invalid_utf8())?;
+            self.window.push_str(text);
+            self.partial.drain(..valid);
+        }
+
+        if self.base + self.window.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "inputs are limited to 4 GiB",
+            ));
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Option<OwnedToken>, StreamError> {
+        if let Some(token) = self.unterminated.take() {
+            return Ok(Some(token));
+        }
+        loop {
+            if !self.eof && self.window.len() - self.pos <= MARGIN {
+                self.fill(self.chunk_size)?;
+                continue;
+            }
+
+            self.saved.clone_from(&self.state);
+            let state = mem::take(&mut self.state);
+            let mut lexer = Lexer::with_state(&self.window, self.pos, state);
+            lexer.skip();
+            let result = lexer.next_token();
+            let end = lexer.offset();
+
+            if !self.eof && !self.is_complete(&result, end) {
+                // Scan the token again once more input is buffered, reading
+                // at least as much as is pending so that long tokens are
+                // rescanned only a logarithmic number of times.
+                drop(lexer);
+                self.state.clone_from(&self.saved);
+                self.skip_trivia();
+                self.fill(self.chunk_size.max(self.window.len() - self.pos))?;
+                continue;
+            }
+
+            self.state = lexer.into_state();
+            self.pos = end;
+            let base = self.base as u32;
+            return match result {
+                Ok(token) if token.token_type == TokenType::EndOfFile => Ok(None),
+                Ok(mut token) => {
+                    let text = self.window[token.start as usize..token.end as usize].to_string();
+                    token.start += base;
+                    token.end += base;
+                    match LexerError::unterminated(&token) {
+                        Some(error) => {
+                            self.unterminated = Some(OwnedToken { token, text });
+                            Err(StreamError::Lexer(error))
+                        }
+                        None => Ok(Some(OwnedToken { token, text })),
+                    }
+                }
+                Err(e) => Err(StreamError::Lexer(e.shifted(base))),
+            };
+        }
+    }
+
+    /// Moves past the whitespace before the next token, so that it can be
+    /// dropped from the window even if no token follows it for a while.
+    fn skip_trivia(&mut self) {
+        let at_start = self.state.at_start();
+        let mut lexer = Lexer::with_state(&self.window, self.pos, mem::take(&mut self.state));
+        lexer.skip();
+        let pos = lexer.offset();
+        let state = lexer.into_state();
+
+        // A `#!` line is only skipped once it is buffered to its end, and
+        // whether one follows is only known once two bytes are.
+        let rest = &self.window.as_bytes()[self.pos..];
+        let shebang = rest.len() < 2
+            || rest.starts_with(b"#!") && scan::find_line_break(rest, ScanMode::Simd) == rest.len();
+        if at_start && shebang {
+            self.state.clone_from(&self.saved);
+        } else {
+            self.state = state;
+            self.pos = pos;
+        }
+    }
+
+    /// Whether more input could not change a token scanned up to `end`.
+    fn is_complete(&self, result: &Result<Token, LexerError>, end: usize) -> bool {
+        if end + MARGIN >= self.window.len() {
+            return false;
+        }
+        // A `/` where a regular expression was allowed is a division only if
+        // the regular expression is unterminated, which the lexer decides by
+        // scanning to the end of the line.
+        match result {
+            Ok(token)
+                if self.saved.regex_allowed()
+                    && matches!(
+                        token.token_type,
+                        TokenType::SlashToken | TokenType::SlashEqualsToken
+                    ) =>
+            {
+                let rest = &self.window.as_bytes()[end..];
+                scan::find_line_break(rest, ScanMode::Simd) < rest.len()
+            }
+            _ => true,
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+impl<R: Read> Iterator for StreamingLexer<R> {
+    type Item = Result<OwnedToken, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(StreamError::Io(e)) => {
+                self.done = true;
+                Some(Err(StreamError::Io(e)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader that returns at most `step` bytes per call.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.bytes.len().min(self.step).min(buf.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    fn expected(text: &str) -> Vec<Result<(Token, &str), String>> {
+        Lexer::new(text)
+            .lex()
+            .map(|t| {
+                t.map(|t| (t, &text[t.start as usize..t.end as usize]))
+                    .map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+
+    fn streamed(
+        text: &str,
+        chunk_size: usize,
+        step: usize,
+    ) -> Vec<Result<(Token, String), String>> {
+        let reader = Trickle {
+            bytes: text.as_bytes(),
+            step,
+        };
+        StreamingLexer::with_chunk_size(reader, chunk_size)
+            .map(|t| t.map(|t| (t.token, t.text)).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_lexer() {
+        let long_string = format!("'{}'", "x".repeat(300));
+        let long_comment = format!("/* {} */", "한".repeat(200));
+        let sources = [
+            include_str!("../benches/corpus/emitter.js"),
+            include_str!("../benches/corpus/store.ts"),
+            "#!/usr/bin/env node\nlet a = 1e+5 / 2;",
+            "#!",
+            "#",
+            "  \n\n  a",
+            "x = a / b; y = /[/]+/g.test(s);",
+            "`a${ {b: `c${d}`} }e` 'unterminated\n0x 3in",
+            &long_string,
+            &long_comment,
+            "",
+        ];
+
+        for text in sources {
+            let expected: Vec<_> = expected(text)
+                .into_iter()
+                .map(|t| t.map(|(t, s)| (t, s.to_string())))
+                .collect();
+            for (chunk_size, step) in [(1, 1), (7, 3), (100, 100), (4096, 17)] {
+                assert_eq!(
+                    streamed(text, chunk_size, step),
+                    expected,
+                    "chunk size {}, step {}: {:?}",
+                    chunk_size,
+                    step,
+                    text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_streaming_absolute_offsets() {
+        let text = format!("{}a", " ".repeat(1000));
+        let tokens: Vec<_> = StreamingLexer::with_chunk_size(text.as_bytes(), 16)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!((tokens[0].token.start, tokens[0].token.end), (1000, 1001));
+        assert_eq!(tokens[0].text, "a");
+    }
+
+    #[test]
+    fn test_streaming_whitespace_is_not_kept() {
+        let chunk_size = 1024;
+        let text = format!("#!a\n{}", " \t\n".repeat(100_000));
+        let reader = Trickle {
+            bytes: text.as_bytes(),
+            step: 100,
+        };
+        let mut lexer = StreamingLexer::with_chunk_size(reader, chunk_size);
+        assert!(lexer.next().is_none());
+        assert!(
+            lexer.window.capacity() <= 8 * chunk_size,
+            "{} bytes buffered",
+            lexer.window.capacity()
+        );
+    }
+
+    #[test]
+    fn test_streaming_invalid_utf8() {
+        let bytes = b"let a = '\xff';";
+        let results: Vec<_> = StreamingLexer::new(&bytes[..]).collect();
+
+        assert!(matches!(results[..], [Err(StreamError::Io(_))]));
+    }
+}
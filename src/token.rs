@@ -134,6 +134,7 @@
 // }
 
 use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
 
 // =================================================================
 // This snippet is from the 'microsoft/typescript-go' project.
@@ -149,8 +150,8 @@ use std::fmt;
 // Source: https://github.com/microsoft/typescript-go/blob/main/internal/ast/kind.go
 // (Note: This code has been modified for this project.)
 // =================================================================
-#[allow(dead_code)]
-#[derive(Debug, PartialEq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TokenType {
     Unknown,
     EndOfFile,
@@ -327,285 +328,252 @@ pub enum TokenType {
     BigIntKeyword,      //BigInt
     OverrideKeyword,    //override
     OfKeyword,          // of, LastKeyword and LastToken and LastContextualKeyword
-    // Parse tree nodes
-    // Names
-    QualifiedName,
-    ComputedPropertyName,
-    // Signature elements
-    TypeParameter,
-    Parameter,
-    Decorator,
-    // TypeMember
-    PropertySignature,
-    PropertyDeclaration,
-    MethodSignature,
-    MethodDeclaration,
-    ClassStaticBlockDeclaration,
-    Constructor,
-    GetAccessor,
-    SetAccessor,
-    CallSignature,
-    ConstructSignature,
-    IndexSignature,
-    // Type
-    TypePredicate,
-    TypeReference,
-    FunctionType,
-    ConstructorType,
-    TypeQuery,
-    TypeLiteral,
-    ArrayType,
-    TupleType,
-    OptionalType,
-    RestType,
-    UnionType,
-    IntersectionType,
-    ConditionalType,
-    InferType,
-    ParenthesizedType,
-    ThisType,
-    TypeOperator,
-    IndexedAccessType,
-    MappedType,
-    LiteralType,
-    NamedTupleMember,
-    TemplateLiteralType,
-    TemplateLiteralTypeSpan,
-    ImportType,
-    // Binding patterns
-    ObjectBindingPattern,
-    ArrayBindingPattern,
-    BindingElement,
-    // Expression
-    ArrayLiteralExpression,
-    ObjectLiteralExpression,
-    PropertyAccessExpression,
-    ElementAccessExpression,
-    CallExpression,
-    NewExpression,
-    TaggedTemplateExpression,
-    TypeAssertionExpression,
-    ParenthesizedExpression,
-    FunctionExpression,
-    ArrowFunction,
-    DeleteExpression,
-    TypeOfExpression,
-    VoidExpression,
-    AwaitExpression,
-    PrefixUnaryExpression,
-    PostfixUnaryExpression,
-    BinaryExpression,
-    ConditionalExpression,
-    TemplateExpression,
-    YieldExpression,
-    SpreadElement,
-    ClassExpression,
-    OmittedExpression,
-    ExpressionWithTypeArguments,
-    AsExpression,
-    NonNullExpression,
-    MetaProperty,
-    SyntheticExpression,
-    SatisfiesExpression,
-    // Misc
-    TemplateSpan,
-    SemicolonClassElement,
-    // Element
-    Block,
-    EmptyStatement,
-    VariableStatement,
-    ExpressionStatement,
-    IfStatement,
-    DoStatement,
-    WhileStatement,
-    ForStatement,
-    ForInStatement,
-    ForOfStatement,
-    ContinueStatement,
-    BreakStatement,
-    ReturnStatement,
-    WithStatement,
-    SwitchStatement,
-    LabeledStatement,
-    ThrowStatement,
-    TryStatement,
-    DebuggerStatement,
-    VariableDeclaration,
-    VariableDeclarationList,
-    FunctionDeclaration,
-    ClassDeclaration,
-    InterfaceDeclaration,
-    TypeAliasDeclaration,
-    EnumDeclaration,
-    ModuleDeclaration,
-    ModuleBlock,
-    CaseBlock,
-    NamespaceExportDeclaration,
-    ImportEqualsDeclaration,
-    ImportDeclaration,
-    ImportClause,
-    NamespaceImport,
-    NamedImports,
-    ImportSpecifier,
-    ExportAssignment,
-    ExportDeclaration,
-    NamedExports,
-    NamespaceExport,
-    ExportSpecifier,
-    MissingDeclaration,
-    // Module references
-    ExternalModuleReference,
-    // JSX
-    JsxElement,
-    JsxSelfClosingElement,
-    JsxOpeningElement,
-    JsxClosingElement,
-    JsxFragment,
-    JsxOpeningFragment,
-    JsxClosingFragment,
-    JsxAttribute,
-    JsxAttributes,
-    JsxSpreadAttribute,
-    JsxExpression,
-    JsxNamespacedName,
-    // Clauses
-    CaseClause,
-    DefaultClause,
-    HeritageClause,
-    CatchClause,
-    // Import attributes
-    ImportAttributes,
-    ImportAttribute,
-    // Property assignments
-    PropertyAssignment,
-    ShorthandPropertyAssignment,
-    SpreadAssignment,
-    // Enum
-    EnumMember,
-    // Top-level nodes
-    SourceFile,
-    Bundle,
-    // JSDoc nodes
-    JSDocTypeExpression,
-    JSDocNameReference,
-    JSDocMemberName, // C#p
-    JSDocAllType,    // The * type
-    JSDocNullableType,
-    JSDocNonNullableType,
-    JSDocOptionalType,
-    JSDocVariadicType,
-    JSDoc,
-    JSDocText,
-    JSDocTypeLiteral,
-    JSDocSignature,
-    JSDocLink,
-    JSDocLinkCode,
-    JSDocLinkPlain,
-    JSDocTag,
-    JSDocAugmentsTag,
-    JSDocImplementsTag,
-    JSDocDeprecatedTag,
-    JSDocPublicTag,
-    JSDocPrivateTag,
-    JSDocProtectedTag,
-    JSDocReadonlyTag,
-    JSDocOverrideTag,
-    JSDocCallbackTag,
-    JSDocOverloadTag,
-    JSDocParameterTag,
-    JSDocReturnTag,
-    JSDocThisTag,
-    JSDocTypeTag,
-    JSDocTemplateTag,
-    JSDocTypedefTag,
-    JSDocSeeTag,
-    JSDocPropertyTag,
-    JSDocSatisfiesTag,
-    JSDocImportTag,
-    // Synthesized list
-    SyntaxList,
-    // Reparsed JS nodes
-    JSTypeAliasDeclaration,
-    JSExportAssignment,
-    CommonJSExport,
-    JSImportDeclaration,
-    // Transformation nodes
-    NotEmittedStatement,
-    PartiallyEmittedExpression,
-    CommaListExpression,
-    SyntheticReferenceExpression,
-    NotEmittedTypeElement,
-    // Enum value count
-    Count,
-    // Markers
-    FirstAssignment,         // = EqualsToken
-    LastAssignment,          // = CaretEqualsToken
-    FirstCompoundAssignment, // = PlusEqualsToken
-    LastCompoundAssignment,  // = CaretEqualsToken
-    FirstReservedWord,       // = BreakKeyword
-    LastReservedWord,        // = WithKeyword
-    FirstKeyword,            // = BreakKeyword
-    LastKeyword,             // = OfKeyword
-    FirstFutureReservedWord, // = ImplementsKeyword
-    LastFutureReservedWord,  // = YieldKeyword
-    FirstTypeNode,           // = TypePredicate
-    LastTypeNode,            // = ImportType
-    FirstPunctuation,        // = LeftBraceToken
-    LastPunctuation,         // = CaretEqualsToken
-    FirstToken,              // = Unknown
-    LastToken,               // = LastKeyword
-    FirstLiteralToken,       // = NumericLiteral
-    LastLiteralToken,        // = NoSubstitutionTemplateLiteral
-    FirstTemplateToken,      // = NoSubstitutionTemplateLiteral
-    LastTemplateToken,       // = TemplateTail
-    FirstBinaryOperator,     // = LessThanToken
-    LastBinaryOperator,      // = CaretEqualsToken
-    FirstStatement,          // = VariableStatement
-    LastStatement,           // = DebuggerStatement
-    FirstNode,               // = QualifiedName
-    FirstJSDocNode,          // = JSDocTypeExpression
-    LastJSDocNode,           // = JSDocImportTag
-    FirstJSDocTagNode,       // = JSDocTag
-    LastJSDocTagNode,        // = JSDocImportTag
-    FirstContextualKeyword,  // = AbstractKeyword
-    LastContextualKeyword,   // = OfKeyword
-    Comment,                 // = SingleLineCommentTrivia | KindMultiLineCommentTrivia
-    FirstTriviaToken,        // = SingleLineCommentTrivia
-    LastTriviaToken,         // = ConflictMarkerTrivia
 }
 
-#[derive(Debug)]
+// Markers, as in `ast/kind.go`. They are constants rather than variants so
+// that `TokenType` stays a dense `u8`.
+impl TokenType {
+    pub const FIRST_ASSIGNMENT: TokenType = TokenType::EqualsToken;
+    pub const LAST_ASSIGNMENT: TokenType = TokenType::CaretEqualsToken;
+    pub const FIRST_COMPOUND_ASSIGNMENT: TokenType = TokenType::PlusEqualsToken;
+    pub const LAST_COMPOUND_ASSIGNMENT: TokenType = TokenType::CaretEqualsToken;
+    pub const FIRST_RESERVED_WORD: TokenType = TokenType::BreakKeyword;
+    pub const LAST_RESERVED_WORD: TokenType = TokenType::WithKeyword;
+    pub const FIRST_KEYWORD: TokenType = TokenType::BreakKeyword;
+    pub const LAST_KEYWORD: TokenType = TokenType::OfKeyword;
+    pub const FIRST_FUTURE_RESERVED_WORD: TokenType = TokenType::ImplementsKeyword;
+    pub const LAST_FUTURE_RESERVED_WORD: TokenType = TokenType::YieldKeyword;
+    pub const FIRST_PUNCTUATION: TokenType = TokenType::LeftBraceToken;
+    pub const LAST_PUNCTUATION: TokenType = TokenType::CaretEqualsToken;
+    pub const FIRST_TOKEN: TokenType = TokenType::Unknown;
+    pub const LAST_TOKEN: TokenType = TokenType::LAST_KEYWORD;
+    pub const FIRST_LITERAL_TOKEN: TokenType = TokenType::NumericLiteral;
+    pub const LAST_LITERAL_TOKEN: TokenType = TokenType::NoSubstitutionTemplateLiteral;
+    pub const FIRST_TEMPLATE_TOKEN: TokenType = TokenType::NoSubstitutionTemplateLiteral;
+    pub const LAST_TEMPLATE_TOKEN: TokenType = TokenType::TemplateTail;
+    pub const FIRST_BINARY_OPERATOR: TokenType = TokenType::LessThanToken;
+    pub const LAST_BINARY_OPERATOR: TokenType = TokenType::CaretEqualsToken;
+    pub const FIRST_CONTEXTUAL_KEYWORD: TokenType = TokenType::AbstractKeyword;
+    pub const LAST_CONTEXTUAL_KEYWORD: TokenType = TokenType::OfKeyword;
+    pub const FIRST_TRIVIA_TOKEN: TokenType = TokenType::SingleLineCommentTrivia;
+    pub const LAST_TRIVIA_TOKEN: TokenType = TokenType::ConflictMarkerTrivia;
+
+    fn between(self, first: TokenType, last: TokenType) -> bool {
+        first <= self && self <= last
+    }
+
+    pub fn is_keyword(self) -> bool {
+        self.between(Self::FIRST_KEYWORD, Self::LAST_KEYWORD)
+    }
+
+    pub fn is_contextual_keyword(self) -> bool {
+        self.between(
+            Self::FIRST_CONTEXTUAL_KEYWORD,
+            Self::LAST_CONTEXTUAL_KEYWORD,
+        )
+    }
+
+    pub fn is_punctuation(self) -> bool {
+        self.between(Self::FIRST_PUNCTUATION, Self::LAST_PUNCTUATION)
+    }
+
+    pub fn is_literal(self) -> bool {
+        self.between(Self::FIRST_LITERAL_TOKEN, Self::LAST_LITERAL_TOKEN)
+    }
+
+    pub fn is_template(self) -> bool {
+        self.between(Self::FIRST_TEMPLATE_TOKEN, Self::LAST_TEMPLATE_TOKEN)
+    }
+
+    pub fn is_assignment(self) -> bool {
+        self.between(Self::FIRST_ASSIGNMENT, Self::LAST_ASSIGNMENT)
+    }
+
+    pub fn is_trivia(self) -> bool {
+        self.between(Self::FIRST_TRIVIA_TOKEN, Self::LAST_TRIVIA_TOKEN)
+    }
+
+    pub fn is_comment(self) -> bool {
+        matches!(
+            self,
+            TokenType::SingleLineCommentTrivia | TokenType::MultiLineCommentTrivia
+        )
+    }
+}
+
+/// Facts about a token that the scanner learns along the way, with the same
+/// bit values as TypeScript's `TokenFlags`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TokenFlags(u16);
+
+impl TokenFlags {
+    pub const NONE: TokenFlags = TokenFlags(0);
+    /// A line break appears between this token and the previous one.
+    pub const PRECEDING_LINE_BREAK: TokenFlags = TokenFlags(1 << 0);
+    /// A `/** ... */` comment appears between this token and the previous one.
+    pub const PRECEDING_JSDOC_COMMENT: TokenFlags = TokenFlags(1 << 1);
+    pub const UNTERMINATED: TokenFlags = TokenFlags(1 << 2);
+    /// The token contains a `\u{...}` escape.
+    pub const EXTENDED_UNICODE_ESCAPE: TokenFlags = TokenFlags(1 << 3);
+    /// e.g. `10e2`
+    pub const SCIENTIFIC: TokenFlags = TokenFlags(1 << 4);
+    /// e.g. `0777`
+    pub const OCTAL: TokenFlags = TokenFlags(1 << 5);
+    /// e.g. `0x00000000`
+    pub const HEX_SPECIFIER: TokenFlags = TokenFlags(1 << 6);
+    /// e.g. `0b0110010000000000`
+    pub const BINARY_SPECIFIER: TokenFlags = TokenFlags(1 << 7);
+    /// e.g. `0o777`
+    pub const OCTAL_SPECIFIER: TokenFlags = TokenFlags(1 << 8);
+    /// e.g. `0b1100_0101`
+    pub const CONTAINS_SEPARATOR: TokenFlags = TokenFlags(1 << 9);
+    /// The token contains a `\uXXXX` escape.
+    pub const UNICODE_ESCAPE: TokenFlags = TokenFlags(1 << 10);
+    /// e.g. `\u{110000}` in a string or template.
+    pub const CONTAINS_INVALID_ESCAPE: TokenFlags = TokenFlags(1 << 11);
+    /// The token contains a `\xXX` escape.
+    pub const HEX_ESCAPE: TokenFlags = TokenFlags(1 << 12);
+    /// e.g. `0888`
+    pub const CONTAINS_LEADING_ZERO: TokenFlags = TokenFlags(1 << 13);
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: TokenFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: TokenFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: TokenFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: TokenFlags) {
+        self.0 &= !other.0;
+    }
+
+    /// The TypeScript names of the flags that are set, e.g.
+    /// `"PrecedingLineBreak"`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        FLAG_NAMES
+            .iter()
+            .filter(move |&&(flag, _)| self.contains(flag))
+            .map(|&(_, name)| name)
+    }
+}
+
+const FLAG_NAMES: [(TokenFlags, &str); 14] = [
+    (TokenFlags::PRECEDING_LINE_BREAK, "PrecedingLineBreak"),
+    (TokenFlags::PRECEDING_JSDOC_COMMENT, "PrecedingJSDocComment"),
+    (TokenFlags::UNTERMINATED, "Unterminated"),
+    (TokenFlags::EXTENDED_UNICODE_ESCAPE, "ExtendedUnicodeEscape"),
+    (TokenFlags::SCIENTIFIC, "Scientific"),
+    (TokenFlags::OCTAL, "Octal"),
+    (TokenFlags::HEX_SPECIFIER, "HexSpecifier"),
+    (TokenFlags::BINARY_SPECIFIER, "BinarySpecifier"),
+    (TokenFlags::OCTAL_SPECIFIER, "OctalSpecifier"),
+    (TokenFlags::CONTAINS_SEPARATOR, "ContainsSeparator"),
+    (TokenFlags::UNICODE_ESCAPE, "UnicodeEscape"),
+    (TokenFlags::CONTAINS_INVALID_ESCAPE, "ContainsInvalidEscape"),
+    (TokenFlags::HEX_ESCAPE, "HexEscape"),
+    (TokenFlags::CONTAINS_LEADING_ZERO, "ContainsLeadingZero"),
+];
+
+impl BitOr for TokenFlags {
+    type Output = TokenFlags;
+
+    fn bitor(self, rhs: TokenFlags) -> TokenFlags {
+        TokenFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TokenFlags {
+    fn bitor_assign(&mut self, rhs: TokenFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct StrSpan {
-    pub start: usize,
-    pub end: usize,
+    pub start: u32,
+    pub end: u32,
 }
 
-#[derive(Debug)]
+/// A token and its position, as absolute byte offsets into the source.
+/// Use a `LineMap` to turn offsets into line/column positions.
+///
+/// Tokens are 12 bytes and `Copy`. Their text is always `source[start..end]`;
+/// values that need more work than slicing, such as the number a numeric
+/// literal denotes, live in a `TokenBuffer`'s literal table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub line: usize,
-    pub loc: usize,
-    pub end: usize,
-    pub lexeme: Option<StrSpan>,
-    pub literal_value: Option<StrSpan>,
+    pub flags: TokenFlags,
+    pub start: u32,
+    pub end: u32,
 }
 
 impl Token {
-    pub fn new(
-        token_type: TokenType,
-        line: usize,
-        loc: usize,
-        end: usize,
-        lexeme: Option<StrSpan>,
-        literal_value: Option<StrSpan>,
-    ) -> Self {
+    pub fn new(token_type: TokenType, flags: TokenFlags, start: u32, end: u32) -> Self {
         Self {
             token_type,
-            line,
-            loc,
+            flags,
+            start,
             end,
-            lexeme,
-            literal_value,
+        }
+    }
+
+    pub fn span(&self) -> StrSpan {
+        StrSpan {
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn has_preceding_line_break(&self) -> bool {
+        self.flags.contains(TokenFlags::PRECEDING_LINE_BREAK)
+    }
+
+    /// The span of a string or template literal's contents, without its
+    /// quotes, backticks or `${`/`}` delimiters. Other tokens return their
+    /// whole span.
+    pub fn value_span(&self) -> StrSpan {
+        let (open, close) = match self.token_type {
+            TokenType::StringLiteral
+            | TokenType::NoSubstitutionTemplateLiteral
+            | TokenType::TemplateTail => (1, 1),
+            TokenType::TemplateHead | TokenType::TemplateMiddle => (1, 2),
+            _ => return self.span(),
+        };
+        let close = if self.flags.contains(TokenFlags::UNTERMINATED) {
+            0
+        } else {
+            close
+        };
+        let start = (self.start + open).min(self.end);
+        StrSpan {
+            start,
+            end: (self.end - close).max(start),
         }
     }
 }
@@ -614,8 +582,48 @@ impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "[{}:{}-{}] Type: {:?}, lexeme: {:?}, literal value: {:?}",
-            self.line, self.loc, self.end, self.token_type, self.lexeme, self.literal_value
+            "[{}-{}] Type: {:?}, flags: {:#06x}",
+            self.start,
+            self.end,
+            self.token_type,
+            self.flags.bits()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_layout() {
+        assert_eq!(size_of::<TokenType>(), 1);
+        assert_eq!(size_of::<Token>(), 12);
+        assert_eq!(size_of::<Option<Token>>(), 12);
+    }
+
+    #[test]
+    fn test_token_type_ranges() {
+        assert!(TokenType::BreakKeyword.is_keyword());
+        assert!(TokenType::OfKeyword.is_contextual_keyword());
+        assert!(!TokenType::Identifier.is_keyword());
+        assert!(TokenType::GreaterThanGreaterThanGreaterThanEqualsToken.is_assignment());
+        assert!(TokenType::TemplateMiddle.is_template());
+        assert!(TokenType::MultiLineCommentTrivia.is_comment());
+        assert_eq!(TokenType::LAST_TOKEN as u8, TokenType::OfKeyword as u8);
+    }
+
+    #[test]
+    fn test_token_flags() {
+        let mut flags = TokenFlags::PRECEDING_LINE_BREAK | TokenFlags::HEX_SPECIFIER;
+        assert!(flags.contains(TokenFlags::HEX_SPECIFIER));
+        assert_eq!(flags.bits(), 0x41);
+        assert_eq!(
+            flags.names().collect::<Vec<_>>(),
+            ["PrecedingLineBreak", "HexSpecifier"]
+        );
+
+        flags.remove(TokenFlags::HEX_SPECIFIER);
+        assert_eq!(flags, TokenFlags::PRECEDING_LINE_BREAK);
+    }
+}
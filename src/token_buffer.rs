@@ -0,0 +1,353 @@
+use std::borrow::Cow;
+
+use crate::interner::Atom;
+use crate::token::{Token, TokenFlags, TokenType};
+
+/// A value computed from a token's text, kept in a `TokenBuffer`'s side
+/// table so that `Token` itself stays small.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiteralValue {
+    Number(f64),
+    /// The name of an identifier or private identifier, with escapes decoded.
+    Identifier(Atom),
+    /// The cooked value of a string literal.
+    String(Atom),
+}
+
+/// The tokens of a file in struct-of-arrays form.
+///
+/// Passes that only look at kinds (or only at offsets) walk one dense array
+/// instead of striding over whole tokens. Literal values are stored in a side
+/// table sorted by token index, since most tokens have none.
+#[derive(Debug, Clone, Default)]
+pub struct TokenBuffer {
+    kinds: Vec<TokenType>,
+    flags: Vec<TokenFlags>,
+    starts: Vec<u32>,
+    ends: Vec<u32>,
+    literals: Vec<(u32, LiteralValue)>,
+}
+
+impl TokenBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            kinds: Vec::with_capacity(capacity),
+            flags: Vec::with_capacity(capacity),
+            starts: Vec::with_capacity(capacity),
+            ends: Vec::with_capacity(capacity),
+            literals: Vec::new(),
+        }
+    }
+
+    /// Appends `token` and returns its index.
+    pub fn push(&mut self, token: Token) -> usize {
+        let index = self.kinds.len();
+        self.kinds.push(token.token_type);
+        self.flags.push(token.flags);
+        self.starts.push(token.start);
+        self.ends.push(token.end);
+        index
+    }
+
+    /// Appends `token` together with its literal value.
+    pub fn push_literal(&mut self, token: Token, value: LiteralValue) -> usize {
+        let index = self.push(token);
+        self.literals.push((index as u32, value));
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    pub fn kind(&self, index: usize) -> TokenType {
+        self.kinds[index]
+    }
+
+    pub fn flags(&self, index: usize) -> TokenFlags {
+        self.flags[index]
+    }
+
+    pub fn start(&self, index: usize) -> u32 {
+        self.starts[index]
+    }
+
+    pub fn end(&self, index: usize) -> u32 {
+        self.ends[index]
+    }
+
+    pub fn get(&self, index: usize) -> Token {
+        Token::new(
+            self.kinds[index],
+            self.flags[index],
+            self.starts[index],
+            self.ends[index],
+        )
+    }
+
+    pub fn kinds(&self) -> &[TokenType] {
+        &self.kinds
+    }
+
+    pub fn starts(&self) -> &[u32] {
+        &self.starts
+    }
+
+    pub fn ends(&self) -> &[u32] {
+        &self.ends
+    }
+
+    /// The literal value of the token at `index`, if it has one.
+    pub fn literal(&self, index: usize) -> Option<LiteralValue> {
+        self.literals
+            .binary_search_by_key(&(index as u32), |&(i, _)| i)
+            .ok()
+            .map(|i| self.literals[i].1)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Token> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+}
+
+/// The value of a `NumericLiteral` whose text is `text`.
+pub fn numeric_value(text: &str, flags: TokenFlags) -> f64 {
+    let radix = if flags.contains(TokenFlags::HEX_SPECIFIER) {
+        16
+    } else if flags.contains(TokenFlags::BINARY_SPECIFIER) {
+        2
+    } else if flags.contains(TokenFlags::OCTAL_SPECIFIER) {
+        8
+    } else if flags.contains(TokenFlags::OCTAL) {
+        // Legacy octal: `0777`.
+        return integer_value(&text[1..], 8);
+    } else {
+        let cleaned;
+        let text = if flags.contains(TokenFlags::CONTAINS_SEPARATOR) {
+            cleaned = text.replace('_', "");
+            &cleaned
+        } else {
+            text
+        };
+        return text.parse().unwrap_or(f64::NAN);
+    };
+    integer_value(&text[2..], radix)
+}
+
+fn integer_value(digits: &str, radix: u32) -> f64 {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(radix))
+        .fold(0.0, |value, digit| value * radix as f64 + digit as f64)
+}
+
+/// The name an identifier token spells, with `\\uXXXX` escapes decoded.
+pub fn identifier_name(text: &str, flags: TokenFlags) -> Cow<'_, str> {
+    if flags.intersects(TokenFlags::UNICODE_ESCAPE | TokenFlags::EXTENDED_UNICODE_ESCAPE) {
+        Cow::Owned(cook(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// The value of a string literal whose contents (without quotes) are `body`.
+pub fn string_value(body: &str) -> Cow<'_, str> {
+    if body.contains('\\') {
+        Cow::Owned(cook(body))
+    } else {
+        Cow::Borrowed(body)
+    }
+}
+
+/// Decodes the escape sequences in `text`. Lone surrogates, which a Rust
+/// string cannot hold, become U+FFFD, and malformed escapes are kept as
+/// written.
+fn cook(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(e) = chars.next() else {
+            out.push(c);
+            break;
+        };
+        match e {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            'r' => out.push('\r'),
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'v' => out.push('\u{b}'),
+            '0' if !chars.peek().is_some_and(char::is_ascii_digit) => out.push('\0'),
+            // Line continuations produce nothing.
+            '\r' => {
+                chars.next_if_eq(&'\n');
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            'x' => match hex(&mut chars, 2) {
+                Some(v) => out.push(char::from_u32(v).unwrap_or('\u{FFFD}')),
+                None => out.push_str("\\x"),
+            },
+            'u' => match unicode_escape(&mut chars) {
+                Some(v) => out.push(v),
+                None => out.push_str("\\u"),
+            },
+            '0'..='7' => {
+                // Legacy octal escape: up to three digits, at most 0o377.
+                let mut v = e.to_digit(8).unwrap();
+                let max = if v <= 3 { 2 } else { 1 };
+                for _ in 0..max {
+                    match chars.peek().and_then(|d| d.to_digit(8)) {
+                        Some(d) => {
+                            v = v * 8 + d;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                out.push(char::from_u32(v).unwrap_or('\u{FFFD}'));
+            }
+            _ => out.push(e),
+        }
+    }
+
+    out
+}
+
+fn hex(chars: &mut std::iter::Peekable<std::str::Chars<'_>>, len: usize) -> Option<u32> {
+    let rest = chars.clone();
+    let mut value = 0;
+    for _ in 0..len {
+        let Some(d) = chars.next().and_then(|c| c.to_digit(16)) else {
+            *chars = rest;
+            return None;
+        };
+        value = value * 16 + d;
+    }
+    Some(value)
+}
+
+/// Decodes the rest of a `\\u` escape: `XXXX`, a surrogate pair written as
+/// two `\\uXXXX` escapes, or `{X...}`.
+fn unicode_escape(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<char> {
+    let rest = chars.clone();
+    if chars.next_if_eq(&'{').is_some() {
+        let mut value: u32 = 0;
+        let mut len = 0;
+        while let Some(d) = chars.peek().and_then(|c| c.to_digit(16)) {
+            value = value.saturating_mul(16).saturating_add(d);
+            len += 1;
+            chars.next();
+        }
+        if len == 0 || chars.next_if_eq(&'}').is_none() {
+            *chars = rest;
+            return None;
+        }
+        return Some(char::from_u32(value).unwrap_or('\u{FFFD}'));
+    }
+
+    let high = hex(chars, 4)?;
+    if (0xD800..0xDC00).contains(&high) {
+        let rest = chars.clone();
+        if chars.next() == Some('\\')
+            && chars.next() == Some('u')
+            && let Some(low @ 0xDC00..0xE000) = hex(chars, 4)
+        {
+            let value = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+            return char::from_u32(value);
+        }
+        *chars = rest;
+    }
+    Some(char::from_u32(high).unwrap_or('\u{FFFD}'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner::Interner;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn test_token_buffer_matches_lexer() {
+        let source = "let x = 0x1f + 1_000 /* c */ + 'a';";
+        let tokens: Vec<Token> = Lexer::new(source).lex().filter_map(Result::ok).collect();
+        let (buffer, errors) = Lexer::new(source).tokenize(&Interner::new());
+
+        assert!(errors.is_empty());
+        assert_eq!(buffer.iter().collect::<Vec<_>>(), tokens);
+        assert_eq!(buffer.kind(3), TokenType::NumericLiteral);
+        assert_eq!(buffer.literal(3), Some(LiteralValue::Number(31.0)));
+        assert_eq!(buffer.literal(5), Some(LiteralValue::Number(1000.0)));
+        assert_eq!(buffer.literal(4), None);
+    }
+
+    #[test]
+    fn test_numeric_value() {
+        let value = |text: &str| {
+            let (buffer, _) = Lexer::new(text).tokenize(&Interner::new());
+            match buffer.literal(0) {
+                Some(LiteralValue::Number(n)) => n,
+                _ => f64::NAN,
+            }
+        };
+
+        assert_eq!(value("0b101"), 5.0);
+        assert_eq!(value("0o17"), 15.0);
+        assert_eq!(value("0XFF"), 255.0);
+        assert_eq!(value("0777"), 511.0);
+        assert_eq!(value("0888"), 888.0);
+        assert_eq!(value("1.5e3"), 1500.0);
+        assert_eq!(value(".5"), 0.5);
+        assert_eq!(value("1_000.000_1"), 1000.0001);
+    }
+
+    #[test]
+    fn test_token_buffer_interns_names_and_strings() {
+        let interner = Interner::new();
+        let source = r#"foo \u0066oo f\u{6f}o 'foo' "f\x6f\157" #foo bar"#;
+        let (buffer, errors) = Lexer::new(source).tokenize(&interner);
+
+        assert!(errors.is_empty());
+        let foo = interner.get("foo").unwrap();
+        let values: Vec<_> = (0..buffer.len())
+            .filter_map(|i| buffer.literal(i))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                LiteralValue::Identifier(foo),
+                LiteralValue::Identifier(foo),
+                LiteralValue::Identifier(foo),
+                LiteralValue::String(foo),
+                LiteralValue::String(foo),
+                LiteralValue::Identifier(interner.get("#foo").unwrap()),
+                LiteralValue::Identifier(interner.get("bar").unwrap()),
+            ]
+        );
+        assert_eq!(interner.len(), 3);
+    }
+
+    #[test]
+    fn test_string_value() {
+        assert_eq!(string_value("plain"), "plain");
+        assert_eq!(string_value(r"a\nb\tc\\"), "a\nb\tc\\");
+        assert_eq!(string_value(r"\0\x41\u0042\u{43}"), "\0ABC");
+        assert_eq!(string_value(r"\uD83D\uDE00"), "😀");
+        assert_eq!(string_value(r"\uD83D"), "\u{FFFD}");
+        assert_eq!(string_value("a\\\nb"), "ab");
+        assert_eq!(string_value(r"\101\'\q"), "A'q");
+        assert_eq!(string_value(r"\xZ\u{}"), r"\xZ\u{}");
+    }
+}
@@ -0,0 +1,82 @@
+//! Trivia tokens for the text the lexer skips between tokens, so that a
+//! token stream can cover its source exactly.
+
+use crate::token::{Token, TokenFlags, TokenType};
+
+/// Splits the text the lexer skipped between `start` and `end` into
+/// whitespace and line break trivia. A `#!` line is reported as a comment,
+/// and text the lexer rejected as `Unknown`.
+pub(crate) fn gap_trivia(text: &str, start: usize, end: usize, tokens: &mut Vec<Token>) {
+    let mut pos = start;
+    while pos < end {
+        let rest = &text[pos..end];
+        let len = if pos == 0 && rest.starts_with("#!") {
+            rest.find(['\n', '\r', '\u{2028}', '\u{2029}'])
+                .unwrap_or(rest.len())
+        } else if rest.starts_with("\r\n") {
+            2
+        } else {
+            let c = rest.chars().next().unwrap();
+            match c {
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => c.len_utf8(),
+                _ => rest
+                    .find(|d| is_line_break(d) || is_whitespace(d) != is_whitespace(c))
+                    .unwrap_or(rest.len()),
+            }
+        };
+
+        let first = rest.chars().next().unwrap();
+        let kind = if first == '#' && pos == 0 {
+            TokenType::SingleLineCommentTrivia
+        } else if is_line_break(first) {
+            TokenType::NewLineTrivia
+        } else if is_whitespace(first) {
+            TokenType::WhitespaceTrivia
+        } else {
+            TokenType::Unknown
+        };
+        tokens.push(Token::new(
+            kind,
+            TokenFlags::NONE,
+            pos as u32,
+            (pos + len) as u32,
+        ));
+        pos += len;
+    }
+}
+
+fn is_line_break(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_whitespace(c: char) -> bool {
+    (c.is_whitespace() && !is_line_break(c) && c != '\u{85}') || c == '\u{feff}'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_trivia() {
+        let text = "#!node\r\n \t\u{2028}@ ";
+        let mut tokens = Vec::new();
+        gap_trivia(text, 0, text.len(), &mut tokens);
+
+        let kinds: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.start, t.end))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (TokenType::SingleLineCommentTrivia, 0, 6),
+                (TokenType::NewLineTrivia, 6, 8),
+                (TokenType::WhitespaceTrivia, 8, 10),
+                (TokenType::NewLineTrivia, 10, 13),
+                (TokenType::Unknown, 13, 14),
+                (TokenType::WhitespaceTrivia, 14, 15),
+            ]
+        );
+    }
+}
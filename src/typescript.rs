@@ -0,0 +1,129 @@
+//! Token dumps in the format `scripts/ts-tokens.mjs` prints for TypeScript's
+//! own scanner, so that the two can be compared line by line.
+//!
+//! Each token is one tab-separated line: its `SyntaxKind` name, its start as
+//! a UTF-16 offset, its text as `JSON.stringify` writes it, and its
+//! `TokenFlags` as a number. Comments are left out, as with `skipTrivia`.
+//! Errors are printed the way TypeScript's scanner reports them: text the
+//! lexer rejects as `Unknown` tokens of one character each, and unterminated
+//! strings, templates and comments with the `Unterminated` flag.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::lexer::{LanguageVariant, Lexer};
+use crate::token::{TokenFlags, TokenType};
+
+/// The name of `kind` in TypeScript's `SyntaxKind`. `TokenType` follows
+/// typescript-go's `Kind` except for a few renamed punctuators.
+pub fn kind_name(kind: TokenType) -> Cow<'static, str> {
+    match kind {
+        TokenType::EndOfFile => "EndOfFileToken".into(),
+        TokenType::LeftBraceToken => "OpenBraceToken".into(),
+        TokenType::RightBraceToken => "CloseBraceToken".into(),
+        TokenType::LeftParenToken => "OpenParenToken".into(),
+        TokenType::RightParenToken => "CloseParenToken".into(),
+        TokenType::LeftBracketToken => "OpenBracketToken".into(),
+        TokenType::RightBracketToken => "CloseBracketToken".into(),
+        _ => format!("{:?}", kind).into(),
+    }
+}
+
+/// The tokens of `text`, one line each, without the `#` header line the
+/// reference script starts with.
+pub fn dump(text: &str, variant: LanguageVariant) -> String {
+    let mut lexer = Lexer::new(text);
+    lexer.set_language_variant(variant);
+    let (tokens, _) = lexer.lex_with_trivia();
+
+    let mut out = String::new();
+    // Tokens come in order, so UTF-16 offsets can be counted as we go.
+    let mut offset = 0;
+    let mut utf16_offset = 0;
+    let mut line = |kind: TokenType, start: usize, end: usize, flags: TokenFlags| {
+        utf16_offset += utf16_len(&text[offset..start]);
+        offset = start;
+        let _ = write!(out, "{}\t{}\t", kind_name(kind), utf16_offset);
+        write_json_string(&mut out, &text[start..end]);
+        let _ = writeln!(out, "\t{}", flags.bits());
+    };
+    for token in tokens {
+        let (start, end) = (token.start as usize, token.end as usize);
+        match token.token_type {
+            kind if kind.is_trivia() => {}
+            TokenType::Unknown => {
+                for (i, c) in text[start..end].char_indices() {
+                    line(
+                        TokenType::Unknown,
+                        start + i,
+                        start + i + c.len_utf8(),
+                        TokenFlags::NONE,
+                    );
+                }
+            }
+            kind => line(kind, start, end, token.flags),
+        }
+    }
+    out
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Appends `s` quoted and escaped exactly as `JSON.stringify` does.
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump() {
+        assert_eq!(
+            dump("é = {a: '\\x41\\f'} // c\n", LanguageVariant::Standard),
+            "Identifier\t0\t\"é\"\t0\n\
+             EqualsToken\t2\t\"=\"\t0\n\
+             OpenBraceToken\t4\t\"{\"\t0\n\
+             Identifier\t5\t\"a\"\t0\n\
+             ColonToken\t6\t\":\"\t0\n\
+             StringLiteral\t8\t\"'\\\\x41\\\\f'\"\t4096\n\
+             CloseBraceToken\t16\t\"}\"\t0\n"
+        );
+    }
+
+    #[test]
+    fn test_dump_errors() {
+        assert_eq!(
+            dump("a § 'b\n/* c", LanguageVariant::Standard),
+            "Identifier\t0\t\"a\"\t0\n\
+             Unknown\t2\t\"§\"\t0\n\
+             StringLiteral\t4\t\"'b\"\t4\n"
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        let mut out = String::new();
+        write_json_string(&mut out, "\u{8}\u{1f}\u{2028}");
+        assert_eq!(out, "\"\\b\\u001f\u{2028}\"");
+    }
+}
@@ -0,0 +1,446 @@
+//! The `rhi` binary, run as a subprocess.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn rhi(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rhi"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Commands that read files may exit without reading stdin.
+    if let Err(e) = child.stdin.take().unwrap().write_all(stdin.as_bytes()) {
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe, "{}", e);
+    }
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_tokens_text() {
+    let output = rhi(&["tokens", "-"], "let a = 0x1;\n");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        [
+            "<stdin>:1:1 LetKeyword 0..3 \"let\"",
+            "<stdin>:1:5 Identifier 4..5 \"a\"",
+            "<stdin>:1:7 EqualsToken 6..7 \"=\"",
+            "<stdin>:1:9 NumericLiteral 8..11 \"0x1\" [HexSpecifier]",
+            "<stdin>:1:12 SemicolonToken 11..12 \";\"",
+        ]
+    );
+}
+
+#[test]
+fn test_tokens_jsonl_with_trivia() {
+    let output = rhi(&["tokens", "--format=jsonl", "--trivia", "-"], "a /* b */");
+
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        [
+            r#"{"file":"<stdin>","kind":"Identifier","start":0,"end":1,"line":1,"column":1,"text":"a","flags":[]}"#,
+            r#"{"file":"<stdin>","kind":"WhitespaceTrivia","start":1,"end":2,"line":1,"column":2,"text":" ","flags":[]}"#,
+            r#"{"file":"<stdin>","kind":"MultiLineCommentTrivia","start":2,"end":9,"line":1,"column":3,"text":"/* b */","flags":[]}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_tokens_jsx_and_ts() {
+    let kinds = |args: &[&str]| {
+        let output = rhi(args, "x = <T>y");
+        stdout(&output)
+            .lines()
+            .map(|line| line.split(' ').nth(1).unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(kinds(&["tokens", "-"]).contains(&"JsxText".to_string()));
+    assert!(!kinds(&["tokens", "--ts", "-"]).contains(&"JsxText".to_string()));
+    assert!(kinds(&["tokens", "--ts", "--jsx", "-"]).contains(&"JsxText".to_string()));
+}
+
+#[test]
+fn test_tokens_exit_codes() {
+    let output = rhi(&["tokens", "--format", "json", "-"], "'a");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains(r#""code":1002"#));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error TS1002"));
+
+    assert_eq!(
+        rhi(&["tokens", "does-not-exist.js"], "").status.code(),
+        Some(2)
+    );
+    assert_eq!(rhi(&["tokens", "--bogus", "-"], "").status.code(), Some(2));
+    assert_eq!(rhi(&["frobnicate"], "").status.code(), Some(2));
+}
+
+#[test]
+fn test_tokens_json_after_unreadable_file() {
+    let dir = std::env::temp_dir().join("rhi-tokens-json");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let ok = dir.join("ok.js");
+    std::fs::write(&ok, "a").unwrap();
+
+    let missing = dir.join("missing.js");
+    let output = rhi(
+        &[
+            "tokens",
+            "--format",
+            "json",
+            missing.to_str().unwrap(),
+            ok.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let json = stdout(&output);
+    assert!(json.starts_with("[\n  {\n"), "{}", json);
+    assert_eq!(json.matches("\"file\"").count(), 1);
+    assert!(!json.contains(",\n  {"), "{}", json);
+}
+
+#[test]
+fn test_highlight_ansi() {
+    let text = "#!/usr/bin/env node\n/** @see x */\nconst a = <b c=\"d\">{`e${1}`}</b> ℮ /f/g;\n";
+    let output = rhi(&["highlight", "-"], text);
+    assert_eq!(output.status.code(), Some(0));
+
+    let ansi = stdout(&output);
+    assert!(ansi.contains("\x1b[35mconst\x1b[0m"));
+    let mut stripped = String::new();
+    let mut rest = ansi;
+    while let Some(i) = rest.find('\x1b') {
+        stripped.push_str(&rest[..i]);
+        rest = &rest[i + rest[i..].find('m').unwrap() + 1..];
+    }
+    stripped.push_str(rest);
+    assert_eq!(stripped, text);
+}
+
+#[test]
+fn test_highlight_html() {
+    let output = rhi(&["highlight", "--format=html", "-"], "a && <b/>");
+    assert_eq!(
+        stdout(&output),
+        "<pre class=\"rhi\"><code>a <span class=\"rhi-punctuation\">&amp;&amp;</span> \
+         <span class=\"rhi-punctuation\">&lt;</span><span class=\"rhi-jsx-tag\">b</span>\
+         <span class=\"rhi-punctuation\">/&gt;</span></code></pre>\n"
+    );
+
+    let output = rhi(&["highlight", "--format=html", "--standalone", "-"], "");
+    assert!(stdout(&output).contains(".rhi-keyword { color: #a626a4 }"));
+
+    assert_eq!(
+        rhi(&["highlight", "--theme", "does-not-exist", "-"], "")
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_lsp() {
+    let frame = |message: &str| format!("Content-Length: {}\r\n\r\n{}", message.len(), message);
+    let initialize = frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#);
+    let shutdown = frame(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#);
+    let exit = frame(r#"{"jsonrpc":"2.0","method":"exit"}"#);
+
+    let output = rhi(
+        &["lsp", "--stdio"],
+        &format!("{}{}{}", initialize, shutdown, exit),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains(r#""serverInfo":{"name":"rhi""#));
+    assert!(stdout(&output).ends_with(r#"{"jsonrpc":"2.0","id":2,"result":null}"#));
+
+    let output = rhi(&["lsp"], &format!("{}{}", initialize, exit));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(rhi(&["lsp", "--tcp"], "").status.code(), Some(2));
+}
+
+#[test]
+fn test_grammar_lint() {
+    let output = rhi(
+        &["grammar", "lint", "-"],
+        "A = B | \"c\" | \"c\" ;\nD = A ;\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        [
+            "<stdin>:1:5: error: 'B' is not defined [undefined]",
+            "<stdin>:1:15: warning: duplicate alternative in 'A' [duplicate-alternative]",
+            "<stdin>:1:9: note: first listed here",
+            "<stdin>:2:1: warning: 'D' is never used [unused]",
+        ]
+    );
+    let output = rhi(&["grammar", "lint", "--start", "D", "-"], "D = \"d\" ;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let output = rhi(&["grammar", "lint", "-"], "A = ( \"a\" ;");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("<stdin>:1:"));
+    assert!(stdout(&output).contains(": error: "));
+
+    assert_eq!(
+        rhi(&["grammar", "lint", "--allow", "bogus", "-"], "")
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(rhi(&["grammar", "frobnicate"], "").status.code(), Some(2));
+}
+
+#[test]
+fn test_grammar_generate() {
+    let grammar = "S = \"a\", { B } ;\nB = \"b\" | \"c\" ;\n";
+    let output = rhi(
+        &["grammar", "generate", "--count=20", "--seed=3", "-"],
+        grammar,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let lines: Vec<_> = stdout(&output).lines().map(str::to_string).collect();
+    assert_eq!(lines.len(), 20);
+    assert!(lines.iter().all(|line| line.starts_with('a')));
+    assert!(lines.iter().any(|line| line.contains(" b")));
+    let again = rhi(
+        &["grammar", "generate", "--count=20", "--seed=3", "-"],
+        grammar,
+    );
+    assert_eq!(stdout(&again), stdout(&output));
+
+    let output = rhi(
+        &[
+            "grammar",
+            "generate",
+            "--count=20",
+            "--weight",
+            "B=0",
+            "--value",
+            "S=x",
+            "--value",
+            "S=y",
+            "-",
+        ],
+        grammar,
+    );
+    assert!(
+        stdout(&output)
+            .lines()
+            .all(|line| line == "x" || line == "y")
+    );
+    let output = rhi(
+        &["grammar", "generate", "--start=B", "--weight=c=0", "-"],
+        grammar,
+    );
+    assert_eq!(stdout(&output), "b\n");
+
+    let output = rhi(&["grammar", "generate", "--start", "X", "-"], grammar);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'X' is not defined"));
+    assert_eq!(
+        rhi(&["grammar", "generate", "--count=many", "-"], grammar)
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_grammar_convert() {
+    let iso = "(* Digits *)\nA = { \"0\" | B }- ;\nB = ? U+0031 ? ;\n";
+    let output = rhi(&["grammar", "convert", "--to", "w3c", "-"], iso);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "(* Digits *)\nA ::= (\"0\" | B)+\nB ::= #x0031\n"
+    );
+    let output = rhi(
+        &["grammar", "convert", "--from=w3c", "--to=iso", "-"],
+        stdout(&output),
+    );
+    assert_eq!(stdout(&output), iso);
+
+    let output = rhi(
+        &["grammar", "convert", "--to", "w3c", "-"],
+        "A = b, ? c ? ;",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("<stdin>:1:8: error: "));
+    assert_eq!(
+        rhi(&["grammar", "convert", "-"], iso).status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        rhi(&["grammar", "convert", "--to=yaml", "-"], iso)
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_grammar_diagram() {
+    let grammar = "A = \"a\", [ B ] ;\nB = \"b\" | \"c\" ;\n";
+    let output = rhi(&["grammar", "diagram", "--production=A", "-"], grammar);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("<svg "));
+    assert!(stdout(&output).contains("<a href=\"B.svg\">"));
+
+    let dir = std::env::temp_dir().join("rhi-grammar-diagram");
+    let _ = std::fs::remove_dir_all(&dir);
+    let output = rhi(
+        &["grammar", "diagram", "--output", dir.to_str().unwrap(), "-"],
+        grammar,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["A.svg", "B.svg"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        rhi(&["grammar", "diagram", "-"], grammar).status.code(),
+        Some(2)
+    );
+    assert_eq!(
+        rhi(&["grammar", "diagram", "--production=C", "-"], grammar)
+            .status
+            .code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_grammar_coverage() {
+    let dir = std::env::temp_dir().join("rhi-grammar-coverage");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let grammar = dir.join("grammar.ebnf");
+    std::fs::write(
+        &grammar,
+        "IdentifierName = Letter, { Letter } ;\nLetter = \"a\" | \"b\" ;\n\
+         Punctuator = \";\" ;\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("src/a.js"), "ab;").unwrap();
+    std::fs::write(dir.join("src/b.ts"), "c;").unwrap();
+    std::fs::write(dir.join("src/notes.txt"), "b").unwrap();
+    let html = dir.join("coverage.html");
+
+    let path = |path: &std::path::Path| path.to_str().unwrap().to_string();
+    let output = rhi(
+        &[
+            "grammar",
+            "coverage",
+            "--grammar",
+            &path(&grammar),
+            "--html",
+            &path(&html),
+            &path(&dir.join("src")),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let name = path(&grammar);
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        [
+            format!("{}: 3 of 3 productions used, 2 of 2 alternatives", name),
+            format!(
+                "{}:1:1: warning: 'IdentifierName' does not match what the lexer \
+                 matched as it 1 time, in Identifier tokens, such as \"c\"",
+                name
+            ),
+        ]
+    );
+    let html = std::fs::read_to_string(&html).unwrap();
+    assert!(html.contains("<td>3 / 3 (100%)</td>"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let output = rhi(&["grammar", "coverage", "--grammar", &name, "-"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let output = rhi(&["grammar", "coverage", "-"], "a");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// The defects in the bundled grammars that the lint was written to find.
+#[test]
+fn test_grammar_lint_bundled() {
+    let docs = concat!(env!("CARGO_MANIFEST_DIR"), "/docs/");
+    let path = |name: &str| format!("{}{}", docs, name);
+
+    let output = rhi(&["grammar", "lint", &path("grammar.ebnf")], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("error: the grammar has no productions [empty-grammar]"));
+
+    let js = path("grammar-js.ebnf");
+    let output = rhi(
+        &[
+            "grammar",
+            "lint",
+            "--allow=left-recursion",
+            "--allow=unused",
+            "--compare",
+            &path("grammar_iso.ebnf"),
+            &js,
+        ],
+        "",
+    );
+    let lines: Vec<_> = stdout(&output)
+        .lines()
+        .filter_map(|line| line.strip_prefix(js.as_str()))
+        .collect();
+    let duplicates: Vec<_> = lines
+        .iter()
+        .filter(|line| {
+            line.ends_with("duplicate alternative in 'Punctuator' [duplicate-alternative]")
+        })
+        .collect();
+    assert_eq!(duplicates.len(), 2);
+    assert!(
+        lines
+            .iter()
+            .any(|line| line
+                .starts_with(":44:1: warning: 'MultiLineComment' is defined differently"))
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.contains("'Punctuator' is defined differently"))
+    );
+
+    // The W3C grammar is generated from the ISO one.
+    let output = rhi(
+        &[
+            "grammar",
+            "lint",
+            "--allow=left-recursion",
+            "--allow=unused",
+            "--allow=duplicate-production",
+            "--compare",
+            &path("grammar_iso.ebnf"),
+            &path("grammar_w3c.ebnf"),
+        ],
+        "",
+    );
+    let mismatches: Vec<_> = stdout(&output)
+        .lines()
+        .filter(|line| line.ends_with("[mismatch]"))
+        .collect();
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
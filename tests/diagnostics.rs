@@ -0,0 +1,29 @@
+//! Reporting lexer errors through the public API only.
+
+use rhi::{ColumnEncoding, Lexer, Position, RenderMode, Renderer, SourceFile};
+
+#[test]
+fn test_error_renders_against_source_file() {
+    let source = SourceFile::new("main.ts", "let a;\nlet b = #;\n");
+    let error = Lexer::new(source.source())
+        .lex()
+        .find_map(Result::err)
+        .unwrap();
+
+    assert_eq!(error.code(), 1127);
+    assert_eq!(
+        source.position(error.pos() as usize, ColumnEncoding::Utf16),
+        Position { line: 2, column: 9 }
+    );
+
+    let diagnostic = error.to_diagnostic(source.source());
+    assert_eq!(
+        source.render(&Renderer::new().mode(RenderMode::Plain), &diagnostic),
+        "main.ts:2:9: error TS1127: Invalid character.\n"
+    );
+    assert!(
+        source
+            .render(&Renderer::new(), &diagnostic)
+            .contains("2 | let b = #;\n")
+    );
+}
@@ -0,0 +1,518 @@
+//! The lexer's punctuators and keywords, checked against the ones the
+//! grammars in `docs/` spell out, in both directions: every terminal of the
+//! grammar lexes as one token of the expected kind, and every punctuator and
+//! keyword the lexer produces appears in the grammar. Programs generated
+//! at random from the grammars lex without errors, and the tokens of the
+//! test corpus match the lexical productions for their kinds.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rhi::ebnf::coverage::Coverage;
+use rhi::ebnf::generate::Generator;
+use rhi::ebnf::{Dialect, ExprKind, Grammar};
+use rhi::{LanguageVariant, Lexer, LexerError, Token, TokenType};
+
+const ISO: &str = include_str!("../docs/grammar_iso.ebnf");
+const JS: &str = include_str!("../docs/grammar-js.ebnf");
+const TS: &str = include_str!("../docs/grammar-ts.ebnf");
+const KEYWORDS: &str = include_str!("../src/keywords.txt");
+
+/// The productions of the lexical grammar that list whole tokens.
+const TOKEN_PRODUCTIONS: &[&str] = &[
+    "Punctuator",
+    "DivPunctuator",
+    "RightBracePunctuator",
+    "ReservedWord",
+];
+
+/// Where the syntactic grammar starts in `grammar_iso.ebnf`; its terminals
+/// are tokens too, such as `?.` and the contextual keywords.
+const SYNTACTIC_GRAMMAR: &str = "=== SYNTACTIC GRAMMAR ===";
+
+/// Terminals the lexer does not produce on their own, with the text it
+/// scans them in and the single token it makes of that.
+const COMBINED: &[(&str, &str, TokenType)] = &[
+    // `PrivateIdentifier = "#", IdentifierName`.
+    ("#", "#name", TokenType::PrivateIdentifier),
+];
+
+/// TypeScript keywords that neither grammar uses yet. Each one that a
+/// grammar gains must be removed from here.
+const MISSING_KEYWORDS: &[&str] = &[
+    "accessor",
+    "any",
+    "assert",
+    "asserts",
+    "bigint",
+    "boolean",
+    "immediate",
+    "intrinsic",
+    "never",
+    "object",
+    "out",
+    "require",
+    "satisfies",
+    "unknown",
+    "using",
+];
+
+/// Punctuators the lexer produces that are missing from the grammar's
+/// `Punctuator` productions, although its syntactic grammar, or the
+/// TypeScript grammar, uses them.
+#[rustfmt::skip]
+const MISSING_PUNCTUATORS: &[&str] = &[
+    // `OptionalChain = "?.", ...`; the lexical grammar has no
+    // `OptionalChainingPunctuator`.
+    "?.",
+    // Decorators, which only the TypeScript grammar has.
+    "@",
+];
+
+/// Programs generated from each start symbol.
+const PROGRAMS: usize = 200;
+
+/// What templates are made of, as the grammar's `TemplateCharacter` lets
+/// a `$` be followed by `{`.
+const TEMPLATE_CHARACTERS: &[&str] = &[
+    "a",
+    "中",
+    "😀",
+    " ",
+    "$",
+    "\\`",
+    "\\${",
+    "\\x41",
+    "\\u{2028}",
+    "\\03",
+    "\\\n",
+    "\n",
+    "\r\n",
+    "\u{2028}",
+];
+
+/// Regular expressions for generated programs, where only a parser knows
+/// whether a `/` starts one; these lex without errors either way.
+const REGULAR_EXPRESSIONS: &[&str] = &["/a/g", "/[/]+/", "/(a|b)*?/iu", "/x{2,}$/m", "/é+/v"];
+
+/// The characters punctuators are made of.
+const PUNCTUATION: &str = "{}()[].;,<>=!+-*/%&|^~?:@#`";
+
+fn parse(text: &str) -> Grammar {
+    Grammar::parse(text, Dialect::Iso).unwrap()
+}
+
+/// The terminals of `names` and of every production they refer to.
+fn token_terminals(grammar: &Grammar, names: &[&str]) -> BTreeSet<String> {
+    let mut terminals = BTreeSet::new();
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut pending: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    while let Some(name) = pending.pop() {
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        let production = grammar
+            .production(&name)
+            .unwrap_or_else(|| panic!("'{}' is not defined", name));
+        production.expr.walk(&mut |expr| match &expr.kind {
+            ExprKind::Terminal(text) => {
+                terminals.insert(text.clone());
+            }
+            ExprKind::Nonterminal(name) => pending.push(name.clone()),
+            _ => {}
+        });
+    }
+    terminals
+}
+
+/// The terminals of every production that starts at or after `offset`.
+fn terminals_after(grammar: &Grammar, offset: usize) -> BTreeSet<String> {
+    let mut terminals = BTreeSet::new();
+    for production in grammar
+        .productions
+        .iter()
+        .filter(|p| p.span.start >= offset)
+    {
+        production.expr.walk(&mut |expr| {
+            if let ExprKind::Terminal(text) = &expr.kind {
+                terminals.insert(text.clone());
+            }
+        });
+    }
+    terminals
+}
+
+fn syntactic_terminals(grammar: &Grammar) -> BTreeSet<String> {
+    let marker = grammar
+        .comments
+        .iter()
+        .find(|comment| comment.text == SYNTACTIC_GRAMMAR)
+        .expect("no syntactic grammar marker");
+    terminals_after(grammar, marker.span.end)
+}
+
+/// The kind of the single token `text` lexes as, or `None` if it is not
+/// exactly one token.
+fn single_token(text: &str) -> Option<TokenType> {
+    let tokens: Vec<_> = Lexer::new(text).lex().collect();
+    match &tokens[..] {
+        [Ok(token)] if token.start == 0 && token.end as usize == text.len() => {
+            Some(token.token_type)
+        }
+        _ => None,
+    }
+}
+
+/// Spelling and `TokenType` name of every keyword the lexer knows.
+fn keywords() -> Vec<(&'static str, &'static str)> {
+    KEYWORDS
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next().unwrap(), fields.next().unwrap())
+        })
+        .collect()
+}
+
+fn is_word(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+}
+
+#[test]
+fn test_grammar_terminals_lex_as_single_tokens() {
+    let grammar = parse(ISO);
+    let reserved = token_terminals(&grammar, &["ReservedWord"]);
+    let mut terminals = token_terminals(&grammar, TOKEN_PRODUCTIONS);
+    terminals.extend(syntactic_terminals(&grammar));
+    let keywords: BTreeMap<_, _> = keywords().into_iter().collect();
+
+    let mut failures = Vec::new();
+    let mut punctuators: BTreeMap<TokenType, String> = BTreeMap::new();
+    for terminal in &terminals {
+        if let Some(&(_, text, expected)) = COMBINED.iter().find(|(t, ..)| t == terminal) {
+            if single_token(text) != Some(expected) {
+                failures.push(format!("{:?} does not lex as {:?}", text, expected));
+            }
+            continue;
+        }
+        let kind = single_token(terminal);
+        if is_word(terminal) {
+            let expected = keywords
+                .get(terminal.as_str())
+                .copied()
+                .unwrap_or("Identifier");
+            if kind.map(|kind| format!("{:?}", kind)).as_deref() != Some(expected) {
+                failures.push(format!(
+                    "{:?} lexes as {:?}, not {}",
+                    terminal, kind, expected
+                ));
+            } else if reserved.contains(terminal) && !kind.is_some_and(TokenType::is_keyword) {
+                failures.push(format!("reserved word {:?} is not a keyword", terminal));
+            }
+            continue;
+        }
+        match kind {
+            Some(kind) if kind.is_punctuation() => {
+                if let Some(other) = punctuators.insert(kind, terminal.clone()) {
+                    failures.push(format!(
+                        "{:?} and {:?} both lex as {:?}",
+                        other, terminal, kind
+                    ));
+                }
+            }
+            _ => failures.push(format!(
+                "{:?} lexes as {:?}, not one punctuator",
+                terminal, kind
+            )),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_lexer_punctuators_are_in_grammar() {
+    let grammar = parse(ISO);
+    let terminals = token_terminals(&grammar, TOKEN_PRODUCTIONS);
+    let mut used = syntactic_terminals(&grammar);
+    used.extend(terminals_after(&parse(TS), 0));
+
+    // Every string of up to four punctuation characters the lexer takes as
+    // a single punctuator.
+    let chars: Vec<char> = PUNCTUATION.chars().collect();
+    let mut texts = vec![String::new()];
+    let mut missing = Vec::new();
+    let mut found = 0;
+    for _ in 0..4 {
+        let mut longer = Vec::new();
+        for text in &texts {
+            for &c in &chars {
+                let text = format!("{}{}", text, c);
+                if let Some(kind) = single_token(&text)
+                    && kind.is_punctuation()
+                {
+                    found += 1;
+                    let allowed = MISSING_PUNCTUATORS.contains(&text.as_str());
+                    if terminals.contains(&text) == allowed {
+                        missing.push(format!("{:?} ({:?})", text, kind));
+                    } else if allowed && !used.contains(&text) {
+                        missing.push(format!(
+                            "{:?} ({:?}), even outside `Punctuator`",
+                            text, kind
+                        ));
+                    }
+                }
+                longer.push(text);
+            }
+        }
+        texts = longer;
+    }
+    assert!(found >= 50, "only {} punctuators found", found);
+    assert!(
+        missing.is_empty(),
+        "not in `Punctuator`, or in it but listed in MISSING_PUNCTUATORS: {}",
+        missing.join(", ")
+    );
+}
+
+#[test]
+fn test_lexer_keywords_are_in_grammar() {
+    let iso = parse(ISO);
+    let reserved = token_terminals(&iso, &["ReservedWord"]);
+    let mut terminals = terminals_after(&iso, 0);
+    terminals.extend(terminals_after(&parse(TS), 0));
+
+    let mut failures = Vec::new();
+    for (text, name) in keywords() {
+        let Some(kind) = single_token(text).filter(|kind| format!("{:?}", kind) == name) else {
+            failures.push(format!("{:?} does not lex as {}", text, name));
+            continue;
+        };
+        let allowed = MISSING_KEYWORDS.contains(&text);
+        let known = if kind.is_contextual_keyword() {
+            terminals.contains(text)
+        } else {
+            reserved.contains(text)
+        };
+        if known == allowed {
+            failures.push(if known {
+                format!(
+                    "{:?} is in a grammar now; remove it from MISSING_KEYWORDS",
+                    text
+                )
+            } else {
+                format!("{:?} ({}) is not in the grammar", text, name)
+            });
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// A generator for the grammars, held to what the grammar text alone does
+/// not say.
+fn generator<'g>(grammars: &[&'g Grammar], seed: u64) -> Generator<'g> {
+    Generator::new(grammars)
+        .seed(seed)
+        // Listed in the syntactic grammar, but one token.
+        .lexical("PrivateIdentifier")
+        // Escapes in identifiers must still spell identifier characters.
+        .values(
+            "UnicodeEscapeSequence",
+            &[
+                "u0041", "u00e9", "u03c0", "u005f", "u{61}", "u{2135}", "u{10400}",
+            ],
+        )
+        .values("TemplateCharacter", TEMPLATE_CHARACTERS)
+        // A `///` comment ends at a line break, which its string may hold.
+        .weight("TripleSlashDirective", 0)
+}
+
+/// The first error lexing `text`. `yield`, `await` and `of` may also be
+/// names, as in `yield /= 2`, where only a parser can tell that the `/`
+/// divides. So if an error follows a regular expression after one of them,
+/// that word is read as a name instead and the whole text is lexed again.
+fn lex_error(text: &str) -> Option<LexerError> {
+    let mut lexer = Lexer::new(text);
+    lexer.set_language_variant(LanguageVariant::Standard);
+    let mut previous: Option<Token> = None;
+    let mut ambiguous: Option<Token> = None;
+    for token in lexer.lex() {
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                let Some(name) = ambiguous else {
+                    return Some(e);
+                };
+                // A name of the same length keeps every offset in place.
+                let mut text = text.to_string();
+                let (start, end) = (name.start as usize, name.end as usize);
+                text.replace_range(start..end, &"_".repeat(end - start));
+                return lex_error(&text);
+            }
+        };
+        if token.token_type == TokenType::RegularExpressionLiteral
+            && let Some(previous) = previous
+            && matches!(
+                previous.token_type,
+                TokenType::YieldKeyword | TokenType::AwaitKeyword | TokenType::OfKeyword
+            )
+        {
+            ambiguous = Some(previous);
+        }
+        previous = Some(token);
+    }
+    None
+}
+
+#[test]
+fn test_lex_error_reads_keywords_as_names() {
+    // Read as a regular expression, `/ "a/` leaves `b"` unterminated.
+    assert!(lex_error("yield / \"a/b\";").is_none());
+    // Errors after the ambiguous `/` are still found.
+    assert!(lex_error("yield / \"a/b\"; 'c").is_some());
+    assert!(lex_error("'c").is_some());
+}
+
+#[test]
+fn test_generated_programs_lex() {
+    let iso = parse(ISO);
+    let ts = parse(TS);
+    let mut failures = Vec::new();
+    for (grammars, start) in [
+        (vec![&iso], "Script"),
+        (vec![&iso], "Module"),
+        (vec![&ts, &iso], "SourceFile"),
+    ] {
+        let mut generator =
+            generator(&grammars, 1).values("RegularExpressionLiteral", REGULAR_EXPRESSIONS);
+        for i in 0..PROGRAMS {
+            let text = generator.generate(start).unwrap();
+            if let Some(e) = lex_error(&text) {
+                failures.push(format!("{} #{}: {}\n  {:?}", start, i, e, text));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_generated_regular_expressions_lex() {
+    let iso = parse(ISO);
+    let mut generator = generator(&[&iso], 1);
+    let mut failures = Vec::new();
+    for _ in 0..PROGRAMS * 5 {
+        let text = generator.generate("RegularExpressionLiteral").unwrap();
+        if single_token(&text) != Some(TokenType::RegularExpressionLiteral) {
+            failures.push(format!("{:?}", text));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "not one regular expression: {}",
+        failures.join(", ")
+    );
+}
+
+/// Text of the corpus that the lexer matched as a production that the
+/// production in `grammar-js.ebnf` (0) or `grammar-ts.ebnf` (1) does not
+/// match. Each one fixed in a grammar must be removed from here.
+const KNOWN_MISMATCHES: &[(usize, &str, TokenType)] = &[
+    // `v\u0061r`: the grammar's `"\"` before `UnicodeEscapeSequence` is lost,
+    // and TypeScript's `Identifier` has no escapes.
+    (0, "IdentifierName", TokenType::Identifier),
+    (0, "Identifier", TokenType::Identifier),
+    (1, "Identifier", TokenType::Identifier),
+    // Legacy octal `017`, and numeric separators in BigInts.
+    (0, "NumericLiteral", TokenType::NumericLiteral),
+    (1, "NumericLiteral", TokenType::NumericLiteral),
+    (0, "NumericLiteral", TokenType::BigIntLiteral),
+    (1, "NumericLiteral", TokenType::BigIntLiteral),
+    (0, "BigIntLiteral", TokenType::BigIntLiteral),
+    (0, "DecimalBigIntegerLiteral", TokenType::BigIntLiteral),
+    // `089`, which is decimal because of its `8`.
+    (0, "DecimalLiteral", TokenType::NumericLiteral),
+    // Escapes such as `\x41`.
+    (1, "StringLiteral", TokenType::StringLiteral),
+    // The `"\"` that starts each is lost.
+    (0, "LineContinuation", TokenType::StringLiteral),
+    (
+        0,
+        "RegularExpressionBackslashSequence",
+        TokenType::RegularExpressionLiteral,
+    ),
+];
+
+/// The JavaScript and TypeScript files under `dir`.
+fn corpus_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            corpus_files(&entry, files);
+        } else if matches!(
+            entry.extension().and_then(|e| e.to_str()),
+            Some("js" | "jsx" | "ts" | "tsx")
+        ) {
+            files.push(entry);
+        }
+    }
+}
+
+#[test]
+fn test_corpus_coverage() {
+    let js = parse(JS);
+    let ts = parse(TS);
+    let mut coverage = Coverage::new(&[&js, &ts]);
+    let mut files = Vec::new();
+    corpus_files(
+        std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests")),
+        &mut files,
+    );
+    assert!(!files.is_empty());
+    for file in &files {
+        let text = std::fs::read_to_string(file).unwrap();
+        let jsx = matches!(
+            file.extension().and_then(|e| e.to_str()),
+            Some("js" | "jsx" | "tsx")
+        );
+        let variant = if jsx {
+            LanguageVariant::Jsx
+        } else {
+            LanguageVariant::Standard
+        };
+        coverage.record_tokens(&text, variant);
+    }
+
+    let mismatches: BTreeSet<(usize, &str, TokenType)> = coverage
+        .mismatches()
+        .iter()
+        .map(|m| (m.grammar, m.production.as_str(), m.token_type))
+        .collect();
+    let known: BTreeSet<(usize, &str, TokenType)> = KNOWN_MISMATCHES.iter().copied().collect();
+    assert_eq!(mismatches, known);
+
+    let used: BTreeSet<(usize, &str)> = coverage
+        .productions()
+        .iter()
+        .filter(|p| p.uses > 0)
+        .map(|p| (p.grammar, p.production.name.as_str()))
+        .collect();
+    for name in [
+        "Comment",
+        "WhiteSpace",
+        "LineTerminatorSequence",
+        "IdentifierName",
+        "ReservedWord",
+        "Punctuator",
+        "NumericLiteral",
+        "StringLiteral",
+        "Template",
+        "TemplateHead",
+    ] {
+        assert!(used.contains(&(0, name)), "'{}' is never used", name);
+    }
+    // Nothing records the syntactic grammar yet.
+    assert!(!used.contains(&(0, "Statement")));
+}
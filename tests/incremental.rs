@@ -0,0 +1,30 @@
+//! Incremental and streaming lexing through the public API only.
+
+use rhi::{Lexer, StreamingLexer, TextEdit, Token, relex};
+
+fn lex(text: &str) -> Vec<Token> {
+    Lexer::new(text).lex().filter_map(Result::ok).collect()
+}
+
+#[test]
+fn test_relex_then_apply() {
+    let text = "let a = 1;\nlet b = `x${c}y`;\n";
+    let edit = TextEdit::new(14..15, "total");
+    let mut tokens = lex(text);
+
+    let delta = relex(&tokens, text, &edit);
+    delta.apply(&mut tokens);
+
+    assert_eq!(tokens, lex(&edit.apply(text)));
+    assert!(delta.tokens.len() < tokens.len());
+}
+
+#[test]
+fn test_streaming_matches_lexer() {
+    let text = include_str!("../benches/corpus/emitter.js");
+    let streamed: Vec<_> = StreamingLexer::with_chunk_size(text.as_bytes(), 100)
+        .map(|t| t.unwrap().token)
+        .collect();
+
+    assert_eq!(streamed, lex(text));
+}
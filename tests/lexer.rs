@@ -0,0 +1,128 @@
+//! Lexing through the public API only.
+
+use rhi::{Lexer, LexerError, ScanMode, SourceFile, Token, TokenFlags, TokenType};
+
+fn lex(text: &str) -> Vec<Token> {
+    Lexer::new(text).lex().map(Result::unwrap).collect()
+}
+
+fn kinds(text: &str) -> Vec<TokenType> {
+    lex(text).into_iter().map(|t| t.token_type).collect()
+}
+
+#[test]
+fn test_lex_statement() {
+    let source = SourceFile::new("main.ts", "const answer = 42; // done\n");
+    let tokens: Vec<_> = Lexer::new(source.source())
+        .lex()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let texts: Vec<_> = tokens.iter().map(|t| source.text(t)).collect();
+    assert_eq!(texts, ["const", "answer", "=", "42", ";", "// done"]);
+    assert_eq!(
+        tokens.iter().map(|t| t.token_type).collect::<Vec<_>>(),
+        [
+            TokenType::ConstKeyword,
+            TokenType::Identifier,
+            TokenType::EqualsToken,
+            TokenType::NumericLiteral,
+            TokenType::SemicolonToken,
+            TokenType::SingleLineCommentTrivia,
+        ]
+    );
+    assert_eq!((tokens[1].start, tokens[1].end), (6, 12));
+}
+
+#[test]
+fn test_lex_flags() {
+    let tokens = lex("a\n/** doc */ 0x1_0");
+
+    assert!(!tokens[0].has_preceding_line_break());
+    assert!(tokens[1].has_preceding_line_break());
+    let number = tokens[2];
+    assert!(number.flags.contains(TokenFlags::PRECEDING_JSDOC_COMMENT));
+    assert!(number.flags.contains(TokenFlags::HEX_SPECIFIER));
+    assert!(number.flags.contains(TokenFlags::CONTAINS_SEPARATOR));
+}
+
+#[test]
+fn test_lex_regex_context() {
+    assert_eq!(
+        kinds("a / b / c"),
+        [
+            TokenType::Identifier,
+            TokenType::SlashToken,
+            TokenType::Identifier,
+            TokenType::SlashToken,
+            TokenType::Identifier,
+        ]
+    );
+    assert_eq!(
+        kinds("return /b/g"),
+        [
+            TokenType::ReturnKeyword,
+            TokenType::RegularExpressionLiteral
+        ]
+    );
+}
+
+#[test]
+fn test_lex_templates() {
+    assert_eq!(
+        kinds("`a${ {b} }c`"),
+        [
+            TokenType::TemplateHead,
+            TokenType::LeftBraceToken,
+            TokenType::Identifier,
+            TokenType::RightBraceToken,
+            TokenType::TemplateTail,
+        ]
+    );
+}
+
+#[test]
+fn test_lex_errors_are_interleaved() {
+    let results: Vec<_> = Lexer::new("a # 'b").lex().collect();
+
+    assert!(matches!(
+        results[..],
+        [
+            Ok(_),
+            Err(LexerError::UnexpectedCharacter { pos: 2 }),
+            Err(LexerError::StringNotTerminated { pos: 4 }),
+            Ok(Token {
+                start: 4,
+                end: 6,
+                ..
+            }),
+        ]
+    ));
+}
+
+#[test]
+fn test_lex_is_fused() {
+    let mut lexer = Lexer::new("a");
+    let mut tokens = lexer.lex();
+
+    assert!(tokens.next().is_some());
+    assert!(tokens.next().is_none());
+    assert!(tokens.next().is_none());
+}
+
+#[test]
+fn test_scan_modes_agree() {
+    let text = include_str!("../benches/corpus/store.ts");
+    let scalar: Vec<_> = Lexer::with_scan_mode(text, ScanMode::Scalar)
+        .lex()
+        .map(|t| t.map_err(|e| e.to_string()))
+        .collect();
+
+    assert_eq!(
+        Lexer::new(text)
+            .lex()
+            .map(|t| t.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>(),
+        scalar
+    );
+}
@@ -0,0 +1,358 @@
+//! The language server, driven by a scripted client in the same process.
+
+use rhi::lsp::{Json, serve};
+
+/// Queues framed messages, then runs a session over them.
+struct Client {
+    input: Vec<u8>,
+    next_id: u32,
+}
+
+impl Client {
+    /// A client that has already sent `initialize` and `initialized`.
+    fn new() -> Self {
+        let mut client = Client {
+            input: Vec::new(),
+            next_id: 0,
+        };
+        client.request("initialize", r#"{"capabilities": {}}"#);
+        client.notify("initialized", "{}");
+        client
+    }
+
+    fn send(&mut self, message: &str) {
+        self.input
+            .extend(format!("Content-Length: {}\r\n\r\n{}", message.len(), message).bytes());
+    }
+
+    /// Sends a request, returning its id.
+    fn request(&mut self, method: &str, params: &str) -> u32 {
+        self.next_id += 1;
+        self.send(&format!(
+            r#"{{"jsonrpc": "2.0", "id": {}, "method": "{}", "params": {}}}"#,
+            self.next_id, method, params
+        ));
+        self.next_id
+    }
+
+    fn notify(&mut self, method: &str, params: &str) {
+        self.send(&format!(
+            r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}}}"#,
+            method, params
+        ));
+    }
+
+    fn open(&mut self, uri: &str, language: &str, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}", "languageId": "{}", "version": 1, "text": {}}}}}"#,
+                uri,
+                language,
+                Json::from(text)
+            ),
+        );
+    }
+
+    /// Replaces the text between two 0-based `(line, character)` positions.
+    fn change(&mut self, uri: &str, version: u32, start: (u32, u32), end: (u32, u32), text: &str) {
+        self.notify(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}", "version": {}}}, "contentChanges": [
+                    {{"range": {{"start": {{"line": {}, "character": {}}},
+                                 "end": {{"line": {}, "character": {}}}}},
+                      "text": {}}}]}}"#,
+                uri,
+                version,
+                start.0,
+                start.1,
+                end.0,
+                end.1,
+                Json::from(text)
+            ),
+        );
+    }
+
+    /// Shuts down and exits, then runs the session.
+    fn finish(mut self) -> Session {
+        self.request("shutdown", "null");
+        self.notify("exit", "null");
+        self.run()
+    }
+
+    fn run(self) -> Session {
+        let mut output = Vec::new();
+        let shut_down = serve(&self.input[..], &mut output).unwrap();
+        Session {
+            shut_down,
+            messages: parse_frames(std::str::from_utf8(&output).unwrap()),
+        }
+    }
+}
+
+struct Session {
+    shut_down: bool,
+    messages: Vec<Json>,
+}
+
+impl Session {
+    fn response(&self, id: u32) -> &Json {
+        self.messages
+            .iter()
+            .find(|m| m["id"] == Json::from(id))
+            .unwrap_or_else(|| panic!("no response to request {}", id))
+    }
+
+    fn result(&self, id: u32) -> &Json {
+        let response = self.response(id);
+        assert!(response["error"].is_null(), "{}", response);
+        &response["result"]
+    }
+
+    /// The params of each `publishDiagnostics` notification, in order.
+    fn diagnostics(&self) -> Vec<&Json> {
+        self.messages
+            .iter()
+            .filter(|m| m["method"].as_str() == Some("textDocument/publishDiagnostics"))
+            .map(|m| &m["params"])
+            .collect()
+    }
+}
+
+fn parse_frames(mut output: &str) -> Vec<Json> {
+    let mut messages = Vec::new();
+    while !output.is_empty() {
+        let (header, rest) = output.split_once("\r\n\r\n").unwrap();
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        messages.push(Json::parse(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    messages
+}
+
+/// `(code, start line, start character, end line, end character)` of each
+/// diagnostic.
+fn ranges(params: &Json) -> Vec<(u64, u64, u64, u64, u64)> {
+    let number = |json: &Json| json.as_u64().unwrap();
+    params["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            let range = &d["range"];
+            (
+                number(&d["code"]),
+                number(&range["start"]["line"]),
+                number(&range["start"]["character"]),
+                number(&range["end"]["line"]),
+                number(&range["end"]["character"]),
+            )
+        })
+        .collect()
+}
+
+fn numbers(json: &Json) -> Vec<u64> {
+    json.as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n.as_u64().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_initialize() {
+    let mut client = Client::new();
+    let hover = client.request("textDocument/hover", "{}");
+    let session = client.finish();
+
+    assert!(session.shut_down);
+    let capabilities = &session.result(1)["capabilities"];
+    assert_eq!(capabilities["positionEncoding"].as_str(), Some("utf-16"));
+    assert_eq!(
+        capabilities["semanticTokensProvider"]["legend"]["tokenTypes"],
+        Json::from(rhi::semantic_tokens::TOKEN_TYPES.to_vec())
+    );
+    assert_eq!(
+        session.response(hover)["error"]["code"],
+        Json::Number(-32601.0)
+    );
+}
+
+#[test]
+fn test_lifecycle_errors() {
+    let mut client = Client {
+        input: Vec::new(),
+        next_id: 0,
+    };
+    let early = client.request("textDocument/foldingRange", "{}");
+    client.send("{not json");
+    client.notify("exit", "null");
+    client.request("initialize", "{}");
+    let session = client.run();
+
+    // Exiting without a shutdown request is reported, and nothing after
+    // `exit` is read.
+    assert!(!session.shut_down);
+    assert_eq!(session.messages.len(), 2);
+    assert_eq!(
+        session.response(early)["error"]["code"],
+        Json::Number(-32002.0)
+    );
+    assert!(session.messages[1]["id"].is_null());
+    assert_eq!(session.messages[1]["error"]["code"], Json::Number(-32700.0));
+}
+
+#[test]
+fn test_diagnostics() {
+    let uri = "file:///a.js";
+    let mut client = Client::new();
+    // The unterminated string hides the unbalanced brackets.
+    client.open(uri, "javascript", "let s = '😀\nf(a];\n");
+    // Close the string: line 0, after the UTF-16 surrogate pair.
+    client.change(uri, 2, (0, 11), (0, 11), "';");
+    client.change(uri, 3, (1, 3), (1, 4), ")");
+    client.notify(
+        "textDocument/didClose",
+        &format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, uri),
+    );
+    let session = client.finish();
+
+    let diagnostics = session.diagnostics();
+    assert_eq!(diagnostics.len(), 4);
+    assert_eq!(ranges(diagnostics[0]), [(1002, 0, 8, 0, 9)]);
+    assert_eq!(diagnostics[0]["version"], Json::Number(1.0));
+    assert_eq!(
+        diagnostics[0]["diagnostics"].as_array().unwrap()[0]["message"].as_str(),
+        Some("Unterminated string literal.")
+    );
+    assert_eq!(ranges(diagnostics[1]), [(1128, 1, 3, 1, 4)]);
+    assert_eq!(diagnostics[1]["version"], Json::Number(2.0));
+    assert_eq!(ranges(diagnostics[2]), []);
+    assert_eq!(ranges(diagnostics[3]), []);
+}
+
+#[test]
+fn test_unclosed_bracket_related_information() {
+    let uri = "file:///b.ts";
+    let mut client = Client::new();
+    client.open(uri, "typescript", "function f() {\n  g(<T>x);\n");
+    let session = client.finish();
+
+    let params = session.diagnostics()[0];
+    assert_eq!(ranges(params), [(1005, 2, 0, 2, 0)]);
+    let related = &params["diagnostics"].as_array().unwrap()[0]["relatedInformation"];
+    let location = &related.as_array().unwrap()[0]["location"];
+    assert_eq!(location["uri"].as_str(), Some(uri));
+    assert_eq!(location["range"]["start"]["character"], Json::Number(13.0));
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let uri = "file:///c.js";
+    let mut client = Client::new();
+    client.open(uri, "javascript", "let a = 1;\n");
+    let full = client.request(
+        "textDocument/semanticTokens/full",
+        &format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, uri),
+    );
+    client.change(uri, 2, (1, 0), (1, 0), "a += 'b';\n");
+    let delta = client.request(
+        "textDocument/semanticTokens/full/delta",
+        &format!(
+            r#"{{"textDocument": {{"uri": "{}"}}, "previousResultId": "1"}}"#,
+            uri
+        ),
+    );
+    let stale = client.request(
+        "textDocument/semanticTokens/full/delta",
+        &format!(
+            r#"{{"textDocument": {{"uri": "{}"}}, "previousResultId": "1"}}"#,
+            uri
+        ),
+    );
+    let unknown = client.request(
+        "textDocument/semanticTokens/full",
+        r#"{"textDocument": {"uri": "file:///missing.js"}}"#,
+    );
+    let session = client.finish();
+
+    let full = session.result(full);
+    assert_eq!(full["resultId"].as_str(), Some("1"));
+    // `let`, `=`, `1`.
+    assert_eq!(
+        numbers(&full["data"]),
+        [0, 0, 3, 0, 0, 0, 6, 1, 5, 0, 0, 2, 1, 2, 0]
+    );
+
+    let delta = session.result(delta);
+    assert_eq!(delta["resultId"].as_str(), Some("2"));
+    let edits = delta["edits"].as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["start"], Json::Number(15.0));
+    assert_eq!(edits[0]["deleteCount"], Json::Number(0.0));
+    // `+=`, `'b'`.
+    assert_eq!(numbers(&edits[0]["data"]), [1, 2, 2, 5, 0, 0, 3, 3, 1, 0]);
+
+    // The previous result is now "2", so a full result comes back.
+    let stale = session.result(stale);
+    assert_eq!(stale["resultId"].as_str(), Some("3"));
+    assert_eq!(numbers(&stale["data"]).len(), 25);
+
+    assert_eq!(
+        session.response(unknown)["error"]["code"],
+        Json::Number(-32602.0)
+    );
+}
+
+#[test]
+fn test_folding_ranges_and_symbols() {
+    let uri = "file:///d.ts";
+    let text = "\
+namespace N {
+  export class C {
+    m() {
+      return 1;
+    }
+  }
+}
+";
+    let mut client = Client::new();
+    client.open(uri, "typescript", text);
+    let params = format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, uri);
+    let folding = client.request("textDocument/foldingRange", &params);
+    let symbols = client.request("textDocument/documentSymbol", &params);
+    let session = client.finish();
+
+    let folding: Vec<_> = session
+        .result(folding)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["startLine"].as_u64().unwrap(),
+                r["endLine"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(folding, [(0, 5), (1, 4), (2, 3)]);
+
+    let namespace = &session.result(symbols).as_array().unwrap()[0];
+    assert_eq!(namespace["name"].as_str(), Some("N"));
+    assert_eq!(namespace["kind"], Json::Number(3.0));
+    assert_eq!(namespace["range"]["end"]["line"], Json::Number(6.0));
+    let class = &namespace["children"].as_array().unwrap()[0];
+    assert_eq!(class["name"].as_str(), Some("C"));
+    assert_eq!(
+        class["selectionRange"]["start"],
+        Json::parse(r#"{"line": 1, "character": 15}"#).unwrap()
+    );
+    let method = &class["children"].as_array().unwrap()[0];
+    assert_eq!(method["name"].as_str(), Some("m"));
+    assert_eq!(method["kind"], Json::Number(6.0));
+}
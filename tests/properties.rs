@@ -0,0 +1,242 @@
+//! Properties the lexer must keep on any input: it never panics, every step
+//! consumes input, token spans are in order and in bounds, and with trivia
+//! they cover the source exactly, as do highlighted spans. Semantic tokens
+//! stay within their lines.
+//!
+//! Inputs are generated from a fixed seed, so failures reproduce. Inputs
+//! that once failed, from here or from `fuzz/`, are kept in
+//! `tests/regressions/` and checked on every run.
+
+use std::{fs, panic};
+
+use rhi::{Highlighter, LanguageVariant, Lexer, LineMap, ScanMode, TokenFlags, semantic_tokens};
+
+const CASES: usize = 3000;
+
+/// Pieces of JavaScript the generator strings together, chosen to reach the
+/// lexer's corner cases: unterminated literals, escapes, templates, regexes,
+/// JSX and non-ASCII text.
+const FRAGMENTS: &[&str] = &[
+    " ",
+    "\t",
+    "\n",
+    "\r\n",
+    "\r",
+    "\u{2028}",
+    "\u{a0}",
+    "\u{feff}",
+    "#!",
+    "#",
+    "a",
+    "é",
+    "𝑥",
+    "_$",
+    "let",
+    "await",
+    "yield",
+    "0",
+    "1",
+    "09",
+    "0x",
+    "0b1",
+    "0o7",
+    "1n",
+    "1_0",
+    ".5",
+    "1e",
+    "1e+",
+    "'",
+    "\"",
+    "\\",
+    "\\u",
+    "\\u{",
+    "\\x",
+    "\\0",
+    "\\8",
+    "{",
+    "}",
+    "(",
+    ")",
+    "[",
+    "]",
+    "`",
+    "${",
+    "/",
+    "/*",
+    "*/",
+    "//",
+    "*",
+    "<",
+    ">",
+    "</",
+    "/>",
+    "=",
+    "=>",
+    "?",
+    "?.",
+    "??=",
+    ".",
+    "...",
+    "@",
+    "!",
+    "~",
+    "&&=",
+    ">>>=",
+    "**",
+    "\u{0}",
+    "\u{1f600}",
+    "x<y>",
+];
+
+/// A xorshift generator: small, and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn generate(rng: &mut Rng) -> String {
+    let mut text = String::new();
+    for _ in 0..rng.below(40) {
+        if rng.below(8) == 0 {
+            // Any character at all, including lone surrogate halves'
+            // neighbours and other unassigned code points.
+            text.extend(char::from_u32(rng.next() as u32 % 0x11_0000));
+        } else {
+            text.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+        }
+    }
+    text
+}
+
+/// Checks every property of `text` in one variant and scan mode.
+fn check(text: &str, variant: LanguageVariant, scan_mode: ScanMode) {
+    let context = || format!("{:?} in {:?}, {:?}", text, variant, scan_mode);
+
+    // Each step of the iterator consumes input, so lexing ends. The one
+    // exception is an unterminated token, which follows its error.
+    let mut lexer = Lexer::with_scan_mode(text, scan_mode);
+    lexer.set_language_variant(variant);
+    let mut tokens = lexer.lex();
+    let mut offset = tokens.lexer().offset();
+    let mut end = 0;
+    while let Some(result) = tokens.next() {
+        let unterminated =
+            matches!(&result, Ok(token) if token.flags.contains(TokenFlags::UNTERMINATED));
+        assert!(
+            tokens.lexer().offset() > offset || unterminated,
+            "no progress at {}: {}",
+            offset,
+            context()
+        );
+        offset = tokens.lexer().offset();
+
+        if let Ok(token) = result {
+            let (start, stop) = (token.start as usize, token.end as usize);
+            assert!(
+                end <= start && start <= stop && stop <= offset,
+                "{:?} at {}..{} after {}: {}",
+                token.token_type,
+                start,
+                stop,
+                end,
+                context()
+            );
+            assert!(text.is_char_boundary(start) && text.is_char_boundary(stop));
+            end = stop;
+        }
+    }
+    assert_eq!(tokens.lexer().offset(), text.len(), "{}", context());
+
+    // With trivia, the spans tile the source.
+    let mut lexer = Lexer::with_scan_mode(text, scan_mode);
+    lexer.set_language_variant(variant);
+    let (tokens, _) = lexer.lex_with_trivia();
+    let mut rebuilt = String::new();
+    for token in &tokens {
+        assert_eq!(token.start as usize, rebuilt.len(), "{}", context());
+        rebuilt.push_str(&text[token.start as usize..token.end as usize]);
+    }
+    assert_eq!(rebuilt, text, "{}", context());
+
+    // So do the highlighter's spans.
+    let mut end = 0;
+    for span in Highlighter::new().variant(variant).spans(text) {
+        assert_eq!(span.span.start, end, "{}", context());
+        end = span.span.end;
+    }
+    assert_eq!(end, text.len(), "{}", context());
+
+    // Semantic tokens decode to positions within their lines.
+    let line_map = LineMap::new(text);
+    let (mut line, mut column) = (0, 0);
+    for token in semantic_tokens::encode(text, variant).chunks(5) {
+        line += token[0] as usize;
+        column = if token[0] == 0 {
+            column + token[1]
+        } else {
+            token[1]
+        };
+        let range = line_map.line_range(text, line + 1);
+        let width: u32 = text[range].chars().map(|c| c.len_utf16() as u32).sum();
+        assert!(
+            line < line_map.line_count() && token[2] > 0 && column + token[2] <= width,
+            "{:?} on line {}: {}",
+            token,
+            line,
+            context()
+        );
+    }
+}
+
+fn check_all(text: &str) {
+    for variant in [LanguageVariant::Standard, LanguageVariant::Jsx] {
+        for scan_mode in [ScanMode::Simd, ScanMode::Scalar] {
+            check(text, variant, scan_mode);
+        }
+    }
+}
+
+#[test]
+fn test_generated_inputs() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..CASES {
+        check_all(&generate(&mut rng));
+    }
+}
+
+#[test]
+fn test_every_prefix() {
+    // Cutting a valid program anywhere leaves an unterminated construct,
+    // which is where the error paths are.
+    let text = "#!x\nlet a = `b${ {c: /[/]/g} }\\u{61}`; <A b='c'>{d}</A>; 0x1_0n /* é */";
+    for (i, _) in text.char_indices() {
+        check_all(&text[..i]);
+    }
+}
+
+#[test]
+fn test_regressions() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/regressions");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "md") {
+            continue;
+        }
+        let bytes = fs::read(&path).unwrap();
+        // The fuzz target skips input that is not UTF-8, so every saved
+        // case is.
+        let text = String::from_utf8(bytes).unwrap();
+        let result = panic::catch_unwind(|| check_all(&text));
+        assert!(result.is_ok(), "{}", path.display());
+    }
+}
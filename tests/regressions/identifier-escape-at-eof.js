@@ -0,0 +1 @@
+a\u{
\ No newline at end of file
@@ -0,0 +1,2 @@
+0x_
+ }
\ No newline at end of file
@@ -0,0 +1,166 @@
+//! The `test262` runner, run as a subprocess over a small fixture checkout,
+//! and over a real checkout in an ignored test.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command, Output};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test262/fixture");
+const FIXTURE_FAILURES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/test262/fixture-failures.txt"
+);
+
+fn test262(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_test262"))
+        .arg(FIXTURE)
+        .arg("--all")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+/// A path in the temporary directory that no other test or test run uses.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("rhi-test262-{}-{}", process::id(), name))
+}
+
+#[test]
+fn test_known_failures() {
+    let output = test262(&["--allowlist", FIXTURE_FAILURES]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+    assert_eq!(
+        stdout(&output),
+        "test262: 6 tests, 5 passed, 1 known failures, 0 new failures, 0 fixed\n"
+    );
+}
+
+#[test]
+fn test_new_failure() {
+    let allowlist = temp_path("empty.txt");
+    fs::write(&allowlist, "").unwrap();
+    let output = test262(&["--allowlist", allowlist.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        [
+            "FAIL test/language/expressions/assignment/invalid-target.js: \
+             Sloppy: expected a SyntaxError, but lexing succeeded",
+            "test262: 6 tests, 5 passed, 0 known failures, 1 new failures, 0 fixed",
+        ]
+    );
+    fs::remove_file(&allowlist).unwrap();
+}
+
+#[test]
+fn test_fixed_and_write_allowlist() {
+    let allowlist = temp_path("stale.txt");
+    fs::write(
+        &allowlist,
+        "test/language/literals/numeric/hex.js\n\
+         test/language/expressions/assignment/invalid-target.js\n",
+    )
+    .unwrap();
+    let output = test262(&[
+        "--filter",
+        "numeric",
+        "--allowlist",
+        allowlist.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout(&output)
+            .contains("FIXED test/language/literals/numeric/hex.js (remove it from the allowlist)")
+    );
+
+    let output = test262(&[
+        "--allowlist",
+        allowlist.to_str().unwrap(),
+        "--write-allowlist",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let written = fs::read_to_string(&allowlist).unwrap();
+    assert_eq!(
+        written
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>(),
+        ["test/language/expressions/assignment/invalid-target.js"]
+    );
+    fs::remove_file(&allowlist).unwrap();
+}
+
+#[test]
+fn test_allowlist_revision() {
+    let root = temp_path("checkout");
+    let test = root.join("test/language/literals/a.js");
+    fs::create_dir_all(test.parent().unwrap()).unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::write(&test, "/*---\nflags: [raw]\n---*/\n'a\n").unwrap();
+    fs::write(root.join(".git/HEAD"), "0123abc\n").unwrap();
+    let allowlist = root.join("allowlist.txt");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_test262"))
+            .arg(&root)
+            .arg("--all")
+            .arg("--allowlist")
+            .arg(&allowlist)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert_eq!(run(&["--write-allowlist"]).status.code(), Some(0));
+    let written = fs::read_to_string(&allowlist).unwrap();
+    assert!(written.contains("# test262 revision: 0123abc\n"));
+    assert!(written.ends_with("\ntest/language/literals/a.js\n"));
+    assert_eq!(run(&[]).status.code(), Some(0));
+
+    fs::write(root.join(".git/HEAD"), "4567def\n").unwrap();
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("is for test262 0123abc, but the checkout is at 4567def")
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+/// Runs the lexer over the test262 checkout in `TEST262_DIR`, which must be
+/// at the revision `known-failures.txt` is pinned to.
+#[test]
+#[ignore = "needs a test262 checkout in TEST262_DIR"]
+fn test_test262_checkout() {
+    let root = env::var_os("TEST262_DIR").expect("TEST262_DIR should name a test262 checkout");
+    let allowlist = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/test262/known-failures.txt"
+    );
+    assert!(
+        fs::read_to_string(allowlist)
+            .unwrap()
+            .contains("# test262 revision: "),
+        "{} is not pinned to a test262 revision; write it with \
+         `cargo run --bin test262 -- <test262 dir> --write-allowlist`",
+        allowlist
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_test262"))
+        .arg(root)
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}{}",
+        stdout(&output),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
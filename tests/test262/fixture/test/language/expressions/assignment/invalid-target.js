@@ -0,0 +1,12 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-assignment-operators-static-semantics-early-errors
+description: An assignment target must be a reference (not a lexical error)
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+1 = 2;
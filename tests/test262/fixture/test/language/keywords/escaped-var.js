@@ -0,0 +1,12 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-keywords-and-reserved-words
+description: Keywords cannot contain escape sequences
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+v\u0061r x = 1;
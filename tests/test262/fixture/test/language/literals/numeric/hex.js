@@ -0,0 +1,7 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-literals-numeric-literals
+description: Hexadecimal literals
+---*/
+
+assert.sameValue(0x1F, 31);
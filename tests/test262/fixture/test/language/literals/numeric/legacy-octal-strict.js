@@ -0,0 +1,13 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-literals-numeric-literals
+description: Legacy octal literals are not allowed in strict code
+flags: [onlyStrict]
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+017;
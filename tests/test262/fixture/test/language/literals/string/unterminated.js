@@ -0,0 +1,13 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-literals-string-literals
+description: A string literal cannot contain a line terminator
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+'a
+';
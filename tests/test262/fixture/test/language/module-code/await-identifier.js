@@ -0,0 +1,14 @@
+// Copyright (C) 2024 the rhi authors. All rights reserved.
+/*---
+esid: sec-modules
+description: await cannot be written with escapes in module code
+flags:
+  - module
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+
+$DONOTEVALUATE();
+
+var aw\u0061it;
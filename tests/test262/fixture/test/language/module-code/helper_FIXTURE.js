@@ -0,0 +1,2 @@
+// Not a test: imported by other module tests.
+export var x = '
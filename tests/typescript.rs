@@ -0,0 +1,255 @@
+//! Compares `rhi::typescript::dump` with golden files of the tokens
+//! TypeScript's scanner produces, as printed by `scripts/ts-tokens.mjs`.
+//! See tests/typescript/README.md for how to regenerate them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rhi::{ColumnEncoding, LanguageVariant, SourceFile, typescript};
+
+/// Every file in the benchmark corpus, plus the inputs next to the goldens.
+fn inputs(root: &Path) -> Vec<PathBuf> {
+    let mut inputs = Vec::new();
+    for dir in ["benches/corpus", "tests/typescript"] {
+        for entry in fs::read_dir(root.join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("js" | "jsx" | "ts" | "mjs" | "cjs" | "mts" | "cts")
+            ) {
+                inputs.push(path);
+            }
+        }
+    }
+    inputs.sort();
+    inputs
+}
+
+/// As `scripts/ts-tokens.mjs` chooses it.
+fn variant(path: &Path) -> LanguageVariant {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("ts" | "mts" | "cts") => LanguageVariant::Standard,
+        _ => LanguageVariant::Jsx,
+    }
+}
+
+/// A known difference between TypeScript's tokens and rhi's.
+struct Divergence {
+    what: &'static str,
+    why: &'static str,
+    /// Whether a TypeScript line and the rhi line in its place differ this
+    /// way.
+    matches: fn(&Line, &Line) -> bool,
+}
+
+const DIVERGENCES: &[Divergence] = &[
+    Divergence {
+        what: "an escaped keyword, such as `\\u0063onst`, is an Identifier",
+        why: "an escaped keyword cannot be used as a keyword, so rhi's lexer \
+              reports what it can be used as; TypeScript returns the keyword's \
+              kind and leaves the error to its parser",
+        matches: |ts, rhi| {
+            ts.kind.ends_with("Keyword")
+                && rhi.kind == "Identifier"
+                && ts.text == rhi.text
+                && ts.text.contains("\\\\u")
+        },
+    },
+    Divergence {
+        what: "a `/` whose regular expression would run past the end of the \
+               line is a division",
+        why: "rhi's lexer has no parser to rescan with, so it takes the \
+              reading that lets the rest of the line lex; TypeScript's rescan \
+              gives an Unterminated RegularExpressionLiteral",
+        matches: |ts, rhi| {
+            ts.kind == "RegularExpressionLiteral"
+                && ts.flags & 4 != 0
+                && matches!(rhi.kind, "SlashToken" | "SlashEqualsToken")
+        },
+    },
+    Divergence {
+        what: "a number followed by a name, as in `3in`, is Unknown",
+        why: "rhi rejects the whole run, as the spec requires no name to \
+              follow a number, and dumps it as one Unknown token per \
+              character; TypeScript returns the number and the name",
+        matches: |ts, rhi| ts.kind == "NumericLiteral" && rhi.kind == "Unknown",
+    },
+];
+
+/// The fields of a dump line.
+struct Line<'a> {
+    kind: &'a str,
+    /// In UTF-16 code units.
+    offset: usize,
+    text: &'a str,
+    flags: u32,
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a str) -> Option<Line<'a>> {
+        let mut fields = line.split('\t');
+        Some(Line {
+            kind: fields.next()?,
+            offset: fields.next()?.parse().ok()?,
+            text: fields.next()?,
+            flags: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// The known divergence that explains `expected` becoming `actual`.
+fn known_divergence(expected: &str, actual: &str) -> Option<&'static Divergence> {
+    let (ts, rhi) = (Line::parse(expected)?, Line::parse(actual)?);
+    if ts.offset != rhi.offset {
+        return None;
+    }
+    DIVERGENCES.iter().find(|d| (d.matches)(&ts, &rhi))
+}
+
+/// The first line, 0-based, at which two dumps differ, ignoring `#` lines
+/// and skipping over known divergences until the two agree on a token again.
+fn first_divergence<'a>(
+    expected: &'a str,
+    actual: &'a str,
+) -> Option<(usize, Option<&'a str>, Option<&'a str>)> {
+    let offset = |line: &str| Line::parse(line).map_or(usize::MAX, |line| line.offset);
+    let mut expected = expected
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .peekable();
+    let mut actual = actual.lines().peekable();
+    for i in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return None,
+            (Some(e), Some(a)) if e != a && known_divergence(e, a).is_some() => {
+                let divergence = known_divergence(e, a).unwrap();
+                println!(
+                    "known: {} ({})\n  typescript: {}\n  rhi:        {}",
+                    divergence.what, divergence.why, e, a
+                );
+                // Both go on from the next token they both start, other than
+                // the rest of a run of characters rhi rejects.
+                let after = offset(e);
+                while let (Some(&e), Some(&a)) = (expected.peek(), actual.peek()) {
+                    let unknown = a.starts_with("Unknown\t");
+                    if offset(e) == offset(a) && offset(e) > after && (e == a || !unknown) {
+                        break;
+                    }
+                    if offset(e) <= offset(a) {
+                        expected.next();
+                    }
+                    if offset(a) <= offset(e) {
+                        actual.next();
+                    }
+                }
+            }
+            (e, a) if e != a => return Some((i, e, a)),
+            _ => {}
+        }
+    }
+    unreachable!()
+}
+
+/// `file:line:col` of the token on a dump line, whose offset is in UTF-16
+/// code units.
+fn location(source: &SourceFile, line: &str) -> String {
+    let utf16_offset: usize = line
+        .split('\t')
+        .nth(1)
+        .and_then(|offset| offset.parse().ok())
+        .unwrap_or(0);
+    let mut units = 0;
+    let offset = source
+        .source()
+        .char_indices()
+        .find(|&(_, c)| {
+            units += c.len_utf16();
+            units > utf16_offset
+        })
+        .map_or(source.source().len(), |(i, _)| i);
+    let position = source.position(offset, ColumnEncoding::Utf16);
+    format!("{}:{}:{}", source.name(), position.line, position.column)
+}
+
+#[test]
+fn test_typescript_goldens() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut failures = Vec::new();
+
+    for input in inputs(root) {
+        let name = input.file_name().unwrap().to_str().unwrap();
+        let golden_path = root
+            .join("tests/typescript")
+            .join(format!("{}.tokens", name));
+        let relative = input.strip_prefix(root).unwrap().display().to_string();
+        let Ok(golden) = fs::read_to_string(&golden_path) else {
+            failures.push(format!(
+                "{}: no golden file; generate it with `node scripts/ts-tokens.mjs {} > {}`",
+                relative,
+                relative,
+                golden_path.strip_prefix(root).unwrap().display()
+            ));
+            continue;
+        };
+
+        let source = SourceFile::new(relative.clone(), fs::read_to_string(&input).unwrap());
+        let actual = typescript::dump(source.source(), variant(&input));
+        if let Some((i, expected, actual)) = first_divergence(&golden, &actual) {
+            let at = location(&source, expected.or(actual).unwrap_or_default());
+            failures.push(format!(
+                "{}: first divergence at token {}, {}\n  typescript: {}\n  rhi:        {}",
+                relative,
+                i + 1,
+                at,
+                expected.unwrap_or("<end of tokens>"),
+                actual.unwrap_or("<end of tokens>")
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n\n"));
+}
+
+#[test]
+fn test_first_divergence() {
+    let golden = "# typescript 5.0.0\nIdentifier\t0\t\"a\"\t0\nDotToken\t1\t\".\"\t0\n";
+
+    assert_eq!(
+        first_divergence(golden, "Identifier\t0\t\"a\"\t0\nDotToken\t1\t\".\"\t0\n"),
+        None
+    );
+    assert_eq!(
+        first_divergence(golden, "Identifier\t0\t\"a\"\t0\n"),
+        Some((1, Some("DotToken\t1\t\".\"\t0"), None))
+    );
+
+    // `3in` and an escaped `const`.
+    let golden = "# typescript 5.0.0\n\
+                  NumericLiteral\t0\t\"3\"\t0\n\
+                  InKeyword\t1\t\"in\"\t0\n\
+                  ConstKeyword\t4\t\"\\\\u0063onst\"\t1024\n";
+    assert_eq!(
+        first_divergence(
+            golden,
+            "Unknown\t0\t\"3\"\t0\nUnknown\t1\t\"i\"\t0\nUnknown\t2\t\"n\"\t0\n\
+             Identifier\t4\t\"\\\\u0063onst\"\t1024\n"
+        ),
+        None
+    );
+    // An unterminated regular expression, then a token rhi gets wrong.
+    let golden = "RegularExpressionLiteral\t0\t\"/ a\"\t4\nIdentifier\t4\t\"b\"\t1\n";
+    assert_eq!(
+        first_divergence(
+            golden,
+            "SlashToken\t0\t\"/\"\t0\nIdentifier\t2\t\"a\"\t0\nIdentifier\t4\t\"c\"\t1\n"
+        ),
+        Some((
+            1,
+            Some("Identifier\t4\t\"b\"\t1"),
+            Some("Identifier\t4\t\"c\"\t1")
+        ))
+    );
+
+    let source = SourceFile::new("a.js", "é\nab");
+    assert_eq!(location(&source, "Identifier\t3\t\"b\"\t1"), "a.js:2:2");
+}
@@ -0,0 +1,6 @@
+// Lexical errors, which TypeScript's scanner reports as `Unknown` tokens or
+// as tokens flagged `Unterminated`.
+let a = 'no closing quote
+let b = 1 § 2 ¤¤ 3;
+let c = "\u{110000}" + '\x4';
+let d = `no closing backtick ${a}
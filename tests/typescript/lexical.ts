@@ -0,0 +1,18 @@
+// Corner cases for the TypeScript comparison, next to the real code in
+// benches/corpus. No JSX: scripts/ts-tokens.mjs does not follow it.
+let numbers = [0, 0.5, .5, 5., 1e10, 1E-7, 0x1F, 0b10, 0o17, 017, 089, 1_000n, 0xFn];
+let strings = ['\x41', "A", '\u{1F600}', '\0', '\08', "line\
+continued", 'é', " "];
+let templates = [`a`, `a${b}c${`d${e}`}f`, tag`\unicode and \xbad`, `${{ a: 1 }.a}`];
+let regexes = [/a/g, /[/]/, /\//u, x / y / z, (a) / 2, a++ / 2];
+if (/^#!/.test(line)) return /* c */ /x/;
+a ??= b?.c ?? d?.[0] ?? e?.(f) ?? (g ? .5 : h);
+x >>>= y >> z >= w >>> 1;
+type T<U> = U extends Array<Array<infer V>> ? V : never;
+class C { #p = 1; static #q() { return this.#p; } }
+const abc = ab\u{63}, 한글 = "ok";
+label: for (const x of xs) { continue label; }
+/** doc */
+function f(this: C, ...rest: number[]): asserts this is C {}
+a
+++b